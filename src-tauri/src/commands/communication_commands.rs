@@ -7,7 +7,10 @@
 use tauri::{AppHandle, Manager};
 
 use crate::{
-    communication_manager::{CommunicationManager, CommunicationManagerState},
+    communication_drivers::framing::Framing,
+    communication_manager::{
+        CommunicationManager, CommunicationManagerState, DeviceParserStatistics,
+    },
     state::{generic_state::result_to_string, mutex_utils::use_state_in_mutex},
 };
 const COM_DEVICE_UPDATE: &str = "com-device-update";
@@ -77,6 +80,49 @@ pub fn init_device_port(
     ))
 }
 
+/// Sets how a device frames its packets, serial ports and files can be framed.
+///
+/// # Arguments
+/// * `communication_manager_state` - The shared state of the communication manager.
+/// * `framing` - How the device wraps its packets.
+/// * `id` - The ID of the device to set the framing of.
+///
+/// # Returns
+/// Result<(), String> - Ok on success, Err with error message on failure.
+#[tauri::command(async)]
+pub fn set_device_framing(
+    communication_manager_state: tauri::State<'_, CommunicationManagerState>,
+    framing: Framing,
+    id: usize,
+) -> Result<(), String> {
+    result_to_string(use_state_in_mutex(
+        &communication_manager_state,
+        &mut |communication_manager: &mut CommunicationManager| {
+            communication_manager.set_framing(framing, id)
+        },
+    ))
+}
+
+/// Gets what the parser of every device has made of the data it received, the same statistics
+/// are sent out every second as a "parser-statistics" event.
+///
+/// # Arguments
+/// * `communication_manager_state` - The shared state of the communication manager.
+///
+/// # Returns
+/// Result<Vec<DeviceParserStatistics>, String> - Always Ok.
+#[tauri::command(async)]
+pub fn get_parser_statistics(
+    communication_manager_state: tauri::State<'_, CommunicationManagerState>,
+) -> Result<Vec<DeviceParserStatistics>, String> {
+    Ok(use_state_in_mutex(
+        &communication_manager_state,
+        &mut |communication_manager: &mut CommunicationManager| {
+            communication_manager.get_parser_statistics()
+        },
+    ))
+}
+
 /// Adds a new RFD (serial) device to the communication manager.
 ///
 /// Emits an update to the frontend after addition.
@@ -204,3 +250,28 @@ pub fn add_featherweight(
     );
     Ok(())
 }
+
+/// Adds a new MAVLink device to the communication manager.
+///
+/// Emits an update to the frontend after addition.
+///
+/// # Arguments
+/// * `app_handle` - The Tauri app handle.
+/// * `communication_manager_state` - The shared state of the communication manager.
+///
+/// # Returns
+/// Result<(), String> - Always Ok.
+#[tauri::command(async)]
+pub fn add_mavlink(
+    app_handle: AppHandle,
+    communication_manager_state: tauri::State<'_, CommunicationManagerState>,
+) -> Result<(), String> {
+    use_state_in_mutex(
+        &communication_manager_state,
+        &mut |communication_manager: &mut CommunicationManager| {
+            communication_manager.add_mavlink();
+            update_coms(&app_handle, communication_manager);
+        },
+    );
+    Ok(())
+}
@@ -1,10 +1,17 @@
 use crate::{
+    communication_manager::CommunicationManagerState,
     data_processing::DataProcessorState,
     file_handling::config_struct::ConfigState,
     models::{
+        c_header,
+        firmware_code::{generate_firmware_code, FirmwareCode},
         packet_structure::{PacketFieldType, PacketMetadataType},
         packet_view_model::{PacketComponentType, PacketStructureViewModel},
+        structure_dsl::serialize_structure,
     },
+    packet_structure_manager::PacketStructureManager,
+    packet_structure_validation::Ambiguity,
+    state::mutex_utils::use_state_in_mutex,
 };
 // # packet_structure_manager_commands
 //
@@ -206,3 +213,149 @@ pub fn delete_packet_structure(
     return Err("WINGS can no longer edit packet structures, edit config instead".to_owned());
     todo!();
 }
+
+/// Returns the packet structure with the given id written as structure text (see `structure_dsl`)
+///
+/// this only reads the structure, so it is still available while editing is disabled
+#[tauri::command]
+pub fn get_packet_structure_text(
+    config_state: tauri::State<'_, ConfigState>,
+    packet_structure_id: usize,
+) -> Result<String, String> {
+    use_state_in_mutex(&config_state, &mut |config| {
+        config
+            .packet_structure_manager
+            .get_packet_structure(packet_structure_id)
+            .map(serialize_structure)
+            .map_err(|error| format!("{:?}", error))
+    })
+}
+
+/// Imports the structs of a C header as packet structures, and saves them to the config file
+///
+/// structures with the name of one that already exists are left alone. returns everything in the
+/// header that couldn't be imported
+#[tauri::command]
+pub fn import_c_header(
+    config_state: tauri::State<'_, ConfigState>,
+    communication_manager_state: tauri::State<'_, CommunicationManagerState>,
+    header: &str,
+) -> Result<Vec<String>, String> {
+    let import = c_header::import_c_header(header);
+    let mut problems: Vec<String> = import
+        .problems
+        .iter()
+        .map(|problem| problem.to_string())
+        .collect();
+    edit_config_structures(
+        &config_state,
+        &communication_manager_state,
+        &mut |packet_structure_manager| {
+            // the whole header is undone as one step
+            packet_structure_manager
+                .transaction("Import C header", |packet_structure_manager| {
+                    for packet_structure in &import.structures {
+                        if packet_structure_manager
+                            .packet_structures
+                            .iter()
+                            .any(|existing| existing.name == packet_structure.name)
+                        {
+                            problems.push(format!(
+                                "'{}' already exists, remove it from the config file to import it again",
+                                packet_structure.name
+                            ));
+                            continue;
+                        }
+                        packet_structure_manager
+                            .register_packet_structure(&mut packet_structure.clone())?;
+                    }
+                    Ok(())
+                })
+                .map_err(|error| format!("{:?}", error))
+        },
+    )?;
+    Ok(problems)
+}
+
+/// Undoes the newest change to the packet structures in the config, and saves the config file
+///
+/// returns the name of the change that was undone, or None if there was nothing to undo
+#[tauri::command]
+pub fn undo_packet_structure_edit(
+    config_state: tauri::State<'_, ConfigState>,
+    communication_manager_state: tauri::State<'_, CommunicationManagerState>,
+) -> Result<Option<String>, String> {
+    edit_config_structures(
+        &config_state,
+        &communication_manager_state,
+        &mut |packet_structure_manager| Ok(packet_structure_manager.undo()),
+    )
+}
+
+/// Redoes the newest undone change to the packet structures in the config, and saves the config
+/// file
+///
+/// returns the name of the change that was redone, or None if there was nothing to redo
+#[tauri::command]
+pub fn redo_packet_structure_edit(
+    config_state: tauri::State<'_, ConfigState>,
+    communication_manager_state: tauri::State<'_, CommunicationManagerState>,
+) -> Result<Option<String>, String> {
+    edit_config_structures(
+        &config_state,
+        &communication_manager_state,
+        &mut |packet_structure_manager| Ok(packet_structure_manager.redo()),
+    )
+}
+
+/// Makes an edit to the packet structures in the config and saves the config file, then passes
+/// the edit on to the packet structure manager packets are parsed with, which updates the
+/// frontend
+fn edit_config_structures<T>(
+    config_state: &ConfigState,
+    communication_manager_state: &CommunicationManagerState,
+    edit: &mut dyn FnMut(&mut PacketStructureManager) -> Result<T, String>,
+) -> Result<T, String> {
+    let (result, before, edited) =
+        use_state_in_mutex(config_state, &mut |config| -> Result<_, String> {
+            let before = config.packet_structure_manager.packet_structures.clone();
+            let result = edit(&mut config.packet_structure_manager)?;
+            if config.packet_structure_manager.packet_structures != before {
+                config.save().map_err(|error| format!("{:?}", error))?;
+            }
+            Ok((result, before, config.packet_structure_manager.clone()))
+        })?;
+    let ps_manager =
+        use_state_in_mutex(communication_manager_state, &mut |communication_manager| {
+            communication_manager.ps_manager.clone()
+        });
+    use_state_in_mutex(&ps_manager, &mut |ps_manager| {
+        ps_manager.apply_edits_from(&before, &edited)
+    })
+    .map_err(|error| format!("{:?}", error))?;
+    Ok(result)
+}
+
+/// Generates a C header and a Rust module laying out every packet structure the way WINGS
+/// expects it to be sent, for firmware to build against
+#[tauri::command]
+pub fn get_firmware_code(
+    config_state: tauri::State<'_, ConfigState>,
+) -> Result<FirmwareCode, String> {
+    use_state_in_mutex(&config_state, &mut |config| {
+        Ok(generate_firmware_code(
+            &config.packet_structure_manager.packet_structures,
+        ))
+    })
+}
+
+/// Returns every pair of packet structures that the same bytes could be parsed as, with an
+/// example of those bytes
+#[tauri::command]
+pub fn get_delimiter_ambiguities(
+    config_state: tauri::State<'_, ConfigState>,
+) -> Result<Vec<Ambiguity>, String> {
+    use_state_in_mutex(&config_state, &mut |config| {
+        Ok(config.packet_structure_manager.find_ambiguities())
+    })
+}
@@ -1,9 +1,9 @@
-use std::time::SystemTime;
+use std::{collections::BTreeMap, time::SystemTime};
 
 use crate::{
     models::{
         packet::{Packet, PacketFieldValue},
-        packet_structure::PacketFieldType,
+        packet_structure::{Endianness, PacketBitfield, PacketField, PacketFieldType},
     },
     packet_structure_manager::PacketStructureManager,
 };
@@ -32,6 +32,7 @@ const ORIENTATION: &str = "Aim_Orientation";
 pub struct AimParser {
     init_time: SystemTime,
     packet_ids: PacketIdList,
+    status_layout: Vec<PacketField>,
 }
 
 //holds all packetIdsRelatedToThisParser,Fields are filled in at initialization
@@ -58,12 +59,55 @@ pub struct PacketIdList {
     orientation: usize,
 }
 
+/// bit layout of the two Aim_Status bytes, in the order the values are reported
+///
+/// the state is the top nibble of the first byte, the rest of the bits are line flags
+fn status_layout() -> Vec<PacketField> {
+    let make_bitfield = |index: usize, offset_in_packet: usize, bit_offset: usize| PacketField {
+        index,
+        name: String::new(),
+        r#type: PacketFieldType::Bool,
+        offset_in_packet,
+        endianness: Endianness::Little,
+        bitfield: Some(PacketBitfield {
+            bit_offset,
+            bit_width: 1,
+        }),
+        string_format: None,
+        array: None,
+        calibration: None,
+        unit: None,
+        labels: BTreeMap::new(),
+        timestamp: None,
+        sequence: None,
+        element_names: vec![],
+    };
+    let mut layout = vec![PacketField {
+        r#type: PacketFieldType::UnsignedByte,
+        bitfield: Some(PacketBitfield {
+            bit_offset: 4,
+            bit_width: 4,
+        }),
+        ..make_bitfield(0, 0, 0)
+    }];
+    // line on flags, line D first
+    for bit_offset in (0..4).rev() {
+        layout.push(make_bitfield(layout.len(), 0, bit_offset));
+    }
+    // line continuity and input flags, most significant bit first
+    for bit_offset in (0..8).rev() {
+        layout.push(make_bitfield(layout.len(), 1, bit_offset));
+    }
+    layout
+}
+
 /// responsible converting raw data to packets
 impl AimParser {
     pub fn default(ps_manager: &mut PacketStructureManager) -> AimParser {
         println!("Creating Aim!");
         AimParser {
             init_time: SystemTime::now(),
+            status_layout: status_layout(),
             packet_ids: PacketIdList {
                 meta: ps_manager.enforce_packet_fields(META, vec!["System time", "RSSI", "SNR"]),
                 accel_z: ps_manager.enforce_packet_fields(
@@ -198,11 +242,8 @@ impl AimParser {
         }
         let time_received = self.init_time.elapsed()?.as_millis() as f64;
         let length = transmission[1];
-        let rssi = i16::from_be_bytes(
-            transmission[2..4]
-                .try_into()
-                .expect("Given slice has incorrect length!"),
-        ) as f64;
+        let rssi = PacketFieldType::SignedShort
+            .parse_with_endianness(&transmission[2..4], Endianness::Big)?;
         let snr = transmission[4] as f64;
         let mut i: usize = 3;
 
@@ -210,7 +251,7 @@ impl AimParser {
             self.packet_ids.meta,
             vec![
                 PacketFieldValue::Number(time_received),
-                PacketFieldValue::Number(rssi),
+                rssi,
                 PacketFieldValue::Number(snr),
             ],
         ));
@@ -240,8 +281,8 @@ impl AimParser {
                 0x03 => {
                     type_id = self.packet_ids.pressure;
 
-                    let value =
-                        PacketFieldType::UnsignedTwoFour.parse(&transmission[i + 2..i + 5])?;
+                    let value = PacketFieldType::UnsignedTwoFour
+                        .parse_with_endianness(&transmission[i + 2..i + 5], Endianness::Big)?;
 
                     data.push(value);
 
@@ -380,22 +421,9 @@ impl AimParser {
                 0x10 => {
                     type_id = self.packet_ids.status;
 
-                    data.push(
-                        PacketFieldType::UnsignedByte
-                            .parse(&[(transmission[i + 2] & 0b11110000) >> 4])?,
-                    );
-                    data.push(PacketFieldType::Bool.parse(&[transmission[i + 2] & 0b00001000])?);
-                    data.push(PacketFieldType::Bool.parse(&[transmission[i + 2] & 0b00000100])?);
-                    data.push(PacketFieldType::Bool.parse(&[transmission[i + 2] & 0b00000010])?);
-                    data.push(PacketFieldType::Bool.parse(&[transmission[i + 2] & 0b00000001])?);
-                    data.push(PacketFieldType::Bool.parse(&[transmission[i + 3] & 0b10000000])?);
-                    data.push(PacketFieldType::Bool.parse(&[transmission[i + 3] & 0b01000000])?);
-                    data.push(PacketFieldType::Bool.parse(&[transmission[i + 3] & 0b00100000])?);
-                    data.push(PacketFieldType::Bool.parse(&[transmission[i + 3] & 0b00010000])?);
-                    data.push(PacketFieldType::Bool.parse(&[transmission[i + 3] & 0b00001000])?);
-                    data.push(PacketFieldType::Bool.parse(&[transmission[i + 3] & 0b00000100])?);
-                    data.push(PacketFieldType::Bool.parse(&[transmission[i + 3] & 0b00000010])?);
-                    data.push(PacketFieldType::Bool.parse(&[transmission[i + 3] & 0b00000001])?);
+                    for field in &self.status_layout {
+                        data.push(field.parse_from_packet(&transmission[i + 2..])?);
+                    }
 
                     i += 2;
                 }
@@ -455,6 +483,7 @@ impl AimParser {
             packets.push(Packet {
                 structure_id: type_id,
                 field_data: data,
+                field_labels: BTreeMap::new(),
             });
         }
         Ok(())
@@ -14,7 +14,11 @@ use std::{
     sync::{Arc, Mutex},
 };
 
-use super::serial_packet_parser::SerialPacketParser;
+use super::{
+    framing::{FramedPacketParser, Framing},
+    parser_statistics::ParserStatistics,
+    serial_packet_parser::SerialPacketParser,
+};
 
 const PRINT_PARSING: bool = false;
 
@@ -27,11 +31,13 @@ const PRINT_PARSING: bool = false;
 /// * `file`: A Handle of the file that is being used as a data source
 /// * `id`: a device id mandated by the `CommsIF``
 /// * `packet_parser`: A packet parser that will be used to process packets from the binary
+/// * `framed_parser`: Used instead of `packet_parser` when the recorded packets were framed
 /// * `packet_structure_manager`: A reference to a PacketStructureManager that defines all the packets the communications driver will be working with
 pub struct BinaryFileAdapter {
     file: Option<File>,
     id: usize,
     packet_parser: SerialPacketParser,
+    framed_parser: Option<FramedPacketParser>,
     packet_structure_manager: Arc<Mutex<PacketStructureManager>>,
 }
 impl CommsIF for BinaryFileAdapter {
@@ -43,6 +49,7 @@ impl CommsIF for BinaryFileAdapter {
         BinaryFileAdapter {
             file: None,
             packet_parser: Default::default(),
+            framed_parser: None,
             id: 0,
             packet_structure_manager,
         }
@@ -78,6 +85,22 @@ impl CommsIF for BinaryFileAdapter {
         "ByteFile".to_owned()
     }
 
+    fn set_framing(&mut self, framing: Framing) -> anyhow::Result<()> {
+        self.packet_parser = Default::default();
+        self.framed_parser = match framing {
+            Framing::Delimiters => None,
+            _ => Some(FramedPacketParser::new(framing)),
+        };
+        Ok(())
+    }
+
+    fn parser_statistics(&self) -> Option<ParserStatistics> {
+        Some(match &self.framed_parser {
+            Some(framed_parser) => framed_parser.statistics(),
+            None => self.packet_parser.statistics(),
+        })
+    }
+
     fn get_device_raw_data(&mut self, data_vector: &mut Vec<u8>) -> anyhow::Result<()> {
         let mut buffer: [u8; 4096] = [0; 4096];
         let _ = self
@@ -94,6 +117,16 @@ impl CommsIF for BinaryFileAdapter {
         data_vector: &mut Vec<u8>,
         packet_vector: &mut Vec<Packet>,
     ) -> anyhow::Result<()> {
+        if let Some(framed_parser) = &mut self.framed_parser {
+            framed_parser.push_data(data_vector, PRINT_PARSING);
+            return use_state_in_mutex(
+                &self.packet_structure_manager,
+                &mut |ps_manager| -> anyhow::Result<()> {
+                    packet_vector.extend(framed_parser.parse_packets(ps_manager, PRINT_PARSING)?);
+                    Ok(())
+                },
+            );
+        }
         self.packet_parser.push_data(data_vector, PRINT_PARSING);
         use_state_in_mutex(
             &self.packet_structure_manager,
@@ -0,0 +1,153 @@
+//! # Delimiter Search
+//!
+//! Finds every delimiter of a set of packet structures in a byte stream in a single pass,
+//! using an Aho-Corasick automaton. Each byte is looked at once no matter how many delimiters
+//! there are, and the search can stop at the end of the received data and carry on from the
+//! same spot once more arrives.
+
+use std::collections::VecDeque;
+
+/// the state the automaton starts in, before any bytes of a delimiter have been seen
+const ROOT: usize = 0;
+
+/// A multi-pattern search over a byte stream
+///
+/// `transitions[state][byte]` is the state after seeing `byte` in `state`, and `found[state]`
+/// lists the patterns that end when the automaton reaches `state`
+#[derive(Debug, Clone)]
+pub struct DelimiterSearch {
+    transitions: Vec<[u32; 256]>,
+    found: Vec<Vec<usize>>,
+    pattern_lengths: Vec<usize>,
+    state: usize,
+}
+
+impl Default for DelimiterSearch {
+    fn default() -> Self {
+        DelimiterSearch::new(&[])
+    }
+}
+
+impl DelimiterSearch {
+    /// Builds the automaton for `patterns`, empty patterns are never found
+    pub fn new(patterns: &[&[u8]]) -> DelimiterSearch {
+        let mut transitions = vec![[ROOT as u32; 256]];
+        let mut found = vec![vec![]];
+        // which transitions are part of a pattern, the rest get filled in afterwards
+        let mut is_child = vec![[false; 256]];
+
+        for (pattern_index, pattern) in patterns.iter().enumerate() {
+            if pattern.is_empty() {
+                continue;
+            }
+            let mut state = ROOT;
+            for &byte in pattern.iter() {
+                if !is_child[state][byte as usize] {
+                    transitions.push([ROOT as u32; 256]);
+                    found.push(vec![]);
+                    is_child.push([false; 256]);
+                    is_child[state][byte as usize] = true;
+                    transitions[state][byte as usize] = (transitions.len() - 1) as u32;
+                }
+                state = transitions[state][byte as usize] as usize;
+            }
+            found[state].push(pattern_index);
+        }
+
+        // go through the states nearest the root first, so the state to fall back to on a
+        // mismatch (the longest suffix that is also a prefix) is always finished before it's
+        // needed. the transitions that aren't part of a pattern are copied from that state
+        let mut fallbacks = vec![ROOT; transitions.len()];
+        let mut queue = VecDeque::new();
+        for byte in 0..256 {
+            if is_child[ROOT][byte] {
+                queue.push_back(transitions[ROOT][byte] as usize);
+            }
+        }
+        while let Some(state) = queue.pop_front() {
+            let fallback_found = found[fallbacks[state]].clone();
+            found[state].extend(fallback_found);
+            for byte in 0..256 {
+                if is_child[state][byte] {
+                    let child = transitions[state][byte] as usize;
+                    fallbacks[child] = transitions[fallbacks[state]][byte] as usize;
+                    queue.push_back(child);
+                } else {
+                    transitions[state][byte] = transitions[fallbacks[state]][byte];
+                }
+            }
+        }
+
+        DelimiterSearch {
+            transitions,
+            found,
+            pattern_lengths: patterns.iter().map(|pattern| pattern.len()).collect(),
+            state: ROOT,
+        }
+    }
+
+    /// Returns the length of the longest pattern, the most bytes a partly seen pattern can span
+    pub fn longest_pattern(&self) -> usize {
+        self.pattern_lengths.iter().copied().max().unwrap_or(0)
+    }
+
+    /// Forgets any partly seen delimiter, so the next search starts fresh
+    pub fn reset(&mut self) {
+        self.state = ROOT;
+    }
+
+    /// Feeds `data[start..]` through the automaton, calling `on_found(pattern, index)` for
+    /// every pattern found, with the index in `data` that the pattern starts at
+    ///
+    /// patterns are reported in the order they end. a pattern that started before `start` is
+    /// found as long as the bytes before `start` were fed in by an earlier search, and it is
+    /// only reported if those bytes are still in `data`
+    pub fn search(&mut self, data: &[u8], start: usize, mut on_found: impl FnMut(usize, usize)) {
+        let mut state = self.state;
+        for (index, &byte) in data.iter().enumerate().skip(start) {
+            state = self.transitions[state][byte as usize] as usize;
+            for &pattern in &self.found[state] {
+                if let Some(pattern_start) = (index + 1).checked_sub(self.pattern_lengths[pattern])
+                {
+                    on_found(pattern, pattern_start);
+                }
+            }
+        }
+        self.state = state;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find_all(search: &mut DelimiterSearch, data: &[u8]) -> Vec<(usize, usize)> {
+        let mut found = vec![];
+        search.search(data, 0, |pattern, index| found.push((pattern, index)));
+        found
+    }
+
+    #[test]
+    fn overlapping_patterns_are_all_found() {
+        let mut search = DelimiterSearch::new(&[b"he", b"she", b"his", b"hers", b""]);
+        assert_eq!(
+            find_all(&mut search, b"ushers"),
+            vec![(1, 1), (0, 2), (3, 2)]
+        );
+    }
+
+    #[test]
+    fn searches_carry_on_where_they_stopped() {
+        let mut search = DelimiterSearch::new(&[&[0xBA, 0x5E, 0xBA, 0x11], &[0xBA, 0x11]]);
+        let mut data = vec![0x00, 0xBA, 0x5E];
+        assert_eq!(find_all(&mut search, &data), vec![]);
+
+        data.extend_from_slice(&[0xBA, 0x11, 0xBA]);
+        let mut found = vec![];
+        search.search(&data, 3, |pattern, index| found.push((pattern, index)));
+        assert_eq!(found, vec![(0, 1), (1, 3)]);
+
+        search.reset();
+        assert_eq!(find_all(&mut search, &[0x11]), vec![]);
+    }
+}
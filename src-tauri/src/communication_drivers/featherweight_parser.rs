@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use chrono::NaiveDate;
 
 use crate::models::packet::{Packet, PacketFieldValue};
@@ -22,6 +24,7 @@ pub fn packet_from_byte_stream(buffer: &[u8], gps_packet_id: usize) -> anyhow::R
     Ok(Packet {
         structure_id: gps_packet_id,
         field_data: arr,
+        field_labels: BTreeMap::new(),
     })
 }
 
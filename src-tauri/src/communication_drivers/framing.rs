@@ -0,0 +1,636 @@
+//! # Framing
+//!
+//! Devices that wrap each packet in a frame (COBS, SLIP or HDLC) don't need their packets to be
+//! found by scanning for delimiters, which can be fooled by payload bytes that happen to look
+//! like a delimiter. This module splits the byte stream into frames, undoes the byte stuffing,
+//! and matches each frame to a packet structure by its first byte, the type byte.
+//!
+//! A packet structure can be received in frames if it starts with a delimiter, the first byte of
+//! which is the type byte. The frame holds the whole packet, type byte included.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    communication_drivers::parser_statistics::ParserStatistics,
+    models::{packet::Packet, packet_structure::PacketStructure},
+    packet_structure_manager::PacketStructureManager,
+};
+
+/// Frames longer than this are thrown away, so a lost end of frame can't fill up memory
+const MAX_FRAME_LENGTH: usize = 4096;
+
+const SLIP_END: u8 = 0xC0;
+const SLIP_ESC: u8 = 0xDB;
+const SLIP_ESC_END: u8 = 0xDC;
+const SLIP_ESC_ESC: u8 = 0xDD;
+
+const HDLC_FLAG: u8 = 0x7E;
+const HDLC_ESC: u8 = 0x7D;
+const HDLC_ESC_XOR: u8 = 0x20;
+/// The frame check sequence of a frame with its own FCS on the end
+const HDLC_GOOD_FCS: u16 = 0xF0B8;
+
+/// How packets are wrapped on the wire
+#[derive(PartialEq, Eq, Serialize, Deserialize, Clone, Copy, Debug, Default)]
+pub enum Framing {
+    /// Packets aren't framed, they are found by their delimiters
+    #[default]
+    Delimiters,
+    /// Consistent Overhead Byte Stuffing, each frame ends with a 0x00
+    Cobs,
+    /// Serial Line Internet Protocol (RFC 1055), each frame ends with 0xC0
+    Slip,
+    /// HDLC-like framing (RFC 1662), frames are between 0x7E flags and end with a CRC-16/X.25
+    Hdlc,
+}
+
+/// Splits a byte stream into frames and undoes their byte stuffing
+#[derive(Debug, Clone, Default)]
+pub struct FrameDecoder {
+    framing: Framing,
+    // the bytes of the frame being received, already unstuffed except for COBS
+    frame: Vec<u8>,
+    // the number of bytes the frame being received took up on the wire
+    frame_raw_length: usize,
+    // the last byte was an escape byte
+    escaped: bool,
+    // the frame being received is broken, skip to the start of the next one
+    discarding: bool,
+    crc_failures: usize,
+    discarded_bytes: usize,
+}
+
+impl FrameDecoder {
+    pub fn new(framing: Framing) -> FrameDecoder {
+        FrameDecoder {
+            framing,
+            ..Default::default()
+        }
+    }
+
+    /// returns the number of frames that were thrown away because their frame CRC didn't match
+    pub fn crc_failures(&self) -> usize {
+        self.crc_failures
+    }
+
+    /// returns the number of bytes on the wire that were part of broken frames
+    pub fn discarded_bytes(&self) -> usize {
+        self.discarded_bytes
+    }
+
+    /// Decodes `data`, calling `on_frame` with the contents of every frame that ends in it
+    ///
+    /// frames can be split between calls. empty and broken frames are skipped. without framing,
+    /// the data is passed straight through
+    pub fn push(&mut self, data: &[u8], mut on_frame: impl FnMut(Vec<u8>)) {
+        if self.framing == Framing::Delimiters {
+            if !data.is_empty() {
+                on_frame(data.to_vec());
+            }
+            return;
+        }
+        for &byte in data {
+            self.frame_raw_length += 1;
+            let frame_ended = match self.framing {
+                Framing::Delimiters => unreachable!(),
+                Framing::Cobs => {
+                    if byte != 0x00 {
+                        self.frame.push(byte);
+                    }
+                    byte == 0x00
+                }
+                Framing::Slip => self.push_escaped(byte, SLIP_END, SLIP_ESC, |byte| match byte {
+                    SLIP_ESC_END => Some(SLIP_END),
+                    SLIP_ESC_ESC => Some(SLIP_ESC),
+                    _ => None,
+                }),
+                Framing::Hdlc => {
+                    self.push_escaped(byte, HDLC_FLAG, HDLC_ESC, |byte| Some(byte ^ HDLC_ESC_XOR))
+                }
+            };
+            if self.frame.len() > MAX_FRAME_LENGTH {
+                self.frame.clear();
+                self.discarding = true;
+            }
+            if !frame_ended {
+                continue;
+            }
+            let frame = std::mem::take(&mut self.frame);
+            let raw_length = std::mem::take(&mut self.frame_raw_length);
+            if std::mem::take(&mut self.discarding) {
+                self.discarded_bytes += raw_length;
+                continue;
+            }
+            if frame.is_empty() {
+                continue;
+            }
+            match self.finish_frame(frame) {
+                Some(frame) => on_frame(frame),
+                None => self.discarded_bytes += raw_length,
+            }
+        }
+    }
+
+    /// Adds a byte of a frame that uses an escape byte, returns true at the end of a frame
+    ///
+    /// `unescape` gives the byte an escape sequence stands for, or None if it isn't valid
+    fn push_escaped(
+        &mut self,
+        byte: u8,
+        end: u8,
+        escape: u8,
+        unescape: impl Fn(u8) -> Option<u8>,
+    ) -> bool {
+        if byte == end {
+            // an escape right before the end of a frame aborts it
+            self.discarding |= self.escaped;
+            self.escaped = false;
+            return true;
+        }
+        if self.escaped {
+            self.escaped = false;
+            match unescape(byte) {
+                Some(byte) => self.frame.push(byte),
+                None => self.discarding = true,
+            }
+        } else if byte == escape {
+            self.escaped = true;
+        } else {
+            self.frame.push(byte);
+        }
+        false
+    }
+
+    /// Decodes a whole frame and checks its frame CRC, returning None if it's broken
+    fn finish_frame(&mut self, frame: Vec<u8>) -> Option<Vec<u8>> {
+        match self.framing {
+            Framing::Delimiters | Framing::Slip => Some(frame),
+            Framing::Cobs => cobs_decode(&frame),
+            Framing::Hdlc => {
+                if frame.len() < 3 {
+                    return None;
+                }
+                if hdlc_fcs(&frame) != HDLC_GOOD_FCS {
+                    self.crc_failures += 1;
+                    return None;
+                }
+                let payload_length = frame.len() - 2;
+                Some(frame[..payload_length].to_vec())
+            }
+        }
+    }
+}
+
+/// Wraps a packet in a frame, so it can be sent to a device that uses `framing`
+pub fn encode_frame(framing: Framing, packet: &[u8]) -> Vec<u8> {
+    match framing {
+        Framing::Delimiters => packet.to_vec(),
+        Framing::Cobs => {
+            let mut frame = cobs_encode(packet);
+            frame.push(0x00);
+            frame
+        }
+        Framing::Slip => {
+            let mut frame = vec![];
+            for &byte in packet {
+                match byte {
+                    SLIP_END => frame.extend_from_slice(&[SLIP_ESC, SLIP_ESC_END]),
+                    SLIP_ESC => frame.extend_from_slice(&[SLIP_ESC, SLIP_ESC_ESC]),
+                    _ => frame.push(byte),
+                }
+            }
+            frame.push(SLIP_END);
+            frame
+        }
+        Framing::Hdlc => {
+            let fcs = !hdlc_fcs(packet);
+            let mut frame = vec![HDLC_FLAG];
+            for &byte in packet.iter().chain(fcs.to_le_bytes().iter()) {
+                match byte {
+                    HDLC_FLAG | HDLC_ESC => {
+                        frame.extend_from_slice(&[HDLC_ESC, byte ^ HDLC_ESC_XOR])
+                    }
+                    _ => frame.push(byte),
+                }
+            }
+            frame.push(HDLC_FLAG);
+            frame
+        }
+    }
+}
+
+/// Replaces every 0x00 in `data` with the distance to the next one, so 0x00 can end the frame
+fn cobs_encode(data: &[u8]) -> Vec<u8> {
+    let mut encoded = vec![0];
+    let mut code_index = 0;
+    for &byte in data {
+        if byte != 0x00 {
+            encoded.push(byte);
+        }
+        let block_length = encoded.len() - code_index;
+        if byte == 0x00 || block_length == 0xFF {
+            encoded[code_index] = block_length as u8;
+            code_index = encoded.len();
+            encoded.push(0);
+        }
+    }
+    encoded[code_index] = (encoded.len() - code_index) as u8;
+    encoded
+}
+
+/// Undoes `cobs_encode` on a frame without its trailing 0x00, returns None if it's malformed
+fn cobs_decode(frame: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(frame.len());
+    let mut index = 0;
+    while index < frame.len() {
+        let code = frame[index] as usize;
+        if code == 0 || index + code > frame.len() {
+            return None;
+        }
+        decoded.extend_from_slice(&frame[index + 1..index + code]);
+        index += code;
+        // a full block of 254 bytes isn't followed by a zero
+        if code < 0xFF && index < frame.len() {
+            decoded.push(0x00);
+        }
+    }
+    Some(decoded)
+}
+
+/// The HDLC frame check sequence (CRC-16/X.25), before its final inversion
+fn hdlc_fcs(data: &[u8]) -> u16 {
+    let mut fcs: u16 = 0xFFFF;
+    for &byte in data {
+        fcs ^= byte as u16;
+        for _ in 0..8 {
+            fcs = if fcs & 1 != 0 {
+                (fcs >> 1) ^ 0x8408
+            } else {
+                fcs >> 1
+            };
+        }
+    }
+    fcs
+}
+
+/// Turns framed data into packets, matching each frame to a packet structure by its type byte
+#[derive(Debug, Clone, Default)]
+pub struct FramedPacketParser {
+    decoder: FrameDecoder,
+    // frames that have been received but not parsed yet
+    frames: Vec<Vec<u8>>,
+    // the packet structures that start with each type byte, by index
+    type_structures: BTreeMap<u8, Vec<usize>>,
+    // the revision of the packet structure manager type_structures was built for
+    type_structures_revision: Option<usize>,
+    statistics: ParserStatistics,
+}
+
+impl FramedPacketParser {
+    pub fn new(framing: Framing) -> FramedPacketParser {
+        FramedPacketParser {
+            decoder: FrameDecoder::new(framing),
+            ..Default::default()
+        }
+    }
+
+    /// Returns how this parser expects packets to be wrapped
+    pub fn framing(&self) -> Framing {
+        self.decoder.framing
+    }
+
+    // adds new unparsed data
+    pub fn push_data(&mut self, data: &[u8], print_flag: bool) {
+        self.statistics.bytes_received += data.len();
+        let frames = &mut self.frames;
+        self.decoder.push(data, |frame| {
+            if print_flag {
+                println!("Frame: {:02X?}", frame);
+            }
+            frames.push(frame);
+        });
+    }
+
+    /// returns the number of frames and packets that were thrown away because their checksum
+    /// didn't match
+    pub fn crc_failures(&self) -> usize {
+        self.statistics().crc_failures()
+    }
+
+    /// returns what has been made of the data pushed so far
+    ///
+    /// frames that don't match a packet structure count as discarded by their decoded length,
+    /// broken frames by the length they took up on the wire
+    pub fn statistics(&self) -> ParserStatistics {
+        let mut statistics = self.statistics.clone();
+        statistics.frame_crc_failures = self.decoder.crc_failures();
+        statistics.bytes_discarded += self.decoder.discarded_bytes();
+        statistics
+    }
+
+    /// matches the frames received so far to packet structures, returning the parsed packets
+    ///
+    /// frames that don't match any packet structure are thrown away
+    pub fn parse_packets(
+        &mut self,
+        packet_structure_manager: &PacketStructureManager,
+        print_flag: bool,
+    ) -> anyhow::Result<Vec<Packet>> {
+        if self.type_structures_revision != Some(packet_structure_manager.revision) {
+            self.build_type_structures(packet_structure_manager);
+        }
+        let mut packets = vec![];
+        for frame in std::mem::take(&mut self.frames) {
+            let structure_indexes = match frame
+                .first()
+                .and_then(|type_byte| self.type_structures.get(type_byte))
+            {
+                Some(structure_indexes) => structure_indexes,
+                None => {
+                    if print_flag {
+                        println!("- No packet structure has this type byte");
+                    }
+                    self.statistics.bytes_discarded += frame.len();
+                    continue;
+                }
+            };
+            // the structures the frame came closest to matching
+            let mut crc_failed = None;
+            let mut cut_off = None;
+            let mut matched = false;
+            for &structure_index in structure_indexes {
+                let packet_structure = &packet_structure_manager.packet_structures[structure_index];
+                match match_frame(packet_structure, &frame) {
+                    FrameMatch::Matched => {}
+                    FrameMatch::CrcFailed => {
+                        crc_failed = crc_failed.or(Some(packet_structure.id));
+                        continue;
+                    }
+                    FrameMatch::CutOff => {
+                        cut_off = cut_off.or(Some(packet_structure.id));
+                        continue;
+                    }
+                    FrameMatch::NotMatched => continue,
+                }
+                // a field that can't be read rules out this structure, not the rest of the frames
+                let field_data = match packet_structure.parse_fields(&frame) {
+                    Ok(field_data) => field_data,
+                    Err(error) => {
+                        if print_flag {
+                            println!("- Fields could not be parsed: {}", error);
+                        }
+                        self.statistics.record_parse_failure(packet_structure.id);
+                        continue;
+                    }
+                };
+                if print_flag {
+                    println!("MATCHED: {}", packet_structure.name);
+                }
+                packets.push(Packet {
+                    structure_id: packet_structure.id,
+                    field_data,
+                    field_labels: BTreeMap::new(),
+                });
+                self.statistics.record_match(packet_structure.id);
+                matched = true;
+                break;
+            }
+            if matched {
+                continue;
+            }
+            self.statistics.bytes_discarded += frame.len();
+            if let Some(packet_structure_id) = crc_failed {
+                if print_flag {
+                    println!("- CRC check failed");
+                }
+                self.statistics.record_crc_failure(packet_structure_id);
+            } else if let Some(packet_structure_id) = cut_off {
+                if print_flag {
+                    println!("- Frame is too short");
+                }
+                self.statistics
+                    .record_partial_packet_dropped(packet_structure_id);
+            }
+        }
+        Ok(packets)
+    }
+
+    /// Finds the type byte of every searchable packet structure that starts with a delimiter
+    fn build_type_structures(&mut self, packet_structure_manager: &PacketStructureManager) {
+        self.type_structures.clear();
+        for (structure_index, packet_structure) in packet_structure_manager
+            .packet_structures
+            .iter()
+            .enumerate()
+        {
+            let layout = &packet_structure_manager.layouts[structure_index];
+            let first_delimiter = match layout.first_delimiter {
+                Some(first_delimiter)
+                    if layout.searchable && layout.first_delimiter_offset == 0 =>
+                {
+                    first_delimiter
+                }
+                _ => continue,
+            };
+            if let Some(&type_byte) = packet_structure.delimiters[first_delimiter]
+                .identifier
+                .first()
+            {
+                self.type_structures
+                    .entry(type_byte)
+                    .or_default()
+                    .push(structure_index);
+            }
+        }
+        self.type_structures_revision = Some(packet_structure_manager.revision);
+    }
+}
+
+/// What came of matching a frame to a packet structure
+enum FrameMatch {
+    Matched,
+    CrcFailed,
+    // the frame ends before the packet does
+    CutOff,
+    NotMatched,
+}
+
+/// Checks whether the frame is a whole packet of the given structure
+fn match_frame(packet_structure: &PacketStructure, frame: &[u8]) -> FrameMatch {
+    match packet_structure.packet_length(frame) {
+        Ok(Some(packet_length)) if packet_length == frame.len() => {}
+        Ok(Some(packet_length)) if packet_length > frame.len() => return FrameMatch::CutOff,
+        Ok(None) => return FrameMatch::CutOff,
+        _ => return FrameMatch::NotMatched,
+    }
+    // variable length packets can end before some of their delimiters
+    let delimiters_match = packet_structure
+        .delimiters
+        .iter()
+        .filter(|delimiter| delimiter.offset_in_packet + delimiter.identifier.len() <= frame.len())
+        .all(|delimiter| frame[delimiter.offset_in_packet..].starts_with(&delimiter.identifier));
+    if !delimiters_match {
+        return FrameMatch::NotMatched;
+    }
+    match packet_structure.check_crcs(frame) {
+        Ok(true) => FrameMatch::Matched,
+        _ => FrameMatch::CrcFailed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::packet::PacketFieldValue;
+
+    fn decode(framing: Framing, data: &[u8]) -> Vec<Vec<u8>> {
+        let mut decoder = FrameDecoder::new(framing);
+        let mut frames = vec![];
+        decoder.push(data, |frame| frames.push(frame));
+        frames
+    }
+
+    #[test]
+    fn frames_survive_encoding() {
+        let mut long_packet: Vec<u8> = (0..600).map(|byte| (byte % 7) as u8).collect();
+        long_packet.extend_from_slice(&[0xFF; 300]);
+        let packets = [
+            vec![0x01, 0x00, 0xC0, 0xDB, 0x7E, 0x7D, 0x00],
+            vec![0x00],
+            long_packet,
+        ];
+        for framing in [Framing::Cobs, Framing::Slip, Framing::Hdlc] {
+            let mut data = vec![];
+            for packet in &packets {
+                data.extend(encode_frame(framing, packet));
+            }
+            assert_eq!(decode(framing, &data), packets, "{:?}", framing);
+        }
+    }
+
+    #[test]
+    fn known_encodings() {
+        assert_eq!(
+            encode_frame(Framing::Cobs, &[0x11, 0x22, 0x00, 0x33]),
+            vec![0x03, 0x11, 0x22, 0x02, 0x33, 0x00]
+        );
+        assert_eq!(
+            encode_frame(Framing::Slip, &[0xC0, 0x01, 0xDB]),
+            vec![0xDB, 0xDC, 0x01, 0xDB, 0xDD, 0xC0]
+        );
+        // the check value of CRC-16/X.25 is 0x906E
+        let frame = encode_frame(Framing::Hdlc, b"123456789");
+        assert_eq!(&frame[frame.len() - 3..], &[0x6E, 0x90, HDLC_FLAG]);
+    }
+
+    #[test]
+    fn broken_frames_are_dropped() {
+        let mut data = encode_frame(Framing::Hdlc, &[0x01, 0x02, 0x03]);
+        data[2] ^= 0xFF;
+        data.extend(encode_frame(Framing::Hdlc, &[0x04, 0x05]));
+        let mut decoder = FrameDecoder::new(Framing::Hdlc);
+        let mut frames = vec![];
+        decoder.push(&data, |frame| frames.push(frame));
+        assert_eq!(frames, vec![vec![0x04, 0x05]]);
+        assert_eq!(decoder.crc_failures(), 1);
+
+        // the start of the stream can be the middle of a frame
+        assert_eq!(
+            decode(Framing::Slip, &[0x55, 0xDB, 0x01, 0xC0, 0x02, 0xC0]),
+            vec![vec![0x02]]
+        );
+        assert_eq!(
+            decode(Framing::Cobs, &[0x05, 0x01, 0x00, 0x02, 0x07, 0x00]),
+            vec![vec![0x07]]
+        );
+    }
+
+    #[test]
+    fn frames_are_matched_by_type_byte() {
+        let mut packet_structure_manager = PacketStructureManager::default();
+        let mut first = PacketStructure::make_default("First".to_owned());
+        first.ez_make("01 u8 u16", &[""; 2], true);
+        let first_id = packet_structure_manager
+            .register_packet_structure(&mut first)
+            .unwrap();
+        let mut second = PacketStructure::make_default("Second".to_owned());
+        second.ez_make("02 u8", &[""; 1], true);
+        let second_id = packet_structure_manager
+            .register_packet_structure(&mut second)
+            .unwrap();
+
+        let mut parser = FramedPacketParser::new(Framing::Cobs);
+        let mut data = vec![];
+        // a payload full of bytes that look like type bytes and frame ends
+        data.extend(encode_frame(Framing::Cobs, &[0x01, 0x02, 0x00, 0x00]));
+        data.extend(encode_frame(Framing::Cobs, &[0x03, 0x00]));
+        data.extend(encode_frame(Framing::Cobs, &[0x02, 0x01]));
+        data.extend(encode_frame(Framing::Cobs, &[0x02, 0x01, 0x01]));
+        let (start, end) = data.split_at(5);
+        parser.push_data(start, false);
+        assert_eq!(
+            parser
+                .parse_packets(&packet_structure_manager, false)
+                .unwrap(),
+            vec![]
+        );
+        parser.push_data(end, false);
+        let packets = parser
+            .parse_packets(&packet_structure_manager, false)
+            .unwrap();
+        assert_eq!(packets.len(), 2);
+        assert_eq!(packets[0].structure_id, first_id);
+        assert_eq!(packets[0].field_data[0], PacketFieldValue::Number(2.0));
+        assert_eq!(packets[1].structure_id, second_id);
+        assert_eq!(packets[1].field_data[0], PacketFieldValue::Number(1.0));
+
+        // one frame had no structure, and one was too long for its structure
+        let statistics = parser.statistics();
+        assert_eq!(statistics.bytes_received, data.len());
+        assert_eq!(statistics.bytes_discarded, 5);
+        assert_eq!(statistics.packets_matched(), 2);
+
+        parser.push_data(&encode_frame(Framing::Cobs, &[0x01, 0x02]), false);
+        parser
+            .parse_packets(&packet_structure_manager, false)
+            .unwrap();
+        let statistics = parser.statistics();
+        assert_eq!(statistics.structures[&first_id].partial_packets_dropped, 1);
+    }
+
+    /// test that a frame whose text isn't UTF-8 doesn't take the other frames down with it
+    #[test]
+    fn unparsable_frames_are_skipped() {
+        let mut packet_structure_manager = PacketStructureManager::default();
+        let mut packet_structure = PacketStructure::make_default("Text".to_owned());
+        packet_structure.ez_make("01 str2", &[""; 1], true);
+        let id = packet_structure_manager
+            .register_packet_structure(&mut packet_structure)
+            .unwrap();
+
+        let mut parser = FramedPacketParser::new(Framing::Slip);
+        let mut data = vec![];
+        data.extend(encode_frame(Framing::Slip, &[0x01, b'o', b'k']));
+        data.extend(encode_frame(Framing::Slip, &[0x01, 0xFF, 0xFE]));
+        data.extend(encode_frame(Framing::Slip, &[0x01, b'h', b'i']));
+        parser.push_data(&data, false);
+        let packets = parser
+            .parse_packets(&packet_structure_manager, false)
+            .unwrap();
+        assert_eq!(
+            packets
+                .iter()
+                .map(|packet| packet.field_data[0].clone())
+                .collect::<Vec<_>>(),
+            vec![
+                PacketFieldValue::String("ok".to_owned()),
+                PacketFieldValue::String("hi".to_owned()),
+            ]
+        );
+        let statistics = parser.statistics();
+        assert_eq!(statistics.structures[&id].parse_failures, 1);
+        assert_eq!(statistics.bytes_discarded, 3);
+    }
+}
@@ -0,0 +1,215 @@
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{anyhow, bail, Context};
+
+use crate::{
+    communication_manager::CommsIF,
+    file_handling::log_handlers::BASE_DIRECTORY,
+    models::{packet::Packet, packet_structure::PacketStructure},
+    packet_structure_manager::PacketStructureManager,
+    state::mutex_utils::use_state_in_mutex,
+};
+
+use super::{
+    mavlink_dialect::{MavlinkDialect, BUILT_IN_DIALECT},
+    mavlink_parser::MavlinkParser,
+};
+
+/// the folder in the WINGS data folder that dialect files are loaded from
+const DIALECT_DIRECTORY: &str = "mavlink_dialects";
+
+/// A serial port device that speaks MAVLink 1 or 2, like a telemetry radio or an autopilot
+///
+/// every message of the loaded dialect is registered as a packet structure named
+/// "MAVLink {MESSAGE_NAME}"
+#[derive(Default)]
+pub struct MavlinkAdapter {
+    port: Option<Box<dyn serialport::SerialPort>>,
+    parser: MavlinkParser,
+    baud: u32,
+    id: usize,
+}
+
+impl CommsIF for MavlinkAdapter {
+    ///creates a new instance of a comms device with the given packet structure manager
+    ///
+    /// the dialect is read from the `.xml` files in the dialect folder of the WINGS data folder,
+    /// or the built in messages if there aren't any
+    fn new(packet_structure_manager: Arc<Mutex<PacketStructureManager>>) -> Self
+    where
+        Self: Sized,
+    {
+        let dialect = load_dialect().unwrap_or_else(|err| {
+            eprintln!(
+                "{:?}",
+                err.context("Failed to load MAVLink dialects, using the built in messages")
+            );
+            built_in_dialect()
+        });
+        let structures = use_state_in_mutex(&packet_structure_manager, &mut |ps_manager| {
+            dialect
+                .messages
+                .iter()
+                .map(|message| register(ps_manager, dialect.packet_structure(message)))
+                .collect()
+        });
+        MavlinkAdapter {
+            port: None,
+            parser: MavlinkParser::new(&dialect, structures),
+            baud: 57600,
+            id: 0,
+        }
+    }
+
+    /// Attempts to set the port for comms with the MAVLink device
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if port_name is invalid, or if unable to clear the device buffer
+    fn init_device(&mut self, port_name: &str, baud: u32) -> anyhow::Result<()> {
+        if port_name.is_empty() {
+            self.port = None;
+        } else {
+            self.baud = baud;
+            let mut new_port = serialport::new(port_name, self.baud).open()?;
+            new_port.clear(serialport::ClearBuffer::All)?;
+            // Short non-zero timeout is needed to receive data from the serialport when
+            // the buffer isn't full yet.
+            new_port.set_timeout(std::time::Duration::from_millis(1))?;
+            self.port = Some(new_port);
+        }
+        Ok(())
+    }
+
+    /// Attempt to write bytes to the MAVLink device
+    ///
+    /// # Errors
+    ///
+    /// returns an error if the device isn't initialized
+    fn write_port(&mut self, packet: &[u8]) -> anyhow::Result<()> {
+        let port = match self.port.as_mut() {
+            Some(some_port) => some_port,
+            None => bail!("No active MAVLink port"),
+        };
+
+        port.write_all(packet)?;
+        Ok(())
+    }
+
+    /// Returns true if there is an active port
+    fn is_init(&self) -> bool {
+        self.port.is_some()
+    }
+    fn set_id(&mut self, id: usize) {
+        self.id = id;
+    }
+    fn get_id(&self) -> usize {
+        self.id
+    }
+
+    fn get_type(&self) -> String {
+        "MAVLink".to_owned()
+    }
+
+    fn get_device_raw_data(&mut self, data_vector: &mut Vec<u8>) -> anyhow::Result<()> {
+        let active_port = match self.port.as_mut() {
+            Some(port) => port,
+            None => bail!("No read port has been set"),
+        };
+
+        let mut buffer = [0; 4096];
+        let bytes_read = active_port.read(&mut buffer)?;
+
+        data_vector.extend_from_slice(&buffer[..bytes_read]);
+        Ok(())
+    }
+
+    fn parse_device_data(
+        &mut self,
+        data_vector: &mut Vec<u8>,
+        packet_vector: &mut Vec<Packet>,
+    ) -> anyhow::Result<()> {
+        self.parser.push_data(data_vector);
+        packet_vector.extend(self.parser.parse_packets());
+        Ok(())
+    }
+}
+
+fn built_in_dialect() -> MavlinkDialect {
+    MavlinkDialect::parse(BUILT_IN_DIALECT, &mut |name| {
+        bail!("the built in dialect can't include {}", name)
+    })
+    .expect("the built in MAVLink dialect is invalid")
+}
+
+/// Reads every dialect file in the dialect folder, with includes looked up in the same folder
+///
+/// messages defined by more than one file (ie. when both a dialect and a file it includes are in
+/// the folder) are taken from the first file they are found in
+fn load_dialect() -> anyhow::Result<MavlinkDialect> {
+    let mut directory =
+        tauri::api::path::data_dir().ok_or(anyhow!("no data dir found on this system"))?;
+    directory.push(BASE_DIRECTORY);
+    directory.push(DIALECT_DIRECTORY);
+    if !directory.exists() {
+        return Ok(built_in_dialect());
+    }
+    let mut paths: Vec<PathBuf> = fs::read_dir(&directory)
+        .with_context(|| format!("Failed to read {}", directory.display()))?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "xml"))
+        .collect();
+    if paths.is_empty() {
+        return Ok(built_in_dialect());
+    }
+    paths.sort();
+    let mut dialect = MavlinkDialect::default();
+    for path in paths {
+        let xml = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let file_dialect = MavlinkDialect::parse(&xml, &mut |name| {
+            Ok(fs::read_to_string(directory.join(name))?)
+        })
+        .with_context(|| format!("Failed to load {}", path.display()))?;
+        for message in file_dialect.messages {
+            if !dialect.messages.iter().any(|m| m.id == message.id) {
+                dialect.messages.push(message);
+            }
+        }
+        for (name, labels) in file_dialect.enums {
+            dialect.enums.entry(name).or_default().extend(labels);
+        }
+    }
+    Ok(dialect)
+}
+
+/// Registers the structure of a message, replacing any structure already registered under its
+/// name so changes to the dialect are picked up
+fn register(
+    ps_manager: &mut PacketStructureManager,
+    mut structure: PacketStructure,
+) -> PacketStructure {
+    let existing_id = ps_manager
+        .packet_structures
+        .iter()
+        .find(|existing| existing.name == structure.name)
+        .map(|existing| existing.id);
+    match existing_id {
+        Some(id) => {
+            structure.id = id;
+            if let Err(err) = ps_manager.update_packet_structure(structure.clone()) {
+                eprintln!("Failed to update {}: {:?}", structure.name, err);
+            }
+        }
+        None => {
+            if let Err(err) = ps_manager.register_packet_structure(&mut structure) {
+                eprintln!("Failed to register {}: {:?}", structure.name, err);
+            }
+        }
+    }
+    structure
+}
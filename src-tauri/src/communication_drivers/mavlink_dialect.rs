@@ -0,0 +1,908 @@
+//! # MAVLink Dialects
+//!
+//! Reads MAVLink dialect XML files (like `common.xml` from the mavlink repository) into message
+//! definitions, and turns every message into a `PacketStructure` describing its payload.
+//!
+//! - `<include>`s are followed through the `read_include` callback, files already read are skipped
+//! - field types are the MAVLink base types and arrays of them, `char[N]` becomes a fixed length
+//!   string
+//! - fields after `<extensions/>` are kept in their XML order at the end of the payload, all other
+//!   fields are sent largest type first
+//! - enums give names to the values of the fields that use them, bitmask enums are left unnamed
+//!
+//! `CRC_EXTRA`, the byte each message mixes into its checksum to catch senders with a different
+//! definition of it, is worked out from the definition the same way the MAVLink generator does.
+
+use std::collections::{BTreeMap, HashSet};
+
+use anyhow::{anyhow, bail, Context};
+
+use crate::models::packet_structure::{
+    Endianness, PacketArray, PacketField, PacketFieldType, PacketStructure, StringFormat,
+};
+
+/// The messages of `common.xml` most radios and autopilots send, used when no dialect files
+/// have been added
+pub const BUILT_IN_DIALECT: &str = r#"<?xml version="1.0"?>
+<mavlink>
+  <version>3</version>
+  <enums>
+    <enum name="MAV_STATE">
+      <entry value="0" name="MAV_STATE_UNINIT"/>
+      <entry name="MAV_STATE_BOOT"/>
+      <entry name="MAV_STATE_CALIBRATING"/>
+      <entry name="MAV_STATE_STANDBY"/>
+      <entry name="MAV_STATE_ACTIVE"/>
+      <entry name="MAV_STATE_CRITICAL"/>
+      <entry name="MAV_STATE_EMERGENCY"/>
+      <entry name="MAV_STATE_POWEROFF"/>
+      <entry name="MAV_STATE_FLIGHT_TERMINATION"/>
+    </enum>
+    <enum name="MAV_SEVERITY">
+      <entry value="0" name="MAV_SEVERITY_EMERGENCY"/>
+      <entry value="1" name="MAV_SEVERITY_ALERT"/>
+      <entry value="2" name="MAV_SEVERITY_CRITICAL"/>
+      <entry value="3" name="MAV_SEVERITY_ERROR"/>
+      <entry value="4" name="MAV_SEVERITY_WARNING"/>
+      <entry value="5" name="MAV_SEVERITY_NOTICE"/>
+      <entry value="6" name="MAV_SEVERITY_INFO"/>
+      <entry value="7" name="MAV_SEVERITY_DEBUG"/>
+    </enum>
+    <enum name="GPS_FIX_TYPE">
+      <entry value="0" name="GPS_FIX_TYPE_NO_GPS"/>
+      <entry value="1" name="GPS_FIX_TYPE_NO_FIX"/>
+      <entry value="2" name="GPS_FIX_TYPE_2D_FIX"/>
+      <entry value="3" name="GPS_FIX_TYPE_3D_FIX"/>
+      <entry value="4" name="GPS_FIX_TYPE_DGPS"/>
+      <entry value="5" name="GPS_FIX_TYPE_RTK_FLOAT"/>
+      <entry value="6" name="GPS_FIX_TYPE_RTK_FIXED"/>
+      <entry value="7" name="GPS_FIX_TYPE_STATIC"/>
+      <entry value="8" name="GPS_FIX_TYPE_PPP"/>
+    </enum>
+  </enums>
+  <messages>
+    <message id="0" name="HEARTBEAT">
+      <field type="uint8_t" name="type" enum="MAV_TYPE"/>
+      <field type="uint8_t" name="autopilot" enum="MAV_AUTOPILOT"/>
+      <field type="uint8_t" name="base_mode" enum="MAV_MODE_FLAG" display="bitmask"/>
+      <field type="uint32_t" name="custom_mode"/>
+      <field type="uint8_t" name="system_status" enum="MAV_STATE"/>
+      <field type="uint8_t_mavlink_version" name="mavlink_version"/>
+    </message>
+    <message id="1" name="SYS_STATUS">
+      <field type="uint32_t" name="onboard_control_sensors_present" enum="MAV_SYS_STATUS_SENSOR" display="bitmask"/>
+      <field type="uint32_t" name="onboard_control_sensors_enabled" enum="MAV_SYS_STATUS_SENSOR" display="bitmask"/>
+      <field type="uint32_t" name="onboard_control_sensors_health" enum="MAV_SYS_STATUS_SENSOR" display="bitmask"/>
+      <field type="uint16_t" name="load" units="d%"/>
+      <field type="uint16_t" name="voltage_battery" units="mV"/>
+      <field type="int16_t" name="current_battery" units="cA"/>
+      <field type="int8_t" name="battery_remaining" units="%"/>
+      <field type="uint16_t" name="drop_rate_comm" units="c%"/>
+      <field type="uint16_t" name="errors_comm"/>
+      <field type="uint16_t" name="errors_count1"/>
+      <field type="uint16_t" name="errors_count2"/>
+      <field type="uint16_t" name="errors_count3"/>
+      <field type="uint16_t" name="errors_count4"/>
+      <extensions/>
+      <field type="uint32_t" name="onboard_control_sensors_present_extended" enum="MAV_SYS_STATUS_SENSOR_EXTENDED" display="bitmask"/>
+      <field type="uint32_t" name="onboard_control_sensors_enabled_extended" enum="MAV_SYS_STATUS_SENSOR_EXTENDED" display="bitmask"/>
+      <field type="uint32_t" name="onboard_control_sensors_health_extended" enum="MAV_SYS_STATUS_SENSOR_EXTENDED" display="bitmask"/>
+    </message>
+    <message id="24" name="GPS_RAW_INT">
+      <field type="uint64_t" name="time_usec" units="us"/>
+      <field type="uint8_t" name="fix_type" enum="GPS_FIX_TYPE"/>
+      <field type="int32_t" name="lat" units="degE7"/>
+      <field type="int32_t" name="lon" units="degE7"/>
+      <field type="int32_t" name="alt" units="mm"/>
+      <field type="uint16_t" name="eph"/>
+      <field type="uint16_t" name="epv"/>
+      <field type="uint16_t" name="vel" units="cm/s"/>
+      <field type="uint16_t" name="cog" units="cdeg"/>
+      <field type="uint8_t" name="satellites_visible"/>
+      <extensions/>
+      <field type="int32_t" name="alt_ellipsoid" units="mm"/>
+      <field type="uint32_t" name="h_acc" units="mm"/>
+      <field type="uint32_t" name="v_acc" units="mm"/>
+      <field type="uint32_t" name="vel_acc" units="mm"/>
+      <field type="uint32_t" name="hdg_acc" units="degE5"/>
+      <field type="uint16_t" name="yaw" units="cdeg"/>
+    </message>
+    <message id="30" name="ATTITUDE">
+      <field type="uint32_t" name="time_boot_ms" units="ms"/>
+      <field type="float" name="roll" units="rad"/>
+      <field type="float" name="pitch" units="rad"/>
+      <field type="float" name="yaw" units="rad"/>
+      <field type="float" name="rollspeed" units="rad/s"/>
+      <field type="float" name="pitchspeed" units="rad/s"/>
+      <field type="float" name="yawspeed" units="rad/s"/>
+    </message>
+    <message id="33" name="GLOBAL_POSITION_INT">
+      <field type="uint32_t" name="time_boot_ms" units="ms"/>
+      <field type="int32_t" name="lat" units="degE7"/>
+      <field type="int32_t" name="lon" units="degE7"/>
+      <field type="int32_t" name="alt" units="mm"/>
+      <field type="int32_t" name="relative_alt" units="mm"/>
+      <field type="int16_t" name="vx" units="cm/s"/>
+      <field type="int16_t" name="vy" units="cm/s"/>
+      <field type="int16_t" name="vz" units="cm/s"/>
+      <field type="uint16_t" name="hdg" units="cdeg"/>
+    </message>
+    <message id="74" name="VFR_HUD">
+      <field type="float" name="airspeed" units="m/s"/>
+      <field type="float" name="groundspeed" units="m/s"/>
+      <field type="int16_t" name="heading" units="deg"/>
+      <field type="uint16_t" name="throttle" units="%"/>
+      <field type="float" name="alt" units="m"/>
+      <field type="float" name="climb" units="m/s"/>
+    </message>
+    <message id="109" name="RADIO_STATUS">
+      <field type="uint8_t" name="rssi"/>
+      <field type="uint8_t" name="remrssi"/>
+      <field type="uint8_t" name="txbuf" units="%"/>
+      <field type="uint8_t" name="noise"/>
+      <field type="uint8_t" name="remnoise"/>
+      <field type="uint16_t" name="rxerrors"/>
+      <field type="uint16_t" name="fixed"/>
+    </message>
+    <message id="253" name="STATUSTEXT">
+      <field type="uint8_t" name="severity" enum="MAV_SEVERITY"/>
+      <field type="char[50]" name="text"/>
+      <extensions/>
+      <field type="uint16_t" name="id"/>
+      <field type="uint8_t" name="chunk_seq"/>
+    </message>
+  </messages>
+</mavlink>
+"#;
+
+/// Every message and named enum read from a dialect file and the files it includes
+#[derive(Debug, Default, Clone)]
+pub struct MavlinkDialect {
+    pub(crate) messages: Vec<MavlinkMessage>,
+    /// enum name to the names of its values, bitmask enums aren't kept
+    pub(crate) enums: BTreeMap<String, BTreeMap<i64, String>>,
+}
+
+/// A `<message>` of a dialect, with its fields in the order they were written
+#[derive(Debug, PartialEq, Clone)]
+pub struct MavlinkMessage {
+    pub(crate) id: u32,
+    pub(crate) name: String,
+    pub(crate) fields: Vec<MavlinkField>,
+}
+
+/// A `<field>` of a message
+///
+/// `extension` is set for fields after `<extensions/>`, which aren't part of `CRC_EXTRA` and are
+/// left out by MAVLink 1 senders
+#[derive(Debug, PartialEq, Clone)]
+pub struct MavlinkField {
+    pub(crate) name: String,
+    pub(crate) r#type: MavlinkType,
+    pub(crate) array_length: Option<usize>,
+    pub(crate) units: Option<String>,
+    pub(crate) r#enum: Option<String>,
+    pub(crate) extension: bool,
+}
+
+/// The base types a MAVLink field can be
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum MavlinkType {
+    Char,
+    UInt8,
+    Int8,
+    UInt16,
+    Int16,
+    UInt32,
+    Int32,
+    UInt64,
+    Int64,
+    Float,
+    Double,
+    /// a `uint8_t` filled in with the protocol version by the sender
+    MavlinkVersion,
+}
+
+impl MavlinkType {
+    fn from_name(name: &str) -> Option<MavlinkType> {
+        Some(match name {
+            "char" => MavlinkType::Char,
+            "uint8_t" => MavlinkType::UInt8,
+            "int8_t" => MavlinkType::Int8,
+            "uint16_t" => MavlinkType::UInt16,
+            "int16_t" => MavlinkType::Int16,
+            "uint32_t" => MavlinkType::UInt32,
+            "int32_t" => MavlinkType::Int32,
+            "uint64_t" => MavlinkType::UInt64,
+            "int64_t" => MavlinkType::Int64,
+            "float" => MavlinkType::Float,
+            "double" => MavlinkType::Double,
+            "uint8_t_mavlink_version" => MavlinkType::MavlinkVersion,
+            _ => return None,
+        })
+    }
+
+    /// the name this type goes by in `CRC_EXTRA`
+    fn crc_name(&self) -> &'static str {
+        match self {
+            MavlinkType::Char => "char",
+            MavlinkType::UInt8 | MavlinkType::MavlinkVersion => "uint8_t",
+            MavlinkType::Int8 => "int8_t",
+            MavlinkType::UInt16 => "uint16_t",
+            MavlinkType::Int16 => "int16_t",
+            MavlinkType::UInt32 => "uint32_t",
+            MavlinkType::Int32 => "int32_t",
+            MavlinkType::UInt64 => "uint64_t",
+            MavlinkType::Int64 => "int64_t",
+            MavlinkType::Float => "float",
+            MavlinkType::Double => "double",
+        }
+    }
+
+    fn field_type(&self) -> PacketFieldType {
+        match self {
+            MavlinkType::Char => PacketFieldType::String,
+            MavlinkType::UInt8 | MavlinkType::MavlinkVersion => PacketFieldType::UnsignedByte,
+            MavlinkType::Int8 => PacketFieldType::SignedByte,
+            MavlinkType::UInt16 => PacketFieldType::UnsignedShort,
+            MavlinkType::Int16 => PacketFieldType::SignedShort,
+            MavlinkType::UInt32 => PacketFieldType::UnsignedInteger,
+            MavlinkType::Int32 => PacketFieldType::SignedInteger,
+            MavlinkType::UInt64 => PacketFieldType::UnsignedLong,
+            MavlinkType::Int64 => PacketFieldType::SignedLong,
+            MavlinkType::Float => PacketFieldType::Float,
+            MavlinkType::Double => PacketFieldType::Double,
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            MavlinkType::Char | MavlinkType::UInt8 | MavlinkType::Int8 => 1,
+            MavlinkType::MavlinkVersion => 1,
+            MavlinkType::UInt16 | MavlinkType::Int16 => 2,
+            MavlinkType::UInt32 | MavlinkType::Int32 | MavlinkType::Float => 4,
+            MavlinkType::UInt64 | MavlinkType::Int64 | MavlinkType::Double => 8,
+        }
+    }
+}
+
+impl MavlinkField {
+    /// the number of bytes the field takes up in the payload
+    fn size(&self) -> usize {
+        self.r#type.size() * self.array_length.unwrap_or(1)
+    }
+}
+
+/// Adds `bytes` to a running X.25 (CRC-16/MCRF4XX) checksum, the checksum MAVLink frames use
+///
+/// a new checksum starts at 0xFFFF
+pub fn crc_x25(mut crc: u16, bytes: &[u8]) -> u16 {
+    for byte in bytes {
+        let mut tmp = byte ^ (crc as u8);
+        tmp ^= tmp << 4;
+        let tmp = tmp as u16;
+        crc = (crc >> 8) ^ (tmp << 8) ^ (tmp << 3) ^ (tmp >> 4);
+    }
+    crc
+}
+
+impl MavlinkMessage {
+    /// Returns the fields in the order they are sent
+    ///
+    /// fields before `<extensions/>` are sorted largest type first (keeping their order
+    /// otherwise), extensions follow in the order they were written
+    pub fn wire_order(&self) -> Vec<&MavlinkField> {
+        let mut base: Vec<&MavlinkField> = self.fields.iter().filter(|f| !f.extension).collect();
+        base.sort_by_key(|field| std::cmp::Reverse(field.r#type.size()));
+        base.extend(self.fields.iter().filter(|f| f.extension));
+        base
+    }
+
+    /// Returns the number of bytes in the payload with every field sent
+    pub fn payload_length(&self) -> usize {
+        self.fields.iter().map(MavlinkField::size).sum()
+    }
+
+    /// Returns the byte mixed into the checksum of this message's frames
+    pub fn crc_extra(&self) -> u8 {
+        let mut crc = crc_x25(0xFFFF, format!("{} ", self.name).as_bytes());
+        for field in self.wire_order().into_iter().filter(|f| !f.extension) {
+            crc = crc_x25(crc, format!("{} ", field.r#type.crc_name()).as_bytes());
+            crc = crc_x25(crc, format!("{} ", field.name).as_bytes());
+            if let Some(length) = field.array_length {
+                crc = crc_x25(crc, &[length as u8]);
+            }
+        }
+        (crc & 0xFF) as u8 ^ (crc >> 8) as u8
+    }
+}
+
+impl MavlinkDialect {
+    /// Reads a dialect file, and every file it includes
+    ///
+    /// `read_include` is given the file name of each `<include>` and returns the text of that file
+    ///
+    /// # Errors
+    ///
+    /// errors if any of the files can't be read or aren't valid dialects, or if two messages share
+    /// an id
+    pub fn parse(
+        xml: &str,
+        read_include: &mut dyn FnMut(&str) -> anyhow::Result<String>,
+    ) -> anyhow::Result<MavlinkDialect> {
+        let mut dialect = MavlinkDialect::default();
+        dialect.read(xml, read_include, &mut HashSet::new())?;
+        Ok(dialect)
+    }
+
+    fn read(
+        &mut self,
+        xml: &str,
+        read_include: &mut dyn FnMut(&str) -> anyhow::Result<String>,
+        included: &mut HashSet<String>,
+    ) -> anyhow::Result<()> {
+        let root = XmlReader::new(xml).document()?;
+        if root.name != "mavlink" {
+            bail!(
+                "line {}: expected <mavlink>, found <{}>",
+                root.line,
+                root.name
+            );
+        }
+        for include in root.children_named("include") {
+            let file_name = include.text.trim();
+            if !included.insert(file_name.to_owned()) {
+                continue;
+            }
+            let include_text = read_include(file_name)
+                .with_context(|| format!("Failed to read included file {}", file_name))?;
+            self.read(&include_text, read_include, included)
+                .with_context(|| format!("Failed to load included file {}", file_name))?;
+        }
+        for enums in root.children_named("enums") {
+            for element in enums.children_named("enum") {
+                self.read_enum(element)?;
+            }
+        }
+        for messages in root.children_named("messages") {
+            for element in messages.children_named("message") {
+                let message = read_message(element)?;
+                if let Some(existing) = self.messages.iter().find(|m| m.id == message.id) {
+                    bail!(
+                        "line {}: message id {} is used by both {} and {}",
+                        element.line,
+                        message.id,
+                        existing.name,
+                        message.name
+                    );
+                }
+                self.messages.push(message);
+            }
+        }
+        Ok(())
+    }
+
+    /// adds the entries of an `<enum>` to any entries it already has from other files
+    fn read_enum(&mut self, element: &XmlElement) -> anyhow::Result<()> {
+        let name = element.required_attribute("name")?;
+        if element.attribute("bitmask") == Some("true") {
+            return Ok(());
+        }
+        let labels = self.enums.entry(name.to_owned()).or_default();
+        let mut next_value = 0;
+        for entry in element.children_named("entry") {
+            let value = match entry.attribute("value") {
+                Some(value) => parse_integer(value)
+                    .ok_or_else(|| anyhow!("line {}: bad enum value \"{}\"", entry.line, value))?,
+                None => next_value,
+            };
+            labels.insert(value, entry.required_attribute("name")?.to_owned());
+            next_value = value + 1;
+        }
+        Ok(())
+    }
+
+    /// Returns the packet structure a message's payload is parsed with
+    ///
+    /// the structure isn't `byte_defined`, MAVLink frames are found by `MavlinkParser` and only
+    /// their payloads are handed to the structure
+    pub fn packet_structure(&self, message: &MavlinkMessage) -> PacketStructure {
+        let mut offsets = BTreeMap::new();
+        let mut offset = 0;
+        for field in message.wire_order() {
+            offsets.insert(field.name.as_str(), offset);
+            offset += field.size();
+        }
+        let fields = message
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(index, field)| {
+                let is_string = field.r#type == MavlinkType::Char;
+                PacketField {
+                    index,
+                    name: field.name.clone(),
+                    r#type: field.r#type.field_type(),
+                    offset_in_packet: offsets[field.name.as_str()],
+                    endianness: Endianness::Little,
+                    bitfield: None,
+                    string_format: is_string.then(|| StringFormat::Fixed {
+                        length: field.array_length.unwrap_or(1),
+                    }),
+                    array: field
+                        .array_length
+                        .filter(|_| !is_string)
+                        .map(|count| PacketArray {
+                            count,
+                            stride: field.r#type.size(),
+                            count_field: None,
+                            group_size: 0,
+                        }),
+                    calibration: None,
+                    unit: field.units.clone(),
+                    labels: field
+                        .r#enum
+                        .as_ref()
+                        .and_then(|name| self.enums.get(name))
+                        .cloned()
+                        .unwrap_or_default(),
+                    timestamp: None,
+                    sequence: None,
+                    element_names: vec![],
+                }
+            })
+            .collect();
+        let mut structure = PacketStructure::make_default(format!("MAVLink {}", message.name));
+        structure.fields = fields;
+        structure.size = Some(message.payload_length());
+        structure
+    }
+}
+
+fn read_message(element: &XmlElement) -> anyhow::Result<MavlinkMessage> {
+    let id_text = element.required_attribute("id")?;
+    let id = id_text
+        .parse()
+        .map_err(|_| anyhow!("line {}: bad message id \"{}\"", element.line, id_text))?;
+    let name = element.required_attribute("name")?.to_owned();
+    let mut fields = vec![];
+    let mut extension = false;
+    for child in &element.children {
+        match child.name.as_str() {
+            "extensions" => extension = true,
+            "field" => fields.push(read_field(child, extension)?),
+            _ => {}
+        }
+    }
+    let message = MavlinkMessage { id, name, fields };
+    if message.payload_length() > 255 {
+        bail!(
+            "line {}: {} is {} bytes long, payloads can be at most 255",
+            element.line,
+            message.name,
+            message.payload_length()
+        );
+    }
+    Ok(message)
+}
+
+fn read_field(element: &XmlElement, extension: bool) -> anyhow::Result<MavlinkField> {
+    let type_text = element.required_attribute("type")?;
+    let bad_type = || {
+        anyhow!(
+            "line {}: unknown field type \"{}\"",
+            element.line,
+            type_text
+        )
+    };
+    let (type_name, array_length) = match type_text.split_once('[') {
+        Some((type_name, length)) => {
+            let length = length
+                .strip_suffix(']')
+                .and_then(|length| length.parse::<usize>().ok())
+                .filter(|length| (1..=255).contains(length))
+                .ok_or_else(bad_type)?;
+            (type_name, Some(length))
+        }
+        None => (type_text, None),
+    };
+    Ok(MavlinkField {
+        name: element.required_attribute("name")?.to_owned(),
+        r#type: MavlinkType::from_name(type_name).ok_or_else(bad_type)?,
+        array_length,
+        units: element.attribute("units").map(str::to_owned),
+        r#enum: element.attribute("enum").map(str::to_owned),
+        extension,
+    })
+}
+
+/// parses a decimal or 0x prefixed hexadecimal number, or a power of 2 written as "2**N"
+fn parse_integer(text: &str) -> Option<i64> {
+    let text = text.trim();
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16).ok()
+    } else if let Some(exponent) = text.strip_prefix("2**") {
+        exponent
+            .parse::<u32>()
+            .ok()
+            .and_then(|e| 2i64.checked_pow(e))
+    } else {
+        text.parse().ok()
+    }
+}
+
+/// An element of an XML document, with the text directly inside it joined together
+#[derive(Debug, Default)]
+struct XmlElement {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<XmlElement>,
+    text: String,
+    line: usize,
+}
+
+impl XmlElement {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn required_attribute(&self, name: &str) -> anyhow::Result<&str> {
+        self.attribute(name).ok_or_else(|| {
+            anyhow!(
+                "line {}: <{}> is missing its \"{}\" attribute",
+                self.line,
+                self.name,
+                name
+            )
+        })
+    }
+
+    fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlElement> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+}
+
+/// Just enough of an XML reader for dialect files
+///
+/// reads elements, attributes, text, CDATA and the predefined and numeric entities, and skips
+/// comments, processing instructions and the doctype
+struct XmlReader<'a> {
+    text: &'a str,
+    position: usize,
+    line: usize,
+}
+
+impl<'a> XmlReader<'a> {
+    fn new(text: &'a str) -> XmlReader<'a> {
+        XmlReader {
+            text,
+            position: 0,
+            line: 1,
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.position..]
+    }
+
+    fn advance(&mut self, length: usize) -> &'a str {
+        let skipped = &self.text[self.position..self.position + length];
+        self.line += skipped.matches('\n').count();
+        self.position += length;
+        skipped
+    }
+
+    /// skips past the next `end`, returning everything before it
+    fn take_until(&mut self, end: &str) -> anyhow::Result<&'a str> {
+        let line = self.line;
+        let length = self.rest().find(end).ok_or_else(|| {
+            anyhow!(
+                "line {}: expected \"{}\" before the end of the file",
+                line,
+                end
+            )
+        })?;
+        let taken = self.advance(length);
+        self.advance(end.len());
+        Ok(taken)
+    }
+
+    fn skip_whitespace(&mut self) {
+        let length = self.rest().len() - self.rest().trim_start().len();
+        self.advance(length);
+    }
+
+    fn expect(&mut self, expected: &str) -> anyhow::Result<()> {
+        if !self.rest().starts_with(expected) {
+            bail!("line {}: expected \"{}\"", self.line, expected);
+        }
+        self.advance(expected.len());
+        Ok(())
+    }
+
+    /// skips a comment, processing instruction or doctype, returning false if there isn't one
+    fn skip_markup(&mut self) -> anyhow::Result<bool> {
+        if self.rest().starts_with("<!--") {
+            self.take_until("-->")?;
+        } else if self.rest().starts_with("<?") {
+            self.take_until("?>")?;
+        } else if self.rest().starts_with("<!DOCTYPE") {
+            self.take_until(">")?;
+        } else {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    fn name(&mut self) -> anyhow::Result<&'a str> {
+        let length = self
+            .rest()
+            .find(|c: char| !(c.is_alphanumeric() || "_-.:".contains(c)))
+            .unwrap_or(self.rest().len());
+        if length == 0 {
+            bail!("line {}: expected a name", self.line);
+        }
+        Ok(self.advance(length))
+    }
+
+    /// reads the root element of the document
+    fn document(&mut self) -> anyhow::Result<XmlElement> {
+        loop {
+            self.skip_whitespace();
+            if !self.skip_markup()? {
+                break;
+            }
+        }
+        let root = self.element()?;
+        loop {
+            self.skip_whitespace();
+            if !self.skip_markup()? {
+                break;
+            }
+        }
+        if !self.rest().is_empty() {
+            bail!(
+                "line {}: unexpected content after the root element",
+                self.line
+            );
+        }
+        Ok(root)
+    }
+
+    fn element(&mut self) -> anyhow::Result<XmlElement> {
+        let mut element = XmlElement {
+            line: self.line,
+            ..Default::default()
+        };
+        self.expect("<")?;
+        element.name = self.name()?.to_owned();
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.advance(2);
+                return Ok(element);
+            }
+            if self.rest().starts_with('>') {
+                self.advance(1);
+                break;
+            }
+            let key = self.name()?.to_owned();
+            self.skip_whitespace();
+            self.expect("=")?;
+            self.skip_whitespace();
+            let line = self.line;
+            let value = if self.rest().starts_with('"') {
+                self.advance(1);
+                self.take_until("\"")?
+            } else if self.rest().starts_with('\'') {
+                self.advance(1);
+                self.take_until("'")?
+            } else {
+                bail!("line {}: attribute values must be quoted", line);
+            };
+            element.attributes.push((key, unescape(value, line)?));
+        }
+        loop {
+            if self.rest().is_empty() {
+                bail!("line {}: <{}> is never closed", element.line, element.name);
+            } else if self.rest().starts_with("</") {
+                self.advance(2);
+                let line = self.line;
+                let name = self.name()?;
+                if name != element.name {
+                    bail!(
+                        "line {}: expected </{}>, found </{}>",
+                        line,
+                        element.name,
+                        name
+                    );
+                }
+                self.skip_whitespace();
+                self.expect(">")?;
+                return Ok(element);
+            } else if self.rest().starts_with("<![CDATA[") {
+                self.advance("<![CDATA[".len());
+                element.text.push_str(self.take_until("]]>")?);
+            } else if self.skip_markup()? {
+            } else if self.rest().starts_with('<') {
+                element.children.push(self.element()?);
+            } else {
+                let line = self.line;
+                let length = self.rest().find('<').unwrap_or(self.rest().len());
+                element
+                    .text
+                    .push_str(&unescape(self.advance(length), line)?);
+            }
+        }
+    }
+}
+
+/// replaces the entities in `text`
+fn unescape(text: &str, line: usize) -> anyhow::Result<String> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find(';')
+            .ok_or_else(|| anyhow!("line {}: unterminated entity", line))?;
+        let entity = &rest[start + 1..start + end];
+        let character = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => match entity.strip_prefix("#x") {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => entity.strip_prefix('#').and_then(|dec| dec.parse().ok()),
+            }
+            .and_then(char::from_u32),
+        };
+        unescaped
+            .push(character.ok_or_else(|| anyhow!("line {}: unknown entity &{};", line, entity))?);
+        rest = &rest[start + end + 1..];
+    }
+    unescaped.push_str(rest);
+    Ok(unescaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn built_in() -> MavlinkDialect {
+        MavlinkDialect::parse(BUILT_IN_DIALECT, &mut |name| bail!("no file {}", name)).unwrap()
+    }
+
+    #[test]
+    fn crc_extra_matches_the_mavlink_generator() {
+        let dialect = built_in();
+        let crc_extras: Vec<(u32, u8)> = dialect
+            .messages
+            .iter()
+            .map(|message| (message.id, message.crc_extra()))
+            .collect();
+        assert_eq!(
+            crc_extras,
+            vec![
+                (0, 50),
+                (1, 124),
+                (24, 24),
+                (30, 39),
+                (33, 104),
+                (74, 20),
+                (109, 185),
+                (253, 83)
+            ]
+        );
+    }
+
+    #[test]
+    fn messages_become_packet_structures() {
+        let dialect = built_in();
+        let heartbeat = &dialect.messages[0];
+        let structure = dialect.packet_structure(heartbeat);
+        assert_eq!(structure.name, "MAVLink HEARTBEAT");
+        assert_eq!(structure.size(), 9);
+        assert!(!structure.byte_defined);
+        let offsets: Vec<(&str, usize)> = structure
+            .fields
+            .iter()
+            .map(|field| (field.name.as_str(), field.offset_in_packet))
+            .collect();
+        assert_eq!(
+            offsets,
+            vec![
+                ("type", 4),
+                ("autopilot", 5),
+                ("base_mode", 6),
+                ("custom_mode", 0),
+                ("system_status", 7),
+                ("mavlink_version", 8)
+            ]
+        );
+        assert_eq!(structure.fields[4].labels[&4], "MAV_STATE_ACTIVE");
+        assert!(structure.fields[0].labels.is_empty());
+
+        let status_text = dialect.packet_structure(&dialect.messages[7]);
+        assert_eq!(status_text.size(), 54);
+        assert_eq!(
+            status_text.fields[1].string_format,
+            Some(StringFormat::Fixed { length: 50 })
+        );
+        assert_eq!(status_text.fields[2].offset_in_packet, 51);
+    }
+
+    #[test]
+    fn includes_are_read_once() {
+        let main = r#"<?xml version="1.0"?>
+<!-- a dialect -->
+<mavlink>
+  <include>minimal.xml</include>
+  <include>extra.xml</include>
+  <messages>
+    <message id="5000" name="ROCKET">
+      <description>apogee &amp; &lt;more&gt;</description>
+      <field type="float[3]" name="accel" units="m/s/s">acceleration</field>
+      <field type="uint8_t" name="state" enum="ROCKET_STATE"/>
+    </message>
+  </messages>
+</mavlink>"#;
+        let minimal = r#"<mavlink><enums><enum name="ROCKET_STATE">
+            <entry value="0x01" name="PAD"/><entry name="BOOST"/></enum></enums></mavlink>"#;
+        let extra = r#"<mavlink><include>minimal.xml</include></mavlink>"#;
+        let mut reads = vec![];
+        let dialect = MavlinkDialect::parse(main, &mut |name| {
+            reads.push(name.to_owned());
+            match name {
+                "minimal.xml" => Ok(minimal.to_owned()),
+                "extra.xml" => Ok(extra.to_owned()),
+                _ => bail!("no file {}", name),
+            }
+        })
+        .unwrap();
+        assert_eq!(reads, vec!["minimal.xml", "extra.xml"]);
+        let structure = dialect.packet_structure(&dialect.messages[0]);
+        assert_eq!(
+            structure.fields[0].array,
+            Some(PacketArray {
+                count: 3,
+                stride: 4,
+                count_field: None,
+                group_size: 0
+            })
+        );
+        assert_eq!(structure.fields[0].unit.as_deref(), Some("m/s/s"));
+        assert_eq!(structure.fields[1].labels[&2], "BOOST");
+        assert_eq!(structure.fields[1].offset_in_packet, 12);
+    }
+
+    #[test]
+    fn errors_give_the_line() {
+        let missing_close = "<mavlink>\n<messages>\n<message id=\"1\" name=\"A\">\n</mavlink>";
+        let err = MavlinkDialect::parse(missing_close, &mut |_| bail!("none")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 4: expected </message>, found </mavlink>"
+        );
+
+        let bad_type = "<mavlink><messages>\n<message id=\"1\" name=\"A\">\n<field type=\"u9\" name=\"x\"/></message></messages></mavlink>";
+        let err = MavlinkDialect::parse(bad_type, &mut |_| bail!("none")).unwrap_err();
+        assert_eq!(err.to_string(), "line 3: unknown field type \"u9\"");
+
+        let duplicate = "<mavlink><messages><message id=\"1\" name=\"A\"/>\n<message id=\"1\" name=\"B\"/></messages></mavlink>";
+        let err = MavlinkDialect::parse(duplicate, &mut |_| bail!("none")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 2: message id 1 is used by both A and B"
+        );
+
+        let missing_include = "<mavlink><include>gone.xml</include></mavlink>";
+        let err = MavlinkDialect::parse(missing_include, &mut |_| bail!("none")).unwrap_err();
+        assert_eq!(err.to_string(), "Failed to read included file gone.xml");
+    }
+}
@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+
+use crate::{
+    communication_drivers::mavlink_dialect::{crc_x25, MavlinkDialect},
+    models::{packet::Packet, packet_structure::PacketStructure},
+};
+
+/// the first byte of a MAVLink 1 frame
+const STX_V1: u8 = 0xFE;
+/// the first byte of a MAVLink 2 frame
+const STX_V2: u8 = 0xFD;
+/// the bytes between the STX and the payload, including the STX
+const HEADER_LENGTH_V1: usize = 6;
+const HEADER_LENGTH_V2: usize = 10;
+const CHECKSUM_LENGTH: usize = 2;
+const SIGNATURE_LENGTH: usize = 13;
+/// the only incompat flag MAVLink 2 defines, frames with any other set can't be read
+const INCOMPAT_FLAG_SIGNED: u8 = 0x01;
+
+/// What the parser needs to know about each message it can read
+struct MessageFormat {
+    structure: PacketStructure,
+    crc_extra: u8,
+    payload_length: usize,
+}
+
+/// Finds MAVLink 1 and 2 frames in a byte stream and parses their payloads into packets
+///
+/// frames of messages that aren't in the dialect, or whose checksum doesn't match, are skipped
+#[derive(Default)]
+pub struct MavlinkParser {
+    unparsed_data: Vec<u8>,
+    crc_failures: usize,
+    messages: HashMap<u32, MessageFormat>,
+}
+
+impl MavlinkParser {
+    /// Creates a parser for the messages of `dialect`
+    ///
+    /// `structures` are the registered packet structures of the messages, in the same order as
+    /// `dialect.messages`, so the packets carry their ids
+    pub fn new(dialect: &MavlinkDialect, structures: Vec<PacketStructure>) -> MavlinkParser {
+        let messages = dialect
+            .messages
+            .iter()
+            .zip(structures)
+            .map(|(message, structure)| {
+                let format = MessageFormat {
+                    structure,
+                    crc_extra: message.crc_extra(),
+                    payload_length: message.payload_length(),
+                };
+                (message.id, format)
+            })
+            .collect();
+        MavlinkParser {
+            messages,
+            ..Default::default()
+        }
+    }
+
+    /// adds new unparsed data
+    pub fn push_data(&mut self, data: &[u8]) {
+        self.unparsed_data.extend(data);
+    }
+
+    /// returns the number of frames that were thrown away because their checksum didn't match
+    pub fn crc_failures(&self) -> usize {
+        self.crc_failures
+    }
+
+    /// Parses every complete frame in the unparsed data
+    ///
+    /// anything after the last complete frame that could still be the start of one is kept for
+    /// the next call
+    pub fn parse_packets(&mut self) -> Vec<Packet> {
+        let mut packets = vec![];
+        let mut start = 0;
+        while start < self.unparsed_data.len() {
+            match self.frame_at(start) {
+                Frame::Incomplete => break,
+                Frame::Invalid => start += 1,
+                Frame::Packet(length, packet) => {
+                    packets.extend(packet);
+                    start += length;
+                }
+            }
+        }
+        self.unparsed_data.drain(..start);
+        packets
+    }
+
+    /// reads the frame starting at `start`
+    fn frame_at(&mut self, start: usize) -> Frame {
+        let data = &self.unparsed_data[start..];
+        let (header_length, payload_length, message_id, signature_length) = match data[0] {
+            STX_V1 => {
+                if data.len() < HEADER_LENGTH_V1 {
+                    return Frame::Incomplete;
+                }
+                (HEADER_LENGTH_V1, data[1] as usize, data[5] as u32, 0)
+            }
+            STX_V2 => {
+                if data.len() < HEADER_LENGTH_V2 {
+                    return Frame::Incomplete;
+                }
+                let incompat_flags = data[2];
+                if incompat_flags & !INCOMPAT_FLAG_SIGNED != 0 {
+                    return Frame::Invalid;
+                }
+                let signature_length = if incompat_flags & INCOMPAT_FLAG_SIGNED != 0 {
+                    SIGNATURE_LENGTH
+                } else {
+                    0
+                };
+                let message_id = u32::from_le_bytes([data[7], data[8], data[9], 0]);
+                (
+                    HEADER_LENGTH_V2,
+                    data[1] as usize,
+                    message_id,
+                    signature_length,
+                )
+            }
+            _ => return Frame::Invalid,
+        };
+        let format = match self.messages.get(&message_id) {
+            Some(format) => format,
+            None => return Frame::Invalid,
+        };
+        let checksum_start = header_length + payload_length;
+        let frame_length = checksum_start + CHECKSUM_LENGTH + signature_length;
+        if data.len() < frame_length {
+            return Frame::Incomplete;
+        }
+        let mut crc = crc_x25(0xFFFF, &data[1..checksum_start]);
+        crc = crc_x25(crc, &[format.crc_extra]);
+        if crc.to_le_bytes() != data[checksum_start..checksum_start + CHECKSUM_LENGTH] {
+            self.crc_failures += 1;
+            return Frame::Invalid;
+        }
+        // MAVLink 2 senders trim the zeros off the end of payloads, and MAVLink 1 senders
+        // leave out extension fields
+        let mut payload = data[header_length..checksum_start].to_vec();
+        payload.resize(format.payload_length.max(payload.len()), 0);
+        let packet = match format.structure.parse_fields(&payload) {
+            Ok(field_data) => Some(Packet::default(format.structure.id, field_data)),
+            Err(err) => {
+                eprintln!("Failed to parse {}: {:?}", format.structure.name, err);
+                None
+            }
+        };
+        Frame::Packet(frame_length, packet)
+    }
+}
+
+enum Frame {
+    /// more data is needed to tell if there is a frame here
+    Incomplete,
+    /// there is no frame that can be read here
+    Invalid,
+    /// a frame of this many bytes, and its packet if it could be parsed
+    Packet(usize, Option<Packet>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        communication_drivers::mavlink_dialect::BUILT_IN_DIALECT, models::packet::PacketFieldValue,
+    };
+
+    fn parser() -> MavlinkParser {
+        let dialect =
+            MavlinkDialect::parse(BUILT_IN_DIALECT, &mut |_| anyhow::bail!("none")).unwrap();
+        let structures = dialect
+            .messages
+            .iter()
+            .enumerate()
+            .map(|(id, message)| {
+                let mut structure = dialect.packet_structure(message);
+                structure.id = id;
+                structure
+            })
+            .collect();
+        MavlinkParser::new(&dialect, structures)
+    }
+
+    /// builds a frame the way a sender would, with the crc extra of the message
+    fn frame(v2: bool, message_id: u32, payload: &[u8], crc_extra: u8) -> Vec<u8> {
+        let mut frame = if v2 {
+            let id = message_id.to_le_bytes();
+            vec![
+                STX_V2,
+                payload.len() as u8,
+                0,
+                0,
+                7,
+                1,
+                1,
+                id[0],
+                id[1],
+                id[2],
+            ]
+        } else {
+            vec![STX_V1, payload.len() as u8, 7, 1, 1, message_id as u8]
+        };
+        frame.extend_from_slice(payload);
+        let crc = crc_x25(crc_x25(0xFFFF, &frame[1..]), &[crc_extra]);
+        frame.extend_from_slice(&crc.to_le_bytes());
+        frame
+    }
+
+    fn heartbeat_payload() -> Vec<u8> {
+        let mut payload = 1234u32.to_le_bytes().to_vec();
+        payload.extend_from_slice(&[2, 3, 0x81, 4, 3]);
+        payload
+    }
+
+    #[test]
+    fn parses_v1_and_v2_frames() {
+        let mut parser = parser();
+        let mut data = vec![0x00, 0x55];
+        data.extend(frame(false, 0, &heartbeat_payload(), 50));
+        data.extend(frame(true, 0, &heartbeat_payload(), 50));
+        parser.push_data(&data);
+        let packets = parser.parse_packets();
+        assert_eq!(packets.len(), 2);
+        for packet in packets {
+            assert_eq!(packet.structure_id, 0);
+            assert_eq!(
+                packet.field_data,
+                vec![
+                    PacketFieldValue::Number(2.0),
+                    PacketFieldValue::Number(3.0),
+                    PacketFieldValue::Number(129.0),
+                    PacketFieldValue::Number(1234.0),
+                    PacketFieldValue::Number(4.0),
+                    PacketFieldValue::Number(3.0),
+                ]
+            );
+        }
+        assert!(parser.unparsed_data.is_empty());
+    }
+
+    #[test]
+    fn truncated_payloads_are_zero_filled() {
+        let mut parser = parser();
+        let mut payload = vec![6];
+        payload.extend_from_slice(b"hi");
+        parser.push_data(&frame(true, 253, &payload, 83));
+        let packets = parser.parse_packets();
+        assert_eq!(packets.len(), 1);
+        assert_eq!(packets[0].structure_id, 7);
+        assert_eq!(
+            packets[0].field_data,
+            vec![
+                PacketFieldValue::Number(6.0),
+                PacketFieldValue::String("hi".to_owned()),
+                PacketFieldValue::Number(0.0),
+                PacketFieldValue::Number(0.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn bad_checksums_and_partial_frames() {
+        let mut parser = parser();
+        let good = frame(true, 30, &[0; 28], 39);
+        let mut data = frame(true, 30, &[0; 28], 40);
+        data.extend_from_slice(&good[..20]);
+        parser.push_data(&data);
+        assert!(parser.parse_packets().is_empty());
+        assert_eq!(parser.crc_failures(), 1);
+        assert_eq!(parser.unparsed_data, good[..20]);
+
+        parser.push_data(&good[20..]);
+        let packets = parser.parse_packets();
+        assert_eq!(packets.len(), 1);
+        assert_eq!(packets[0].structure_id, 3);
+        assert!(parser.unparsed_data.is_empty());
+    }
+}
@@ -1,8 +1,14 @@
 pub mod aim_adapter;
 pub mod aim_parser;
 pub mod binary_file_adapter;
+pub mod delimiter_search;
 pub mod featherweight_adapter;
 pub mod featherweight_parser;
+pub mod framing;
+pub mod mavlink_adapter;
+pub mod mavlink_dialect;
+pub mod mavlink_parser;
+pub mod parser_statistics;
 pub mod serial_packet_parser;
 pub mod serial_port_adapter;
 pub mod teledongle_adapter;
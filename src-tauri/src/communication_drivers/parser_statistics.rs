@@ -0,0 +1,83 @@
+//! # Parser Statistics
+//!
+//! Counters kept by the packet parsers, so a device that is quiet can be told apart from one
+//! that is sending data nothing can be made of.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+/// What a parser has made of the packets of one packet structure
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct StructureStatistics {
+    pub(crate) packets_matched: usize,
+    /// packets that were thrown away because a checksum didn't match
+    pub(crate) crc_failures: usize,
+    /// packets whose start was found, but that were thrown away before the rest arrived
+    pub(crate) partial_packets_dropped: usize,
+    /// packets that were found and passed their checks, but whose fields couldn't be read
+    pub(crate) parse_failures: usize,
+}
+
+/// What a parser has made of the data it was given
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ParserStatistics {
+    pub(crate) bytes_received: usize,
+    /// bytes that were thrown away without being part of a packet
+    pub(crate) bytes_discarded: usize,
+    /// frames that were thrown away because their frame CRC didn't match, before it was known
+    /// which packet structure they were
+    pub(crate) frame_crc_failures: usize,
+    /// keyed by packet structure id
+    pub(crate) structures: BTreeMap<usize, StructureStatistics>,
+}
+
+impl ParserStatistics {
+    pub fn record_match(&mut self, packet_structure_id: usize) {
+        self.structures
+            .entry(packet_structure_id)
+            .or_default()
+            .packets_matched += 1;
+    }
+
+    pub fn record_crc_failure(&mut self, packet_structure_id: usize) {
+        self.structures
+            .entry(packet_structure_id)
+            .or_default()
+            .crc_failures += 1;
+    }
+
+    pub fn record_partial_packet_dropped(&mut self, packet_structure_id: usize) {
+        self.structures
+            .entry(packet_structure_id)
+            .or_default()
+            .partial_packets_dropped += 1;
+    }
+
+    pub fn record_parse_failure(&mut self, packet_structure_id: usize) {
+        self.structures
+            .entry(packet_structure_id)
+            .or_default()
+            .parse_failures += 1;
+    }
+
+    /// Returns the number of packets and frames thrown away because a checksum didn't match
+    pub fn crc_failures(&self) -> usize {
+        self.frame_crc_failures
+            + self
+                .structures
+                .values()
+                .map(|structure| structure.crc_failures)
+                .sum::<usize>()
+    }
+
+    /// Returns the number of packets of every packet structure that have been matched
+    pub fn packets_matched(&self) -> usize {
+        self.structures
+            .values()
+            .map(|structure| structure.packets_matched)
+            .sum()
+    }
+}
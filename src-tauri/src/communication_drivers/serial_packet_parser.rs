@@ -1,13 +1,53 @@
-use std::cmp::max;
+use std::{
+    cmp::{max, min},
+    collections::BTreeMap,
+};
 
 use crate::{
-    models::packet::{Packet, PacketFieldValue},
-    packet_structure_manager::PacketStructureManager,
+    communication_drivers::{
+        delimiter_search::DelimiterSearch, parser_statistics::ParserStatistics,
+    },
+    models::{packet::Packet, packet_structure::PacketStructure},
+    packet_structure_manager::{PacketLayout, PacketStructureManager},
 };
 
+/// A place a packet could start, found from its first delimiter
+///
+/// candidates are checked in the order of where their delimiter is, and then the order of the
+/// packet structures, so the same packets are found no matter how the data was split up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Candidate {
+    delimiter_index: usize,
+    structure_index: usize,
+    // the packet has been waited on, so it counts as a partial packet if it's dropped
+    waited: bool,
+}
+
+/// What came of checking a candidate against the data received so far
+enum CandidateResult {
+    Matched(Packet, usize),
+    Incomplete,
+    CrcFailed,
+    Rejected,
+    // the packet passed every check, but its fields couldn't be read
+    ParseFailed,
+}
+
 #[derive(Default)]
 pub struct SerialPacketParser {
     unparsed_data: Vec<u8>,
+    statistics: ParserStatistics,
+
+    // finds the first delimiter of every searchable packet structure at once
+    delimiter_search: DelimiterSearch,
+    // the revision of the packet structure manager the search was built for
+    search_revision: Option<usize>,
+    // the packet structures that start with each pattern in the search
+    pattern_structures: Vec<Vec<usize>>,
+    // how much of unparsed_data has been fed through the search
+    searched_length: usize,
+    // packets that have been found but not fully received yet
+    candidates: Vec<Candidate>,
 }
 
 /// responsible converting raw data to packets
@@ -15,12 +55,26 @@ impl SerialPacketParser {
     // adds new unparsed data
     pub fn push_data(&mut self, data: &[u8], print_flag: bool) {
         self.unparsed_data.extend(data);
+        self.statistics.bytes_received += data.len();
         if print_flag {
             println!("Unparsed data: {:02X?}", self.unparsed_data);
         }
     }
 
+    /// returns the number of packets that were thrown away because their checksum didn't match
+    pub fn crc_failures(&self) -> usize {
+        self.statistics.crc_failures()
+    }
+
+    /// returns what has been made of the data pushed so far
+    pub fn statistics(&self) -> ParserStatistics {
+        self.statistics.clone()
+    }
+
     /// processes the raw data queue, returning a Vector(aka. array) of the processed packets
+    ///
+    /// only the data pushed since the last call is searched for delimiters, packets that were
+    /// found before but hadn't been fully received are checked again
     pub fn parse_packets(
         &mut self,
         packet_structure_manager: &PacketStructureManager,
@@ -29,133 +83,271 @@ impl SerialPacketParser {
         if print_flag {
             println!("Unparsed data length: {}", self.unparsed_data.len());
         }
-        let mut packets: Vec<Packet> = vec![];
+        if self.search_revision != Some(packet_structure_manager.revision) {
+            self.build_search(packet_structure_manager);
+        }
 
+        let pattern_structures = &self.pattern_structures;
+        let candidates = &mut self.candidates;
+        self.delimiter_search.search(
+            &self.unparsed_data,
+            self.searched_length,
+            |pattern, delimiter_index| {
+                candidates.extend(pattern_structures[pattern].iter().map(|&structure_index| {
+                    Candidate {
+                        delimiter_index,
+                        structure_index,
+                        waited: false,
+                    }
+                }))
+            },
+        );
+        self.searched_length = self.unparsed_data.len();
+        self.candidates.sort_unstable();
+
+        let mut packets: Vec<Packet> = vec![];
         let mut last_successful_match_end_index: Option<usize> = None;
+        let mut matched_bytes = 0;
+        let mut waiting = vec![];
+        for candidate in std::mem::take(&mut self.candidates) {
+            let packet_structure =
+                &packet_structure_manager.packet_structures[candidate.structure_index];
+            let layout = &packet_structure_manager.layouts[candidate.structure_index];
+            if print_flag {
+                println!(
+                    "At index {}, matching structure {}",
+                    candidate.delimiter_index, candidate.structure_index
+                );
+            }
 
-        let mut maximum_index = self.unparsed_data.len();
-        maximum_index = maximum_index
-            .checked_sub(packet_structure_manager.minimum_packet_structure_size)
-            .unwrap_or(maximum_index); //don't look for packets that cant be completely inside the buffer
-        maximum_index += packet_structure_manager.maximum_first_delimiter + 1; //look at the furthest point where a first delimiter could appear
-        for i in 0..maximum_index {
-            // Try to find a matching packet for the data
-            for j in 0..packet_structure_manager.packet_structures.len() {
-                let packet_structure = &packet_structure_manager.packet_structures[j];
-                if !packet_structure.byte_defined {
-                    continue;
-                }
+            if layout.first_delimiter_offset > candidate.delimiter_index {
                 if print_flag {
-                    println!("At index {}, matching structure {}", i, j);
+                    println!("- Packet starts before data begins!");
                 }
-                if i + packet_structure.size()
-                    > (self.unparsed_data.len() + (packet_structure.delimiters[0].offset_in_packet))
-                {
+                continue;
+            }
+            let packet_start_index = candidate.delimiter_index - layout.first_delimiter_offset;
+
+            if let Some(last_successful_match_end_index) = last_successful_match_end_index {
+                // Use < instead of <= as the "last index" points to the byte *after*
+                // the packet ended.
+                if packet_start_index < last_successful_match_end_index {
+                    // The current packet cannot overlap with a previous one
                     if print_flag {
-                        println!("Packet out of bounds");
+                        println!("- Overlaps with previous packet");
                     }
-                    continue;
-                }
-                if !is_delimiter_match(
-                    &self.unparsed_data,
-                    i,
-                    &packet_structure.delimiters[0].identifier,
-                    print_flag,
-                ) {
-                    if print_flag {
-                        println!("- First delimiter did not match");
+                    if candidate.waited {
+                        self.statistics
+                            .record_partial_packet_dropped(packet_structure.id);
                     }
                     continue;
                 }
+            }
 
-                if packet_structure.delimiters[0].offset_in_packet > i {
-                    println!("- Packet starts before data begins!");
-                    continue;
+            match check_candidate(
+                &self.unparsed_data,
+                packet_structure,
+                layout,
+                packet_start_index,
+                print_flag,
+            ) {
+                CandidateResult::Matched(packet, packet_end_index) => {
+                    self.statistics.record_match(packet.structure_id);
+                    matched_bytes += packet_end_index - packet_start_index;
+                    packets.push(packet);
+                    // This points to the index *after* the packet ends.
+                    last_successful_match_end_index = Some(packet_end_index);
                 }
-
-                let packet_start_index = i - packet_structure.delimiters[0].offset_in_packet;
-
-                if let Some(last_successful_match_end_index) = last_successful_match_end_index {
-                    // Use < instead of <= as the "last index" points to the byte *after*
-                    // the packet ended.
-                    if packet_start_index < last_successful_match_end_index {
-                        // The current packet cannot overlap with a previous one``
-                        if print_flag {
-                            println!("- Overlaps with previous packet");
-                        }
-                        continue;
-                    }
+                CandidateResult::Incomplete => waiting.push(Candidate {
+                    waited: true,
+                    ..candidate
+                }),
+                CandidateResult::CrcFailed => {
+                    self.statistics.record_crc_failure(packet_structure.id)
                 }
-
-                let mut is_remaining_delimiters_matched = true;
-
-                for delimiter in &packet_structure.delimiters[1..] {
-                    let delimiter_start_index = packet_start_index + delimiter.offset_in_packet;
-                    if !is_delimiter_match(
-                        &self.unparsed_data,
-                        delimiter_start_index,
-                        &delimiter.identifier,
-                        print_flag,
-                    ) {
-                        is_remaining_delimiters_matched = false;
-                        break;
+                CandidateResult::Rejected => {
+                    if candidate.waited {
+                        self.statistics
+                            .record_partial_packet_dropped(packet_structure.id);
                     }
                 }
-
-                if !is_remaining_delimiters_matched {
-                    if print_flag {
-                        println!("- Remaining delimiters did not match");
-                    }
-                    continue;
+                CandidateResult::ParseFailed => {
+                    self.statistics.record_parse_failure(packet_structure.id)
                 }
-
-                // The packet is a match, parse its data
-                let mut field_data: Vec<PacketFieldValue> =
-                    vec![PacketFieldValue::Number(0.0); packet_structure.fields.len()];
-
-                for (k, field_data_item) in field_data
-                    .iter_mut()
-                    .enumerate()
-                    .take(packet_structure.fields.len())
-                {
-                    let field = &packet_structure.fields[k];
-                    let field_start_index = packet_start_index + field.offset_in_packet;
-                    *field_data_item = field.r#type.parse(
-                        &self.unparsed_data
-                            [field_start_index..(field_start_index + field.r#type.size()?)],
-                    )?
-                }
-                if print_flag {
-                    println!(
-                        "MATCHED: {:02X?}",
-                        &self.unparsed_data
-                            [packet_start_index..(packet_start_index + packet_structure.size())]
-                    );
-                }
-                packets.push(Packet {
-                    structure_id: packet_structure.id,
-                    field_data,
-                });
-
-                // This points to the index *after* the packet ends.
-                last_successful_match_end_index =
-                    Some(packet_start_index + packet_structure.size());
             }
         }
+        self.candidates = waiting;
 
-        // Throw away any garbage data that remains so that it does not have to be re-parsed
+        // Throw away data that can't be part of a packet anymore so that it does not have to be
+        // kept around. a delimiter that is partly received can't start earlier than the longest
+        // delimiter, and its packet can start up to the furthest first delimiter before it
+        let mut first_needed_index = self
+            .unparsed_data
+            .len()
+            .saturating_sub(self.delimiter_search.longest_pattern().saturating_sub(1))
+            .saturating_sub(packet_structure_manager.maximum_first_delimiter);
+        for candidate in &self.candidates {
+            let layout = &packet_structure_manager.layouts[candidate.structure_index];
+            first_needed_index = min(
+                first_needed_index,
+                candidate.delimiter_index - layout.first_delimiter_offset,
+            );
+        }
         let last_parsed_index = max(
-            self.unparsed_data
-                .len()
-                .saturating_sub(packet_structure_manager.maximum_packet_structure_size),
+            first_needed_index,
             last_successful_match_end_index.unwrap_or(0),
         );
         if print_flag {
             println!("LPI: {}", last_parsed_index);
         }
         self.unparsed_data.drain(0..last_parsed_index);
+        self.searched_length -= last_parsed_index;
+        // every packet matched in this call ends before the data that was kept
+        self.statistics.bytes_discarded += last_parsed_index - matched_bytes;
+        // packets that overlap with the last match can't be matched anymore
+        let statistics = &mut self.statistics;
+        self.candidates.retain_mut(|candidate| {
+            let layout = &packet_structure_manager.layouts[candidate.structure_index];
+            match (candidate.delimiter_index - layout.first_delimiter_offset)
+                .checked_sub(last_parsed_index)
+            {
+                Some(_) => {
+                    candidate.delimiter_index -= last_parsed_index;
+                    true
+                }
+                None => {
+                    statistics.record_partial_packet_dropped(
+                        packet_structure_manager.packet_structures[candidate.structure_index].id,
+                    );
+                    false
+                }
+            }
+        });
         Ok(packets)
     }
+
+    /// Builds the delimiter search from the first delimiters of the searchable packet structures
+    ///
+    /// the data that was already searched is searched again, as the delimiters might have changed
+    fn build_search(&mut self, packet_structure_manager: &PacketStructureManager) {
+        let mut patterns: BTreeMap<&[u8], usize> = BTreeMap::new();
+        let mut pattern_list: Vec<&[u8]> = vec![];
+        self.pattern_structures.clear();
+        for (structure_index, packet_structure) in packet_structure_manager
+            .packet_structures
+            .iter()
+            .enumerate()
+        {
+            let layout = &packet_structure_manager.layouts[structure_index];
+            let first_delimiter = match layout.first_delimiter {
+                Some(first_delimiter) if layout.searchable => first_delimiter,
+                _ => continue,
+            };
+            let identifier = packet_structure.delimiters[first_delimiter]
+                .identifier
+                .as_slice();
+            let pattern = *patterns.entry(identifier).or_insert_with(|| {
+                pattern_list.push(identifier);
+                self.pattern_structures.push(vec![]);
+                pattern_list.len() - 1
+            });
+            self.pattern_structures[pattern].push(structure_index);
+        }
+        self.delimiter_search = DelimiterSearch::new(&pattern_list);
+        self.search_revision = Some(packet_structure_manager.revision);
+        self.searched_length = 0;
+        self.candidates.clear();
+    }
+}
+
+/// Checks whether a packet of the given structure starting at `packet_start_index` has been
+/// received, and parses it if it has
+fn check_candidate(
+    data: &[u8],
+    packet_structure: &PacketStructure,
+    layout: &PacketLayout,
+    packet_start_index: usize,
+    print_flag: bool,
+) -> CandidateResult {
+    let packet_length = match packet_structure.packet_length(&data[packet_start_index..]) {
+        Ok(Some(packet_length)) if packet_start_index + packet_length <= data.len() => {
+            packet_length
+        }
+        Ok(_) => {
+            if print_flag {
+                println!("- Packet has not been fully received");
+            }
+            return CandidateResult::Incomplete;
+        }
+        Err(_) => {
+            if print_flag {
+                println!("- Packet length is invalid");
+            }
+            return CandidateResult::Rejected;
+        }
+    };
+
+    // variable length packets can end before some of their delimiters
+    let is_remaining_delimiters_matched = packet_structure
+        .delimiters
+        .iter()
+        .enumerate()
+        .filter(|(index, delimiter)| {
+            Some(*index) != layout.first_delimiter
+                && delimiter.offset_in_packet + delimiter.identifier.len() <= packet_length
+        })
+        .all(|(_, delimiter)| {
+            is_delimiter_match(
+                data,
+                packet_start_index + delimiter.offset_in_packet,
+                &delimiter.identifier,
+                print_flag,
+            )
+        });
+    if !is_remaining_delimiters_matched {
+        if print_flag {
+            println!("- Remaining delimiters did not match");
+        }
+        return CandidateResult::Rejected;
+    }
+
+    let packet_end_index = packet_start_index + packet_length;
+    if !packet_structure
+        .check_crcs(&data[packet_start_index..packet_end_index])
+        .unwrap_or(false)
+    {
+        if print_flag {
+            println!("- CRC check failed");
+        }
+        return CandidateResult::CrcFailed;
+    }
+
+    // The packet is a match, parse its data. garbage can still get this far when the structure
+    // has no checksum, so a field that can't be read only rules out this packet
+    let field_data =
+        match packet_structure.parse_fields(&data[packet_start_index..packet_end_index]) {
+            Ok(field_data) => field_data,
+            Err(error) => {
+                if print_flag {
+                    println!("- Fields could not be parsed: {}", error);
+                }
+                return CandidateResult::ParseFailed;
+            }
+        };
+    if print_flag {
+        println!(
+            "MATCHED: {:02X?}",
+            &data[packet_start_index..packet_end_index]
+        );
+    }
+    CandidateResult::Matched(
+        Packet {
+            structure_id: packet_structure.id,
+            field_data,
+            field_labels: BTreeMap::new(),
+        },
+        packet_end_index,
+    )
 }
 
 //checks if the delimiter of a packet can be found in the given data
@@ -183,7 +375,10 @@ fn is_delimiter_match(
 
 #[cfg(test)]
 mod tests {
-    use crate::models::packet_structure::PacketStructure;
+    use crate::models::{
+        packet::PacketFieldValue,
+        packet_structure::{PacketLength, PacketStructure},
+    };
 
     use super::*; //lets the unit tests use everything in this file
     /// test for basic packet recognition and parsing
@@ -416,6 +611,311 @@ mod tests {
         assert_eq!(parsed[1].field_data[4], PacketFieldValue::Number(4.0));
     }
 
+    /// test that big-endian fields are parsed in their own byte order
+    #[test]
+    fn big_endian_fields() {
+        let mut packet_structure_manager = PacketStructureManager::default();
+        let mut p_structure = PacketStructure::make_default("Test Structure".to_owned());
+        p_structure.ez_make("ba5eba11 u16be u16 i32be ca11ab1e", &[""; 3], true);
+        let id = packet_structure_manager
+            .register_packet_structure(&mut p_structure)
+            .unwrap();
+        let mut packet_parser = SerialPacketParser::default();
+        let data = [
+            0x11, 0xBA, 0x5E, 0xBA, 0x12, 0x34, 0x12, 0x34, 0xFF, 0xFF, 0xED, 0xCC, 0x1E, 0xAB,
+            0x11, 0xCA,
+        ];
+        packet_parser.push_data(&data, false);
+        let parsed = packet_parser
+            .parse_packets(&packet_structure_manager, false)
+            .expect("");
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].structure_id, id);
+        assert_eq!(
+            parsed[0].field_data[0],
+            PacketFieldValue::Number(0x1234 as f64)
+        );
+        assert_eq!(
+            parsed[0].field_data[1],
+            PacketFieldValue::Number(0x3412 as f64)
+        );
+        assert_eq!(parsed[0].field_data[2], PacketFieldValue::Number(-4660.0));
+    }
+
+    #[test]
+    fn strings() {
+        let mut packet_structure_manager = PacketStructureManager::default();
+        let mut p_structure = PacketStructure::make_default("Test Structure".to_owned());
+        p_structure.ez_make(
+            "ba5eba11 str6 u8str3 u8 ca11ab1e",
+            &["callsign", "version", "state"],
+            true,
+        );
+        let id = packet_structure_manager
+            .register_packet_structure(&mut p_structure)
+            .unwrap();
+        let mut packet_parser = SerialPacketParser::default();
+        let data = [
+            0x11, 0xBA, 0x5E, 0xBA, b'K', b'D', b'0', 0x00, 0x00, 0x00, 0x02, b'v', b'2', 0x00,
+            0x05, 0x00, 0x1E, 0xAB, 0x11, 0xCA,
+        ];
+        packet_parser.push_data(&data, false);
+        let parsed = packet_parser
+            .parse_packets(&packet_structure_manager, false)
+            .expect("");
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].structure_id, id);
+        assert_eq!(
+            parsed[0].field_data,
+            vec![
+                PacketFieldValue::String("KD0".to_owned()),
+                PacketFieldValue::String("v2".to_owned()),
+                PacketFieldValue::Number(5.0),
+            ]
+        );
+    }
+
+    /// test that a packet whose text isn't UTF-8 is skipped without losing the packets around it
+    #[test]
+    fn unparsable_strings_are_skipped() {
+        let mut packet_structure_manager = PacketStructureManager::default();
+        let mut p_structure = PacketStructure::make_default("Test Structure".to_owned());
+        p_structure.ez_make("ba5eba11 str3 u8 ca11ab1e", &["callsign", "state"], true);
+        let id = packet_structure_manager
+            .register_packet_structure(&mut p_structure)
+            .unwrap();
+        let mut packet_parser = SerialPacketParser::default();
+        let data = [
+            0x11, 0xBA, 0x5E, 0xBA, b'K', b'D', b'0', 0x01, 0x1E, 0xAB, 0x11,
+            0xCA, // good packet
+            0x11, 0xBA, 0x5E, 0xBA, 0xFF, 0xFE, 0x00, 0x02, 0x1E, 0xAB, 0x11,
+            0xCA, // not UTF-8
+            0x11, 0xBA, 0x5E, 0xBA, b'K', b'D', b'1', 0x03, 0x1E, 0xAB, 0x11,
+            0xCA, // good packet
+            0x11, 0xBA, 0x5E, 0xBA, b'K', // cut off packet
+        ];
+        packet_parser.push_data(&data, false);
+        let parsed = packet_parser
+            .parse_packets(&packet_structure_manager, false)
+            .expect("");
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].field_data[1], PacketFieldValue::Number(1.0));
+        assert_eq!(parsed[1].field_data[1], PacketFieldValue::Number(3.0));
+        assert_eq!(packet_parser.statistics().structures[&id].parse_failures, 1);
+
+        // the cut off packet is still waited on
+        packet_parser.push_data(&[b'D', b'2', 0x04, 0x1E, 0xAB, 0x11, 0xCA], false);
+        let parsed = packet_parser
+            .parse_packets(&packet_structure_manager, false)
+            .expect("");
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].field_data[1], PacketFieldValue::Number(4.0));
+    }
+
+    #[test]
+    fn arrays() {
+        let mut packet_structure_manager = PacketStructureManager::default();
+        let mut p_structure = PacketStructure::make_default("Test Structure".to_owned());
+        p_structure.ez_make(
+            "ba5eba11 u8 {u8,i8}[3@0] i16[2] ca11ab1e",
+            &["channels", "sat.svid", "sat.cn0", "accel"],
+            true,
+        );
+        let id = packet_structure_manager
+            .register_packet_structure(&mut p_structure)
+            .unwrap();
+        let mut packet_parser = SerialPacketParser::default();
+        let data = [
+            0x11, 0xBA, 0x5E, 0xBA, 0x02, 0x07, 0xFE, 0x09, 0x03, 0xAA, 0xAA, 0x00, 0x01, 0x00,
+            0xFF, 0xFF, 0x1E, 0xAB, 0x11, 0xCA,
+        ];
+        packet_parser.push_data(&data, false);
+        let parsed = packet_parser
+            .parse_packets(&packet_structure_manager, false)
+            .expect("");
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].structure_id, id);
+        let field_data = &parsed[0].field_data;
+        assert_eq!(field_data.len(), p_structure.value_count());
+        assert_eq!(field_data[0], PacketFieldValue::Number(2.0));
+        // svid and cn0 of each satellite, only 2 of the 3 satellites were sent
+        assert_eq!(field_data[1], PacketFieldValue::Number(7.0));
+        assert_eq!(field_data[2], PacketFieldValue::Number(-2.0));
+        assert_eq!(field_data[3], PacketFieldValue::Number(9.0));
+        assert_eq!(field_data[4], PacketFieldValue::Number(3.0));
+        assert!(matches!(field_data[5], PacketFieldValue::Number(n) if n.is_nan()));
+        assert!(matches!(field_data[6], PacketFieldValue::Number(n) if n.is_nan()));
+        assert_eq!(field_data[7], PacketFieldValue::Number(1.0));
+        assert_eq!(field_data[8], PacketFieldValue::Number(-1.0));
+    }
+
+    #[test]
+    fn crc_failures_are_rejected() {
+        let mut packet_structure_manager = PacketStructureManager::default();
+        let mut p_structure = PacketStructure::make_default("Test Structure".to_owned());
+        p_structure.ez_make("ba5eba11 u16 u8 crc16be", &["value", "count"], true);
+        packet_structure_manager
+            .register_packet_structure(&mut p_structure)
+            .unwrap();
+        let mut packet_parser = SerialPacketParser::default();
+        let data = [
+            0x11, 0xBA, 0x5E, 0xBA, 0x34, 0x12, 0x07, 0x00, 0x80, 0x48, // good packet
+            0x11, 0xBA, 0x5E, 0xBA, 0x34, 0x12, 0x08, 0x00, 0x80, 0x48, // corrupted count
+            0x11, 0xBA, 0x5E, 0xBA, 0x34, 0x12, 0x07, 0x00, 0x80, 0x48, // good packet
+        ];
+        packet_parser.push_data(&data, false);
+        let parsed = packet_parser
+            .parse_packets(&packet_structure_manager, false)
+            .expect("");
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[1].field_data[1], PacketFieldValue::Number(7.0));
+        assert_eq!(packet_parser.crc_failures(), 1);
+    }
+
+    /// test that the statistics tell garbage, packets and cut off packets apart
+    #[test]
+    fn statistics() {
+        let mut packet_structure_manager = PacketStructureManager::default();
+        let mut p_structure = PacketStructure::make_default("Test Structure".to_owned());
+        p_structure.ez_make("ba5eba11 0010 0008 i64 u16 u16 u8 u8", &[""; 5], true);
+        let id = packet_structure_manager
+            .register_packet_structure(&mut p_structure)
+            .unwrap();
+        let mut packet_parser = SerialPacketParser::default();
+        let data = [
+            0x01, 0x02, 0x03, // garbage
+            0x11, 0xBA, 0x5E, 0xBA, 0x10, // cut off packet
+        ];
+        packet_parser.push_data(&data, false);
+        packet_parser
+            .parse_packets(&packet_structure_manager, false)
+            .expect("");
+        let data = [
+            0x11, 0xBA, 0x5E, 0xBA, 0x10, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x01, 0x00, 0x02, 0x00, 0x03, 0x04,
+        ];
+        packet_parser.push_data(&data, false);
+        let parsed = packet_parser
+            .parse_packets(&packet_structure_manager, false)
+            .expect("");
+        assert_eq!(parsed.len(), 1);
+
+        let statistics = packet_parser.statistics();
+        assert_eq!(statistics.bytes_received, 30);
+        assert_eq!(statistics.bytes_discarded, 8);
+        assert_eq!(statistics.structures[&id].packets_matched, 1);
+        assert_eq!(statistics.structures[&id].partial_packets_dropped, 1);
+        assert_eq!(statistics.crc_failures(), 0);
+    }
+
+    #[test]
+    fn variable_length_packets() {
+        let mut packet_structure_manager = PacketStructureManager::default();
+        let mut p_structure = PacketStructure::make_default("Test Structure".to_owned());
+        p_structure.ez_make(
+            "ba5eba11 u8 u8 u16 u8[4@1]",
+            &["len", "count", "value", "extra"],
+            true,
+        );
+        p_structure.length_field = Some(PacketLength {
+            field_index: 0,
+            adjustment: 0,
+            max_size: 12,
+        });
+        packet_structure_manager
+            .register_packet_structure(&mut p_structure)
+            .unwrap();
+        let mut packet_parser = SerialPacketParser::default();
+        let data = [
+            // 10 byte packet with 2 extra bytes
+            0x11, 0xBA, 0x5E, 0xBA, 0x0A, 0x02, 0x34, 0x12, 0xAA, 0xBB,
+            // 8 byte packet with nothing extra
+            0x11, 0xBA, 0x5E, 0xBA, 0x08, 0x00, 0x01, 0x00, // start of a 12 byte packet
+            0x11, 0xBA, 0x5E, 0xBA, 0x0C, 0x04,
+        ];
+        packet_parser.push_data(&data, false);
+        let parsed = packet_parser
+            .parse_packets(&packet_structure_manager, false)
+            .expect("");
+        assert_eq!(parsed.len(), 2);
+        let nan_count = |field_data: &Vec<PacketFieldValue>| {
+            field_data
+                .iter()
+                .filter(|value| matches!(value, PacketFieldValue::Number(n) if n.is_nan()))
+                .count()
+        };
+        assert_eq!(
+            parsed[0].field_data[2],
+            PacketFieldValue::Number(0x1234 as f64)
+        );
+        assert_eq!(
+            parsed[0].field_data[3],
+            PacketFieldValue::Number(0xAA as f64)
+        );
+        assert_eq!(
+            parsed[0].field_data[4],
+            PacketFieldValue::Number(0xBB as f64)
+        );
+        assert_eq!(nan_count(&parsed[0].field_data), 2);
+        assert_eq!(parsed[1].field_data[2], PacketFieldValue::Number(1.0));
+        assert_eq!(nan_count(&parsed[1].field_data), 4);
+
+        // the rest of the last packet shows up
+        packet_parser.push_data(&[0x02, 0x00, 0x01, 0x02, 0x03, 0x04], false);
+        let parsed = packet_parser
+            .parse_packets(&packet_structure_manager, false)
+            .expect("");
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].field_data[2], PacketFieldValue::Number(2.0));
+        assert_eq!(parsed[0].field_data[6], PacketFieldValue::Number(4.0));
+        assert_eq!(nan_count(&parsed[0].field_data), 0);
+    }
+
+    #[test]
+    fn bitfields() {
+        let mut packet_structure_manager = PacketStructureManager::default();
+        let mut p_structure = PacketStructure::make_default("Test Structure".to_owned());
+        p_structure.ez_make(
+            "ba5eba11 u8:4 bool:1 bool:1 i8:2 u16 ca11ab1e",
+            &["state", "line a", "line b", "trim", "altitude"],
+            true,
+        );
+        let id = packet_structure_manager
+            .register_packet_structure(&mut p_structure)
+            .unwrap();
+        let mut packet_parser = SerialPacketParser::default();
+        let data = [
+            0x11,
+            0xBA,
+            0x5E,
+            0xBA,
+            0b1001_0111,
+            0x00,
+            0x34,
+            0x12,
+            0x1E,
+            0xAB,
+            0x11,
+            0xCA,
+        ];
+        packet_parser.push_data(&data, false);
+        let parsed = packet_parser
+            .parse_packets(&packet_structure_manager, false)
+            .expect("");
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].structure_id, id);
+        assert_eq!(
+            parsed[0].field_data,
+            vec![
+                PacketFieldValue::Number(7.0),
+                PacketFieldValue::Bool(true),
+                PacketFieldValue::Bool(false),
+                PacketFieldValue::Number(-2.0),
+                PacketFieldValue::Number(0x1234 as f64),
+            ]
+        );
+    }
+
     // test for when packets dont make it into the pushed data state
     #[test]
     fn delimiter_led_packet_half_in_buffer() {
@@ -433,4 +933,129 @@ mod tests {
             .expect("");
         assert_eq!(parsed, vec![]); //did we not parse anything?
     }
+
+    /// test that packets are found when the data, and even a delimiter, is split between reads
+    #[test]
+    fn packets_split_between_reads() {
+        let mut packet_structure_manager = PacketStructureManager::default();
+        let mut p_structure = PacketStructure::make_default("Test Structure".to_owned());
+        p_structure.ez_make("ba5eba11 0010 0008 i64 u16 u16 u8 u8", &[""; 5], true);
+        packet_structure_manager
+            .register_packet_structure(&mut p_structure)
+            .unwrap();
+        let mut packet_parser = SerialPacketParser::default();
+        let data = [
+            0x11, 0xBA, 0x5E, 0xBA, 0x10, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x01, 0x00, 0x02, 0x00, 0x03, 0x04, 0xBA, 0xBB, 0xE1,
+        ];
+        let mut parsed = vec![];
+        for _ in 0..3 {
+            for chunk in data.chunks(2) {
+                packet_parser.push_data(chunk, false);
+                parsed.extend(
+                    packet_parser
+                        .parse_packets(&packet_structure_manager, false)
+                        .expect(""),
+                );
+            }
+        }
+        assert_eq!(parsed.len(), 3); //did we catch every packet?
+        assert_eq!(parsed[2].field_data[4], PacketFieldValue::Number(4.0));
+
+        // the parser has to notice when the packet structures change
+        let mut wacky_structure = PacketStructure::make_default("Wacky Structure".to_owned());
+        wacky_structure.ez_make("i16 fa1a1a1a u8 u64", &[""; 3], true);
+        packet_structure_manager
+            .register_packet_structure(&mut wacky_structure)
+            .unwrap();
+        packet_parser.push_data(
+            &[
+                0x01, 0x00, 0x00, 0x00, 0x1A, 0x1A, 0x1A, 0xFA, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            ],
+            false,
+        );
+        let parsed = packet_parser
+            .parse_packets(&packet_structure_manager, false)
+            .expect("");
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].field_data[2], PacketFieldValue::Number(3.0));
+    }
+
+    /// measures how fast the default structures are found in a stream with garbage between
+    /// packets, run with `cargo test --release parsing_throughput -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn parsing_throughput() {
+        let packet_structure_manager =
+            crate::state::packet_structure_manager_state::default_packet_structure_manager();
+        let packets: Vec<(usize, Vec<u8>)> = packet_structure_manager
+            .packet_structures
+            .iter()
+            .filter(|packet_structure| packet_structure.byte_defined)
+            .map(|packet_structure| {
+                let mut packet = vec![0; packet_structure.size()];
+                for delimiter in &packet_structure.delimiters {
+                    let offset = delimiter.offset_in_packet;
+                    packet[offset..offset + delimiter.identifier.len()]
+                        .copy_from_slice(&delimiter.identifier);
+                }
+                packet_structure.write_crcs(&mut packet).unwrap();
+                (packet_structure.id, packet)
+            })
+            .collect();
+
+        // a simple LCG keeps the garbage the same from run to run
+        let mut seed: u32 = 1;
+        let mut data = vec![];
+        let mut sent = vec![];
+        while data.len() < 4_000_000 {
+            for _ in 0..64 {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                data.push((seed >> 16) as u8);
+            }
+            let (id, packet) = &packets[sent.len() % packets.len()];
+            data.extend_from_slice(packet);
+            sent.push(*id);
+        }
+
+        let mut packet_parser = SerialPacketParser::default();
+        let mut received = vec![];
+        let start = std::time::Instant::now();
+        // serial ports are read every millisecond, which is a few dozen bytes at high baud rates
+        for chunk in data.chunks(64) {
+            packet_parser.push_data(chunk, false);
+            received.extend(
+                packet_parser
+                    .parse_packets(&packet_structure_manager, false)
+                    .unwrap()
+                    .into_iter()
+                    .map(|packet| packet.structure_id),
+            );
+        }
+        let seconds = start.elapsed().as_secs_f64();
+        println!(
+            "{} packets of {} found, {:.2} MB/s",
+            received.len(),
+            sent.len(),
+            data.len() as f64 / seconds / 1_000_000.0
+        );
+
+        // every packet sent is found in order. the only extras are 2 runs of garbage that happen
+        // to look like leep packets, which are only recognised by 3 bytes of delimiters
+        let mut unsent = sent.iter().peekable();
+        let extras: Vec<&str> = received
+            .iter()
+            .filter(|id| unsent.next_if_eq(id).is_none())
+            .map(|id| {
+                packet_structure_manager
+                    .get_packet_structure(*id)
+                    .unwrap()
+                    .name
+                    .as_str()
+            })
+            .collect();
+        assert_eq!(unsent.next(), None);
+        assert_eq!(extras, ["leep_gps", "leep_volt"]);
+    }
 }
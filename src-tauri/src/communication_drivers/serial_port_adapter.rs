@@ -7,13 +7,19 @@ use crate::{
     packet_structure_manager::PacketStructureManager, state::mutex_utils::use_state_in_mutex,
 };
 
-use super::serial_packet_parser::SerialPacketParser;
+use super::{
+    framing::{encode_frame, FramedPacketParser, Framing},
+    parser_statistics::ParserStatistics,
+    serial_packet_parser::SerialPacketParser,
+};
 
 const PRINT_PARSING: bool = false;
 #[derive(Default)]
 pub struct SerialPortAdapter {
     port: Option<Box<dyn serialport::SerialPort>>,
     packet_parser: SerialPacketParser,
+    // used instead of packet_parser when the device frames its packets
+    framed_parser: Option<FramedPacketParser>,
     baud: u32,
     id: usize,
     packet_structure_manager: Arc<Mutex<PacketStructureManager>>,
@@ -28,6 +34,7 @@ impl CommsIF for SerialPortAdapter {
         SerialPortAdapter {
             port: None,
             packet_parser: Default::default(),
+            framed_parser: None,
             baud: 0,
             id: 0,
             packet_structure_manager,
@@ -65,7 +72,12 @@ impl CommsIF for SerialPortAdapter {
             None => bail!("No active test port"),
         };
 
-        test_port.write_all(packet)?;
+        match &self.framed_parser {
+            Some(framed_parser) => {
+                test_port.write_all(&encode_frame(framed_parser.framing(), packet))?
+            }
+            None => test_port.write_all(packet)?,
+        }
         Ok(())
     }
 
@@ -84,6 +96,22 @@ impl CommsIF for SerialPortAdapter {
         "SerialPort".to_owned()
     }
 
+    fn set_framing(&mut self, framing: Framing) -> anyhow::Result<()> {
+        self.packet_parser = Default::default();
+        self.framed_parser = match framing {
+            Framing::Delimiters => None,
+            _ => Some(FramedPacketParser::new(framing)),
+        };
+        Ok(())
+    }
+
+    fn parser_statistics(&self) -> Option<ParserStatistics> {
+        Some(match &self.framed_parser {
+            Some(framed_parser) => framed_parser.statistics(),
+            None => self.packet_parser.statistics(),
+        })
+    }
+
     fn get_device_raw_data(&mut self, data_vector: &mut Vec<u8>) -> anyhow::Result<()> {
         let active_port = match self.port.as_mut() {
             Some(port) => port,
@@ -101,6 +129,16 @@ impl CommsIF for SerialPortAdapter {
         data_vector: &mut Vec<u8>,
         packet_vector: &mut Vec<Packet>,
     ) -> anyhow::Result<()> {
+        if let Some(framed_parser) = &mut self.framed_parser {
+            framed_parser.push_data(data_vector, PRINT_PARSING);
+            return use_state_in_mutex(
+                &self.packet_structure_manager,
+                &mut |parser| -> anyhow::Result<()> {
+                    packet_vector.extend(framed_parser.parse_packets(parser, PRINT_PARSING)?);
+                    Ok(())
+                },
+            );
+        }
         self.packet_parser.push_data(data_vector, PRINT_PARSING);
         use_state_in_mutex(
             &self.packet_structure_manager,
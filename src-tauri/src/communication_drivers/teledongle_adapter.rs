@@ -7,14 +7,176 @@ use anyhow::bail;
 
 use crate::{
     communication_manager::CommsIF,
-    models::{packet::Packet, packet_structure::PacketStructure},
+    models::{packet::Packet, structure_dsl::parse_structure},
     packet_structure_manager::PacketStructureManager,
     state::mutex_utils::use_state_in_mutex,
 };
 
-use super::teledongle_packet_parser::AltosPacketParser;
+use super::{parser_statistics::ParserStatistics, teledongle_packet_parser::AltosPacketParser};
+
 const PRINT_PARSING: bool = false;
 
+/// The packet structures sent by AltOS flight computers, as (name, structure text)
+///
+/// AltOS counts time in 16 bit, 100Hz ticks
+const ALTOS_PACKETS: &[(&str, &str)] = &[
+    // TeleMega IMU Data Packet.
+    (
+        "Altus TeleMega IMU Sensor Data",
+        r#"
+            _2
+            Timestamp: u16 clock(100, 16)
+            0x08
+            "Angle from vertical in degrees": u8
+            accel: i16
+            "pressure (Pa * 10)": i32
+            "temperature (°C * 100)": i16
+            accel_x: i16
+            accel_y: i16
+            accel_z: i16
+            gyro_x: i16
+            gyro_y: i16
+            gyro_z: i16
+            mag_x: i16
+            mag_y: i16
+            mag_z: i16
+        "#,
+    ),
+    // TeleMega Kalman Data Packet.
+    (
+        "Altus TeleMega Kalman and Voltage Data",
+        r#"
+            _2
+            Timestamp: u16 clock(100, 16)
+            0x09
+            state: u8 {
+                0 = Startup, 1 = Idle, 2 = Pad, 3 = Boost, 4 = Fast, 5 = Coast,
+                6 = Drogue, 7 = Main, 8 = Landed, 9 = Invalid, 10 = Stateless
+            }
+            v_batt: i16
+            v_pyro: i16
+            sense_1: i8
+            sense_2: i8
+            sense_3: i8
+            sense_4: i8
+            sense_5: i8
+            sense_6: i8
+            ground_pres: i32
+            ground_accel: i16
+            accel_plus_g: i16
+            accel_minus_g: i16
+            acceleration: i16
+            # speed is sent in 16ths of a foot per second and height in feet
+            speed: i16 "m/s" scale(0.01904999939040002)
+            height: i16 "m" scale(0.3047999902464003)
+        "#,
+    ),
+    // TeleMetrum sensor data packet
+    (
+        "Altus TeleMetrum v2 Sensor Data",
+        r#"
+            _2
+            Timestamp: u16 clock(100, 16)
+            0x0a
+            "Flight state": u8 {
+                0 = Startup, 1 = Idle, 2 = Pad, 3 = Boost, 4 = Fast, 5 = Coast,
+                6 = Drogue, 7 = Main, 8 = Landed, 9 = Invalid, 10 = Stateless
+            }
+            accelerometer: i16
+            "pressure sensor (Pa * 10)": i32
+            "temperature sensor (°C * 100)": i16
+            "acceleration m/s² * 16": i16
+            "speed m/s * 16": i16
+            "height m": i16
+            "battery voltage": i16
+            "drogue continuity sense": i16
+            "main continuity sense": i16
+        "#,
+    ),
+    // TeleMetrum calibration data
+    (
+        "Altus TeleMetrum v2 Calibration Data",
+        r#"
+            _2
+            Timestamp: u16 clock(100, 16)
+            0x0b
+            _3
+            ground_pres: i32
+            ground_accel: i16
+            accel_plus_g: i16
+            accel_minus_g: i16
+        "#,
+    ),
+    // AltusMetrum config packet
+    (
+        "Altus Configuration Data",
+        r#"
+            _2
+            Timestamp: u16 clock(100, 16)
+            0x04
+            "Device type": u8
+            "Flight number": u16
+            "Config major version": u8
+            "Config minor version": u8
+            "Apogee deploy delay in seconds": u16
+            "Main deploy alt in meters": u16
+            "Maximum flight log size (kB)": u16
+            "Radio ID String": u64
+            "Software Version String": u64
+        "#,
+    ),
+    // AltusMetrum gps packet
+    (
+        "Altus GPS Location",
+        r#"
+            _2
+            Timestamp: u16 clock(100, 16)
+            0x05
+            flags: u8
+            "altitude (m)": i16
+            # latitude and longitude are sent in units of 10^-7 degrees
+            Lat: i32 "°" scale(0.0000001)
+            Long: i32 "°" scale(0.0000001)
+            year: u8
+            month: u8
+            day: u8
+            hour: u8
+            minute: u8
+            second: u8
+            "pdop * 5": u8
+            "hdop * 5": u8
+            "vdop * 5": u8
+            mode: u8
+            "ground_speed cm/s": u16
+            "climb_rate cm/s": i16
+            "course / 2": u8
+        "#,
+    ),
+    // AltusMetrum satellite packet
+    (
+        "Altus GPS Satellite Data",
+        r#"
+            _2
+            Timestamp: u16 clock(100, 16)
+            0x06
+            # channels is the number of satellites actually reported
+            channels: u8
+            # each satellite keeps the name it had as a separate field, and its svid and c_n_1
+            # are numbered together like they were
+            sat: {
+                svid: u8 names(
+                    sat_1_svid, sat_2_svid, sat_3_svid, sat_4_svid, sat_5_svid, sat_6_svid,
+                    sat_7_svid, sat_8_svid, sat_9_svid, sat_10_svid, sat_11_svid, sat_12_svid
+                )
+                cn0: u8 names(
+                    sat_1_c_n_1, sat_2_c_n_1, sat_3_c_n_1, sat_4_c_n_1, sat_5_c_n_1, sat_6_c_n_1,
+                    sat_7_c_n_1, sat_8_c_n_1, sat_9_c_n_1, sat_10_c_n_1, sat_11_c_n_1, sat_12_c_n_1
+                )
+            }[12 @ channels]
+        "#,
+    ),
+];
+
 #[derive(Default)]
 pub struct TeleDongleAdapter {
     port: Option<Box<dyn serialport::SerialPort>>,
@@ -31,169 +193,12 @@ impl CommsIF for TeleDongleAdapter {
         Self: Sized,
     {
         use_state_in_mutex(&packet_structure_manager, &mut |ps_manager| {
-            //################################
-            //Altus Metrum Hardcoded packets start here
-            //################################
-
-            // TeleMega IMU Data Packet.
-            let mut telemega_imu_data = PacketStructure::default();
-            telemega_imu_data.ez_make(
-                "_2 u16 08 u8 i16 i32 i16 i16 i16 i16 i16 i16 i16 i16 i16 i16",
-                &[
-                    "Timestamp",
-                    "Angle from vertical in degrees",
-                    "accel",
-                    "pressure (Pa * 10)",
-                    "temperature (°C * 100)",
-                    "accel_x",
-                    "accel_y",
-                    "accel_z",
-                    "gyro_x",
-                    "gyro_y",
-                    "gyro_z",
-                    "mag_x",
-                    "mag_y",
-                    "mag_z",
-                ],
-                true,
-            );
-            telemega_imu_data.name = "Altus TeleMega IMU Sensor Data".to_owned();
-            _ = ps_manager.register_packet_structure(&mut telemega_imu_data);
-
-            // TeleMega Kalman Data Packet.
-            let mut telemega_kalman_structure = PacketStructure::default();
-            telemega_kalman_structure.ez_make(
-                "_2 u16 09 u8 i16 i16 i8 i8 i8 i8 i8 i8 i32 i16 i16 i16 i16 i16 i16",
-                &[
-                    "Timestamp",
-                    "state",
-                    "v_batt",
-                    "v_pyro",
-                    "sense_1",
-                    "sense_2",
-                    "sense_3",
-                    "sense_4",
-                    "sense_5",
-                    "sense_6",
-                    "ground_pres",
-                    "ground_accel",
-                    "accel_plus_g",
-                    "accel_minus_g",
-                    "acceleration",
-                    "speed",
-                    "height",
-                ],
-                true,
-            );
-            telemega_kalman_structure.name = "Altus TeleMega Kalman and Voltage Data".to_owned();
-            _ = ps_manager.register_packet_structure(&mut telemega_kalman_structure);
-
-            //TeleMetrum sensor data packet
-            let mut metrum_sensor_data = PacketStructure::default();
-            metrum_sensor_data.ez_make(
-                "_2 u16 0A u8 i16 i32 i16 i16 i16 i16 i16 i16 i16",
-                &[
-                    "Timestamp",
-                    "Flight state",
-                    "accelerometer",
-                    "pressure sensor (Pa * 10)",
-                    "temperature sensor (°C * 100)",
-                    "acceleration m/s² * 16",
-                    "speed m/s * 16",
-                    "height m",
-                    "battery voltage",
-                    "drogue continuity sense",
-                    "main continuity sense",
-                ],
-                true,
-            );
-            metrum_sensor_data.name = "Altus TeleMetrum v2 Sensor Data".to_owned();
-            _ = ps_manager.register_packet_structure(&mut metrum_sensor_data);
-
-            //TeleMetrum calibration data
-            let mut metrum_calibration_data = PacketStructure::default();
-            metrum_calibration_data.ez_make(
-                "_2 u16 0B _3 i32 i16 i16 i16",
-                &[
-                    "Timestamp",
-                    "ground_pres",
-                    "ground_accel",
-                    "accel_plus_g",
-                    "accel_minus_g",
-                ],
-                true,
-            );
-            metrum_calibration_data.name = "Altus TeleMetrum v2 Calibration Data".to_owned();
-            _ = ps_manager.register_packet_structure(&mut metrum_calibration_data);
-
-            //AltusMetrum config packet
-            let mut altus_config_packet = PacketStructure::default();
-            altus_config_packet.ez_make(
-                "_2 u16 04 u8 u16 u8 u8 u16 u16 u16 u64 u64",
-                &[
-                    "Timestamp",
-                    "Device type",
-                    "Flight number",
-                    "Config major version",
-                    "Config minor version",
-                    "Apogee deploy delay in seconds",
-                    "Main deploy alt in meters",
-                    "Maximum flight log size (kB)",
-                    "Radio ID String",
-                    "Software Version String",
-                ],
-                true,
-            );
-            altus_config_packet.name = "Altus Configuration Data".to_owned();
-            _ = ps_manager.register_packet_structure(&mut altus_config_packet);
-
-            //AltusMetrum gps packet
-            let mut altus_gps_packet = PacketStructure::default();
-            altus_gps_packet.ez_make(
-                "_2 u16 05 u8 i16 i32 i32 u8 u8 u8 u8 u8 u8 u8 u8 u8 u8 u16 i16 u8",
-                &[
-                    "Timestamp",
-                    "flags",
-                    "altitude (m)",
-                    "Lat",
-                    "Long",
-                    "year",
-                    "month",
-                    "day",
-                    "hour",
-                    "minute",
-                    "second",
-                    "pdop * 5",
-                    "hdop * 5",
-                    "vdop * 5",
-                    "mode",
-                    "ground_speed cm/s",
-                    "climb_rate cm/s",
-                    "course / 2",
-                ],
-                true,
-            );
-            altus_gps_packet.name = "Altus GPS Location".to_owned();
-            _ = ps_manager.register_packet_structure(&mut altus_gps_packet);
-
-            //AltusMetrum satellite packet
-            let mut altus_satellite_packet = PacketStructure::default();
-            altus_satellite_packet.ez_make("_2 u16 06 u8 u8 u8 u8 u8 u8 u8 u8 u8 u8 u8 u8 u8 u8 u8 u8 u8 u8 u8 u8 u8 u8 u8 u8 u8", 
-            &["Timestamp","channels",
-                    "sat_1_svid", "sat_1_c_n_1",
-                    "sat_2_svid", "sat_2_c_n_1",
-                    "sat_3_svid", "sat_3_c_n_1",
-                    "sat_4_svid", "sat_4_c_n_1",
-                    "sat_5_svid", "sat_5_c_n_1",
-                    "sat_6_svid", "sat_6_c_n_1",
-                    "sat_7_svid", "sat_7_c_n_1",
-                    "sat_8_svid", "sat_8_c_n_1",
-                    "sat_9_svid", "sat_9_c_n_1",
-                    "sat_10_svid", "sat_10_c_n_1",
-                    "sat_11_svid", "sat_11_c_n_1",
-                    "sat_12_svid", "sat_12_c_n_1"],true);
-            altus_satellite_packet.name = "Altus GPS Satellite Data".to_owned();
-            _ = ps_manager.register_packet_structure(&mut altus_satellite_packet);
+            for (name, text) in ALTOS_PACKETS {
+                let mut structure =
+                    parse_structure(name, text).expect("AltOS packet structures are valid");
+                // the TeleDongle adapter registers these every time it's created
+                _ = ps_manager.register_packet_structure(&mut structure);
+            }
         });
         TeleDongleAdapter {
             port: None,
@@ -273,6 +278,10 @@ impl CommsIF for TeleDongleAdapter {
         "TeleDongle".to_owned()
     }
 
+    fn parser_statistics(&self) -> Option<ParserStatistics> {
+        Some(self.packet_parser.statistics())
+    }
+
     fn get_device_raw_data(&mut self, data_vector: &mut Vec<u8>) -> anyhow::Result<()> {
         let active_port = match self.port.as_mut() {
             Some(port) => port,
@@ -1,18 +1,14 @@
-use std::cmp::max;
+use std::{cmp::max, collections::BTreeMap};
 
 use crate::{
-    models::{
-        self,
-        packet::{Packet, PacketFieldValue},
-    },
+    communication_drivers::parser_statistics::ParserStatistics, models::packet::Packet,
     packet_structure_manager::PacketStructureManager,
 };
 
 #[derive(Default)]
 pub struct AltosPacketParser {
     unparsed_data: Vec<u8>,
-    iterator: u64,
-    last: u64,
+    statistics: ParserStatistics,
 }
 
 /// responsible converting raw data to packets
@@ -20,11 +16,23 @@ impl AltosPacketParser {
     // adds new unparsed data
     pub fn push_data(&mut self, data: &[u8], print_flag: bool) {
         self.unparsed_data.extend(data);
+        self.statistics.bytes_received += data.len();
         if print_flag {
             println!("Unparsed data: {:02X?}", self.unparsed_data);
         }
     }
 
+    /// returns the number of packets that were thrown away because their checksum didn't match,
+    /// either the one checked by the TeleDongle or one declared in the packet structure
+    pub fn crc_failures(&self) -> usize {
+        self.statistics.crc_failures()
+    }
+
+    /// returns what has been made of the data pushed so far
+    pub fn statistics(&self) -> ParserStatistics {
+        self.statistics.clone()
+    }
+
     /// processes the raw data queue, returning a Vector(aka. array) of the processed packets
     pub fn parse_packets(
         &mut self,
@@ -41,6 +49,7 @@ impl AltosPacketParser {
 
         for j in 0..packet_structure_manager.packet_structures.len() {
             let packet_structure = &packet_structure_manager.packet_structures[j];
+            let size = packet_structure_manager.layouts[j].size;
 
             if print_flag {
                 println!("At index {}, matching structure {}", 0, j);
@@ -52,8 +61,7 @@ impl AltosPacketParser {
                 continue;
             }
 
-            if packet_structure.size()
-                > (self.unparsed_data.len() + (packet_structure.delimiters[0].offset_in_packet))
+            if size > (self.unparsed_data.len() + (packet_structure.delimiters[0].offset_in_packet))
             {
                 if print_flag {
                     println!("Packet out of bounds");
@@ -109,62 +117,46 @@ impl AltosPacketParser {
                 continue;
             }
 
-            if !checksum(&self.unparsed_data, packet_start_index, print_flag) {
+            if !checksum(&self.unparsed_data, packet_start_index, print_flag)
+                || !packet_structure
+                    .check_crcs(&self.unparsed_data[packet_start_index..])
+                    .unwrap_or(false)
+            {
                 if print_flag {
                     println!("- CRC check failed");
                 }
+                self.statistics.record_crc_failure(packet_structure.id);
                 continue;
             }
 
             // The packet is a match, parse its data
-            let mut field_data: Vec<PacketFieldValue> =
-                vec![PacketFieldValue::Number(0.0); packet_structure.fields.len()];
-            for (k, field_data_item) in field_data
-                .iter_mut()
-                .enumerate()
-                .take(packet_structure.fields.len())
-            {
-                let field = &packet_structure.fields[k];
-                let field_start_index = packet_start_index + field.offset_in_packet;
-                *field_data_item = field.r#type.parse(
-                    &self.unparsed_data
-                        [field_start_index..(field_start_index + field.r#type.size()?)],
-                )?;
-            }
-            //START AltusMetrum, timestamp code
-            if packet_structure.name == "telemega_kalman"
-                || packet_structure.name == "TeleMetrum v1.x Sensor Data"
-                || packet_structure.name == "TeleMetrum v2 Sensor Data"
-            {
-                let mut timestamp = serde_json::from_str::<u64>(
-                    &(serde_json::to_string(&field_data[0]).unwrap_or_default()),
-                )
-                .unwrap_or_default()
-                    + self.iterator;
-                if timestamp < self.last {
-                    println!("turnover");
-                    self.iterator += 65535;
-                    timestamp += 65535;
-                }
-                self.last = timestamp;
-                field_data[0] = models::packet::PacketFieldValue::Number(timestamp as f64);
-            }
-
-            //END AltusMetrum, timestamp code
+            // timestamps are unwrapped once the packet leaves the parser
+            let field_data =
+                match packet_structure.parse_fields(&self.unparsed_data[packet_start_index..]) {
+                    Ok(field_data) => field_data,
+                    Err(_) => {
+                        if print_flag {
+                            println!("- Fields could not be parsed");
+                        }
+                        self.statistics.record_parse_failure(packet_structure.id);
+                        continue;
+                    }
+                };
             if print_flag {
                 println!(
                     "MATCHED: {:02X?}",
-                    &self.unparsed_data
-                        [packet_start_index..(packet_start_index + packet_structure.size())]
+                    &self.unparsed_data[packet_start_index..(packet_start_index + size)]
                 );
             }
+            self.statistics.record_match(packet_structure.id);
             packets.push(Packet {
                 structure_id: packet_structure.id,
                 field_data,
+                field_labels: BTreeMap::new(),
             });
 
             // This points to the index *after* the packet ends.
-            last_successful_match_end_index = Some(packet_start_index + packet_structure.size());
+            last_successful_match_end_index = Some(packet_start_index + size);
         }
 
         // Throw away any garbage data that remains so that it does not have to be re-parsed
@@ -177,6 +169,10 @@ impl AltosPacketParser {
         if print_flag {
             println!("LPI: {}", last_parsed_index);
         }
+        // every read is a whole packet along with what the TeleDongle adds to it
+        if last_successful_match_end_index.is_none() {
+            self.statistics.bytes_discarded += self.unparsed_data.len();
+        }
         self.unparsed_data = vec![];
         Ok(packets)
     }
@@ -228,9 +224,11 @@ fn checksum(data: &[u8], start_index: usize, print_flag: bool) -> bool {
 
 #[cfg(test)]
 mod parser_tests {
-    use models::packet_structure::PacketCRC;
-
-    use crate::models::packet_structure::PacketStructure;
+    use crate::models::{
+        packet::PacketFieldValue,
+        packet_structure::{CrcAlgorithm, Endianness, PacketCRC, PacketStructure},
+        structure_dsl::parse_structure,
+    };
 
     use super::*; //lets the unit tests use everything in this file
     /// test for basic packet recognition and parsing
@@ -253,6 +251,11 @@ mod parser_tests {
         let crc = PacketCRC {
             length: 1,
             offset_in_packet: data.len() - 1,
+            // the TeleDongle checks the radio's CRC itself and only reports the result
+            algorithm: CrcAlgorithm::Unchecked,
+            endianness: Endianness::Little,
+            covered_start: 0,
+            covered_end: None,
         };
 
         p_structure.packet_crc.push(crc);
@@ -471,6 +474,44 @@ mod parser_tests {
         assert_eq!(parsed[1].field_data[4], PacketFieldValue::Number(4.0));
     }
 
+    // test that a packet whose fields can't be parsed doesn't stop the reads after it
+    #[test]
+    fn unparsable_packet_is_skipped() {
+        let mut packet_structure_manager = PacketStructureManager::default();
+        let mut p_structure = parse_structure(
+            "Satellites",
+            "_2 Timestamp: u16 0x06 channels: u8 sat: { svid: u8 cn0: u8 }[12 @ channels]",
+        )
+        .unwrap();
+        let id = packet_structure_manager
+            .register_packet_structure(&mut p_structure)
+            .unwrap();
+        let mut packet_parser = AltosPacketParser::default();
+
+        // length byte, 32 packet bytes whose last one says the radio's CRC is good, then rssi
+        let satellite_read = |channels: u8| {
+            let mut data = vec![0x00, 0x20, 0x00, 0x00, 0x10, 0x00, 0x06, channels];
+            data.extend([0x01; 24]);
+            data.extend([0x00, 0x80, 0x00]);
+            data
+        };
+        packet_parser.push_data(&satellite_read(13), false);
+        let parsed = packet_parser
+            .parse_packets(&packet_structure_manager, false)
+            .expect("parser failed");
+        assert_eq!(parsed, vec![]);
+        assert_eq!(packet_parser.statistics().structures[&id].parse_failures, 1);
+
+        packet_parser.push_data(&satellite_read(2), false);
+        let parsed = packet_parser
+            .parse_packets(&packet_structure_manager, false)
+            .expect("parser failed");
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].structure_id, id);
+        assert_eq!(parsed[0].field_data[0], PacketFieldValue::Number(16.0));
+        assert_eq!(parsed[0].field_data[1], PacketFieldValue::Number(2.0));
+    }
+
     // test for when packets dont make it into the pushed data state
     #[test]
     fn delimiter_led_packet_half_in_buffer() {
@@ -8,6 +8,7 @@
 use std::{
     collections::HashMap,
     sync::{Arc, Mutex},
+    time::Instant,
 };
 
 use anyhow::bail;
@@ -17,12 +18,16 @@ use serde::Serialize;
 use crate::{
     communication_drivers::{
         aim_adapter::AimAdapter, binary_file_adapter::BinaryFileAdapter,
-        featherweight_adapter::FeatherweightAdapter, serial_port_adapter::SerialPortAdapter,
-        teledongle_adapter::TeleDongleAdapter,
+        featherweight_adapter::FeatherweightAdapter, framing::Framing,
+        mavlink_adapter::MavlinkAdapter, parser_statistics::ParserStatistics,
+        serial_port_adapter::SerialPortAdapter, teledongle_adapter::TeleDongleAdapter,
     },
     file_handling::log_handlers::LogHandler,
+    link_health::{LinkHealth, LINK_HEALTH_FIELDS, LINK_HEALTH_STRUCTURE},
     models::packet::Packet,
     packet_structure_manager::PacketStructureManager,
+    state::mutex_utils::use_state_in_mutex,
+    timestamp_unwrapper::TimestampUnwrapper,
 };
 
 /// Represents a device name and its metadata for display and selection.
@@ -35,6 +40,15 @@ pub struct DeviceName {
     pub product_name: Option<String>,
 }
 
+/// The parser statistics of one device, for the frontend
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceParserStatistics {
+    id: usize,
+    device_type: String,
+    statistics: ParserStatistics,
+}
+
 /// Represents a communication device for display purposes.
 #[derive(Serialize)]
 pub struct DisplayComDevice {
@@ -55,6 +69,11 @@ pub struct CommunicationManager {
     pub old_device_names: Vec<DeviceName>,
     pub ps_manager: Arc<Mutex<PacketStructureManager>>,
     name_to_value: HashMap<String, String>,
+    // each device's clock rolls over on its own, keyed by device id
+    timestamp_unwrappers: HashMap<usize, TimestampUnwrapper>,
+    // lost and repeated packets are counted per device too, keyed by device id
+    link_healths: HashMap<usize, LinkHealth>,
+    link_health_id: usize,
 }
 
 /// Trait for communication device adapters.
@@ -85,6 +104,19 @@ pub trait CommsIF {
     fn set_id(&mut self, id: usize);
     fn get_id(&self) -> usize;
     fn get_type(&self) -> String;
+
+    /// Set how packets are framed on the wire, only devices that parse packet structures from
+    /// raw bytes can be framed
+    fn set_framing(&mut self, framing: Framing) -> anyhow::Result<()> {
+        _ = framing;
+        bail!("{} devices can't change their framing", self.get_type())
+    }
+
+    /// Get what the device's parser has made of the data received so far, devices that don't
+    /// parse packet structures from raw bytes have none
+    fn parser_statistics(&self) -> Option<ParserStatistics> {
+        None
+    }
 }
 
 impl CommunicationManager {
@@ -96,6 +128,12 @@ impl CommunicationManager {
             old_device_names: Default::default(),
             ps_manager: ps_manager.clone(),
             name_to_value: Default::default(),
+            timestamp_unwrappers: Default::default(),
+            link_healths: Default::default(),
+            // Ensures the link health packet exists with the necessary fields.
+            link_health_id: use_state_in_mutex(&ps_manager, &mut |ps_manager| {
+                ps_manager.enforce_packet_fields(LINK_HEALTH_STRUCTURE, LINK_HEALTH_FIELDS.to_vec())
+            }),
         }
     }
 
@@ -204,10 +242,32 @@ impl CommunicationManager {
             }
         }
 
+        let first_new_packet = return_buffer.len();
         let result = self.comms_objects[index].parse_device_data(&mut raw_bytes, return_buffer);
         if let Err(error) = result {
             return Err(error.context("failed to get raw data"));
         }
+
+        // parsers hand back raw values, name and convert them into real units here
+        let now = Instant::now();
+        let timestamp_unwrapper = self.timestamp_unwrappers.entry(id).or_default();
+        let link_health = self.link_healths.entry(id).or_default();
+        use_state_in_mutex(&self.ps_manager, &mut |ps_manager| {
+            for packet in &mut return_buffer[first_new_packet..] {
+                // packets the parser made up without a structure are left as they are,
+                // labels come first as they name the raw codes
+                _ = ps_manager.label_packet(packet);
+                if let Ok(packet_structure) = ps_manager.get_packet_structure(packet.structure_id) {
+                    // sequence numbers are counted as they were sent
+                    link_health.track_packet(packet_structure, packet, now);
+                    timestamp_unwrapper.unwrap_packet(packet_structure, packet);
+                }
+                _ = ps_manager.calibrate_packet(packet);
+            }
+        });
+        if let Some(packet) = link_health.report(id, self.link_health_id, now) {
+            return_buffer.push(packet);
+        }
         Ok(())
     }
 
@@ -250,6 +310,9 @@ impl CommunicationManager {
                         .ok_or(anyhow::anyhow!("Could not find a device with that name"))?
                 };
 
+                // a reconnected device starts its clock and sequence numbers over
+                self.timestamp_unwrappers.remove(&id);
+                self.link_healths.remove(&id);
                 match self.comms_objects[index].init_device(name, baud) {
                     Ok(_) => Ok(()),
                     Err(message) => Err(message),
@@ -263,6 +326,22 @@ impl CommunicationManager {
         }
     }
 
+    /// Sets how the selected device frames its packets
+    ///
+    /// # Errors
+    ///
+    /// The device doesn't exist, or can't be framed
+    pub fn set_framing(&mut self, framing: Framing, id: usize) -> anyhow::Result<()> {
+        match self.find(id, false) {
+            Some(index) => self.comms_objects[index].set_framing(framing),
+            None => bail!(format!(
+                "could not find a device with that ID: {} {}",
+                id,
+                self.comms_objects.len()
+            )),
+        }
+    }
+
     /// Disconnects the selected device struct to its hardware counterpart
     ///
     /// # Errors
@@ -273,6 +352,8 @@ impl CommunicationManager {
         match index {
             Some(index) => {
                 self.comms_objects.remove(index);
+                self.timestamp_unwrappers.remove(&id);
+                self.link_healths.remove(&id);
                 Ok(())
             }
             None => bail!(format!(
@@ -334,6 +415,16 @@ impl CommunicationManager {
         self.comms_objects[self.comms_objects.len() - 1].get_id()
     }
 
+    /// Adds a MAVLink device object to the manager
+    pub fn add_mavlink(&mut self) -> usize {
+        let mut new_device: MavlinkAdapter = MavlinkAdapter::new(self.ps_manager.clone());
+        new_device.set_id(self.id_iterator);
+        self.id_iterator += 1;
+        self.comms_objects
+            .push(Box::new(new_device) as Box<dyn CommsIF + Send>);
+        self.comms_objects[self.comms_objects.len() - 1].get_id()
+    }
+
     //translates the device ID to array index
     fn find(&mut self, index: usize, print_flag: bool) -> Option<usize> {
         let mut i = 0;
@@ -362,6 +453,20 @@ impl CommunicationManager {
         }
     }
 
+    /// Get the parser statistics of every device that keeps them
+    pub fn get_parser_statistics(&self) -> Vec<DeviceParserStatistics> {
+        self.comms_objects
+            .iter()
+            .filter_map(|device| {
+                Some(DeviceParserStatistics {
+                    id: device.get_id(),
+                    device_type: device.get_type(),
+                    statistics: device.parser_statistics()?,
+                })
+            })
+            .collect()
+    }
+
     //should be get_device_ids
     pub fn get_initialized_devices(&self) -> Vec<usize> {
         let mut return_me = vec![];
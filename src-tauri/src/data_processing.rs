@@ -3,7 +3,7 @@
 //! This module is responsible for processing raw data packets as they are received.
 
 use std::{
-    collections::VecDeque,
+    collections::{BTreeMap, VecDeque},
     sync::{Arc, Mutex},
 };
 
@@ -27,8 +27,6 @@ pub type DataProcessorState = Mutex<DataProcessor>;
 pub struct DataProcessor {
     daq_id: usize,
     daq_adv_id: usize,
-    altus_sense_id: usize,
-    altus_gps_id: usize,
     daq_timestamp_buffer: VecDeque<f64>,
     impulse_estimate: f64,
     max_impulse_estimate: f64,
@@ -64,9 +62,6 @@ impl DataProcessor {
                         "Max_pressure",
                     ],
                 ),
-                altus_sense_id: ps_ref
-                    .get_packet_structure_by_name("Altus TeleMega Kalman and Voltage Data"),
-                altus_gps_id: ps_ref.get_packet_structure_by_name("Altus GPS Location"),
                 daq_timestamp_buffer: VecDeque::new(),
                 impulse_estimate: 0.0,
                 max_impulse_estimate: 0.0,
@@ -79,28 +74,17 @@ impl DataProcessor {
         })
     }
 
-    /// Processes a vector of incoming packets, performing conversions and deriving new data.
+    /// Processes a vector of incoming packets, deriving new data.
     ///
     /// This is the core method of the `DataProcessor`. It iterates through a batch of packets
     /// and applies specific logic based on the packet's type (identified by its `structure_id`).
+    /// Packets arrive already calibrated, so unit conversions belong in the packet structures.
     ///
     /// # Arguments
     /// * `input_array` - A mutable vector of `Packet`s to be processed. Some packets may be modified in-place.
     pub fn daq_processing(&mut self, input_array: &mut Vec<Packet>) {
         let mut output_array = vec![];
         for packet in input_array {
-            // --- Altus TeleMega Kalman and Voltage Data Processing ---
-            if packet.structure_id == self.altus_sense_id && packet.field_data.len() >= 17 {
-                // Perform unit conversions on altitude and velocity fields.
-                packet.field_data[15].edit_number(&mut |n| *n / (16.0 * 3.28084));
-                packet.field_data[16].edit_number(&mut |n| *n / (3.28084));
-            }
-            // --- Altus GPS Location Processing ---
-            if packet.structure_id == self.altus_gps_id && packet.field_data.len() >= 5 {
-                println!("here");
-                packet.field_data[3].edit_number(&mut |n| *n / (100.0 * 100000.0));
-                packet.field_data[4].edit_number(&mut |n| *n / (100.0 * 100000.0));
-            }
             // --- DAQ (Data Acquisition) System Processing ---
             if packet.structure_id == self.daq_id && packet.field_data.len() == 5 {
                 let mut time = packet.field_data[0].clone();
@@ -108,14 +92,9 @@ impl DataProcessor {
                     self.daq_timestamp_buffer.push_front(*time);
                     *time
                 });
-                let mut load_cell_raw = packet.field_data[1].clone();
-                let mut pressure_raw = packet.field_data[2].clone();
-
-                // Convert raw pressure sensor values to PSI.
-                let mut pressure_psi =
-                    pressure_raw.new_number(&mut |v| ((*v - 5.0) / 4.0) * 3000.0);
-                // Convert raw load cell values to Newtons of thrust.
-                let mut load_cell_newtons = load_cell_raw.new_number(&mut |v| (*v * 920.0) + 84.3);
+                // the daq structure calibrates these into Newtons and PSI
+                let mut load_cell_newtons = packet.field_data[1].clone();
+                let mut pressure_psi = packet.field_data[2].clone();
 
                 // Main burn detection and impulse calculation logic.
                 load_cell_newtons.edit_number(&mut |n| {
@@ -166,6 +145,7 @@ impl DataProcessor {
                             PacketFieldValue::Number(self.burn_time),
                             PacketFieldValue::Number(self.max_pressure),
                         ],
+                        field_labels: BTreeMap::new(),
                     });
                     *n
                 });
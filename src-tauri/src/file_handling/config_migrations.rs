@@ -0,0 +1,207 @@
+//! Upgrading config files written by older versions of WINGS.
+//!
+//! Every config file records the `version` of the layout it was written with. Files with an older
+//! version are run through the chain of `MIGRATIONS` one step at a time until they match
+//! `CONFIG_VERSION`, so a config from any past season can still be opened.
+//!
+//! Whenever `PacketStructure`, `PacketField` or any of their parts gain a field that can't be
+//! defaulted by serde, bump `CONFIG_VERSION` and add a migration filling in that field.
+
+use anyhow::{anyhow, bail, Context, Error};
+use serde_json::{json, Map, Value};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The version of the config layout written by this version of WINGS
+pub const CONFIG_VERSION: u32 = 1;
+
+/// A step in the migration chain, upgrading a config file by a single version
+type Migration = fn(&mut Map<String, Value>) -> Result<(), Error>;
+
+/// `MIGRATIONS[n]` upgrades a config file from version `n` to version `n + 1`
+const MIGRATIONS: [Migration; CONFIG_VERSION as usize] = [unversioned_to_v1];
+
+/// Finds the version of a parsed config file, files from before versioning count as version 0
+///
+/// # Errors
+///
+/// errors if the file isn't a json object, or if its version isn't a whole number
+pub fn config_version(config: &Value) -> Result<u32, Error> {
+    let config = config
+        .as_object()
+        .ok_or(anyhow!("Config file is not a json object"))?;
+    match config.get("version") {
+        None => Ok(0),
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or(anyhow!("Config file has an invalid version {}", version)),
+    }
+}
+
+/// Upgrades a parsed config file to `CONFIG_VERSION`, returning the version it started at
+///
+/// # Errors
+///
+/// errors if the file is from a newer version of WINGS, or if any migration fails
+pub fn upgrade(config: &mut Value) -> Result<u32, Error> {
+    let original_version = config_version(config)?;
+    if original_version > CONFIG_VERSION {
+        bail!(
+            "Config file is version {}, but this version of WINGS only understands up to version {}",
+            original_version,
+            CONFIG_VERSION
+        );
+    }
+    let object = config
+        .as_object_mut()
+        .ok_or(anyhow!("Config file is not a json object"))?;
+    for version in original_version..CONFIG_VERSION {
+        MIGRATIONS[version as usize](object).with_context(|| {
+            format!(
+                "Failed to upgrade config file from version {} to {}",
+                version,
+                version + 1
+            )
+        })?;
+        object.insert("version".to_owned(), json!(version + 1));
+    }
+    Ok(original_version)
+}
+
+/// Copies a config file next to itself before it gets upgraded, returning the path of the copy
+///
+/// backups are named after the version they hold, and are never overwritten
+///
+/// # Errors
+///
+/// errors if the file can't be copied
+pub fn back_up(path: &Path, version: u32) -> Result<PathBuf, Error> {
+    back_up_as(path, &format!("v{}", version))
+}
+
+/// Copies a config file next to itself, returning the path of the copy
+///
+/// backups are named after `label`, and are never overwritten
+///
+/// # Errors
+///
+/// errors if the file can't be copied
+pub fn back_up_as(path: &Path, label: &str) -> Result<PathBuf, Error> {
+    let stem = path
+        .file_stem()
+        .map_or("config".into(), |stem| stem.to_string_lossy());
+    let mut copy = 0;
+    let backup_path = loop {
+        let suffix = match copy {
+            0 => String::new(),
+            copy => format!("_{}", copy),
+        };
+        let backup_path = path.with_file_name(format!("{}_{}_backup{}.json", stem, label, suffix));
+        if !backup_path.exists() {
+            break backup_path;
+        }
+        copy += 1;
+    };
+    fs::copy(path, &backup_path).with_context(|| {
+        format!(
+            "Failed to back up config file {} to {}",
+            path.display(),
+            backup_path.display()
+        )
+    })?;
+    Ok(backup_path)
+}
+
+/// Upgrades config files from before versioning
+///
+/// older files can be missing `byte_defined`, `packet_crc` and `size`, which were added to
+/// packet structures since. their 24 bit fields were always read big-endian, which is written
+/// out so they aren't given the byte order fields default to
+fn unversioned_to_v1(config: &mut Map<String, Value>) -> Result<(), Error> {
+    let packet_structures = config
+        .get_mut("packet_structures")
+        .and_then(Value::as_array_mut)
+        .ok_or(anyhow!("Config file has no list of packet structures"))?;
+    for (index, packet_structure) in packet_structures.iter_mut().enumerate() {
+        let packet_structure = packet_structure
+            .as_object_mut()
+            .ok_or(anyhow!("Packet structure {} is not a json object", index))?;
+        packet_structure
+            .entry("byte_defined")
+            .or_insert(json!(false));
+        packet_structure.entry("packet_crc").or_insert(json!([]));
+        packet_structure.entry("size").or_insert(Value::Null);
+        let fields = packet_structure
+            .get_mut("fields")
+            .and_then(Value::as_array_mut)
+            .ok_or(anyhow!("Packet structure {} has no list of fields", index))?;
+        for field in fields.iter_mut().filter_map(Value::as_object_mut) {
+            let is_24_bit = matches!(
+                field.get("type").and_then(Value::as_str),
+                Some("Unsigned 24" | "Signed 24")
+            );
+            if is_24_bit {
+                field.entry("endianness").or_insert(json!("Big"));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*; // lets the unit tests use everything in this file
+
+    /// the hard coded config as written by WINGS before config files had a version
+    pub(crate) const UNVERSIONED_CONFIG: &str =
+        include_str!("../../test utilities/config_unversioned.json");
+
+    //tests that a file written before versioning gains what was added to packet structures since
+    #[test]
+    fn unversioned_files_are_upgraded() {
+        let mut config: Value = serde_json::from_str(UNVERSIONED_CONFIG).unwrap();
+        assert_eq!(upgrade(&mut config).unwrap(), 0);
+        assert_eq!(config["version"], json!(1));
+        for packet_structure in config["packet_structures"].as_array().unwrap() {
+            let packet_structure = packet_structure.as_object().unwrap();
+            for key in ["byte_defined", "packet_crc", "size"] {
+                assert!(packet_structure.contains_key(key));
+            }
+        }
+        let gse_log = &config["packet_structures"][12];
+        assert_eq!(gse_log["name"], "gse_log");
+        assert_eq!(gse_log["byte_defined"], json!(false));
+        assert_eq!(gse_log["packet_crc"], json!([]));
+        assert_eq!(gse_log["size"], Value::Null);
+        let alt_baro = &config["packet_structures"][13];
+        assert_eq!(alt_baro["name"], "alt_baro");
+        assert_eq!(alt_baro["packet_crc"][0]["offset_in_packet"], json!(9));
+        let endianness: Vec<&Value> = alt_baro["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|field| &field["endianness"])
+            .collect();
+        assert_eq!(endianness, [&json!("Big"), &json!("Big"), &Value::Null]);
+
+        assert!(upgrade(&mut json!({ "structures": [] })).is_err());
+        assert!(upgrade(&mut json!({ "packet_structures": [{ "name": "no fields" }] })).is_err());
+    }
+
+    //tests that current files are left alone and newer ones are refused
+    #[test]
+    fn versions_are_checked() {
+        let current = json!({ "version": CONFIG_VERSION, "packet_structures": [] });
+        let mut upgraded = current.clone();
+        assert_eq!(upgrade(&mut upgraded).unwrap(), CONFIG_VERSION);
+        assert_eq!(upgraded, current);
+
+        let mut newer = json!({ "version": CONFIG_VERSION + 1, "packet_structures": [] });
+        assert!(upgrade(&mut newer).is_err());
+        assert!(upgrade(&mut json!({ "version": "one" })).is_err());
+        assert!(upgrade(&mut json!([])).is_err());
+    }
+}
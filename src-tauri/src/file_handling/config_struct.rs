@@ -1,8 +1,21 @@
 use crate::{
+    file_handling::{
+        config_migrations::{self, CONFIG_VERSION},
+        log_handlers::BASE_DIRECTORY,
+    },
+    models::packet_structure::PacketStructure,
     packet_structure_manager::PacketStructureManager,
     state::packet_structure_manager_state::default_packet_structure_manager,
 };
-use std::sync::Mutex;
+use anyhow::{anyhow, bail, Context, Error};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+const CONFIG_FILE_NAME: &str = "config.json";
 
 /// a `Mutex` of `ConfigStruct`
 pub type ConfigState = Mutex<ConfigStruct>;
@@ -10,79 +23,439 @@ pub type ConfigState = Mutex<ConfigStruct>;
 #[derive(Debug, Clone)]
 pub struct ConfigStruct {
     pub packet_structure_manager: PacketStructureManager,
+    /// why the config file can't be saved over, set when part of it couldn't be loaded and a
+    /// copy of it couldn't be kept either
+    save_blocked: Option<String>,
+}
+
+/// The layout of the config file on disk
+///
+/// packet structures are registered again when the file is read, so their ids can be left out.
+/// `version` tracks changes to this layout, see `config_migrations`
+#[derive(Serialize, Deserialize)]
+struct ConfigFile {
+    version: u32,
+    packet_structures: Vec<PacketStructure>,
 }
 
 /// Defines a Default
 fn hard_coded_config() -> ConfigStruct {
     ConfigStruct {
         packet_structure_manager: default_packet_structure_manager(),
+        save_blocked: None,
     }
 }
 
-///Default will either be what is already in the config file, or the DEFAULT_CONFIG const
+///Default will either be what is already in the config file, or the hard coded config
 impl Default for ConfigStruct {
     fn default() -> Self {
-        hard_coded_config()
-
-        // todo!() !!! This code has been temporarily commented out because a new method needs to be developed for saving and loading the packet structure manager from json (possibly ez-make?)
-
-        // match read_config() {
-        //     Ok(config_struct) => return config_struct,
-        //     Err(err) => {
-        //         eprintln!(
-        //             "{}",
-        //             err.context(
-        //                 "Failed to load from config file, attempting new config generation"
-        //             )
-        //         );
-        //         let result = make_config();
-        //         match result {
-        //             Ok(_) => {
-        //                 return hard_coded_config();
-        //             }
-        //             Err(error) => {
-        //                 eprintln!("{}", error.context("Failed config file generation"));
-        //                 return hard_coded_config();
-        //             }
-        //         }
-        //     }
-        // }
+        match config_path() {
+            Ok(path) => ConfigStruct::load_or_default(&path),
+            Err(err) => {
+                eprintln!("{:?}", err.context("Using the hard coded config"));
+                hard_coded_config()
+            }
+        }
     }
 }
 
-// fn read_config() -> Result<ConfigStruct, Error> {
-//     let mut path_buf = tauri::api::path::data_dir().expect("no data dir found on this system");
-//     path_buf.push(BASE_DIRECTORY);
-//     path_buf.push("config");
-//     path_buf.set_extension("json");
-//     let file_string =
-//         read_to_string(path_buf.as_path()).with_context(|| "Failed to read config to string")?;
-//     let config_struct = serde_json::from_str(&file_string)
-//         .with_context(|| "Failed to parse config to struct, most likely bad formatting")?;
-//     Ok(config_struct)
-// }
-
-// fn make_config() -> Result<(), Error> {
-//     let mut path_buf = tauri::api::path::data_dir().expect("no data dir found on this system");
-//     path_buf.push(BASE_DIRECTORY);
-//     path_buf.push("config");
-//     path_buf.set_extension("json");
-//     fs::write(
-//         path_buf.as_path(),
-//         serde_json::to_string(&hard_coded_config())?,
-//     )
-//     .with_context(|| "Unable to write default config to config file")?;
-//     Ok(())
-// }
-impl ConfigStruct {}
+/// Returns the path of the config file, which is kept in the WINGS folder of the data directory
+fn config_path() -> Result<PathBuf, Error> {
+    let mut path_buf =
+        tauri::api::path::data_dir().ok_or(anyhow!("no data dir found on this system"))?;
+    path_buf.push(BASE_DIRECTORY);
+    path_buf.push(CONFIG_FILE_NAME);
+    Ok(path_buf)
+}
+
+impl ConfigStruct {
+    /// Loads the config file at `path`, falling back to the hard coded config if it can't be
+    ///
+    /// a missing config file is made from the hard coded config so there is a file to edit.
+    /// a config file that fails to load is left alone so the mistake in it can be fixed, and a
+    /// copy of it is kept in case the hard coded config is saved over it
+    fn load_or_default(path: &Path) -> ConfigStruct {
+        if !path.exists() {
+            let config = hard_coded_config();
+            if let Err(err) = config.write(path) {
+                eprintln!("{:?}", err.context("Failed config file generation"));
+            }
+            return config;
+        }
+        match ConfigStruct::read(path) {
+            Ok(config) => config,
+            Err(err) => {
+                eprintln!(
+                    "{:?}",
+                    err.context("Failed to load from config file, using the hard coded config")
+                );
+                let mut config = hard_coded_config();
+                config.save_blocked = keep_unloaded_copy(path);
+                config
+            }
+        }
+    }
+
+    /// Reads a config file, registering every packet structure in it
+    ///
+    /// files written by older versions of WINGS are upgraded in place, after being backed up
+    ///
+    /// # Errors
+    ///
+    /// errors if the file can't be read, parsed or upgraded. packet structures that can't be
+    /// registered are skipped and reported, and a copy of the file is kept since saving would
+    /// leave them out
+    pub fn read(path: &Path) -> Result<ConfigStruct, Error> {
+        let file_string = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        let mut config_value: serde_json::Value =
+            serde_json::from_str(&file_string).with_context(|| {
+                format!(
+                    "Failed to parse config file {}, most likely bad formatting",
+                    path.display()
+                )
+            })?;
+        let original_version = config_migrations::upgrade(&mut config_value)
+            .with_context(|| format!("Failed to upgrade config file {}", path.display()))?;
+        let config_file: ConfigFile = serde_json::from_value(config_value).with_context(|| {
+            format!(
+                "Failed to parse config file {}, most likely bad formatting",
+                path.display()
+            )
+        })?;
+        if original_version < CONFIG_VERSION {
+            config_migrations::back_up(path, original_version)?;
+            fs::write(path, serde_json::to_string_pretty(&config_file)?).with_context(|| {
+                format!("Unable to write upgraded config file {}", path.display())
+            })?;
+        }
+        let mut packet_structure_manager = PacketStructureManager::default();
+        let mut skipped = false;
+        for mut packet_structure in config_file.packet_structures {
+            if let Err(err) =
+                packet_structure_manager.register_packet_structure(&mut packet_structure)
+            {
+                eprintln!(
+                    "Skipped packet structure {} from config file {}: {}",
+                    packet_structure.name,
+                    path.display(),
+                    err.to_string()
+                );
+                skipped = true;
+            }
+        }
+        let save_blocked = match skipped {
+            true => keep_unloaded_copy(path),
+            false => None,
+        };
+        // loading isn't an edit that can be undone
+        packet_structure_manager.clear_history();
+        for diagnostic in packet_structure_manager.validate() {
+            eprintln!(
+                "{:?} in config file {}: {}",
+                diagnostic.severity,
+                path.display(),
+                diagnostic.message
+            );
+        }
+        Ok(ConfigStruct {
+            packet_structure_manager,
+            save_blocked,
+        })
+    }
+
+    /// Writes every packet structure to the config file in the data directory
+    ///
+    /// # Errors
+    ///
+    /// errors if there is no data directory, if the file can't be written, or if the file
+    /// couldn't all be loaded and no copy of it could be kept
+    pub fn save(&self) -> Result<(), Error> {
+        if let Some(reason) = &self.save_blocked {
+            bail!("Not saving over the config file, {}", reason);
+        }
+        self.write(&config_path()?)
+    }
+
+    /// Writes every packet structure to a config file, making the folders it goes in if needed
+    ///
+    /// # Errors
+    ///
+    /// errors if the folders or the file can't be written
+    pub fn write(&self, path: &Path) -> Result<(), Error> {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)
+                .with_context(|| format!("Failed to make directory {}", directory.display()))?;
+        }
+        let config_file = ConfigFile {
+            version: CONFIG_VERSION,
+            packet_structures: self.packet_structure_manager.packet_structures.clone(),
+        };
+        fs::write(path, serde_json::to_string_pretty(&config_file)?)
+            .with_context(|| format!("Unable to write config file {}", path.display()))?;
+        Ok(())
+    }
+}
+
+/// Keeps a copy of a config file that couldn't all be loaded, as saving would lose what wasn't
+///
+/// returns why the file can't be saved over if no copy could be made
+fn keep_unloaded_copy(path: &Path) -> Option<String> {
+    match config_migrations::back_up_as(path, "unloaded") {
+        Ok(backup_path) => {
+            eprintln!(
+                "Kept a copy of config file {} at {}",
+                path.display(),
+                backup_path.display()
+            );
+            None
+        }
+        Err(err) => {
+            eprintln!("{:?}", err);
+            Some(format!(
+                "it couldn't all be loaded and a copy of it couldn't be kept: {}",
+                err
+            ))
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use super::*; // lets the unit tests use everything in this file
+    use crate::{
+        file_handling::config_migrations::tests::UNVERSIONED_CONFIG,
+        models::packet_structure::Endianness,
+    };
+
+    /// returns a config path that no other test uses, without a file at it
+    fn test_path(name: &str) -> PathBuf {
+        let mut path_buf = std::env::temp_dir();
+        path_buf.push(format!("wings_config_test_{}_{}", std::process::id(), name));
+        path_buf.push(CONFIG_FILE_NAME);
+        _ = fs::remove_file(&path_buf);
+        path_buf
+    }
 
     #[test]
     #[ignore]
     fn print_config_as_read() {
         println!("{:#?}", ConfigStruct::default());
     }
+
+    //tests that every packet structure survives being written and read back
+    #[test]
+    fn write_and_read() {
+        let path = test_path("round_trip");
+        let config = hard_coded_config();
+        config.write(&path).unwrap();
+        let read = ConfigStruct::read(&path).unwrap();
+        assert_eq!(
+            read.packet_structure_manager.packet_structures,
+            config.packet_structure_manager.packet_structures
+        );
+    }
+
+    //tests that a missing config file is made from the hard coded config
+    #[test]
+    fn missing_file_is_created() {
+        let path = test_path("missing");
+        let config = ConfigStruct::load_or_default(&path);
+        assert!(path.exists());
+        assert_eq!(
+            config.packet_structure_manager.packet_structures,
+            hard_coded_config()
+                .packet_structure_manager
+                .packet_structures
+        );
+    }
+
+    //tests that a broken config file falls back to the hard coded config and is left alone
+    #[test]
+    fn broken_file_is_kept() {
+        let path = test_path("broken");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "{\"packet_structures\": [").unwrap();
+        let error = ConfigStruct::read(&path).unwrap_err();
+        assert!(format!("{}", error).contains("Failed to parse config file"));
+        let backup_path = path.with_file_name("config_unloaded_backup.json");
+        _ = fs::remove_file(&backup_path);
+        let config = ConfigStruct::load_or_default(&path);
+        assert!(!config.packet_structure_manager.packet_structures.is_empty());
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "{\"packet_structures\": ["
+        );
+        assert_eq!(
+            fs::read_to_string(&backup_path).unwrap(),
+            "{\"packet_structures\": ["
+        );
+    }
+
+    //tests that a structure that can't be registered is skipped, keeping the rest of the file
+    #[test]
+    fn broken_structures_are_skipped() {
+        let path = test_path("skipped");
+        let backup_path = path.with_file_name("config_unloaded_backup.json");
+        _ = fs::remove_file(&backup_path);
+        let mut packet_structures = hard_coded_config()
+            .packet_structure_manager
+            .packet_structures
+            .clone();
+        let mut broken_structure = packet_structures[0].clone();
+        broken_structure.name = "broken".to_owned();
+        broken_structure.fields[1].offset_in_packet = broken_structure.fields[0].offset_in_packet;
+        packet_structures.insert(0, broken_structure);
+        let file = serde_json::to_string(&ConfigFile {
+            version: CONFIG_VERSION,
+            packet_structures: packet_structures.clone(),
+        })
+        .unwrap();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, &file).unwrap();
+
+        let read = ConfigStruct::read(&path).unwrap();
+        let names: Vec<&str> = read
+            .packet_structure_manager
+            .packet_structures
+            .iter()
+            .map(|packet_structure| packet_structure.name.as_str())
+            .collect();
+        let expected: Vec<&str> = packet_structures[1..]
+            .iter()
+            .map(|packet_structure| packet_structure.name.as_str())
+            .collect();
+        assert_eq!(names, expected);
+        assert_eq!(read.save_blocked, None);
+        assert_eq!(fs::read_to_string(&backup_path).unwrap(), file);
+    }
+
+    //tests that a structure added to the file is registered
+    #[test]
+    fn structures_are_registered() {
+        let path = test_path("registered");
+        let mut packet_structures = hard_coded_config()
+            .packet_structure_manager
+            .packet_structures
+            .clone();
+        let mut new_structure = packet_structures[0].clone();
+        new_structure.name = "new firmware".to_owned();
+        new_structure.delimiters[0].identifier = vec![0x12, 0x34];
+        packet_structures.push(new_structure);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(
+            &path,
+            serde_json::to_string(&ConfigFile {
+                version: CONFIG_VERSION,
+                packet_structures,
+            })
+            .unwrap(),
+        )
+        .unwrap();
+        let read = ConfigStruct::read(&path).unwrap();
+        let registered = read
+            .packet_structure_manager
+            .packet_structures
+            .iter()
+            .find(|packet_structure| packet_structure.name == "new firmware")
+            .unwrap();
+        assert_eq!(registered.delimiters[0].identifier, vec![0x12, 0x34]);
+    }
+
+    //tests that fields saved without a byte order take the byte order of their type
+    #[test]
+    fn missing_byte_orders_are_defaulted() {
+        let path = test_path("byte_order");
+        let mut packet_structure = PacketStructure::make_default("no byte order".to_owned());
+        packet_structure.ez_make("beef u24 i24 u16be", &["a", "b", "c"], false);
+        let mut file = serde_json::to_value(&ConfigFile {
+            version: CONFIG_VERSION,
+            packet_structures: vec![packet_structure],
+        })
+        .unwrap();
+        for field in file["packet_structures"][0]["fields"]
+            .as_array_mut()
+            .unwrap()
+        {
+            field.as_object_mut().unwrap().remove("endianness");
+        }
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, file.to_string()).unwrap();
+
+        let read = ConfigStruct::read(&path).unwrap();
+        let read_structure = read
+            .packet_structure_manager
+            .packet_structures
+            .iter()
+            .find(|packet_structure| packet_structure.name == "no byte order")
+            .unwrap();
+        assert_eq!(
+            read_structure
+                .fields
+                .iter()
+                .map(|field| field.endianness)
+                .collect::<Vec<_>>(),
+            [Endianness::Big, Endianness::Big, Endianness::Little]
+        );
+    }
+
+    //tests that a config from before versioning is upgraded in place with a backup of the original
+    #[test]
+    fn old_files_are_upgraded() {
+        let path = test_path("upgrade");
+        let backup_path = path.with_file_name("config_v0_backup.json");
+        _ = fs::remove_file(&backup_path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, UNVERSIONED_CONFIG).unwrap();
+        let read = ConfigStruct::read(&path).unwrap();
+        assert_eq!(read.save_blocked, None);
+        let old_config: serde_json::Value = serde_json::from_str(UNVERSIONED_CONFIG).unwrap();
+        let old_structures = old_config["packet_structures"].as_array().unwrap();
+        let structures = &read.packet_structure_manager.packet_structures;
+        assert_eq!(structures.len(), old_structures.len());
+        for (structure, old_structure) in structures.iter().zip(old_structures) {
+            assert_eq!(structure.name, old_structure["name"]);
+            let offsets: Vec<usize> = old_structure["fields"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|field| field["offsetInPacket"].as_u64().unwrap() as usize)
+                .collect();
+            assert_eq!(
+                structure
+                    .fields
+                    .iter()
+                    .map(|field| field.offset_in_packet)
+                    .collect::<Vec<_>>(),
+                offsets
+            );
+        }
+        let alt_baro = structures
+            .iter()
+            .find(|packet_structure| packet_structure.name == "alt_baro")
+            .unwrap();
+        assert_eq!(
+            alt_baro
+                .fields
+                .iter()
+                .map(|field| field.endianness)
+                .collect::<Vec<_>>(),
+            [Endianness::Big, Endianness::Big, Endianness::Little]
+        );
+        assert_eq!(alt_baro.packet_crc[0].offset_in_packet, 9);
+        let gse_log = structures
+            .iter()
+            .find(|packet_structure| packet_structure.name == "gse_log")
+            .unwrap();
+        assert!(!gse_log.byte_defined);
+        assert_eq!(
+            fs::read_to_string(&backup_path).unwrap(),
+            UNVERSIONED_CONFIG
+        );
+        let upgraded: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(upgraded["version"], CONFIG_VERSION);
+        ConfigStruct::read(&path).unwrap();
+        assert!(!path.with_file_name("config_v1_backup.json").exists());
+    }
 }
@@ -1,2 +1,3 @@
+pub mod config_migrations;
 pub mod config_struct;
 pub mod log_handlers;
@@ -0,0 +1,422 @@
+//! # Link Health
+//!
+//! Packets with a sequence number in them show when a link drops, repeats or reorders packets.
+//! This module counts those up separately for every device, and reports them as a packet of
+//! their own so link health can be graphed and logged like any other data.
+
+use std::{
+    collections::{BTreeSet, HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use crate::models::{
+    packet::{Packet, PacketFieldValue},
+    packet_structure::{PacketStructure, SequenceFormat},
+};
+
+/// The name of the packet structure link health is reported in
+pub const LINK_HEALTH_STRUCTURE: &str = "link_health";
+/// The fields of a link health packet, loss is given as a percentage of the packets sent over
+/// the last 10 and 60 seconds
+///
+/// new fields go on the end, as link health structures saved by older versions only gain the
+/// fields past the ones they have
+pub const LINK_HEALTH_FIELDS: [&str; 9] = [
+    "Time",
+    "Device",
+    "Received",
+    "Lost",
+    "Duplicates",
+    "Reordered",
+    "Loss_10s",
+    "Loss_60s",
+    "Resets",
+];
+/// the windows loss percentages are worked out over, in the order they are reported
+const LOSS_WINDOWS: [Duration; 2] = [Duration::from_secs(10), Duration::from_secs(60)];
+/// how often a device's link health is reported
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+/// how many of the most recent lost packets are remembered, so a packet that shows up late can be
+/// told apart from one that was sent twice
+const MAX_REMEMBERED_LOST: usize = 1024;
+/// how many packets back a sequence number can jump before it's taken as the device starting its
+/// count over, such as after a reboot, rather than a repeat
+const MAX_JUMP_BACK: u64 = MAX_REMEMBERED_LOST as u64;
+
+/// Where a single sequence number field is up to
+struct SequenceCounter {
+    /// the last value of the counter that moved it forward
+    last: u64,
+    /// how many steps the counter has moved forward since the first packet, through rollovers
+    position: u64,
+    /// the positions of packets that were skipped over and haven't shown up yet
+    lost: BTreeSet<u64>,
+}
+
+/// The packets a device sent during one `REPORT_INTERVAL`
+struct LossBucket {
+    start: Instant,
+    received: u64,
+    // goes down when packets lost in an earlier bucket show up late
+    lost: i64,
+}
+
+/// Counts the lost, repeated and reordered packets of a single device
+#[derive(Default)]
+pub struct LinkHealth {
+    /// (structure id, field index) -> counter
+    counters: HashMap<(usize, usize), SequenceCounter>,
+    /// packets with a sequence number that weren't repeats
+    received: u64,
+    /// packets that were skipped over and haven't shown up since
+    lost: u64,
+    duplicates: u64,
+    /// packets that showed up after a later one
+    reordered: u64,
+    /// times the device started its count over
+    resets: u64,
+    buckets: VecDeque<LossBucket>,
+    started: Option<Instant>,
+    last_report: Option<Instant>,
+}
+
+/// What a sequence number says about the packet it came in
+///
+/// ordered from the least to the most news about the sequence, so a packet with more than one
+/// sequence number goes by the one that tells the most
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
+enum Arrival {
+    Duplicate,
+    /// a packet that was counted as lost
+    Late,
+    /// the next packet, after `skipped` packets that were lost
+    InOrder {
+        skipped: u64,
+    },
+    /// the sequence started over, so the packet starts it again
+    Reset,
+}
+
+impl SequenceCounter {
+    fn new(value: u64) -> SequenceCounter {
+        SequenceCounter {
+            last: value,
+            position: 0,
+            lost: BTreeSet::new(),
+        }
+    }
+
+    /// Works out where `value` comes in the sequence
+    ///
+    /// values less than half the counter ahead of the last one (counting through a rollover)
+    /// move the sequence forward. values behind it are late packets if they were counted as lost,
+    /// repeats if they're close behind, and otherwise the counter starting over from a new value
+    fn arrive(&mut self, value: u64, format: SequenceFormat) -> Arrival {
+        let mask = match format.wrap_bits {
+            1..=63 => (1u64 << format.wrap_bits) - 1,
+            _ => u64::MAX,
+        };
+        let step = format.step.max(1);
+        let value = value & mask;
+        let forward = value.wrapping_sub(self.last) & mask;
+        if forward == 0 {
+            return Arrival::Duplicate;
+        }
+        if forward <= mask / 2 {
+            let steps = ((forward + step / 2) / step).max(1);
+            let skipped = steps - 1;
+            self.lost.extend(
+                (self.position + 1..self.position + steps)
+                    .rev()
+                    .take(MAX_REMEMBERED_LOST),
+            );
+            while self.lost.len() > MAX_REMEMBERED_LOST {
+                self.lost.pop_first();
+            }
+            self.position += steps;
+            self.last = value;
+            return Arrival::InOrder { skipped };
+        }
+        let behind = ((self.last.wrapping_sub(value) & mask) + step / 2) / step;
+        match self.position.checked_sub(behind) {
+            Some(position) if behind > 0 && self.lost.remove(&position) => Arrival::Late,
+            _ if behind > MAX_JUMP_BACK => {
+                // packets lost before the reset can't show up in the new count
+                self.lost.clear();
+                self.position += 1;
+                self.last = value;
+                Arrival::Reset
+            }
+            _ => Arrival::Duplicate,
+        }
+    }
+}
+
+impl LinkHealth {
+    /// Counts the sequence numbers of a parsed packet, before any of its fields are converted
+    ///
+    /// every sequence number in the packet moves its own counter, but the packet is only counted
+    /// once
+    pub fn track_packet(
+        &mut self,
+        packet_structure: &PacketStructure,
+        packet: &Packet,
+        now: Instant,
+    ) {
+        let mut packet_arrival = None;
+        for field in &packet_structure.fields {
+            let format = match field.sequence {
+                Some(format) => format,
+                None => continue,
+            };
+            for element in field.elements() {
+                let value = match packet.field_data.get(element.index) {
+                    Some(PacketFieldValue::Number(value)) if value.is_finite() => *value,
+                    _ => continue,
+                };
+                let key = (packet_structure.id, element.index);
+                // values below 0 are signed counters that rolled over
+                let value = value as i64 as u64;
+                let arrival = match self.counters.get_mut(&key) {
+                    Some(counter) => counter.arrive(value, format),
+                    None => {
+                        self.counters.insert(key, SequenceCounter::new(value));
+                        Arrival::InOrder { skipped: 0 }
+                    }
+                };
+                packet_arrival = packet_arrival.max(Some(arrival));
+            }
+        }
+        if let Some(arrival) = packet_arrival {
+            self.count(arrival, now);
+        }
+    }
+
+    fn count(&mut self, arrival: Arrival, now: Instant) {
+        let (received, lost) = match arrival {
+            Arrival::InOrder { skipped } => {
+                self.lost += skipped;
+                (1, skipped as i64)
+            }
+            Arrival::Late => {
+                self.reordered += 1;
+                self.lost = self.lost.saturating_sub(1);
+                (1, -1)
+            }
+            Arrival::Reset => {
+                self.resets += 1;
+                (1, 0)
+            }
+            Arrival::Duplicate => {
+                self.duplicates += 1;
+                return;
+            }
+        };
+        self.received += 1;
+        self.started.get_or_insert(now);
+
+        match self.buckets.back_mut() {
+            Some(bucket) if now.saturating_duration_since(bucket.start) < REPORT_INTERVAL => {
+                bucket.received += received;
+                bucket.lost += lost;
+            }
+            _ => self.buckets.push_back(LossBucket {
+                start: now,
+                received,
+                lost,
+            }),
+        }
+        let longest_window = LOSS_WINDOWS[LOSS_WINDOWS.len() - 1];
+        while let Some(bucket) = self.buckets.front() {
+            if now.saturating_duration_since(bucket.start) < longest_window {
+                break;
+            }
+            self.buckets.pop_front();
+        }
+    }
+
+    /// Returns the percentage of the packets sent in the last `window` that were lost
+    pub fn loss_percentage(&self, window: Duration, now: Instant) -> f64 {
+        let (received, lost) = self
+            .buckets
+            .iter()
+            .filter(|bucket| now.saturating_duration_since(bucket.start) < window)
+            .fold((0, 0), |(received, lost), bucket| {
+                (received + bucket.received, lost + bucket.lost)
+            });
+        let lost = lost.max(0) as u64;
+        if received + lost == 0 {
+            return 0.0;
+        }
+        lost as f64 * 100.0 / (received + lost) as f64
+    }
+
+    /// Makes a link health packet for the device with `device_id`, if a sequence number has been
+    /// seen and the last one wasn't made too recently
+    ///
+    /// the time of the packet is in seconds since the first sequence number was seen
+    pub fn report(
+        &mut self,
+        device_id: usize,
+        structure_id: usize,
+        now: Instant,
+    ) -> Option<Packet> {
+        let started = self.started?;
+        if let Some(last_report) = self.last_report {
+            if now.saturating_duration_since(last_report) < REPORT_INTERVAL {
+                return None;
+            }
+        }
+        self.last_report = Some(now);
+
+        let mut values = vec![
+            now.saturating_duration_since(started).as_secs_f64(),
+            device_id as f64,
+            self.received as f64,
+            self.lost as f64,
+            self.duplicates as f64,
+            self.reordered as f64,
+        ];
+        values.extend(
+            LOSS_WINDOWS
+                .iter()
+                .map(|window| self.loss_percentage(*window, now)),
+        );
+        values.push(self.resets as f64);
+        Some(Packet::default(
+            structure_id,
+            values.into_iter().map(PacketFieldValue::Number).collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::structure_dsl::parse_structure;
+
+    use super::*;
+
+    fn make_structure() -> PacketStructure {
+        parse_structure("test", "count: u8 seq(8) state: u8").unwrap()
+    }
+
+    fn track_all(link_health: &mut LinkHealth, counts: &[f64], now: Instant) {
+        let structure = make_structure();
+        for count in counts {
+            let packet = Packet::default(
+                structure.id,
+                vec![
+                    PacketFieldValue::Number(*count),
+                    PacketFieldValue::Number(2.0),
+                ],
+            );
+            link_health.track_packet(&structure, &packet, now);
+        }
+    }
+
+    fn counts(link_health: &LinkHealth) -> (u64, u64, u64, u64) {
+        (
+            link_health.received,
+            link_health.lost,
+            link_health.duplicates,
+            link_health.reordered,
+        )
+    }
+
+    #[test]
+    fn gaps_through_rollovers_are_lost_packets() {
+        let mut link_health = LinkHealth::default();
+        track_all(
+            &mut link_health,
+            &[250.0, 251.0, 254.0, 255.0, 1.0, 2.0],
+            Instant::now(),
+        );
+        assert_eq!(counts(&link_health), (6, 3, 0, 0));
+    }
+
+    #[test]
+    fn late_packets_and_repeats() {
+        let mut link_health = LinkHealth::default();
+        track_all(
+            &mut link_health,
+            &[10.0, 12.0, 13.0, 11.0, 13.0, 11.0, 5.0],
+            Instant::now(),
+        );
+        // 11 was reordered, then the second 11, the second 13 and the old 5 are repeats
+        assert_eq!(counts(&link_health), (4, 0, 3, 1));
+    }
+
+    #[test]
+    fn restarted_counts_are_resets() {
+        let structure = parse_structure("test", "count: u16 seq(16)").unwrap();
+        let mut link_health = LinkHealth::default();
+        for count in [4998.0, 5000.0, 0.0, 1.0, 1.0, 3.0, 2.0] {
+            let packet = Packet::default(structure.id, vec![PacketFieldValue::Number(count)]);
+            link_health.track_packet(&structure, &packet, Instant::now());
+        }
+        // the device rebooted at 0, then the second 1 is a repeat and 2 is late
+        assert_eq!(counts(&link_health), (6, 1, 1, 1));
+        assert_eq!(link_health.resets, 1);
+    }
+
+    #[test]
+    fn packets_are_counted_once() {
+        let structure = parse_structure("test", "count: u8[2] seq(8)").unwrap();
+        let mut link_health = LinkHealth::default();
+        for counts in [
+            [0.0, 10.0],
+            [1.0, 11.0],
+            [3.0, 13.0],
+            [3.0, 13.0],
+            [2.0, 12.0],
+        ] {
+            let packet = Packet::default(
+                structure.id,
+                counts.into_iter().map(PacketFieldValue::Number).collect(),
+            );
+            link_health.track_packet(&structure, &packet, Instant::now());
+        }
+        assert_eq!(counts(&link_health), (4, 0, 1, 1));
+    }
+
+    #[test]
+    fn steps_count_packets_of_a_timestamp() {
+        let mut structure = make_structure();
+        structure.fields[0].sequence = Some(SequenceFormat {
+            wrap_bits: 8,
+            step: 10,
+        });
+        let mut link_health = LinkHealth::default();
+        for count in [200.0, 210.0, 241.0, 5.0] {
+            let packet = Packet::default(structure.id, vec![PacketFieldValue::Number(count)]);
+            link_health.track_packet(&structure, &packet, Instant::now());
+        }
+        assert_eq!(counts(&link_health), (4, 3, 0, 0));
+    }
+
+    #[test]
+    fn loss_is_reported_over_windows() {
+        let start = Instant::now();
+        let mut link_health = LinkHealth::default();
+        assert_eq!(link_health.report(3, 7, start), None);
+
+        // 2 of 4 lost a while ago, then none of 5 lost recently
+        track_all(&mut link_health, &[0.0, 3.0], start);
+        let recently = start + Duration::from_secs(30);
+        track_all(&mut link_health, &[4.0, 5.0, 6.0, 7.0, 8.0], recently);
+
+        let packet = link_health.report(3, 7, recently).unwrap();
+        assert_eq!(packet.structure_id, 7);
+        assert_eq!(
+            packet.field_data,
+            [30.0, 3.0, 7.0, 2.0, 0.0, 0.0, 0.0, 2.0 * 100.0 / 9.0, 0.0]
+                .into_iter()
+                .map(PacketFieldValue::Number)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(link_health.report(3, 7, recently), None);
+        assert!(link_health
+            .report(3, 7, recently + REPORT_INTERVAL)
+            .is_some());
+    }
+}
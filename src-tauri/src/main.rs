@@ -9,13 +9,17 @@ mod communication_drivers;
 mod communication_manager;
 mod data_processing;
 mod file_handling;
+mod link_health;
 mod models;
 mod packet_generator;
 mod packet_structure_events;
+mod packet_structure_history;
 mod packet_structure_manager;
+mod packet_structure_validation;
 mod receiving_loop;
 mod sending_loop;
 mod state;
+mod timestamp_unwrapper;
 
 use std::sync::{Arc, Mutex};
 
@@ -32,15 +36,17 @@ use tauri::Manager;
 
 use crate::commands::{
     communication_commands::{
-        add_aim, add_altus_metrum, add_featherweight, add_file_manager, add_rfd, delete_device,
-        init_device_port,
+        add_aim, add_altus_metrum, add_featherweight, add_file_manager, add_mavlink, add_rfd,
+        delete_device, get_parser_statistics, init_device_port, set_device_framing,
     },
     file_commands::set_read,
     packet_structure_manager_commands::{
         add_delimiter, add_field, add_gap_after, add_packet_structure, delete_packet_structure,
-        delete_packet_structure_component, register_empty_packet_structure,
-        set_delimiter_identifier, set_delimiter_name, set_field_metadata_type, set_field_name,
-        set_field_type, set_gap_size, set_packet_name,
+        delete_packet_structure_component, get_delimiter_ambiguities, get_firmware_code,
+        get_packet_structure_text, import_c_header, redo_packet_structure_edit,
+        register_empty_packet_structure, set_delimiter_identifier, set_delimiter_name,
+        set_field_metadata_type, set_field_name, set_field_type, set_gap_size, set_packet_name,
+        undo_packet_structure_edit,
     },
     sending_commands::{start_sending_loop, stop_sending_loop},
 };
@@ -62,6 +68,8 @@ fn main() {
             // Device and communication commands
             delete_device,
             init_device_port,
+            set_device_framing,
+            get_parser_statistics,
             start_sending_loop,
             stop_sending_loop,
             // Packet structure commands
@@ -79,12 +87,19 @@ fn main() {
             add_packet_structure,
             register_empty_packet_structure,
             delete_packet_structure,
+            get_packet_structure_text,
+            import_c_header,
+            undo_packet_structure_edit,
+            redo_packet_structure_edit,
+            get_firmware_code,
+            get_delimiter_ambiguities,
             // Device-specific commands
             add_altus_metrum,
             add_rfd,
             add_file_manager,
             add_aim,
             add_featherweight,
+            add_mavlink,
             // File read command
             set_read
         ])
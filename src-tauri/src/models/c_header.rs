@@ -0,0 +1,1890 @@
+//! # C Header Import
+//!
+//! Reads the structs firmware sends telemetry as out of a C header, and turns them into
+//! `PacketStructure`s laid out the way GCC and Clang lay them out on little-endian targets.
+//!
+//! - `uint8_t` through `int64_t`, `char`, `short`, `int`, `long long`, `float`, `double` and
+//!   `bool`, plus typedefs of any of them
+//! - `__attribute__((packed))`, `__attribute__((aligned(N)))`, `__packed`, `_Alignas(N)`,
+//!   `#pragma pack(N)`, `#pragma pack(push, N)` and `#pragma pack(pop)`
+//! - arrays of any number of dimensions, sized with numbers, `#define`d constants or enum values.
+//!   `char` arrays become fixed length strings
+//! - nested structs, whose members are named like "outer.inner". arrays of nested structs become
+//!   one array field per member, or one set of fields per element if a member is an array itself
+//! - enums, which become ints with their values named
+//! - bitfields, packed into containers of their type starting from the least significant bit
+//!
+//! Every named struct that isn't used inside another struct becomes a packet structure, named after
+//! its typedef if it has one. Structs with members that can't be sent as they are (pointers,
+//! unions, `long` ...) are left out, and everything that couldn't be imported is reported as a
+//! `HeaderProblem`. Preprocessor conditionals aren't evaluated, so both sides of an `#if` are read.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+use crate::models::packet_structure::{
+    Endianness, PacketArray, PacketBitfield, PacketField, PacketFieldType, PacketStructure,
+    StringFormat,
+};
+
+/// The fixed width integer types, and the packet field types they map to
+const FIXED_WIDTH_TYPES: &[(&str, PacketFieldType)] = &[
+    ("uint8_t", PacketFieldType::UnsignedByte),
+    ("int8_t", PacketFieldType::SignedByte),
+    ("uint16_t", PacketFieldType::UnsignedShort),
+    ("int16_t", PacketFieldType::SignedShort),
+    ("uint32_t", PacketFieldType::UnsignedInteger),
+    ("int32_t", PacketFieldType::SignedInteger),
+    ("uint64_t", PacketFieldType::UnsignedLong),
+    ("int64_t", PacketFieldType::SignedLong),
+];
+
+/// (size, unsigned type, signed type) of every integer type a bitfield container can be
+const CONTAINER_TYPES: &[(usize, PacketFieldType, PacketFieldType)] = &[
+    (
+        1,
+        PacketFieldType::UnsignedByte,
+        PacketFieldType::SignedByte,
+    ),
+    (
+        2,
+        PacketFieldType::UnsignedShort,
+        PacketFieldType::SignedShort,
+    ),
+    (
+        3,
+        PacketFieldType::UnsignedTwoFour,
+        PacketFieldType::SignedTwoFour,
+    ),
+    (
+        4,
+        PacketFieldType::UnsignedInteger,
+        PacketFieldType::SignedInteger,
+    ),
+    (
+        5,
+        PacketFieldType::UnsignedFourZero,
+        PacketFieldType::SignedFourZero,
+    ),
+    (
+        6,
+        PacketFieldType::UnsignedFourEight,
+        PacketFieldType::SignedFourEight,
+    ),
+    (
+        8,
+        PacketFieldType::UnsignedLong,
+        PacketFieldType::SignedLong,
+    ),
+];
+
+#[derive(PartialEq, Clone, Debug)]
+/// Something in a header that couldn't be imported, `line` counts from 1
+pub struct HeaderProblem {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for HeaderProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+/// The packet structures found in a header, and everything in it that couldn't be imported
+pub struct HeaderImport {
+    pub structures: Vec<PacketStructure>,
+    pub problems: Vec<HeaderProblem>,
+}
+
+/// Reads every struct in a C header into a packet structure
+///
+/// the structures aren't registered, so they are left with an id of 0 and no delimiters
+pub fn import_c_header(text: &str) -> HeaderImport {
+    let mut problems = vec![];
+    let text = strip_comments(text, &mut problems);
+    let mut tokens = lex(&text, 1, &mut problems);
+    tokens.push(Token {
+        kind: TokenKind::End,
+        line: text.lines().count().max(1),
+    });
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        constants: HashMap::new(),
+        typedefs: HashMap::new(),
+        tags: HashMap::new(),
+        records: vec![],
+        pack: None,
+        pack_stack: vec![],
+        problems,
+    };
+    parser.parse();
+    let structures = parser.packet_structures();
+    let mut problems = parser.problems;
+    problems.sort_by_key(|problem| problem.line);
+    HeaderImport {
+        structures,
+        problems,
+    }
+}
+
+fn problem(line: usize, message: impl Into<String>) -> HeaderProblem {
+    HeaderProblem {
+        line,
+        message: message.into(),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// A `#pragma pack`, which caps the alignment of every member of the structs after it
+enum Pack {
+    Set(Option<usize>),
+    Push(Option<usize>),
+    Pop,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum TokenKind {
+    Word(String),
+    Number(i64),
+    /// a string literal, only ever skipped over
+    Text,
+    Symbol(char),
+    /// a `#define` of a name to the tokens after it
+    Define(String, Vec<Token>),
+    Pack(Pack),
+    End,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Token {
+    kind: TokenKind,
+    line: usize,
+}
+
+/// describes a token for problem messages
+fn describe(kind: Option<&TokenKind>) -> String {
+    match kind {
+        Some(TokenKind::Word(word)) => format!("'{}'", word),
+        Some(TokenKind::Number(number)) => format!("the number {}", number),
+        Some(TokenKind::Text) => "a string".to_owned(),
+        Some(TokenKind::Symbol(symbol)) => format!("'{}'", symbol),
+        Some(TokenKind::Define(..)) | Some(TokenKind::Pack(_)) => {
+            "a preprocessor directive".to_owned()
+        }
+        Some(TokenKind::End) => "the end of the header".to_owned(),
+        None => "the end of the line".to_owned(),
+    }
+}
+
+/// replaces comments with spaces, keeping line breaks so lines can still be counted
+fn strip_comments(text: &str, problems: &mut Vec<HeaderProblem>) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+    let mut quote = None;
+    while let Some(c) = chars.next() {
+        if c == '\n' {
+            line += 1;
+        }
+        match quote {
+            Some(quote_char) => {
+                stripped.push(c);
+                if c == '\\' {
+                    if let Some(escaped) = chars.next() {
+                        line += usize::from(escaped == '\n');
+                        stripped.push(escaped);
+                    }
+                } else if c == quote_char || c == '\n' {
+                    quote = None;
+                }
+            }
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                stripped.push(c);
+            }
+            None if c == '/' && chars.peek() == Some(&'/') => {
+                while chars.peek().is_some_and(|next| *next != '\n') {
+                    chars.next();
+                }
+                stripped.push(' ');
+            }
+            None if c == '/' && chars.peek() == Some(&'*') => {
+                chars.next();
+                let start = line;
+                let mut last = ' ';
+                let mut closed = false;
+                for next in chars.by_ref() {
+                    if next == '\n' {
+                        line += 1;
+                        stripped.push('\n');
+                    }
+                    if last == '*' && next == '/' {
+                        closed = true;
+                        break;
+                    }
+                    last = next;
+                }
+                if !closed {
+                    problems.push(problem(start, "this comment never ends"));
+                }
+                stripped.push(' ');
+            }
+            None => stripped.push(c),
+        }
+    }
+    stripped
+}
+
+/// reads a number like "12", "0x1Fu" or "010", returning `None` for anything that isn't an integer
+fn parse_number(text: &str) -> Option<i64> {
+    let digits = text.trim_end_matches(['u', 'U', 'l', 'L']);
+    let (digits, radix) = if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        (hex, 16)
+    } else if let Some(binary) = digits
+        .strip_prefix("0b")
+        .or_else(|| digits.strip_prefix("0B"))
+    {
+        (binary, 2)
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        (&digits[1..], 8)
+    } else {
+        (digits, 10)
+    };
+    u64::from_str_radix(digits, radix)
+        .ok()
+        .map(|number| number as i64)
+}
+
+/// reads the value of a character literal like 'A' or '\n', without its quotes
+fn parse_character(text: &str) -> Option<i64> {
+    let mut chars = text.chars();
+    let value = match (chars.next()?, chars.next()) {
+        ('\\', Some(escaped)) => match escaped {
+            'n' => 10,
+            't' => 9,
+            'r' => 13,
+            '0' => 0,
+            'x' => return parse_number(&format!("0x{}", chars.as_str())),
+            other => other as i64,
+        },
+        (c, _) => c as i64,
+    };
+    Some(value)
+}
+
+/// splits text into tokens, turning preprocessor directives into a single token each
+fn lex(text: &str, first_line: usize, problems: &mut Vec<HeaderProblem>) -> Vec<Token> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = vec![];
+    let mut position = 0;
+    let mut line = first_line;
+    let mut line_start = true;
+    while let Some(&c) = chars.get(position) {
+        if c == '\n' {
+            line += 1;
+            line_start = true;
+            position += 1;
+            continue;
+        }
+        if c == '\\' && chars.get(position + 1) == Some(&'\n') {
+            line += 1;
+            position += 2;
+            continue;
+        }
+        if c.is_whitespace() {
+            position += 1;
+            continue;
+        }
+        let token_line = line;
+        if c == '#' && line_start {
+            // directives run to the end of the line, including any escaped line breaks
+            let mut directive = String::new();
+            position += 1;
+            while let Some(&c) = chars.get(position) {
+                if c == '\n' {
+                    break;
+                }
+                if c == '\\' && chars.get(position + 1) == Some(&'\n') {
+                    directive.push(' ');
+                    line += 1;
+                    position += 2;
+                    continue;
+                }
+                directive.push(c);
+                position += 1;
+            }
+            if let Some(kind) = directive_token(&directive, token_line, problems) {
+                tokens.push(Token {
+                    kind,
+                    line: token_line,
+                });
+            }
+            continue;
+        }
+        line_start = false;
+        let start = position;
+        let kind = if c.is_alphabetic() || c == '_' {
+            while chars
+                .get(position)
+                .is_some_and(|c| c.is_alphanumeric() || *c == '_')
+            {
+                position += 1;
+            }
+            TokenKind::Word(chars[start..position].iter().collect())
+        } else if c.is_ascii_digit() {
+            while chars
+                .get(position)
+                .is_some_and(|c| c.is_alphanumeric() || *c == '_' || *c == '.')
+            {
+                position += 1;
+            }
+            let text: String = chars[start..position].iter().collect();
+            match parse_number(&text) {
+                Some(number) => TokenKind::Number(number),
+                None => TokenKind::Word(text),
+            }
+        } else if c == '"' || c == '\'' {
+            position += 1;
+            while let Some(&next) = chars.get(position) {
+                if next == '\n' {
+                    break;
+                }
+                position += 1;
+                if next == '\\' {
+                    position += 1;
+                } else if next == c {
+                    break;
+                }
+            }
+            let contents: String = chars[start + 1..position.saturating_sub(1).max(start + 1)]
+                .iter()
+                .collect();
+            match c {
+                '"' => TokenKind::Text,
+                _ => TokenKind::Number(parse_character(&contents).unwrap_or(0)),
+            }
+        } else {
+            position += 1;
+            TokenKind::Symbol(c)
+        };
+        tokens.push(Token {
+            kind,
+            line: token_line,
+        });
+    }
+    tokens
+}
+
+/// reads the directives that matter for layout, `#define` and `#pragma pack`, ignoring the rest
+fn directive_token(
+    directive: &str,
+    line: usize,
+    problems: &mut Vec<HeaderProblem>,
+) -> Option<TokenKind> {
+    let directive = directive.trim_start();
+    let name_end = directive
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(directive.len());
+    let (name, rest) = directive.split_at(name_end);
+    match name {
+        "define" => {
+            let rest = rest.trim_start();
+            let macro_end = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            let (macro_name, value) = rest.split_at(macro_end);
+            // function-like macros aren't expanded
+            if macro_name.is_empty() || value.starts_with('(') {
+                return None;
+            }
+            Some(TokenKind::Define(
+                macro_name.to_owned(),
+                lex(value, line, problems),
+            ))
+        }
+        "pragma" => {
+            let tokens = lex(rest, line, problems);
+            match tokens.first().map(|token| &token.kind) {
+                Some(TokenKind::Word(word)) if word == "pack" => {}
+                _ => return None,
+            }
+            let arguments: Vec<&TokenKind> = tokens[1..]
+                .iter()
+                .map(|token| &token.kind)
+                .filter(|kind| {
+                    !matches!(
+                        kind,
+                        TokenKind::Symbol('(') | TokenKind::Symbol(')') | TokenKind::Symbol(',')
+                    )
+                })
+                .collect();
+            let value = arguments.iter().find_map(|kind| match kind {
+                TokenKind::Number(number) if *number > 0 => Some(*number as usize),
+                _ => None,
+            });
+            match arguments.first() {
+                None | Some(TokenKind::Number(_)) => Some(TokenKind::Pack(Pack::Set(value))),
+                Some(TokenKind::Word(word)) if word == "push" => {
+                    Some(TokenKind::Pack(Pack::Push(value)))
+                }
+                Some(TokenKind::Word(word)) if word == "pop" => Some(TokenKind::Pack(Pack::Pop)),
+                _ => {
+                    problems.push(problem(
+                        line,
+                        format!("unknown #pragma pack{}", rest.trim()),
+                    ));
+                    None
+                }
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Works out the value of constant expressions, like array sizes and enum values
+struct Evaluator<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    constants: &'a HashMap<String, i64>,
+}
+
+impl Evaluator<'_> {
+    fn kind(&self, ahead: usize) -> Option<&TokenKind> {
+        self.tokens
+            .get(self.position + ahead)
+            .map(|token| &token.kind)
+    }
+
+    /// returns the (precedence, length in tokens, symbol) of the binary operator at the cursor
+    fn binary_operator(&self) -> Option<(u8, usize, char)> {
+        match (self.kind(0)?, self.kind(1)) {
+            (TokenKind::Symbol('<'), Some(TokenKind::Symbol('<'))) => Some((4, 2, '<')),
+            (TokenKind::Symbol('>'), Some(TokenKind::Symbol('>'))) => Some((4, 2, '>')),
+            (TokenKind::Symbol(symbol), _) => {
+                let precedence = match symbol {
+                    '|' => 1,
+                    '^' => 2,
+                    '&' => 3,
+                    '+' | '-' => 5,
+                    '*' | '/' | '%' => 6,
+                    _ => return None,
+                };
+                Some((precedence, 1, *symbol))
+            }
+            _ => None,
+        }
+    }
+
+    fn expression(&mut self, minimum_precedence: u8) -> Result<i64, String> {
+        let mut value = self.unary()?;
+        while let Some((precedence, length, operator)) = self.binary_operator() {
+            if precedence < minimum_precedence {
+                break;
+            }
+            self.position += length;
+            let right = self.expression(precedence + 1)?;
+            value = match operator {
+                '|' => Some(value | right),
+                '^' => Some(value ^ right),
+                '&' => Some(value & right),
+                '<' => u32::try_from(right)
+                    .ok()
+                    .and_then(|right| value.checked_shl(right)),
+                '>' => u32::try_from(right)
+                    .ok()
+                    .and_then(|right| value.checked_shr(right)),
+                '+' => value.checked_add(right),
+                '-' => value.checked_sub(right),
+                '*' => value.checked_mul(right),
+                '/' => value.checked_div(right),
+                _ => value.checked_rem(right),
+            }
+            .ok_or("the value can't be worked out, it overflows or divides by zero")?;
+        }
+        Ok(value)
+    }
+
+    fn unary(&mut self) -> Result<i64, String> {
+        let kind = self.kind(0).cloned();
+        self.position += 1;
+        match kind {
+            Some(TokenKind::Number(number)) => Ok(number),
+            Some(TokenKind::Symbol('-')) => self
+                .unary()?
+                .checked_neg()
+                .ok_or("the value can't be worked out, it overflows".to_owned()),
+            Some(TokenKind::Symbol('+')) => self.unary(),
+            Some(TokenKind::Symbol('~')) => Ok(!self.unary()?),
+            Some(TokenKind::Symbol('(')) => {
+                // casts like "(uint8_t)" don't change the value
+                if let (Some(TokenKind::Word(word)), Some(TokenKind::Symbol(')'))) =
+                    (self.kind(0), self.kind(1))
+                {
+                    if !self.constants.contains_key(word) {
+                        self.position += 2;
+                        return self.unary();
+                    }
+                }
+                let value = self.expression(0)?;
+                match self.kind(0) {
+                    Some(TokenKind::Symbol(')')) => {
+                        self.position += 1;
+                        Ok(value)
+                    }
+                    other => Err(format!("expected ')' but found {}", describe(other))),
+                }
+            }
+            Some(TokenKind::Word(word)) => self
+                .constants
+                .get(&word)
+                .copied()
+                .ok_or(format!("'{}' isn't a known constant", word)),
+            other => Err(format!(
+                "expected a number but found {}",
+                describe(other.as_ref())
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+/// A C type, as far as its layout goes
+enum CType {
+    /// a number, with names for its values if it is an enum
+    Scalar(PacketFieldType, BTreeMap<i64, String>),
+    /// a `char`, arrays of them become strings
+    Char,
+    /// a struct, by its index in `Parser::records`
+    Struct(usize),
+    Array(Box<CType>, usize),
+    /// a type that can't be sent as it is, and why
+    Unmappable(String),
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+/// The layout attributes given to a struct or member
+struct Attributes {
+    packed: bool,
+    aligned: Option<usize>,
+}
+
+impl Attributes {
+    fn merge(self, other: Attributes) -> Attributes {
+        Attributes {
+            packed: self.packed || other.packed,
+            aligned: self.aligned.max(other.aligned),
+        }
+    }
+}
+
+/// A declared name, with the type the declaration gives it
+struct Declarator {
+    name: Option<String>,
+    ctype: CType,
+    bit_width: Option<usize>,
+    attributes: Attributes,
+    line: usize,
+}
+
+/// A struct that has been laid out, with its fields placed relative to its start
+struct Record {
+    tag: Option<String>,
+    typedef_name: Option<String>,
+    line: usize,
+    fields: Vec<PacketField>,
+    size: usize,
+    alignment: usize,
+    /// some of its members couldn't be mapped, which have already been reported
+    broken: bool,
+    /// it is a member of another struct, so isn't a packet by itself
+    nested: bool,
+}
+
+impl Record {
+    fn name(&self) -> Option<&String> {
+        self.typedef_name.as_ref().or(self.tag.as_ref())
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    /// `#define`d numbers and enum values
+    constants: HashMap<String, i64>,
+    typedefs: HashMap<String, CType>,
+    /// struct, union and enum tags, ie "struct gps"
+    tags: HashMap<String, CType>,
+    records: Vec<Record>,
+    pack: Option<usize>,
+    pack_stack: Vec<Option<usize>>,
+    problems: Vec<HeaderProblem>,
+}
+
+impl Parser {
+    /// applies any directives at the cursor, then returns the token there
+    fn current(&mut self) -> Token {
+        while let Some(token) = self.tokens.get(self.position).cloned() {
+            match token.kind {
+                TokenKind::Define(name, tokens) => {
+                    let mut evaluator = Evaluator {
+                        tokens: &tokens,
+                        position: 0,
+                        constants: &self.constants,
+                    };
+                    if let Ok(value) = evaluator.expression(0) {
+                        if evaluator.position == tokens.len() {
+                            self.constants.insert(name, value);
+                        }
+                    }
+                }
+                TokenKind::Pack(Pack::Set(pack)) => self.pack = pack,
+                TokenKind::Pack(Pack::Push(pack)) => {
+                    self.pack_stack.push(self.pack);
+                    if pack.is_some() {
+                        self.pack = pack;
+                    }
+                }
+                TokenKind::Pack(Pack::Pop) => self.pack = self.pack_stack.pop().flatten(),
+                _ => return token,
+            }
+            self.position += 1;
+        }
+        unreachable!("the tokens always finish with an End token")
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.current();
+        if token.kind != TokenKind::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn skip_symbol(&mut self, symbol: char) -> bool {
+        let matched = self.current().kind == TokenKind::Symbol(symbol);
+        if matched {
+            self.position += 1;
+        }
+        matched
+    }
+
+    fn expect_symbol(&mut self, symbol: char) -> Result<(), HeaderProblem> {
+        let token = self.current();
+        match self.skip_symbol(symbol) {
+            true => Ok(()),
+            false => Err(problem(
+                token.line,
+                format!(
+                    "expected '{}' but found {}",
+                    symbol,
+                    describe(Some(&token.kind))
+                ),
+            )),
+        }
+    }
+
+    /// skips `const` and the other qualifiers that don't change the layout of a type
+    fn skip_qualifiers(&mut self) {
+        while let TokenKind::Word(word) = self.current().kind {
+            match word.as_str() {
+                "const" | "volatile" | "restrict" | "static" | "extern" | "register" | "inline"
+                | "__inline" | "__inline__" | "__extension__" | "__volatile__" | "__const"
+                | "__restrict" => self.position += 1,
+                _ => return,
+            }
+        }
+    }
+
+    fn expression(&mut self) -> Result<i64, HeaderProblem> {
+        let line = self.current().line;
+        let mut evaluator = Evaluator {
+            tokens: &self.tokens[self.position..],
+            position: 0,
+            constants: &self.constants,
+        };
+        let value = evaluator.expression(0);
+        self.position += evaluator.position;
+        value.map_err(|message| problem(line, message))
+    }
+
+    /// reads an expression that has to be a count of something
+    fn count(&mut self, what: &str) -> Result<usize, HeaderProblem> {
+        let line = self.current().line;
+        let value = self.expression()?;
+        usize::try_from(value)
+            .map_err(|_| problem(line, format!("{} can't be negative, it is {}", what, value)))
+    }
+
+    /// skips the rest of a declaration that isn't needed, like a function or a variable
+    fn skip_statement(&mut self) {
+        let mut depth = 0usize;
+        let mut function_body = false;
+        let mut last = TokenKind::End;
+        loop {
+            let token = self.next();
+            match token.kind {
+                TokenKind::End => return,
+                TokenKind::Symbol('{') if depth == 0 && last == TokenKind::Symbol(')') => {
+                    function_body = true;
+                    depth += 1;
+                }
+                TokenKind::Symbol('(') | TokenKind::Symbol('[') | TokenKind::Symbol('{') => {
+                    depth += 1
+                }
+                TokenKind::Symbol(')') | TokenKind::Symbol(']') | TokenKind::Symbol('}') => {
+                    depth = depth.saturating_sub(1);
+                    if depth == 0 && function_body {
+                        return;
+                    }
+                }
+                TokenKind::Symbol(';') if depth == 0 => return,
+                _ => {}
+            }
+            last = token.kind;
+        }
+    }
+
+    /// skips the rest of a struct member, stopping before the end of the struct
+    fn skip_member(&mut self) {
+        let mut depth = 0usize;
+        loop {
+            match self.current().kind {
+                TokenKind::End => return,
+                TokenKind::Symbol('}') if depth == 0 => return,
+                TokenKind::Symbol(';') if depth == 0 => {
+                    self.position += 1;
+                    return;
+                }
+                TokenKind::Symbol('(') | TokenKind::Symbol('[') | TokenKind::Symbol('{') => {
+                    depth += 1
+                }
+                TokenKind::Symbol(')') | TokenKind::Symbol(']') | TokenKind::Symbol('}') => {
+                    depth = depth.saturating_sub(1)
+                }
+                _ => {}
+            }
+            self.position += 1;
+        }
+    }
+
+    fn parse(&mut self) {
+        loop {
+            let token = self.current();
+            let result = match &token.kind {
+                TokenKind::End => return,
+                // the braces of `extern "C" { ... }` and stray semicolons
+                TokenKind::Symbol(';') | TokenKind::Symbol('}') | TokenKind::Text => {
+                    self.position += 1;
+                    continue;
+                }
+                TokenKind::Word(word) if word == "extern" => {
+                    self.position += 1;
+                    if self.current().kind == TokenKind::Text {
+                        self.position += 1;
+                        self.skip_symbol('{');
+                    }
+                    continue;
+                }
+                TokenKind::Word(word) if word == "typedef" => {
+                    self.position += 1;
+                    self.typedef()
+                }
+                _ => self.specifier().map(|_| {
+                    if !self.skip_symbol(';') {
+                        self.skip_statement();
+                    }
+                }),
+            };
+            if let Err(problem) = result {
+                self.problems.push(problem);
+                self.skip_statement();
+            }
+        }
+    }
+
+    fn typedef(&mut self) -> Result<(), HeaderProblem> {
+        let base = self.specifier()?;
+        loop {
+            let declarator = self.declarator(base.clone())?;
+            if let Some(name) = declarator.name {
+                if let CType::Struct(index) = declarator.ctype {
+                    let record = &mut self.records[index];
+                    record.typedef_name = record.typedef_name.take().or(Some(name.clone()));
+                }
+                self.typedefs.insert(name, declarator.ctype);
+            }
+            if !self.skip_symbol(',') {
+                break;
+            }
+        }
+        self.expect_symbol(';')
+    }
+
+    /// reads the type at the start of a declaration
+    fn specifier(&mut self) -> Result<CType, HeaderProblem> {
+        let mut attributes = Attributes::default();
+        let mut words: Vec<String> = vec![];
+        loop {
+            self.skip_qualifiers();
+            let token = self.current();
+            let word = match token.kind {
+                TokenKind::Word(word) => word,
+                _ => break,
+            };
+            match word.as_str() {
+                "__attribute__" | "__attribute" | "__packed" | "__PACKED" | "_Alignas"
+                | "alignas" => attributes = attributes.merge(self.attributes()?),
+                "struct" | "union" | "enum" if words.is_empty() => {
+                    self.position += 1;
+                    let ctype = match word.as_str() {
+                        "enum" => self.enumeration(attributes, token.line)?,
+                        kind => self.record(kind, attributes, token.line)?,
+                    };
+                    self.skip_qualifiers();
+                    return Ok(ctype);
+                }
+                "signed" | "unsigned" | "short" | "long" | "int" | "char" | "float" | "double"
+                | "_Bool" | "bool" | "void" => {
+                    self.position += 1;
+                    words.push(word);
+                }
+                _ if words.is_empty() => {
+                    self.position += 1;
+                    return Ok(self.named_type(&word));
+                }
+                // the name being declared
+                _ => break,
+            }
+        }
+        match words.is_empty() {
+            true => Ok(CType::Unmappable("there is no type".to_owned())),
+            false => Ok(builtin_type(&words)),
+        }
+    }
+
+    /// finds the type a name refers to, which is either a typedef or a fixed width integer
+    fn named_type(&self, name: &str) -> CType {
+        if let Some(ctype) = self.typedefs.get(name) {
+            return ctype.clone();
+        }
+        if let Some((_, r#type)) = FIXED_WIDTH_TYPES
+            .iter()
+            .find(|(type_name, _)| *type_name == name)
+        {
+            return CType::Scalar(*r#type, BTreeMap::new());
+        }
+        match name {
+            "size_t" | "ssize_t" | "uintptr_t" | "intptr_t" | "ptrdiff_t" | "wchar_t" => {
+                CType::Unmappable(format!("the size of {} depends on the platform", name))
+            }
+            _ => CType::Unmappable(format!("the type '{}' is never defined", name)),
+        }
+    }
+
+    /// reads any `__attribute__((...))`, `__packed` or `_Alignas(N)` at the cursor
+    fn attributes(&mut self) -> Result<Attributes, HeaderProblem> {
+        let mut attributes = Attributes::default();
+        loop {
+            let token = self.current();
+            let word = match token.kind {
+                TokenKind::Word(word) => word,
+                _ => return Ok(attributes),
+            };
+            match word.as_str() {
+                "__packed" | "__PACKED" => {
+                    self.position += 1;
+                    attributes.packed = true;
+                }
+                "_Alignas" | "alignas" => {
+                    self.position += 1;
+                    self.expect_symbol('(')?;
+                    let aligned = self.count("an alignment")?;
+                    self.expect_symbol(')')?;
+                    attributes.aligned = attributes.aligned.max(Some(aligned));
+                }
+                "__attribute__" | "__attribute" => {
+                    self.position += 1;
+                    self.expect_symbol('(')?;
+                    self.expect_symbol('(')?;
+                    loop {
+                        let item = self.next();
+                        let name = match item.kind {
+                            TokenKind::Symbol(')') => break,
+                            TokenKind::Symbol(',') => continue,
+                            TokenKind::Word(name) => name,
+                            other => {
+                                return Err(problem(
+                                    item.line,
+                                    format!(
+                                        "expected an attribute but found {}",
+                                        describe(Some(&other))
+                                    ),
+                                ))
+                            }
+                        };
+                        match name.trim_matches('_') {
+                            "packed" => attributes.packed = true,
+                            "aligned" => {
+                                self.expect_symbol('(')?;
+                                let aligned = self.count("an alignment")?;
+                                self.expect_symbol(')')?;
+                                attributes.aligned = attributes.aligned.max(Some(aligned));
+                            }
+                            // attributes that don't change the layout, and their arguments
+                            _ => {
+                                if self.current().kind == TokenKind::Symbol('(') {
+                                    self.skip_arguments();
+                                }
+                            }
+                        }
+                    }
+                    self.expect_symbol(')')?;
+                }
+                _ => return Ok(attributes),
+            }
+        }
+    }
+
+    /// skips a balanced set of parentheses starting at the cursor
+    fn skip_arguments(&mut self) {
+        let mut depth = 0usize;
+        loop {
+            let token = self.next();
+            match token.kind {
+                TokenKind::Symbol('(') => depth += 1,
+                TokenKind::Symbol(')') => depth -= 1,
+                TokenKind::End => return,
+                _ => {}
+            }
+            if depth == 0 {
+                return;
+            }
+        }
+    }
+
+    /// reads a struct or union after its keyword, laying it out if it has a body
+    fn record(
+        &mut self,
+        kind: &str,
+        attributes: Attributes,
+        line: usize,
+    ) -> Result<CType, HeaderProblem> {
+        let mut attributes = attributes.merge(self.attributes()?);
+        let tag = match self.current().kind {
+            TokenKind::Word(tag) => {
+                self.position += 1;
+                Some(tag)
+            }
+            _ => None,
+        };
+        if !self.skip_symbol('{') {
+            let tag = tag.ok_or_else(|| {
+                problem(line, format!("expected a name or a body after '{}'", kind))
+            })?;
+            return Ok(self
+                .tags
+                .get(&format!("{} {}", kind, tag))
+                .cloned()
+                .unwrap_or_else(|| {
+                    CType::Unmappable(format!("{} {} is never defined", kind, tag))
+                }));
+        }
+        let (members, broken) = self.members(line)?;
+        attributes = attributes.merge(self.attributes()?);
+        let ctype = match kind {
+            "union" => CType::Unmappable(
+                "unions can't be mapped, as which member was sent isn't known".to_owned(),
+            ),
+            _ => {
+                let record = self.layout(tag.clone(), line, members, broken, attributes);
+                self.records.push(record);
+                CType::Struct(self.records.len() - 1)
+            }
+        };
+        if let Some(tag) = tag {
+            self.tags.insert(format!("{} {}", kind, tag), ctype.clone());
+        }
+        Ok(ctype)
+    }
+
+    /// reads the members of a struct up to its closing brace
+    ///
+    /// members that can't be read are reported and skipped, marking the struct as broken
+    fn members(&mut self, line: usize) -> Result<(Vec<Declarator>, bool), HeaderProblem> {
+        let mut members = vec![];
+        let mut broken = false;
+        while !self.skip_symbol('}') {
+            if self.current().kind == TokenKind::End {
+                return Err(problem(line, "this struct never ends"));
+            }
+            if let Err(problem) = self.member(&mut members) {
+                self.problems.push(problem);
+                self.skip_member();
+                broken = true;
+            }
+        }
+        Ok((members, broken))
+    }
+
+    fn member(&mut self, members: &mut Vec<Declarator>) -> Result<(), HeaderProblem> {
+        let line = self.current().line;
+        let base = self.specifier()?;
+        if self.skip_symbol(';') {
+            // anonymous structs put their members straight into the struct around them
+            members.push(Declarator {
+                name: None,
+                ctype: base,
+                bit_width: None,
+                attributes: Attributes::default(),
+                line,
+            });
+            return Ok(());
+        }
+        loop {
+            members.push(self.declarator(base.clone())?);
+            if !self.skip_symbol(',') {
+                break;
+            }
+        }
+        self.expect_symbol(';')
+    }
+
+    /// reads the name being declared, along with any pointers, array sizes or bit width
+    fn declarator(&mut self, base: CType) -> Result<Declarator, HeaderProblem> {
+        let line = self.current().line;
+        let mut pointer = false;
+        loop {
+            self.skip_qualifiers();
+            if !self.skip_symbol('*') {
+                break;
+            }
+            pointer = true;
+        }
+        if self.current().kind == TokenKind::Symbol('(') {
+            // function pointers and pointers to arrays, like "void (*callback)(int)"
+            let name = match (
+                self.tokens.get(self.position + 1).map(|token| &token.kind),
+                self.tokens.get(self.position + 2).map(|token| &token.kind),
+            ) {
+                (Some(TokenKind::Symbol('*')), Some(TokenKind::Word(name))) => Some(name.clone()),
+                _ => None,
+            };
+            let mut depth = 0usize;
+            loop {
+                match self.current().kind {
+                    TokenKind::End => break,
+                    TokenKind::Symbol(',') | TokenKind::Symbol(';') | TokenKind::Symbol('}')
+                        if depth == 0 =>
+                    {
+                        break
+                    }
+                    TokenKind::Symbol('(') | TokenKind::Symbol('[') => depth += 1,
+                    TokenKind::Symbol(')') | TokenKind::Symbol(']') => {
+                        depth = depth.saturating_sub(1)
+                    }
+                    _ => {}
+                }
+                self.position += 1;
+            }
+            return Ok(Declarator {
+                name,
+                ctype: CType::Unmappable("it is a pointer".to_owned()),
+                bit_width: None,
+                attributes: Attributes::default(),
+                line,
+            });
+        }
+
+        let mut attributes = self.attributes()?;
+        let name = match self.current().kind {
+            TokenKind::Word(name) => {
+                self.position += 1;
+                Some(name)
+            }
+            _ => None,
+        };
+        attributes = attributes.merge(self.attributes()?);
+        let mut dimensions = vec![];
+        while self.skip_symbol('[') {
+            match self.skip_symbol(']') {
+                true => dimensions.push(0),
+                false => {
+                    dimensions.push(self.count("an array size")?);
+                    self.expect_symbol(']')?;
+                }
+            }
+        }
+        let bit_width = match self.skip_symbol(':') {
+            true => Some(self.count("a bit width")?),
+            false => None,
+        };
+        attributes = attributes.merge(self.attributes()?);
+
+        let mut ctype = match pointer {
+            true => CType::Unmappable("it is a pointer".to_owned()),
+            false => base,
+        };
+        for count in dimensions.into_iter().rev() {
+            ctype = match count {
+                0 => CType::Unmappable("arrays without a size can't be mapped".to_owned()),
+                count => CType::Array(Box::new(ctype), count),
+            };
+        }
+        Ok(Declarator {
+            name,
+            ctype,
+            bit_width,
+            attributes,
+            line,
+        })
+    }
+
+    /// reads an enum after its keyword, registering its values as constants
+    fn enumeration(&mut self, attributes: Attributes, line: usize) -> Result<CType, HeaderProblem> {
+        let tag = match self.current().kind {
+            TokenKind::Word(tag) => {
+                self.position += 1;
+                Some(tag)
+            }
+            _ => None,
+        };
+        // enums can be given their type, ie "enum state : uint8_t"
+        let mut r#type = None;
+        if self.skip_symbol(':') {
+            match self.specifier()? {
+                CType::Scalar(underlying, _) => r#type = Some(underlying),
+                _ => return Err(problem(line, "enums can only be based on integer types")),
+            }
+        }
+        if !self.skip_symbol('{') {
+            let tag = tag.ok_or_else(|| problem(line, "expected a name or a body after 'enum'"))?;
+            return Ok(match (self.tags.get(&format!("enum {}", tag)), r#type) {
+                (Some(ctype), _) => ctype.clone(),
+                (None, Some(r#type)) => CType::Scalar(r#type, BTreeMap::new()),
+                (None, None) => CType::Unmappable(format!("enum {} is never defined", tag)),
+            });
+        }
+        let mut labels = BTreeMap::new();
+        let mut value = 0i64;
+        loop {
+            if self.skip_symbol('}') {
+                break;
+            }
+            let token = self.next();
+            let name = match token.kind {
+                TokenKind::Word(name) => name,
+                other => {
+                    return Err(problem(
+                        token.line,
+                        format!(
+                            "expected the name of an enum value but found {}",
+                            describe(Some(&other))
+                        ),
+                    ))
+                }
+            };
+            self.attributes()?;
+            if self.skip_symbol('=') {
+                value = self.expression()?;
+            }
+            self.constants.insert(name.clone(), value);
+            // the first name given to a value is kept
+            labels.entry(value).or_insert(name);
+            value = value.wrapping_add(1);
+            if !self.skip_symbol(',') {
+                self.expect_symbol('}')?;
+                break;
+            }
+        }
+        let attributes = attributes.merge(self.attributes()?);
+        let negative = labels.keys().any(|value| *value < 0);
+        let r#type = r#type.unwrap_or_else(|| {
+            // packed enums shrink to the smallest type that holds all of their values
+            let size = match attributes.packed {
+                true => [1, 2]
+                    .into_iter()
+                    .find(|size| {
+                        let bits = size * 8 - usize::from(negative);
+                        labels
+                            .keys()
+                            .all(|value| *value >= -(1 << bits) && *value < (1 << bits))
+                    })
+                    .unwrap_or(4),
+                false => 4,
+            };
+            integer_type(size, negative)
+        });
+        let ctype = CType::Scalar(r#type, labels);
+        if let Some(tag) = tag {
+            self.tags.insert(format!("enum {}", tag), ctype.clone());
+        }
+        Ok(ctype)
+    }
+
+    /// returns the (size, alignment) of a type
+    fn type_layout(&self, ctype: &CType) -> (usize, usize) {
+        match ctype {
+            CType::Scalar(r#type, _) => {
+                let size = r#type.size().unwrap_or(1);
+                (size, size)
+            }
+            CType::Char => (1, 1),
+            CType::Struct(index) => (self.records[*index].size, self.records[*index].alignment),
+            CType::Array(element, count) => {
+                let (size, alignment) = self.type_layout(element);
+                (size * count, alignment)
+            }
+            CType::Unmappable(_) => (0, 1),
+        }
+    }
+
+    /// returns why a type can't be mapped, if it can't be
+    fn unmappable_reason(&self, ctype: &CType) -> Option<String> {
+        match ctype {
+            CType::Unmappable(reason) => Some(reason.clone()),
+            CType::Array(element, _) => self.unmappable_reason(element),
+            CType::Struct(index) if self.records[*index].broken => {
+                Some(match self.records[*index].name() {
+                    Some(name) => format!("'{}' has members that can't be mapped", name),
+                    None => "it has members that can't be mapped".to_owned(),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// marks the struct a member is made of as nested, so it isn't imported as a packet by itself
+    fn mark_nested(&mut self, ctype: &CType) {
+        match ctype {
+            CType::Struct(index) => self.records[*index].nested = true,
+            CType::Array(element, _) => self.mark_nested(element),
+            _ => {}
+        }
+    }
+
+    /// lays out the members of a struct the way a C compiler would
+    fn layout(
+        &mut self,
+        tag: Option<String>,
+        line: usize,
+        members: Vec<Declarator>,
+        mut broken: bool,
+        attributes: Attributes,
+    ) -> Record {
+        let pack = match attributes.packed {
+            true => Some(1),
+            false => self.pack,
+        };
+        let mut fields = vec![];
+        // the next free bit, counted from the start of the struct
+        let mut bit = 0;
+        let mut alignment = 1;
+        for member in members {
+            self.mark_nested(&member.ctype);
+            let member_name = member
+                .name
+                .clone()
+                .unwrap_or_else(|| "an unnamed member".to_owned());
+            if let Some(reason) = self.unmappable_reason(&member.ctype) {
+                self.problems.push(problem(
+                    member.line,
+                    format!("'{}' can't be mapped: {}", member_name, reason),
+                ));
+                broken = true;
+                continue;
+            }
+            let (size, natural_alignment) = self.type_layout(&member.ctype);
+            let mut member_alignment = match member.attributes.packed {
+                true => 1,
+                false => natural_alignment,
+            };
+            member_alignment = pack.map_or(member_alignment, |pack| member_alignment.min(pack));
+            member_alignment = member_alignment.max(member.attributes.aligned.unwrap_or(1));
+
+            let bit_width = match member.bit_width {
+                Some(bit_width) => bit_width,
+                None => {
+                    let offset = round_up(bit_ceil(bit), member_alignment);
+                    alignment = alignment.max(member_alignment);
+                    match (&member.name, &member.ctype) {
+                        (Some(name), ctype) => self.member_fields(ctype, name, offset, &mut fields),
+                        (None, CType::Struct(index)) => {
+                            fields.extend(self.records[*index].fields.iter().map(|field| {
+                                PacketField {
+                                    offset_in_packet: field.offset_in_packet + offset,
+                                    ..field.clone()
+                                }
+                            }))
+                        }
+                        _ => {}
+                    }
+                    bit = (offset + size) * 8;
+                    continue;
+                }
+            };
+
+            let (r#type, labels) = match &member.ctype {
+                CType::Scalar(r#type, labels)
+                    if !matches!(r#type, PacketFieldType::Float | PacketFieldType::Double) =>
+                {
+                    (*r#type, labels.clone())
+                }
+                CType::Char => (PacketFieldType::SignedByte, BTreeMap::new()),
+                _ => {
+                    self.problems.push(problem(
+                        member.line,
+                        format!("'{}' can't be a bitfield, it isn't an integer", member_name),
+                    ));
+                    broken = true;
+                    continue;
+                }
+            };
+            if bit_width > size * 8 {
+                self.problems.push(problem(
+                    member.line,
+                    format!(
+                        "'{}' is {} bits wide, which doesn't fit in its type",
+                        member_name, bit_width
+                    ),
+                ));
+                broken = true;
+                continue;
+            }
+            let unit = size * 8;
+            if bit_width == 0 {
+                // a zero width bitfield starts a new container
+                bit = round_up(bit, unit);
+                continue;
+            }
+            let (offset, bit_offset, r#type) = if member.attributes.packed || pack == Some(1) {
+                // packed bitfields carry on straight after the last bit, so the container might
+                // have to be wider than the type to hold a bitfield that crosses into more bytes
+                let bit_offset = bit % 8;
+                let r#type = match r#type {
+                    PacketFieldType::Bool => r#type,
+                    r#type => integer_type(
+                        bit_ceil(bit_offset + bit_width),
+                        CONTAINER_TYPES
+                            .iter()
+                            .any(|(_, _, signed)| *signed == r#type),
+                    ),
+                };
+                (bit / 8, bit_offset, r#type)
+            } else {
+                // unpacked bitfields never cross the boundary of a container of their type
+                if bit % unit + bit_width > unit {
+                    bit = round_up(bit, unit);
+                }
+                if member.name.is_some() {
+                    alignment = alignment.max(member_alignment);
+                }
+                (bit / unit * size, bit % unit, r#type)
+            };
+            bit += bit_width;
+            if let Some(name) = member.name {
+                let mut field = new_field(name, r#type, offset, None, labels);
+                field.bitfield = Some(PacketBitfield {
+                    bit_offset,
+                    bit_width,
+                });
+                fields.push(field);
+            }
+        }
+        let alignment = alignment.max(attributes.aligned.unwrap_or(1));
+        Record {
+            tag,
+            typedef_name: None,
+            line,
+            fields,
+            size: round_up(bit_ceil(bit), alignment),
+            alignment,
+            broken,
+            nested: false,
+        }
+    }
+
+    /// adds the fields making up a member of type `ctype` placed at `offset` to `fields`
+    fn member_fields(
+        &self,
+        ctype: &CType,
+        name: &str,
+        offset: usize,
+        fields: &mut Vec<PacketField>,
+    ) {
+        match ctype {
+            CType::Scalar(r#type, labels) => fields.push(new_field(
+                name.to_owned(),
+                *r#type,
+                offset,
+                None,
+                labels.clone(),
+            )),
+            CType::Char => fields.push(new_field(
+                name.to_owned(),
+                PacketFieldType::String,
+                offset,
+                Some(StringFormat::Fixed { length: 1 }),
+                BTreeMap::new(),
+            )),
+            CType::Struct(index) => {
+                fields.extend(self.records[*index].fields.iter().map(|field| PacketField {
+                    name: format!("{}.{}", name, field.name),
+                    offset_in_packet: field.offset_in_packet + offset,
+                    ..field.clone()
+                }))
+            }
+            CType::Array(element, length) if matches!(**element, CType::Char) => {
+                fields.push(new_field(
+                    name.to_owned(),
+                    PacketFieldType::String,
+                    offset,
+                    Some(StringFormat::Fixed { length: *length }),
+                    BTreeMap::new(),
+                ))
+            }
+            CType::Array(element, count) => {
+                // arrays of arrays are read as one long array, except for arrays of strings
+                let mut element = &**element;
+                let mut count = *count;
+                while let CType::Array(inner, inner_count) = element {
+                    if matches!(**inner, CType::Char) {
+                        break;
+                    }
+                    count *= inner_count;
+                    element = inner;
+                }
+                let (element_size, _) = self.type_layout(element);
+                let mut element_fields = vec![];
+                self.member_fields(element, name, offset, &mut element_fields);
+                if element_fields.iter().all(|field| field.array.is_none()) {
+                    // the members of a struct are numbered together, element by element
+                    let group_size = match element_fields.len() {
+                        1 => 0,
+                        members => members,
+                    };
+                    fields.extend(element_fields.into_iter().map(|field| PacketField {
+                        array: Some(PacketArray {
+                            count,
+                            stride: element_size,
+                            count_field: None,
+                            group_size,
+                        }),
+                        ..field
+                    }));
+                } else {
+                    // fields can't be arrays of arrays, so each element gets its own fields
+                    for element_index in 0..count {
+                        self.member_fields(
+                            element,
+                            &format!("{}[{}]", name, element_index),
+                            offset + element_index * element_size,
+                            fields,
+                        );
+                    }
+                }
+            }
+            CType::Unmappable(_) => {}
+        }
+    }
+
+    /// turns every struct that isn't nested in another into a packet structure
+    fn packet_structures(&mut self) -> Vec<PacketStructure> {
+        let mut structures: Vec<PacketStructure> = vec![];
+        let mut problems = vec![];
+        for record in &self.records {
+            let name = match record.name() {
+                Some(name) if !record.nested => name.clone(),
+                _ => continue,
+            };
+            if record.broken {
+                problems.push(problem(
+                    record.line,
+                    format!(
+                        "'{}' was left out because some of its members can't be mapped",
+                        name
+                    ),
+                ));
+                continue;
+            }
+            if structures.iter().any(|structure| structure.name == name) {
+                problems.push(problem(
+                    record.line,
+                    format!(
+                        "more than one struct is named '{}', only the first was imported",
+                        name
+                    ),
+                ));
+                continue;
+            }
+            let mut structure = PacketStructure::make_default(name);
+            structure.byte_defined = true;
+            for field in &record.fields {
+                let index = structure.next_field_index(field);
+                structure.fields.push(PacketField {
+                    index,
+                    ..field.clone()
+                });
+            }
+            // keep any padding at the end of the struct as part of the packet
+            if record.size > structure.size() {
+                structure.size = Some(record.size);
+            }
+            structures.push(structure);
+        }
+        self.problems.extend(problems);
+        structures
+    }
+}
+
+/// works out the type of a list of builtin type keywords, like "unsigned short int"
+fn builtin_type(words: &[String]) -> CType {
+    let has = |keyword: &str| words.iter().any(|word| word == keyword);
+    let longs = words.iter().filter(|word| *word == "long").count();
+    let integer = |size| CType::Scalar(integer_type(size, !has("unsigned")), BTreeMap::new());
+    if has("void") {
+        CType::Unmappable("void has no size".to_owned())
+    } else if has("float") {
+        CType::Scalar(PacketFieldType::Float, BTreeMap::new())
+    } else if has("double") && longs > 0 {
+        CType::Unmappable("the size of long double depends on the platform".to_owned())
+    } else if has("double") {
+        CType::Scalar(PacketFieldType::Double, BTreeMap::new())
+    } else if has("_Bool") || has("bool") {
+        CType::Scalar(PacketFieldType::Bool, BTreeMap::new())
+    } else if has("char") && !has("signed") && !has("unsigned") {
+        CType::Char
+    } else if has("char") {
+        integer(1)
+    } else if has("short") {
+        integer(2)
+    } else if longs == 1 {
+        CType::Unmappable(
+            "the size of long depends on the platform, use int32_t or int64_t".to_owned(),
+        )
+    } else if longs > 1 {
+        integer(8)
+    } else {
+        integer(4)
+    }
+}
+
+/// returns the smallest integer type at least `size` bytes wide
+fn integer_type(size: usize, signed: bool) -> PacketFieldType {
+    let (_, unsigned_type, signed_type) = CONTAINER_TYPES
+        .iter()
+        .find(|(container_size, _, _)| *container_size >= size)
+        .unwrap_or(&CONTAINER_TYPES[CONTAINER_TYPES.len() - 1]);
+    match signed {
+        true => *signed_type,
+        false => *unsigned_type,
+    }
+}
+
+fn new_field(
+    name: String,
+    r#type: PacketFieldType,
+    offset_in_packet: usize,
+    string_format: Option<StringFormat>,
+    labels: BTreeMap<i64, String>,
+) -> PacketField {
+    PacketField {
+        index: 0,
+        name,
+        r#type,
+        offset_in_packet,
+        endianness: Endianness::Little,
+        bitfield: None,
+        string_format,
+        array: None,
+        calibration: None,
+        unit: None,
+        labels,
+        timestamp: None,
+        sequence: None,
+        element_names: vec![],
+    }
+}
+
+/// returns the number of bytes needed to hold `bits` bits
+fn bit_ceil(bits: usize) -> usize {
+    bits.div_ceil(8)
+}
+
+fn round_up(value: usize, multiple: usize) -> usize {
+    value.div_ceil(multiple.max(1)) * multiple.max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field<'a>(structure: &'a PacketStructure, name: &str) -> &'a PacketField {
+        structure
+            .fields
+            .iter()
+            .find(|field| field.name == name)
+            .unwrap()
+    }
+
+    fn offsets(structure: &PacketStructure) -> Vec<(&str, usize)> {
+        structure
+            .fields
+            .iter()
+            .map(|field| (field.name.as_str(), field.offset_in_packet))
+            .collect()
+    }
+
+    //tests that an unpacked struct gets the padding a C compiler would give it
+    #[test]
+    fn aligned_layout() {
+        let import = import_c_header(
+            r#"#include <stdint.h>
+#define SAT_COUNT 4
+#define NAME_LENGTH (SAT_COUNT * 2) // room for a callsign
+
+typedef enum { IDLE, BOOST = 2, COAST } flight_state_t;
+
+/* telemetry sent every 10ms */
+typedef struct telemetry {
+    uint8_t id;
+    uint32_t time_ms;
+    int16_t height;
+    double pressure;
+    char name[NAME_LENGTH];
+    flight_state_t state;
+    bool armed;
+} telemetry_t;
+
+int send_telemetry(const telemetry_t *telemetry);
+"#,
+        );
+        assert_eq!(import.problems, vec![]);
+        let structure = &import.structures[0];
+        assert_eq!(structure.name, "telemetry_t");
+        assert!(structure.byte_defined);
+        assert_eq!(
+            offsets(structure),
+            vec![
+                ("id", 0),
+                ("time_ms", 4),
+                ("height", 8),
+                ("pressure", 16),
+                ("name", 24),
+                ("state", 32),
+                ("armed", 36),
+            ]
+        );
+        assert_eq!(
+            field(structure, "name").string_format,
+            Some(StringFormat::Fixed { length: 8 })
+        );
+        let state = field(structure, "state");
+        assert_eq!(state.r#type, PacketFieldType::UnsignedInteger);
+        assert_eq!(state.labels[&3], "COAST");
+        assert_eq!(field(structure, "armed").index, 6);
+        // padded out to a multiple of the double
+        assert_eq!(structure.size(), 40);
+    }
+
+    //tests packed structs and #pragma pack
+    #[test]
+    fn packed_layout() {
+        let import = import_c_header(
+            "struct __attribute__((packed)) gps {
+                uint8_t fix;
+                int32_t lat, lon;
+                uint16_t sats;
+            };
+            #pragma pack(push, 2)
+            typedef struct {
+                uint8_t a;
+                uint32_t b;
+                uint8_t c;
+            } pack2_t;
+            #pragma pack(pop)
+            typedef struct {
+                uint8_t a;
+                uint32_t b;
+            } after_t;",
+        );
+        assert_eq!(import.problems, vec![]);
+        let gps = &import.structures[0];
+        assert_eq!(gps.name, "gps");
+        assert_eq!(
+            offsets(gps),
+            vec![("fix", 0), ("lat", 1), ("lon", 5), ("sats", 9)]
+        );
+        assert_eq!(gps.size(), 11);
+        let pack2 = &import.structures[1];
+        assert_eq!(offsets(pack2), vec![("a", 0), ("b", 2), ("c", 6)]);
+        assert_eq!(pack2.size(), 8);
+        assert_eq!(offsets(&import.structures[2]), vec![("a", 0), ("b", 4)]);
+    }
+
+    //tests that nested structs are flattened into the structs that use them
+    #[test]
+    fn nested_structs_and_arrays() {
+        let import = import_c_header(
+            "typedef struct { float x, y, z; } vec3_t;
+            typedef struct { uint8_t id; uint8_t cn0; } sat_t;
+            typedef struct { uint8_t count; int16_t samples[2]; } burst_t;
+            typedef struct {
+                vec3_t acc;
+                sat_t sats[3];
+                int16_t matrix[2][3];
+                burst_t bursts[2];
+                char callsigns[2][6];
+            } imu_t;",
+        );
+        assert_eq!(import.problems, vec![]);
+        assert_eq!(import.structures.len(), 1);
+        let structure = &import.structures[0];
+        assert_eq!(
+            offsets(structure),
+            vec![
+                ("acc.x", 0),
+                ("acc.y", 4),
+                ("acc.z", 8),
+                ("sats.id", 12),
+                ("sats.cn0", 13),
+                ("matrix", 18),
+                ("bursts[0].count", 30),
+                ("bursts[0].samples", 32),
+                ("bursts[1].count", 36),
+                ("bursts[1].samples", 38),
+                ("callsigns", 42),
+            ]
+        );
+        let array = |name| {
+            field(structure, name)
+                .array
+                .map(|array| (array.count, array.stride))
+        };
+        assert_eq!(array("sats.cn0"), Some((3, 2)));
+        assert_eq!(array("matrix"), Some((6, 2)));
+        assert_eq!(array("bursts[1].samples"), Some((2, 2)));
+        assert_eq!(array("callsigns"), Some((2, 6)));
+        // each satellite's id and cn0 are numbered together
+        assert_eq!(field(structure, "sats.id").index, 3);
+        assert_eq!(field(structure, "sats.cn0").index, 4);
+        assert_eq!(field(structure, "callsigns").index, 21);
+        assert_eq!(structure.size(), 56);
+    }
+
+    //tests that bitfields are packed the same way as GCC packs them
+    #[test]
+    fn bitfields() {
+        let import = import_c_header(
+            "typedef struct {
+                uint8_t mode : 3;
+                uint8_t armed : 1;
+                uint8_t error : 5;
+                uint16_t channel : 12;
+                uint16_t : 0;
+                int32_t offset : 20;
+            } status_t;
+            typedef struct __attribute__((packed)) {
+                uint8_t a : 6;
+                uint8_t b : 6;
+                int16_t c : 10;
+            } packed_bits_t;",
+        );
+        assert_eq!(import.problems, vec![]);
+        let bits = |structure: &PacketStructure, name| {
+            let field = field(structure, name);
+            let bitfield = field.bitfield.unwrap();
+            (
+                field.r#type,
+                field.offset_in_packet,
+                bitfield.bit_offset,
+                bitfield.bit_width,
+            )
+        };
+        let status = &import.structures[0];
+        assert_eq!(
+            bits(status, "mode"),
+            (PacketFieldType::UnsignedByte, 0, 0, 3)
+        );
+        assert_eq!(
+            bits(status, "armed"),
+            (PacketFieldType::UnsignedByte, 0, 3, 1)
+        );
+        assert_eq!(
+            bits(status, "error"),
+            (PacketFieldType::UnsignedByte, 1, 0, 5)
+        );
+        assert_eq!(
+            bits(status, "channel"),
+            (PacketFieldType::UnsignedShort, 2, 0, 12)
+        );
+        assert_eq!(
+            bits(status, "offset"),
+            (PacketFieldType::SignedInteger, 4, 0, 20)
+        );
+        assert_eq!(status.size(), 8);
+
+        // packed bitfields cross into the next byte, so get a wider container
+        let packed = &import.structures[1];
+        assert_eq!(bits(packed, "b"), (PacketFieldType::UnsignedShort, 0, 6, 6));
+        assert_eq!(bits(packed, "c"), (PacketFieldType::SignedShort, 1, 4, 10));
+        assert_eq!(packed.size(), 3);
+    }
+
+    //tests that everything that can't be mapped is reported, and the structs using it left out
+    #[test]
+    fn problems_are_reported() {
+        let import = import_c_header(
+            "typedef struct {
+                uint8_t *buffer;
+                long count;
+                union { uint8_t a; float b; } u;
+                void (*callback)(int);
+            } broken_t;
+            typedef struct {
+                broken_t inner;
+                uint8_t data[];
+            } outer_t;
+            typedef struct {
+                mystery_t value;
+                uint8_t fine;
+            } unknown_t;
+            typedef struct { uint8_t fine; } good_t;
+            static inline int helper(int x) { return x; }",
+        );
+        let problems: Vec<(usize, &str)> = import
+            .problems
+            .iter()
+            .map(|problem| (problem.line, problem.message.as_str()))
+            .collect();
+        assert_eq!(
+            problems,
+            vec![
+                (2, "'buffer' can't be mapped: it is a pointer"),
+                (
+                    3,
+                    "'count' can't be mapped: the size of long depends on the platform, use int32_t or int64_t"
+                ),
+                (
+                    4,
+                    "'u' can't be mapped: unions can't be mapped, as which member was sent isn't known"
+                ),
+                (5, "'callback' can't be mapped: it is a pointer"),
+                (7, "'outer_t' was left out because some of its members can't be mapped"),
+                (8, "'inner' can't be mapped: 'broken_t' has members that can't be mapped"),
+                (9, "'data' can't be mapped: arrays without a size can't be mapped"),
+                (11, "'unknown_t' was left out because some of its members can't be mapped"),
+                (12, "'value' can't be mapped: the type 'mystery_t' is never defined"),
+            ]
+        );
+        assert_eq!(import.structures.len(), 1);
+        assert_eq!(import.structures[0].name, "good_t");
+    }
+}
@@ -0,0 +1,1287 @@
+//! # Firmware Code Generation
+//!
+//! Turns packet structures into code for the other end of the radio link, so firmware and WINGS
+//! share one description of every packet instead of two that drift apart:
+//!
+//! - a C header with a packed struct for every packet structure, plus its size and delimiters
+//! - a Rust module with a struct for every packet structure, which encodes into exactly the bytes
+//!   `generate_packet` makes (checksums included) and decodes back
+//!
+//! Both put every delimiter, field, gap and checksum at the byte `generate_packet` puts it at.
+//! Gaps are spelled out as `_gap` members, and each C struct checks its size with
+//! `_Static_assert`, so a compiler that lays one out differently fails to build rather than
+//! sending packets WINGS can't read. The C structs follow GCC and Clang's layout on little-endian
+//! targets, the same layout `c_header` reads.
+//!
+//! Structures C can't declare (fields that overlap each other, arrays of bitfields, array elements
+//! that don't fit in their stride) are left out of both files and reported in `problems`.
+
+use std::collections::{BTreeMap, HashSet};
+
+use anyhow::{anyhow, bail};
+use serde::Serialize;
+
+use crate::models::packet_structure::{
+    CrcAlgorithm, Endianness, PacketArray, PacketBitfield, PacketCRC, PacketDelimiter, PacketField,
+    PacketFieldType, PacketStructure, StringFormat,
+};
+
+/// Words that can't be used as a C or Rust identifier as they are
+const KEYWORDS: &[&str] = &[
+    "as", "async", "auto", "await", "bool", "break", "case", "char", "const", "continue", "crate",
+    "default", "do", "double", "dyn", "else", "enum", "extern", "false", "float", "fn", "for",
+    "goto", "if", "impl", "in", "inline", "int", "let", "long", "loop", "match", "mod", "move",
+    "mut", "pub", "ref", "register", "restrict", "return", "self", "short", "signed", "sizeof",
+    "static", "struct", "super", "switch", "trait", "true", "type", "typedef", "union", "unsafe",
+    "unsigned", "use", "void", "volatile", "where", "while",
+];
+
+/// Functions every generated Rust module uses to move values in and out of packets
+const RUST_HELPERS: &str = r#"/// Returns a mask covering `bit_width` bits, starting from the least significant bit
+fn mask(bit_width: usize) -> u64 {
+    if bit_width >= 64 {
+        u64::MAX
+    } else {
+        (1 << bit_width) - 1
+    }
+}
+
+/// Reads `bit_width` bits starting at `bit_offset` out of a little or big-endian container
+fn extract_bits(container: &[u8], big_endian: bool, bit_offset: usize, bit_width: usize) -> u64 {
+    let mut raw = 0u64;
+    for (i, byte) in container.iter().enumerate() {
+        let shift = if big_endian { container.len() - 1 - i } else { i } * 8;
+        raw |= (*byte as u64) << shift;
+    }
+    (raw >> bit_offset) & mask(bit_width)
+}
+
+/// Writes `value` into `bit_width` bits starting at `bit_offset`, leaving the other bits alone
+fn insert_bits(
+    container: &mut [u8],
+    big_endian: bool,
+    bit_offset: usize,
+    bit_width: usize,
+    value: u64,
+) {
+    let mut raw = extract_bits(container, big_endian, 0, 64);
+    raw &= !(mask(bit_width) << bit_offset);
+    raw |= (value & mask(bit_width)) << bit_offset;
+    let length = container.len();
+    for (i, byte) in container.iter_mut().enumerate() {
+        let shift = if big_endian { length - 1 - i } else { i } * 8;
+        *byte = (raw >> shift) as u8;
+    }
+}
+
+/// Sign-extends a value from the top bit of a `bit_width` bit integer
+fn sign_extend(raw: u64, bit_width: usize) -> i64 {
+    let unused_bits = 64 - bit_width;
+    ((raw << unused_bits) as i64) >> unused_bits
+}
+
+/// Writes text padded with NULs, cutting it off if it doesn't fit
+fn write_text(bytes: &mut [u8], text: &str) {
+    let length = text.len().min(bytes.len());
+    bytes[..length].copy_from_slice(&text.as_bytes()[..length]);
+}
+
+/// Reads text that ends at its first NUL
+fn read_text(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|byte| *byte == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+/// Writes text after a `prefix` byte long length, cutting it off if it doesn't fit
+fn write_prefixed_text(bytes: &mut [u8], prefix: usize, big_endian: bool, text: &str) {
+    let length = text.len().min(bytes.len() - prefix);
+    insert_bits(&mut bytes[..prefix], big_endian, 0, prefix * 8, length as u64);
+    bytes[prefix..prefix + length].copy_from_slice(&text.as_bytes()[..length]);
+}
+
+/// Reads text after a `prefix` byte long length
+fn read_prefixed_text(bytes: &[u8], prefix: usize, big_endian: bool) -> String {
+    let length = extract_bits(&bytes[..prefix], big_endian, 0, prefix * 8) as usize;
+    let text = &bytes[prefix..(prefix + length).min(bytes.len())];
+    String::from_utf8_lossy(text).into_owned()
+}
+"#;
+
+/// The code generated for a set of packet structures
+#[derive(PartialEq, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FirmwareCode {
+    pub c_header: String,
+    pub rust_module: String,
+    /// every structure that was left out, and why
+    pub problems: Vec<String>,
+}
+
+/// Generates a C header and a Rust module describing the given packet structures
+pub fn generate_firmware_code(packet_structures: &[PacketStructure]) -> FirmwareCode {
+    let mut c_header = String::from(
+        "/*\n * Packet structures generated by WINGS, regenerate this file instead of editing it\n \
+         *\n * Every struct is laid out byte for byte the way WINGS expects its packet to be sent,\n \
+         * so a filled in struct can be sent as it is. Members marked big-endian have to be byte\n \
+         * swapped first, ie with __builtin_bswap16.\n */\n\
+         #ifndef WINGS_PACKETS_H\n#define WINGS_PACKETS_H\n\n\
+         #include <stdbool.h>\n#include <stdint.h>\n",
+    );
+    let mut rust_module = String::from(
+        "//! Packet structures generated by WINGS, regenerate this file instead of editing it\n\
+         //!\n\
+         //! Every packet structure has a module with a `Packet` holding its fields. `encode` turns\n\
+         //! a `Packet` into the bytes WINGS expects, delimiters and checksums included, and\n\
+         //! `decode` reads one back.\n\
+         #![allow(dead_code, unused_mut, clippy::all)]\n\n",
+    );
+    rust_module.push_str(RUST_HELPERS);
+
+    let mut problems = vec![];
+    let mut c_names = Identifiers::default();
+    let mut rust_names = Identifiers::default();
+    let mut checksums: Vec<CrcAlgorithm> = vec![];
+    for packet_structure in packet_structures {
+        let layout = match c_layout(packet_structure) {
+            Ok(layout) => layout,
+            Err(error) => {
+                problems.push(format!(
+                    "'{}' was left out: {}",
+                    packet_structure.name, error
+                ));
+                continue;
+            }
+        };
+        match write_c_struct(packet_structure, &layout, &mut c_names) {
+            Ok(c_struct) => c_header.push_str(&c_struct),
+            Err(error) => {
+                problems.push(format!(
+                    "'{}' was left out: {}",
+                    packet_structure.name, error
+                ));
+                continue;
+            }
+        }
+        rust_module.push_str(&write_rust_module(packet_structure, &mut rust_names));
+        for crc in &packet_structure.packet_crc {
+            if crc.algorithm != CrcAlgorithm::Unchecked && !checksums.contains(&crc.algorithm) {
+                checksums.push(crc.algorithm);
+            }
+        }
+    }
+    c_header.push_str("\n#endif /* WINGS_PACKETS_H */\n");
+    for algorithm in checksums {
+        rust_module.push('\n');
+        rust_module.push_str(rust_checksum_function(algorithm));
+    }
+    FirmwareCode {
+        c_header,
+        rust_module,
+        problems,
+    }
+}
+
+/// Hands out identifiers that are unique within one scope
+#[derive(Default)]
+struct Identifiers {
+    used: HashSet<String>,
+}
+
+impl Identifiers {
+    /// returns `name`, or `name` with a number after it if `name` has already been handed out
+    fn unique(&mut self, name: String) -> String {
+        let mut candidate = name.clone();
+        let mut copy = 2;
+        while !self.used.insert(candidate.clone()) {
+            candidate = format!("{}_{}", name, copy);
+            copy += 1;
+        }
+        candidate
+    }
+}
+
+/// Turns a name into something that can be used as an identifier in both C and Rust
+fn identifier(name: &str) -> String {
+    let mut identifier: String = name
+        .trim()
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c,
+            false => '_',
+        })
+        .collect();
+    if identifier.is_empty() || identifier.starts_with(|c: char| c.is_ascii_digit()) {
+        identifier.insert(0, '_');
+    }
+    if KEYWORDS.contains(&identifier.as_str()) {
+        identifier.push('_');
+    }
+    identifier
+}
+
+/// Something taking up bytes in a C struct
+enum Member<'a> {
+    Delimiter(&'a PacketDelimiter),
+    Crc(&'a PacketCRC),
+    /// a field, or a whole array if the field is an array with nothing between its elements
+    Field(&'a PacketField),
+    /// a single element of an array field, inside a `Group`
+    Element(&'a PacketField),
+    /// bitfields sharing a container
+    Bitfields(Vec<&'a PacketField>),
+    /// array fields whose elements take turns, declared as an array of structs
+    Group {
+        array: PacketArray,
+        fields: Vec<&'a PacketField>,
+    },
+}
+
+/// A member and the bytes it covers, `end` is exclusive
+struct Placed<'a> {
+    start: usize,
+    end: usize,
+    member: Member<'a>,
+}
+
+impl Placed<'_> {
+    fn describe(&self) -> String {
+        match &self.member {
+            Member::Delimiter(delimiter) => format!("delimiter '{}'", delimiter.name),
+            Member::Crc(crc) => format!("the checksum at byte {}", crc.offset_in_packet),
+            Member::Field(field) | Member::Element(field) => format!("'{}'", field.name),
+            Member::Bitfields(fields) | Member::Group { fields, .. } => {
+                format!("'{}'", fields[0].name)
+            }
+        }
+    }
+}
+
+/// Works out the members of a structure's C struct, in the order they are declared
+///
+/// # Errors
+///
+/// errors if anything overlaps, or if a field can't be declared in C
+fn c_layout(packet_structure: &PacketStructure) -> anyhow::Result<Vec<Placed<'_>>> {
+    let mut members = vec![];
+    for delimiter in &packet_structure.delimiters {
+        members.push(Placed {
+            start: delimiter.offset_in_packet,
+            end: delimiter.offset_in_packet + delimiter.identifier.len(),
+            member: Member::Delimiter(delimiter),
+        });
+    }
+    for crc in &packet_structure.packet_crc {
+        members.push(Placed {
+            start: crc.offset_in_packet,
+            end: crc.offset_in_packet + crc.length,
+            member: Member::Crc(crc),
+        });
+    }
+
+    let mut containers: BTreeMap<(usize, usize), Vec<&PacketField>> = BTreeMap::new();
+    let mut arrays = vec![];
+    for field in &packet_structure.fields {
+        let size = field
+            .size()
+            .map_err(|error| anyhow!("'{}' has no size: {}", field.name, error))?;
+        match (field.bitfield, field.array) {
+            (Some(_), Some(_)) => {
+                bail!(
+                    "'{}' is an array of bitfields, which C can't declare",
+                    field.name
+                )
+            }
+            (Some(_), None) => containers
+                .entry((field.offset_in_packet, size))
+                .or_default()
+                .push(field),
+            (None, Some(array)) => arrays.push((field, array, size)),
+            (None, None) => members.push(Placed {
+                start: field.offset_in_packet,
+                end: field.offset_in_packet + size,
+                member: Member::Field(field),
+            }),
+        }
+    }
+    for ((offset, size), fields) in containers {
+        members.push(Placed {
+            start: offset,
+            end: offset + size,
+            member: Member::Bitfields(fields),
+        });
+    }
+
+    // arrays with the same count and stride whose first elements fit in one stride take turns,
+    // like the members of an array of structs
+    arrays.sort_by_key(|(field, _, _)| field.offset_in_packet);
+    let mut groups: Vec<(usize, PacketArray, Vec<&PacketField>)> = vec![];
+    for (field, array, size) in arrays {
+        if size > array.stride && array.count > 1 {
+            bail!("the elements of '{}' overlap each other", field.name);
+        }
+        let group = groups.iter_mut().find(|(start, group_array, _)| {
+            group_array.count == array.count
+                && group_array.stride == array.stride
+                && field.offset_in_packet + size <= start + array.stride
+        });
+        match group {
+            Some((_, _, fields)) => fields.push(field),
+            None => groups.push((field.offset_in_packet, array, vec![field])),
+        }
+    }
+    for (start, array, fields) in groups {
+        let member = match fields[..] {
+            [field] if field.size()? == array.stride || array.count == 1 => Member::Field(field),
+            _ => Member::Group { array, fields },
+        };
+        let end = match member {
+            Member::Field(field) => start + (array.count - 1) * array.stride + field.size()?,
+            _ => start + array.count * array.stride,
+        };
+        members.push(Placed { start, end, member });
+    }
+
+    members.retain(|member| member.end > member.start);
+    members.sort_by_key(|member| (member.start, member.end));
+    for pair in members.windows(2) {
+        if pair[1].start < pair[0].end {
+            bail!("{} overlaps {}", pair[0].describe(), pair[1].describe());
+        }
+    }
+    if let Some(last) = members.last() {
+        if last.end > packet_structure.size() {
+            bail!(
+                "{} runs past the end of the {} byte packet",
+                last.describe(),
+                packet_structure.size()
+            );
+        }
+    }
+    Ok(members)
+}
+
+/// Writes the constants, struct and size check for one packet structure
+fn write_c_struct(
+    packet_structure: &PacketStructure,
+    layout: &[Placed],
+    names: &mut Identifiers,
+) -> anyhow::Result<String> {
+    let base_name = identifier(&packet_structure.name);
+    let type_name = names.unique(format!("{}_t", base_name));
+    let prefix = names.unique(base_name.to_uppercase());
+    let size_name = names.unique(format!("{}_SIZE", prefix));
+
+    let mut text = format!("\n/* {}, ", packet_structure.name);
+    match packet_structure.length_field {
+        Some(_) => text.push_str(&format!(
+            "up to {} bytes long */\n",
+            packet_structure.size()
+        )),
+        None => text.push_str(&format!("{} bytes long */\n", packet_structure.size())),
+    }
+    text.push_str(&format!(
+        "#define {} {}\n",
+        size_name,
+        packet_structure.size()
+    ));
+
+    let mut delimiter_constants = vec![];
+    for delimiter in &packet_structure.delimiters {
+        let constant = names.unique(format!(
+            "{}_{}",
+            prefix,
+            identifier(&delimiter.name).to_uppercase()
+        ));
+        text.push_str(&format!(
+            "static const uint8_t {}[{}] = {{{}}};\n",
+            constant,
+            delimiter.identifier.len(),
+            delimiter
+                .identifier
+                .iter()
+                .map(|byte| format!("0x{:02x}", byte))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+        delimiter_constants.push(constant);
+    }
+
+    text.push_str("typedef struct __attribute__((packed)) {\n");
+    let context = CContext {
+        packet_structure,
+        delimiter_constants: &delimiter_constants,
+    };
+    context.write_members(
+        &mut text,
+        layout,
+        packet_structure.size(),
+        1,
+        &mut Identifiers::default(),
+    )?;
+    text.push_str(&format!("}} {};\n", type_name));
+    text.push_str(&format!(
+        "_Static_assert(sizeof({0}) == {1}, \"{0} must match the packet WINGS expects\");\n",
+        type_name, size_name
+    ));
+    Ok(text)
+}
+
+/// What writing the members of a C struct needs to know about the structure they belong to
+struct CContext<'a> {
+    packet_structure: &'a PacketStructure,
+    /// the name of the constant holding each delimiter, by position in `delimiters`
+    delimiter_constants: &'a [String],
+}
+
+impl CContext<'_> {
+    /// writes members one per line, with gaps filling any bytes between them up to `size`
+    fn write_members(
+        &self,
+        text: &mut String,
+        layout: &[Placed],
+        size: usize,
+        depth: usize,
+        names: &mut Identifiers,
+    ) -> anyhow::Result<()> {
+        let indent = "    ".repeat(depth);
+        let mut gaps = 0;
+        let mut write_gap = |text: &mut String, names: &mut Identifiers, length: usize| {
+            let name = names.unique(format!("_gap{}", gaps));
+            gaps += 1;
+            text.push_str(&format!("{}uint8_t {}[{}];\n", indent, name, length));
+        };
+        let mut cursor = 0;
+        for placed in layout {
+            if placed.start > cursor {
+                write_gap(text, names, placed.start - cursor);
+            }
+            self.write_member(text, placed, depth, names)?;
+            cursor = placed.end;
+        }
+        if size > cursor {
+            write_gap(text, names, size - cursor);
+        }
+        Ok(())
+    }
+
+    fn write_member(
+        &self,
+        text: &mut String,
+        placed: &Placed,
+        depth: usize,
+        names: &mut Identifiers,
+    ) -> anyhow::Result<()> {
+        let indent = "    ".repeat(depth);
+        let (declaration, notes) = match &placed.member {
+            Member::Delimiter(delimiter) => {
+                let position = self
+                    .packet_structure
+                    .delimiters
+                    .iter()
+                    .position(|other| std::ptr::eq(other, *delimiter))
+                    .unwrap_or_default();
+                (
+                    format!(
+                        "uint8_t {}[{}]",
+                        names.unique(identifier(&delimiter.name)),
+                        delimiter.identifier.len()
+                    ),
+                    vec![format!("always {}", self.delimiter_constants[position])],
+                )
+            }
+            Member::Crc(crc) => {
+                let declaration = match crc.length {
+                    1 | 2 | 4 | 8 => format!(
+                        "uint{}_t {}",
+                        crc.length * 8,
+                        names.unique("checksum".to_owned())
+                    ),
+                    length => format!(
+                        "uint8_t {}[{}]",
+                        names.unique("checksum".to_owned()),
+                        length
+                    ),
+                };
+                (declaration, vec![describe_checksum(crc)])
+            }
+            Member::Field(field) => {
+                let name = names.unique(identifier(&field.name));
+                let (declaration, mut notes) =
+                    field_declaration(field, &name, field.array.map(|array| array.count));
+                notes.extend(self.count_note(field));
+                (declaration, notes)
+            }
+            Member::Element(field) => {
+                let member_name = field
+                    .name
+                    .split_once('.')
+                    .map_or(field.name.as_str(), |(_, member_name)| member_name);
+                let name = names.unique(identifier(member_name));
+                field_declaration(field, &name, None)
+            }
+            Member::Bitfields(fields) => return self.write_bitfields(text, fields, depth, names),
+            Member::Group { array, fields } => {
+                text.push_str(&format!("{}struct __attribute__((packed)) {{\n", indent));
+                let elements: Vec<Placed> = fields
+                    .iter()
+                    .map(|field| {
+                        let start = field.offset_in_packet - placed.start;
+                        Ok(Placed {
+                            start,
+                            end: start + field.size()?,
+                            member: Member::Element(field),
+                        })
+                    })
+                    .collect::<anyhow::Result<_>>()?;
+                self.write_members(
+                    text,
+                    &elements,
+                    array.stride,
+                    depth + 1,
+                    &mut Identifiers::default(),
+                )?;
+                let group_name = fields[0]
+                    .name
+                    .split_once('.')
+                    .map_or(fields[0].name.as_str(), |(group_name, _)| group_name);
+                let declaration = format!(
+                    "}} {}[{}]",
+                    names.unique(identifier(group_name)),
+                    array.count
+                );
+                let notes: Vec<String> = self.count_note(fields[0]).into_iter().collect();
+                write_declaration(text, &indent, &declaration, &notes);
+                return Ok(());
+            }
+        };
+        write_declaration(text, &indent, &declaration, &notes);
+        Ok(())
+    }
+
+    /// writes bitfields that share a container
+    ///
+    /// little-endian containers become C bitfields, with unnamed ones filling the unused bits.
+    /// big-endian containers can't be declared as bitfields, so they are left as a plain integer
+    /// with the position of every bitfield noted next to it
+    fn write_bitfields(
+        &self,
+        text: &mut String,
+        fields: &[&PacketField],
+        depth: usize,
+        names: &mut Identifiers,
+    ) -> anyhow::Result<()> {
+        let indent = "    ".repeat(depth);
+        let mut fields: Vec<(&PacketField, PacketBitfield)> = fields
+            .iter()
+            .filter_map(|field| Some((*field, field.bitfield?)))
+            .collect();
+        fields.sort_by_key(|(_, bitfield)| bitfield.bit_offset);
+        let size = fields[0].0.size()?;
+        let container_bits = size * 8;
+
+        if size > 1
+            && fields
+                .iter()
+                .any(|(field, _)| field.endianness == Endianness::Big)
+        {
+            let name = names.unique(format!("bits_{}", fields[0].0.offset_in_packet));
+            let declaration = match size {
+                2 | 4 | 8 => format!("uint{}_t {}", container_bits, name),
+                _ => format!("uint8_t {}[{}]", name, size),
+            };
+            let mut notes = vec!["big-endian".to_owned()];
+            for (field, bitfield) in &fields {
+                notes.push(match bitfield.bit_width {
+                    1 => format!("{} is bit {}", field.name, bitfield.bit_offset),
+                    width => format!(
+                        "{} is bits {} to {}",
+                        field.name,
+                        bitfield.bit_offset,
+                        bitfield.bit_offset + width - 1
+                    ),
+                });
+            }
+            write_declaration(text, &indent, &declaration, &notes);
+            return Ok(());
+        }
+
+        let padding_type = unsigned_c_type(size);
+        let mut bit = 0;
+        for (field, bitfield) in fields {
+            if bitfield.bit_offset < bit {
+                bail!("bitfield '{}' overlaps the bitfield before it", field.name);
+            }
+            if bitfield.bit_offset > bit {
+                text.push_str(&format!(
+                    "{}{} : {};\n",
+                    indent,
+                    padding_type,
+                    bitfield.bit_offset - bit
+                ));
+            }
+            let field_type = match field.r#type {
+                PacketFieldType::Bool if bitfield.bit_width == 1 => "bool",
+                PacketFieldType::Bool => padding_type,
+                r#type => c_type(r#type),
+            };
+            let declaration = format!(
+                "{} {} : {}",
+                field_type,
+                names.unique(identifier(&field.name)),
+                bitfield.bit_width
+            );
+            write_declaration(text, &indent, &declaration, &field_notes(field));
+            bit = bitfield.bit_offset + bitfield.bit_width;
+        }
+        if container_bits > bit {
+            text.push_str(&format!(
+                "{}{} : {};\n",
+                indent,
+                padding_type,
+                container_bits - bit
+            ));
+        }
+        Ok(())
+    }
+
+    /// notes where the number of elements sent is read from, for arrays with a count field
+    fn count_note(&self, field: &PacketField) -> Option<String> {
+        let count_field = field.array?.count_field?;
+        let count_field = self
+            .packet_structure
+            .fields
+            .iter()
+            .find(|other| other.index == count_field && other.array.is_none())?;
+        Some(format!("the number sent is in {}", count_field.name))
+    }
+}
+
+fn write_declaration(text: &mut String, indent: &str, declaration: &str, notes: &[String]) {
+    match notes.is_empty() {
+        true => text.push_str(&format!("{}{};\n", indent, declaration)),
+        false => text.push_str(&format!(
+            "{}{}; /* {} */\n",
+            indent,
+            declaration,
+            notes.join(", ")
+        )),
+    }
+}
+
+/// Declares a field that isn't a bitfield, `count` makes it an array
+fn field_declaration(
+    field: &PacketField,
+    name: &str,
+    count: Option<usize>,
+) -> (String, Vec<String>) {
+    let dimensions = count.map_or(String::new(), |count| format!("[{}]", count));
+    let mut notes = vec![];
+    let multi_byte = field.size().is_ok_and(|size| size > 1);
+    let declaration = match (field.r#type, field.string_format) {
+        (PacketFieldType::String, Some(StringFormat::Fixed { length })) => {
+            format!("char {}{}[{}]", name, dimensions, length)
+        }
+        (PacketFieldType::String, Some(StringFormat::LengthPrefixed { prefix, max_length })) => {
+            if field.endianness == Endianness::Big && prefix.size().is_ok_and(|size| size > 1) {
+                notes.push("big-endian length".to_owned());
+            }
+            format!(
+                "struct __attribute__((packed)) {{ {} length; char text[{}]; }} {}{}",
+                c_type(prefix),
+                max_length,
+                name,
+                dimensions
+            )
+        }
+        (
+            r#type @ (PacketFieldType::UnsignedTwoFour
+            | PacketFieldType::SignedTwoFour
+            | PacketFieldType::UnsignedFourZero
+            | PacketFieldType::SignedFourZero
+            | PacketFieldType::UnsignedFourEight
+            | PacketFieldType::SignedFourEight),
+            _,
+        ) => {
+            let bits = r#type.size().unwrap_or_default() * 8;
+            if count.is_none() && field.endianness == Endianness::Little {
+                // a bitfield as wide as the integer fills exactly its bytes in a packed struct
+                format!("{} {} : {}", c_type(r#type), name, bits)
+            } else {
+                let signed = c_type(r#type).starts_with('i');
+                notes.push(format!(
+                    "{} {} bit integer",
+                    if signed { "signed" } else { "unsigned" },
+                    bits
+                ));
+                format!("uint8_t {}{}[{}]", name, dimensions, bits / 8)
+            }
+        }
+        (r#type, _) => format!("{} {}{}", c_type(r#type), name, dimensions),
+    };
+    if field.endianness == Endianness::Big && multi_byte && field.r#type != PacketFieldType::String
+    {
+        notes.push("big-endian".to_owned());
+    }
+    notes.extend(field_notes(field));
+    (declaration, notes)
+}
+
+/// Notes shared by every kind of field
+fn field_notes(field: &PacketField) -> Vec<String> {
+    let mut notes = vec![];
+    if let Some(unit) = &field.unit {
+        notes.push(format!("in {}", unit));
+    }
+    if !field.labels.is_empty() {
+        notes.push(
+            field
+                .labels
+                .iter()
+                .map(|(value, label)| format!("{} = {}", value, label))
+                .collect::<Vec<_>>()
+                .join(", "),
+        );
+    }
+    notes
+}
+
+fn describe_checksum(crc: &PacketCRC) -> String {
+    let covered_end = crc.covered_end.unwrap_or(crc.offset_in_packet);
+    let algorithm = match crc.algorithm {
+        CrcAlgorithm::Unchecked => {
+            return "reserved for a checksum WINGS doesn't check".to_owned();
+        }
+        CrcAlgorithm::Crc8 => "CRC-8",
+        CrcAlgorithm::Crc16Ccitt => "CRC-16-CCITT",
+        CrcAlgorithm::Crc32 => "CRC-32",
+        CrcAlgorithm::Xor => "XOR",
+        CrcAlgorithm::Sum => "sum",
+    };
+    let mut note = format!(
+        "{} of bytes {} to {}",
+        algorithm,
+        crc.covered_start,
+        covered_end.saturating_sub(1)
+    );
+    if crc.endianness == Endianness::Big && crc.length > 1 {
+        note.push_str(", big-endian");
+    }
+    note
+}
+
+/// The C type a field of the given type is declared as, odd width integers give the type their
+/// bitfields are declared with
+fn c_type(r#type: PacketFieldType) -> &'static str {
+    match r#type {
+        PacketFieldType::UnsignedByte => "uint8_t",
+        PacketFieldType::SignedByte => "int8_t",
+        PacketFieldType::UnsignedShort => "uint16_t",
+        PacketFieldType::SignedShort => "int16_t",
+        PacketFieldType::UnsignedTwoFour | PacketFieldType::UnsignedInteger => "uint32_t",
+        PacketFieldType::SignedTwoFour | PacketFieldType::SignedInteger => "int32_t",
+        PacketFieldType::UnsignedFourZero
+        | PacketFieldType::UnsignedFourEight
+        | PacketFieldType::UnsignedLong => "uint64_t",
+        PacketFieldType::SignedFourZero
+        | PacketFieldType::SignedFourEight
+        | PacketFieldType::SignedLong => "int64_t",
+        PacketFieldType::Float => "float",
+        PacketFieldType::Double => "double",
+        PacketFieldType::String => "char",
+        PacketFieldType::Bool => "bool",
+    }
+}
+
+/// The smallest unsigned C type holding `size` bytes
+fn unsigned_c_type(size: usize) -> &'static str {
+    match size {
+        1 => "uint8_t",
+        2 => "uint16_t",
+        3 | 4 => "uint32_t",
+        _ => "uint64_t",
+    }
+}
+
+/// The Rust type a field of the given type is held in
+fn rust_type(r#type: PacketFieldType) -> &'static str {
+    match r#type {
+        PacketFieldType::UnsignedByte => "u8",
+        PacketFieldType::SignedByte => "i8",
+        PacketFieldType::UnsignedShort => "u16",
+        PacketFieldType::SignedShort => "i16",
+        PacketFieldType::UnsignedTwoFour | PacketFieldType::UnsignedInteger => "u32",
+        PacketFieldType::SignedTwoFour | PacketFieldType::SignedInteger => "i32",
+        PacketFieldType::UnsignedFourZero
+        | PacketFieldType::UnsignedFourEight
+        | PacketFieldType::UnsignedLong => "u64",
+        PacketFieldType::SignedFourZero
+        | PacketFieldType::SignedFourEight
+        | PacketFieldType::SignedLong => "i64",
+        PacketFieldType::Float => "f32",
+        PacketFieldType::Double => "f64",
+        PacketFieldType::String => "String",
+        PacketFieldType::Bool => "bool",
+    }
+}
+
+/// The value a field of the given type starts at
+fn rust_default(r#type: PacketFieldType, count: Option<usize>) -> String {
+    let value = match r#type {
+        PacketFieldType::Float | PacketFieldType::Double => "0.0",
+        PacketFieldType::Bool => "false",
+        PacketFieldType::String => "String::new()",
+        _ => "0",
+    };
+    match (count, r#type) {
+        (None, _) => value.to_owned(),
+        (Some(_), PacketFieldType::String) => "std::array::from_fn(|_| String::new())".to_owned(),
+        (Some(count), _) => format!("[{}; {}]", value, count),
+    }
+}
+
+fn rust_checksum_function(algorithm: CrcAlgorithm) -> &'static str {
+    match algorithm {
+        CrcAlgorithm::Unchecked => "",
+        CrcAlgorithm::Crc8 => {
+            "/// CRC-8 with polynomial 0x07 and an initial value of 0
+fn crc8(bytes: &[u8]) -> u64 {
+    let mut crc = 0u8;
+    for byte in bytes {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 };
+        }
+    }
+    crc as u64
+}
+"
+        }
+        CrcAlgorithm::Crc16Ccitt => {
+            "/// CRC-16-CCITT with polynomial 0x1021 and an initial value of 0xFFFF
+fn crc16_ccitt(bytes: &[u8]) -> u64 {
+    let mut crc = 0xFFFFu16;
+    for byte in bytes {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc as u64
+}
+"
+        }
+        CrcAlgorithm::Crc32 => {
+            "/// The CRC-32 used by ethernet and zip files
+fn crc32(bytes: &[u8]) -> u64 {
+    let mut crc = 0xFFFFFFFFu32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+        }
+    }
+    !crc as u64
+}
+"
+        }
+        CrcAlgorithm::Xor => {
+            "/// Every byte XOR'd together
+fn xor_checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0, |crc, byte| crc ^ byte) as u64
+}
+"
+        }
+        CrcAlgorithm::Sum => {
+            "/// Every byte added together, cut down to the checksum's width where it is used
+fn sum_checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0u64, |sum, byte| sum.wrapping_add(*byte as u64))
+}
+"
+        }
+    }
+}
+
+fn rust_checksum_call(crc: &PacketCRC) -> String {
+    let function = match crc.algorithm {
+        CrcAlgorithm::Unchecked => return String::new(),
+        CrcAlgorithm::Crc8 => "crc8",
+        CrcAlgorithm::Crc16Ccitt => "crc16_ccitt",
+        CrcAlgorithm::Crc32 => "crc32",
+        CrcAlgorithm::Xor => "xor_checksum",
+        CrcAlgorithm::Sum => "sum_checksum",
+    };
+    format!(
+        "{}(&bytes[{}..{}])",
+        function,
+        crc.covered_start,
+        crc.covered_end.unwrap_or(crc.offset_in_packet)
+    )
+}
+
+/// The bytes `length` bytes long starting at `at`, which is either a byte or the variable `at`
+fn rust_range(at: Option<usize>, length: usize) -> String {
+    match at {
+        Some(at) => format!("{}..{}", at, at + length),
+        None => format!("at..at + {}", length),
+    }
+}
+
+/// Writes a module with a `Packet` encoding and decoding one packet structure
+fn write_rust_module(packet_structure: &PacketStructure, names: &mut Identifiers) -> String {
+    let module_name = names.unique(identifier(&packet_structure.name).to_lowercase());
+    let size = packet_structure.size();
+    let mut constants = Identifiers::default();
+    constants.unique("SIZE".to_owned());
+    let mut text = format!(
+        "\n/// {}, {} bytes\npub mod {} {{\n    use super::*;\n\n    pub const SIZE: usize = {};\n",
+        packet_structure.name,
+        match packet_structure.length_field {
+            Some(_) => format!("up to {}", size),
+            None => size.to_string(),
+        },
+        module_name,
+        size
+    );
+    let mut delimiter_constants = vec![];
+    for delimiter in &packet_structure.delimiters {
+        let constant = constants.unique(identifier(&delimiter.name).to_uppercase());
+        text.push_str(&format!(
+            "    /// sent at byte {}\n    pub const {}: [u8; {}] = [{}];\n",
+            delimiter.offset_in_packet,
+            constant,
+            delimiter.identifier.len(),
+            delimiter
+                .identifier
+                .iter()
+                .map(|byte| format!("0x{:02x}", byte))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+        delimiter_constants.push(constant);
+    }
+
+    let mut fields = Identifiers::default();
+    let field_names: Vec<String> = packet_structure
+        .fields
+        .iter()
+        .map(|field| fields.unique(identifier(&field.name).to_lowercase()))
+        .collect();
+    let field_type = |field: &PacketField| match field.array {
+        Some(array) => format!("[{}; {}]", rust_type(field.r#type), array.count),
+        None => rust_type(field.r#type).to_owned(),
+    };
+
+    text.push_str("\n    #[derive(Clone, Debug, PartialEq)]\n    pub struct Packet {\n");
+    for (field, name) in packet_structure.fields.iter().zip(&field_names) {
+        if let Some(unit) = &field.unit {
+            text.push_str(&format!("        /// in {}\n", unit));
+        }
+        text.push_str(&format!("        pub {}: {},\n", name, field_type(field)));
+    }
+    text.push_str("    }\n\n    impl Default for Packet {\n        fn default() -> Self {\n");
+    text.push_str("            Packet {\n");
+    for (field, name) in packet_structure.fields.iter().zip(&field_names) {
+        text.push_str(&format!(
+            "                {}: {},\n",
+            name,
+            rust_default(field.r#type, field.array.map(|array| array.count))
+        ));
+    }
+    text.push_str("            }\n        }\n    }\n\n    impl Packet {\n");
+
+    // encoding follows `generate_packet`: delimiters, then fields, then checksums
+    text.push_str(
+        "        /// Encodes the packet into the bytes WINGS expects\n        \
+         pub fn encode(&self) -> [u8; SIZE] {\n            let mut bytes = [0u8; SIZE];\n",
+    );
+    for (delimiter, constant) in packet_structure.delimiters.iter().zip(&delimiter_constants) {
+        text.push_str(&format!(
+            "            bytes[{}].copy_from_slice(&{});\n",
+            rust_range(Some(delimiter.offset_in_packet), delimiter.identifier.len()),
+            constant
+        ));
+    }
+    for (field, name) in packet_structure.fields.iter().zip(&field_names) {
+        let array = match field.array {
+            Some(array) => array,
+            None => {
+                let statement = encode_statement(field, Some(field.offset_in_packet), name);
+                text.push_str(&format!("            {}\n", statement));
+                continue;
+            }
+        };
+        // arrays with a count field only fill in the elements that were sent
+        let count_field = array.count_field.and_then(|count_field| {
+            packet_structure
+                .fields
+                .iter()
+                .position(|other| other.index == count_field && other.array.is_none())
+        });
+        let length = match count_field {
+            Some(position) => format!(
+                "(self.{} as usize).min({})",
+                field_names[position], array.count
+            ),
+            None => array.count.to_string(),
+        };
+        text.push_str(&format!(
+            "            for i in 0..{} {{\n                let at = {} + i * {};\n                {}\n            }}\n",
+            length,
+            field.offset_in_packet,
+            array.stride,
+            encode_statement(field, None, &format!("{}[i]", name))
+        ));
+    }
+    for crc in &packet_structure.packet_crc {
+        if crc.algorithm == CrcAlgorithm::Unchecked {
+            continue;
+        }
+        text.push_str(&format!(
+            "            let crc = {};\n            insert_bits(&mut bytes[{}], {}, 0, {}, crc);\n",
+            rust_checksum_call(crc),
+            rust_range(Some(crc.offset_in_packet), crc.length),
+            crc.endianness == Endianness::Big,
+            crc.length * 8
+        ));
+    }
+    text.push_str("            bytes\n        }\n\n");
+
+    text.push_str(
+        "        /// Decodes a packet, or returns None if its delimiters or checksums don't match\n        \
+         pub fn decode(bytes: &[u8; SIZE]) -> Option<Self> {\n",
+    );
+    for (delimiter, constant) in packet_structure.delimiters.iter().zip(&delimiter_constants) {
+        text.push_str(&format!(
+            "            if bytes[{}] != {} {{\n                return None;\n            }}\n",
+            rust_range(Some(delimiter.offset_in_packet), delimiter.identifier.len()),
+            constant
+        ));
+    }
+    for crc in &packet_structure.packet_crc {
+        if crc.algorithm == CrcAlgorithm::Unchecked {
+            continue;
+        }
+        text.push_str(&format!(
+            "            if extract_bits(&bytes[{}], {}, 0, {2}) != {3} & mask({2}) {{\n                return None;\n            }}\n",
+            rust_range(Some(crc.offset_in_packet), crc.length),
+            crc.endianness == Endianness::Big,
+            crc.length * 8,
+            rust_checksum_call(crc)
+        ));
+    }
+    text.push_str("            let mut packet = Packet::default();\n");
+    for (field, name) in packet_structure.fields.iter().zip(&field_names) {
+        match field.array {
+            None => text.push_str(&format!(
+                "            packet.{} = {};\n",
+                name,
+                decode_expression(field, Some(field.offset_in_packet))
+            )),
+            Some(array) => text.push_str(&format!(
+                "            for i in 0..{} {{\n                let at = {} + i * {};\n                packet.{}[i] = {};\n            }}\n",
+                array.count,
+                field.offset_in_packet,
+                array.stride,
+                name,
+                decode_expression(field, None)
+            )),
+        }
+    }
+    text.push_str("            Some(packet)\n        }\n    }\n}\n");
+    text
+}
+
+/// The statement writing `value` into a field (or array element) starting at `at`
+fn encode_statement(field: &PacketField, at: Option<usize>, value: &str) -> String {
+    let big_endian = field.endianness == Endianness::Big;
+    let size = field.size().unwrap_or_default();
+    match (field.r#type, field.string_format) {
+        (PacketFieldType::String, Some(StringFormat::Fixed { length })) => format!(
+            "write_text(&mut bytes[{}], &self.{});",
+            rust_range(at, length),
+            value
+        ),
+        (PacketFieldType::String, Some(StringFormat::LengthPrefixed { prefix, .. })) => format!(
+            "write_prefixed_text(&mut bytes[{}], {}, {}, &self.{});",
+            rust_range(at, size),
+            prefix.size().unwrap_or_default(),
+            big_endian,
+            value
+        ),
+        (r#type, _) => {
+            let (bit_offset, bit_width) = field.bitfield.map_or((0, size * 8), |bitfield| {
+                (bitfield.bit_offset, bitfield.bit_width)
+            });
+            let raw = match r#type {
+                PacketFieldType::Float => format!("self.{}.to_bits() as u64", value),
+                PacketFieldType::Double => format!("self.{}.to_bits()", value),
+                _ => format!("self.{} as u64", value),
+            };
+            format!(
+                "insert_bits(&mut bytes[{}], {}, {}, {}, {});",
+                rust_range(at, size),
+                big_endian,
+                bit_offset,
+                bit_width,
+                raw
+            )
+        }
+    }
+}
+
+/// The expression reading a field (or array element) starting at `at`
+fn decode_expression(field: &PacketField, at: Option<usize>) -> String {
+    let big_endian = field.endianness == Endianness::Big;
+    let size = field.size().unwrap_or_default();
+    match (field.r#type, field.string_format) {
+        (PacketFieldType::String, Some(StringFormat::Fixed { length })) => {
+            format!("read_text(&bytes[{}])", rust_range(at, length))
+        }
+        (PacketFieldType::String, Some(StringFormat::LengthPrefixed { prefix, .. })) => format!(
+            "read_prefixed_text(&bytes[{}], {}, {})",
+            rust_range(at, size),
+            prefix.size().unwrap_or_default(),
+            big_endian
+        ),
+        (r#type, _) => {
+            let (bit_offset, bit_width) = field.bitfield.map_or((0, size * 8), |bitfield| {
+                (bitfield.bit_offset, bitfield.bit_width)
+            });
+            let raw = format!(
+                "extract_bits(&bytes[{}], {}, {}, {})",
+                rust_range(at, size),
+                big_endian,
+                bit_offset,
+                bit_width
+            );
+            match r#type {
+                PacketFieldType::Bool => format!("{} != 0", raw),
+                PacketFieldType::Float => format!("f32::from_bits({} as u32)", raw),
+                PacketFieldType::Double => format!("f64::from_bits({})", raw),
+                PacketFieldType::SignedByte
+                | PacketFieldType::SignedShort
+                | PacketFieldType::SignedTwoFour
+                | PacketFieldType::SignedInteger
+                | PacketFieldType::SignedFourZero
+                | PacketFieldType::SignedFourEight
+                | PacketFieldType::SignedLong => {
+                    format!(
+                        "sign_extend({}, {}) as {}",
+                        raw,
+                        bit_width,
+                        rust_type(r#type)
+                    )
+                }
+                r#type => format!("{} as {}", raw, rust_type(r#type)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{c_header::import_c_header, structure_dsl::parse_structure};
+
+    fn make_structure() -> PacketStructure {
+        parse_structure(
+            "rocket telemetry",
+            r#"
+                packed
+                sync = 0xba5eba11
+                Timestamp: u32
+                _2
+                mode: u8:3
+                armed: u8:1
+                error: u8:2 at 10.6
+                altitude: u24le "m"
+                count: u8
+                sat: { id: u8, cn0: u8 }[4 @ count]
+                acc: f32[3]
+                callsign: str6
+                crc16be
+            "#,
+        )
+        .unwrap()
+    }
+
+    //tests that the generated C header reads back with everything at the bytes WINGS uses
+    #[test]
+    fn c_header_matches_the_structure() {
+        let structure = make_structure();
+        let code = generate_firmware_code(std::slice::from_ref(&structure));
+        assert_eq!(code.problems, Vec::<String>::new());
+        assert!(code.c_header.contains("#define ROCKET_TELEMETRY_SIZE 43\n"));
+        assert!(code.c_header.contains(
+            "static const uint8_t ROCKET_TELEMETRY_SYNC[4] = {0xba, 0x5e, 0xba, 0x11};\n"
+        ));
+        assert!(code
+            .c_header
+            .contains("    uint16_t checksum; /* CRC-16-CCITT of bytes 0 to 40, big-endian */\n"));
+
+        let import = import_c_header(&code.c_header);
+        assert!(import.problems.is_empty(), "{:?}", import.problems);
+        let imported = &import.structures[0];
+        assert_eq!(imported.name, "rocket_telemetry_t");
+        assert_eq!(imported.size(), structure.size());
+
+        // (name, first bit) of every field, bitfields and all
+        let bits = |field: &PacketField| {
+            field.offset_in_packet * 8 + field.bitfield.map_or(0, |bitfield| bitfield.bit_offset)
+        };
+        for field in structure.fields.iter().flat_map(|field| field.elements()) {
+            let name = field.name.replace('[', "_").replace(']', "");
+            let matching = imported
+                .fields
+                .iter()
+                .flat_map(|field| field.elements())
+                .find(|imported| imported.name.replace('[', "_").replace(']', "") == name)
+                .unwrap_or_else(|| panic!("{} is missing", field.name));
+            assert_eq!(bits(&matching), bits(&field), "{}", field.name);
+        }
+        let sync = imported.fields.iter().find(|field| field.name == "sync");
+        assert_eq!(sync.map(|field| field.offset_in_packet), Some(0));
+        let checksum = imported
+            .fields
+            .iter()
+            .find(|field| field.name == "checksum");
+        assert_eq!(checksum.map(|field| field.offset_in_packet), Some(41));
+    }
+
+    //tests that the rust module writes and reads every field where the structure has it
+    #[test]
+    fn rust_module_places_everything() {
+        let code = generate_firmware_code(&[make_structure()]);
+        let module = &code.rust_module;
+        assert!(module.contains("pub mod rocket_telemetry {"));
+        assert!(module.contains("            bytes[0..4].copy_from_slice(&SYNC);\n"));
+        assert!(module.contains(
+            "            insert_bits(&mut bytes[10..11], false, 6, 2, self.error as u64);\n"
+        ));
+        assert!(module.contains("            for i in 0..(self.count as usize).min(4) {\n"));
+        assert!(module.contains("                let at = 15 + i * 2;\n"));
+        assert!(module.contains("                packet.sat_cn0[i] = extract_bits(&bytes[at..at + 1], false, 0, 8) as u8;\n"));
+        assert!(module.contains(
+            "            packet.altitude = extract_bits(&bytes[11..14], false, 0, 24) as u32;\n"
+        ));
+        assert!(module.contains("            let crc = crc16_ccitt(&bytes[0..41]);\n"));
+        assert!(module.contains("fn crc16_ccitt(bytes: &[u8]) -> u64 {"));
+        assert!(!module.contains("fn crc32("));
+    }
+
+    //tests that structures C can't declare are left out of both files
+    #[test]
+    fn overlapping_structures_are_left_out() {
+        let overlapping = parse_structure("overlapping", "a: u32 b: u16 at 2").unwrap();
+        let fine = parse_structure("fine", "a: u32").unwrap();
+        let code = generate_firmware_code(&[overlapping, fine]);
+        assert_eq!(
+            code.problems,
+            vec!["'overlapping' was left out: 'a' overlaps 'b'".to_owned()]
+        );
+        assert!(!code.c_header.contains("overlapping_t"));
+        assert!(!code.rust_module.contains("pub mod overlapping"));
+        assert!(code.c_header.contains("} fine_t;\n"));
+        assert!(code.rust_module.contains("pub mod fine {"));
+    }
+}
@@ -1,3 +1,6 @@
+pub mod c_header;
+pub mod firmware_code;
 pub mod packet;
 pub mod packet_structure;
 pub mod packet_view_model;
+pub mod structure_dsl;
@@ -1,15 +1,23 @@
+use std::collections::BTreeMap;
+
 use serde::Serialize;
 
-use crate::models::packet_structure::PacketFieldType;
+use crate::models::packet_structure::{
+    CrcAlgorithm, Endianness, PacketBitfield, PacketCRC, PacketField, PacketFieldType, StringFormat,
+};
 
 #[derive(PartialEq, Serialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 /// Represents a packet of data
 ///
 /// This includes all of the variables that have been received within a packet of data and its timestamp
+///
+/// `field_labels` holds the names of the values of enumerated fields, keyed by field index
 pub struct Packet {
     pub(crate) structure_id: usize,
     pub(crate) field_data: Vec<PacketFieldValue>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) field_labels: BTreeMap<usize, FieldLabel>,
 }
 
 impl Packet {
@@ -17,9 +25,20 @@ impl Packet {
         Packet {
             structure_id: type_id,
             field_data: values,
+            field_labels: BTreeMap::new(),
         }
     }
 }
+
+#[derive(PartialEq, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+/// Represents the name given to the value of an enumerated field
+///
+/// values that the field doesn't have a name for are labelled "Unknown" and flagged as not known
+pub struct FieldLabel {
+    pub(crate) label: String,
+    pub(crate) known: bool,
+}
 #[derive(PartialEq, Serialize, Clone, Debug)]
 #[serde(untagged)]
 pub enum PacketFieldValue {
@@ -60,11 +79,21 @@ impl PacketFieldValue {
                 PacketFieldType::SignedLong => Ok(i64::to_le_bytes(*i as i64).to_vec()),
                 PacketFieldType::Float => Ok(f32::to_le_bytes(*i as f32).to_vec()),
                 PacketFieldType::Double => Ok(f64::to_le_bytes(*i).to_vec()),
-                PacketFieldType::UnsignedTwoFour => {
-                    u32::to_le_bytes(*i as u32).to_vec();
-                    todo!("make these only spit out 3 u8s");
+                PacketFieldType::UnsignedTwoFour
+                | PacketFieldType::UnsignedFourZero
+                | PacketFieldType::UnsignedFourEight => {
+                    let bits = field_type.size()? * 8;
+                    // saturate like the casts above do
+                    let raw = (*i as u64).min((1 << bits) - 1);
+                    Ok(raw.to_le_bytes()[..bits / 8].to_vec())
+                }
+                PacketFieldType::SignedTwoFour
+                | PacketFieldType::SignedFourZero
+                | PacketFieldType::SignedFourEight => {
+                    let bits = field_type.size()? * 8;
+                    let raw = (*i as i64).clamp(-(1 << (bits - 1)), (1 << (bits - 1)) - 1);
+                    Ok(raw.to_le_bytes()[..bits / 8].to_vec())
                 }
-                PacketFieldType::SignedTwoFour => Ok(i32::to_le_bytes(*i as i32).to_vec()),
                 _ => Err(anyhow::anyhow!(
                     "Number value being processed as String or bool"
                 )),
@@ -72,6 +101,19 @@ impl PacketFieldValue {
         }
     }
 
+    /// Converts this value to a vec of bytes in the given byte order
+    pub fn to_bytes(
+        &self,
+        field_type: PacketFieldType,
+        endianness: Endianness,
+    ) -> anyhow::Result<Vec<u8>> {
+        let mut bytes = self.to_le_bytes(field_type)?;
+        if endianness == Endianness::Big && matches!(self, PacketFieldValue::Number(_)) {
+            bytes.reverse();
+        }
+        Ok(bytes)
+    }
+
     /// Returns the matching PacketFieldType for this parsed value.
     pub fn get_field_type(&self) -> PacketFieldType {
         match self {
@@ -83,8 +125,26 @@ impl PacketFieldValue {
 }
 
 impl PacketFieldType {
-    /// takes raw bytes and assigns them the PacketFieldType which they match
+    /// returns the byte order a field of this type is in unless it says otherwise
+    ///
+    /// 24 bit values were always read big-endian before fields had a byte order, so they stay
+    /// that way for the fields and devices that were set up for it. everything else is
+    /// little-endian
+    pub fn default_endianness(&self) -> Endianness {
+        match self {
+            PacketFieldType::UnsignedTwoFour | PacketFieldType::SignedTwoFour => Endianness::Big,
+            _ => Endianness::Little,
+        }
+    }
+
+    /// takes raw bytes in the type's `default_endianness` and assigns them the PacketFieldType
+    /// which they match
     pub fn parse(&self, bytes: &[u8]) -> anyhow::Result<PacketFieldValue> {
+        self.parse_with_endianness(bytes, self.default_endianness())
+    }
+
+    /// takes raw little-endian bytes and assigns them the PacketFieldType which they match
+    fn parse_little_endian(&self, bytes: &[u8]) -> anyhow::Result<PacketFieldValue> {
         match self {
             PacketFieldType::UnsignedByte => Ok(PacketFieldValue::Number(
                 u8::from_le_bytes(slice_to_fixed_size::<1>(bytes)).into(),
@@ -116,19 +176,21 @@ impl PacketFieldType {
             PacketFieldType::Double => Ok(PacketFieldValue::Number(f64::from_le_bytes(
                 slice_to_fixed_size::<8>(bytes),
             ))),
-            PacketFieldType::UnsignedTwoFour => {
-                let mut raw: u32 = 0;
-                raw |= (bytes[0] as u32) << 16;
-                raw |= (bytes[1] as u32) << 8;
-                raw |= bytes[2] as u32;
-                Ok(PacketFieldValue::Number(raw.into()))
-            }
-            PacketFieldType::SignedTwoFour => {
-                let mut raw: u32 = 0;
-                raw |= (bytes[0] as u32) << 16;
-                raw |= (bytes[1] as u32) << 8;
-                raw |= bytes[2] as u32;
-                Ok(PacketFieldValue::Number(raw.into()))
+            PacketFieldType::UnsignedTwoFour
+            | PacketFieldType::UnsignedFourZero
+            | PacketFieldType::UnsignedFourEight => {
+                let raw = container_to_raw(self.odd_width_bytes(bytes)?, Endianness::Little);
+                Ok(PacketFieldValue::Number(raw as f64))
+            }
+            PacketFieldType::SignedTwoFour
+            | PacketFieldType::SignedFourZero
+            | PacketFieldType::SignedFourEight => {
+                let raw = container_to_raw(self.odd_width_bytes(bytes)?, Endianness::Little);
+                // shift the sign bit up to the top of an i64 and back down to sign-extend it
+                let unused_bits = 64 - self.size()? * 8;
+                Ok(PacketFieldValue::Number(
+                    ((raw << unused_bits) as i64 >> unused_bits) as f64,
+                ))
             }
             PacketFieldType::String => {
                 Ok(PacketFieldValue::String(String::from_utf8(bytes.to_vec())?))
@@ -137,6 +199,26 @@ impl PacketFieldType {
         }
     }
 
+    /// takes raw bytes in the given byte order and assigns them the PacketFieldType which they match
+    ///
+    /// big-endian bytes are flipped and then parsed the same way little-endian bytes are
+    pub fn parse_with_endianness(
+        &self,
+        bytes: &[u8],
+        endianness: Endianness,
+    ) -> anyhow::Result<PacketFieldValue> {
+        match (endianness, self) {
+            (Endianness::Little, _) | (_, PacketFieldType::String) | (_, PacketFieldType::Bool) => {
+                self.parse_little_endian(bytes)
+            }
+            (Endianness::Big, _) => {
+                let mut flipped = bytes.to_vec();
+                flipped.reverse();
+                self.parse_little_endian(&flipped)
+            }
+        }
+    }
+
     ///parses the given string into the field
     ///
     /// # Errors
@@ -150,6 +232,10 @@ impl PacketFieldType {
             | PacketFieldType::SignedShort
             | PacketFieldType::UnsignedTwoFour
             | PacketFieldType::SignedTwoFour
+            | PacketFieldType::UnsignedFourZero
+            | PacketFieldType::SignedFourZero
+            | PacketFieldType::UnsignedFourEight
+            | PacketFieldType::SignedFourEight
             | PacketFieldType::UnsignedInteger
             | PacketFieldType::SignedInteger
             | PacketFieldType::UnsignedLong
@@ -173,12 +259,480 @@ impl PacketFieldType {
             PacketFieldType::Double => Ok(8),
             PacketFieldType::UnsignedTwoFour => Ok(3),
             PacketFieldType::SignedTwoFour => Ok(3),
+            PacketFieldType::UnsignedFourZero | PacketFieldType::SignedFourZero => Ok(5),
+            PacketFieldType::UnsignedFourEight | PacketFieldType::SignedFourEight => Ok(6),
             PacketFieldType::String => Err(anyhow::anyhow!(
                 "size of string is unknown from this context"
             )),
             PacketFieldType::Bool => Ok(1),
         }
     }
+
+    /// returns the bytes an odd width integer is made of, which don't fit a fixed size array
+    fn odd_width_bytes<'a>(&self, bytes: &'a [u8]) -> anyhow::Result<&'a [u8]> {
+        let size = self.size()?;
+        bytes.get(..size).ok_or(anyhow::anyhow!(
+            "{:?} needs {} bytes but only {} were given",
+            self,
+            size,
+            bytes.len()
+        ))
+    }
+}
+
+impl PacketField {
+    /// returns the number of bytes this field takes up in a packet
+    ///
+    /// bitfields return the size of their whole container
+    ///
+    /// # Errors
+    ///
+    /// errors if this is a String field without a string format
+    pub fn size(&self) -> anyhow::Result<usize> {
+        match (self.r#type, self.string_format) {
+            (PacketFieldType::String, Some(string_format)) => string_format.size(),
+            _ => self.r#type.size(),
+        }
+    }
+
+    /// returns the number of values this field parses to, arrays give one value per element
+    pub fn value_count(&self) -> usize {
+        self.array.map_or(1, |array| array.count)
+    }
+
+    /// returns a field for each value this field parses to
+    ///
+    /// array elements are named like "sat[3].cn0" for an array named "sat.cn0", or "accel[3]" for
+    /// an array without a "." in its name, unless they were given an element name. the elements
+    /// of a group member are `group_size` indexes apart. fields that aren't arrays just return
+    /// themselves
+    pub fn elements(&self) -> Vec<PacketField> {
+        let array = match self.array {
+            Some(array) => array,
+            None => return vec![self.clone()],
+        };
+        (0..array.count)
+            .map(|element_index| PacketField {
+                index: self.index + element_index * array.group_size.max(1),
+                name: match (
+                    self.element_names.get(element_index),
+                    self.name.split_once('.'),
+                ) {
+                    (Some(element_name), _) => element_name.clone(),
+                    (None, Some((array_name, member_name))) => {
+                        format!("{array_name}[{element_index}].{member_name}")
+                    }
+                    (None, None) => format!("{}[{element_index}]", self.name),
+                },
+                offset_in_packet: self.offset_in_packet + element_index * array.stride,
+                array: None,
+                element_names: vec![],
+                ..self.clone()
+            })
+            .collect()
+    }
+
+    /// finds this field inside the bytes of a full packet and parses it
+    ///
+    /// `packet` should start at the first byte of the packet, the field's offset is applied here
+    ///
+    /// # Errors
+    ///
+    /// errors if the field does not fit inside the given packet, or if it cant be parsed
+    pub fn parse_from_packet(&self, packet: &[u8]) -> anyhow::Result<PacketFieldValue> {
+        let field_end = self.offset_in_packet + self.size()?;
+        if field_end > packet.len() {
+            return Err(anyhow::anyhow!(
+                "Field {} ends at byte {}, but the packet is only {} bytes long",
+                self.name,
+                field_end,
+                packet.len()
+            ));
+        }
+        let container = &packet[self.offset_in_packet..field_end];
+        match (self.bitfield, self.string_format) {
+            (Some(bitfield), _) => bitfield.parse(container, self.r#type, self.endianness),
+            (None, Some(string_format)) if self.r#type == PacketFieldType::String => {
+                string_format.parse(container, self.endianness)
+            }
+            _ => self
+                .r#type
+                .parse_with_endianness(container, self.endianness),
+        }
+    }
+
+    /// converts the value to bytes and places it into a full packet at this field's offset
+    ///
+    /// bitfields only overwrite their own bits, so other bitfields sharing the container are kept
+    ///
+    /// # Errors
+    ///
+    /// errors if the field does not fit inside the given packet, or if the value cant be converted
+    pub fn write_to_packet(
+        &self,
+        value: &PacketFieldValue,
+        packet: &mut [u8],
+    ) -> anyhow::Result<()> {
+        let size = self.size()?;
+        let field_end = self.offset_in_packet + size;
+        if field_end > packet.len() {
+            return Err(anyhow::anyhow!(
+                "Field {} ends at byte {}, but the packet is only {} bytes long",
+                self.name,
+                field_end,
+                packet.len()
+            ));
+        }
+        let container = &mut packet[self.offset_in_packet..field_end];
+        match (self.bitfield, self.string_format) {
+            (Some(bitfield), _) => bitfield.write(value, container, self.r#type, self.endianness),
+            (None, Some(string_format)) if self.r#type == PacketFieldType::String => {
+                string_format.write(value, container, self.endianness)
+            }
+            _ => {
+                let bytes = value.to_bytes(self.r#type, self.endianness)?;
+                if bytes.len() < size {
+                    return Err(anyhow::anyhow!(
+                        "Field {} needs {} bytes but its value only made {}",
+                        self.name,
+                        size,
+                        bytes.len()
+                    ));
+                }
+                container.copy_from_slice(&bytes[..size]);
+                Ok(())
+            }
+        }
+    }
+}
+
+impl PacketBitfield {
+    /// pulls this bitfield's bits out of its container and assigns them the container's type
+    ///
+    /// signed containers sign-extend from the top bit of the bitfield, and bool containers
+    /// are true if any of the bits are set
+    ///
+    /// # Errors
+    ///
+    /// errors if the bits dont fit in the container, or if the container isnt an integer or bool
+    pub fn parse(
+        &self,
+        container: &[u8],
+        container_type: PacketFieldType,
+        endianness: Endianness,
+    ) -> anyhow::Result<PacketFieldValue> {
+        self.check_container(container, container_type)?;
+        let bits = (container_to_raw(container, endianness) >> self.bit_offset) & self.mask();
+        Ok(match container_type {
+            PacketFieldType::Bool => PacketFieldValue::Bool(bits != 0),
+            PacketFieldType::SignedByte
+            | PacketFieldType::SignedShort
+            | PacketFieldType::SignedTwoFour
+            | PacketFieldType::SignedFourZero
+            | PacketFieldType::SignedFourEight
+            | PacketFieldType::SignedInteger
+            | PacketFieldType::SignedLong => {
+                let unused_bits = 64 - self.bit_width;
+                PacketFieldValue::Number((((bits << unused_bits) as i64) >> unused_bits) as f64)
+            }
+            _ => PacketFieldValue::Number(bits as f64),
+        })
+    }
+
+    /// places the value into this bitfield's bits, leaving the rest of the container untouched
+    ///
+    /// values that are too large for the bitfield are truncated to its width
+    ///
+    /// # Errors
+    ///
+    /// errors if the bits dont fit in the container, if the container isnt an integer or bool,
+    /// or if the value is a string
+    pub fn write(
+        &self,
+        value: &PacketFieldValue,
+        container: &mut [u8],
+        container_type: PacketFieldType,
+        endianness: Endianness,
+    ) -> anyhow::Result<()> {
+        self.check_container(container, container_type)?;
+        let bits = match value {
+            PacketFieldValue::Bool(i) => *i as u64,
+            PacketFieldValue::Number(i) => (*i as i64) as u64,
+            PacketFieldValue::String(_) => {
+                return Err(anyhow::anyhow!("String value being placed in a bitfield"))
+            }
+        } & self.mask();
+        let mut raw = container_to_raw(container, endianness);
+        raw &= !(self.mask() << self.bit_offset);
+        raw |= bits << self.bit_offset;
+        let container_len = container.len();
+        for (i, byte) in container.iter_mut().enumerate() {
+            let shift = match endianness {
+                Endianness::Little => i * 8,
+                Endianness::Big => (container_len - 1 - i) * 8,
+            };
+            *byte = (raw >> shift) as u8;
+        }
+        Ok(())
+    }
+
+    /// returns a mask covering bit_width bits, starting from the least significant bit
+    fn mask(&self) -> u64 {
+        if self.bit_width >= 64 {
+            u64::MAX
+        } else {
+            (1 << self.bit_width) - 1
+        }
+    }
+
+    fn check_container(
+        &self,
+        container: &[u8],
+        container_type: PacketFieldType,
+    ) -> anyhow::Result<()> {
+        if matches!(
+            container_type,
+            PacketFieldType::Float | PacketFieldType::Double | PacketFieldType::String
+        ) {
+            return Err(anyhow::anyhow!(
+                "Bitfields must be inside an integer or bool container"
+            ));
+        }
+        if self.bit_width == 0 || self.bit_offset + self.bit_width > container.len() * 8 {
+            return Err(anyhow::anyhow!(
+                "Bitfield of {} bits at bit {} does not fit inside a {} byte container",
+                self.bit_width,
+                self.bit_offset,
+                container.len()
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl StringFormat {
+    /// returns the number of bytes reserved for the string, including any length prefix
+    ///
+    /// # Errors
+    ///
+    /// errors if the length prefix is not an unsigned integer type
+    pub fn size(&self) -> anyhow::Result<usize> {
+        match self {
+            StringFormat::Fixed { length } => Ok(*length),
+            StringFormat::LengthPrefixed { prefix, max_length } => {
+                Ok(Self::prefix_size(*prefix)? + max_length)
+            }
+        }
+    }
+
+    /// takes the bytes reserved for the string and pulls the text out of them
+    ///
+    /// fixed strings end at their first NUL, prefixed strings end where their prefix says
+    ///
+    /// # Errors
+    ///
+    /// errors if the text is not valid UTF-8, or if the prefix is longer than the reserved space
+    pub fn parse(&self, bytes: &[u8], endianness: Endianness) -> anyhow::Result<PacketFieldValue> {
+        let text = match self {
+            StringFormat::Fixed { .. } => match bytes.iter().position(|byte| *byte == 0) {
+                Some(end) => &bytes[..end],
+                None => bytes,
+            },
+            StringFormat::LengthPrefixed { prefix, max_length } => {
+                let prefix_size = Self::prefix_size(*prefix)?;
+                let length =
+                    match prefix.parse_with_endianness(&bytes[..prefix_size], endianness)? {
+                        PacketFieldValue::Number(length) => length as usize,
+                        _ => unreachable!("unsigned integer types always parse to numbers"),
+                    };
+                if length > *max_length {
+                    return Err(anyhow::anyhow!(
+                        "String length prefix of {} is longer than the maximum of {}",
+                        length,
+                        max_length
+                    ));
+                }
+                &bytes[prefix_size..prefix_size + length]
+            }
+        };
+        Ok(PacketFieldValue::String(String::from_utf8(text.to_vec())?))
+    }
+
+    /// places the text and any length prefix into the bytes reserved for the string,
+    /// unused bytes are filled with NULs
+    ///
+    /// # Errors
+    ///
+    /// errors if the value is not a string, or if the text does not fit
+    pub fn write(
+        &self,
+        value: &PacketFieldValue,
+        bytes: &mut [u8],
+        endianness: Endianness,
+    ) -> anyhow::Result<()> {
+        let text = match value {
+            PacketFieldValue::String(text) => text.as_bytes(),
+            _ => return Err(anyhow::anyhow!("Non string value being placed in a string")),
+        };
+        let text_start = match self {
+            StringFormat::Fixed { .. } => 0,
+            StringFormat::LengthPrefixed { prefix, .. } => Self::prefix_size(*prefix)?,
+        };
+        if text_start + text.len() > bytes.len() {
+            return Err(anyhow::anyhow!(
+                "String of {} bytes does not fit in the {} bytes reserved for it",
+                text.len(),
+                bytes.len() - text_start
+            ));
+        }
+        if let StringFormat::LengthPrefixed { prefix, .. } = self {
+            let length_bytes =
+                PacketFieldValue::Number(text.len() as f64).to_bytes(*prefix, endianness)?;
+            bytes[..text_start].copy_from_slice(&length_bytes);
+        }
+        bytes[text_start..text_start + text.len()].copy_from_slice(text);
+        bytes[text_start + text.len()..].fill(0);
+        Ok(())
+    }
+
+    fn prefix_size(prefix: PacketFieldType) -> anyhow::Result<usize> {
+        match prefix {
+            PacketFieldType::UnsignedByte
+            | PacketFieldType::UnsignedShort
+            | PacketFieldType::UnsignedInteger => prefix.size(),
+            _ => Err(anyhow::anyhow!(
+                "String length prefixes must be an unsigned byte, short, or integer"
+            )),
+        }
+    }
+}
+
+impl CrcAlgorithm {
+    /// calculates the checksum of `bytes` for a checksum field that is `length` bytes long
+    ///
+    /// # Errors
+    ///
+    /// errors if the algorithm produces a different number of bytes than `length`
+    pub fn calculate(&self, bytes: &[u8], length: usize) -> anyhow::Result<u64> {
+        let expected_length = match self {
+            CrcAlgorithm::Unchecked | CrcAlgorithm::Sum => length,
+            CrcAlgorithm::Crc8 | CrcAlgorithm::Xor => 1,
+            CrcAlgorithm::Crc16Ccitt => 2,
+            CrcAlgorithm::Crc32 => 4,
+        };
+        if length != expected_length || length == 0 || length > 8 {
+            return Err(anyhow::anyhow!(
+                "A {:?} checksum can't be {} bytes long",
+                self,
+                length
+            ));
+        }
+        Ok(match self {
+            CrcAlgorithm::Unchecked => 0,
+            CrcAlgorithm::Crc8 => bytes.iter().fold(0u8, |crc, byte| {
+                (0..8).fold(crc ^ byte, |crc, _| {
+                    if crc & 0x80 != 0 {
+                        (crc << 1) ^ 0x07
+                    } else {
+                        crc << 1
+                    }
+                })
+            }) as u64,
+            CrcAlgorithm::Crc16Ccitt => bytes.iter().fold(0xFFFFu16, |crc, byte| {
+                (0..8).fold(crc ^ ((*byte as u16) << 8), |crc, _| {
+                    if crc & 0x8000 != 0 {
+                        (crc << 1) ^ 0x1021
+                    } else {
+                        crc << 1
+                    }
+                })
+            }) as u64,
+            CrcAlgorithm::Crc32 => !bytes.iter().fold(0xFFFFFFFFu32, |crc, byte| {
+                (0..8).fold(crc ^ *byte as u32, |crc, _| {
+                    if crc & 1 != 0 {
+                        (crc >> 1) ^ 0xEDB88320
+                    } else {
+                        crc >> 1
+                    }
+                })
+            }) as u64,
+            CrcAlgorithm::Xor => bytes.iter().fold(0, |crc, byte| crc ^ byte) as u64,
+            CrcAlgorithm::Sum => {
+                let sum = bytes
+                    .iter()
+                    .fold(0u64, |sum, byte| sum.wrapping_add(*byte as u64));
+                match length {
+                    8 => sum,
+                    _ => sum & ((1 << (length * 8)) - 1),
+                }
+            }
+        })
+    }
+}
+
+impl PacketCRC {
+    /// returns the checksum calculated from the bytes this checksum covers
+    ///
+    /// # Errors
+    ///
+    /// errors if the covered bytes are outside of the packet, or if the length doesn't suit
+    /// the algorithm
+    pub fn calculate(&self, packet: &[u8]) -> anyhow::Result<u64> {
+        let covered_end = self.covered_end.unwrap_or(self.offset_in_packet);
+        let covered = packet
+            .get(self.covered_start..covered_end)
+            .ok_or(anyhow::anyhow!(
+                "Checksum covers bytes {}..{}, which are outside of the packet",
+                self.covered_start,
+                covered_end
+            ))?;
+        self.algorithm.calculate(covered, self.length)
+    }
+
+    /// returns true if the checksum sent in the packet matches the bytes it covers
+    ///
+    /// # Errors
+    ///
+    /// errors if the checksum or the bytes it covers are outside of the packet
+    pub fn check(&self, packet: &[u8]) -> anyhow::Result<bool> {
+        if self.algorithm == CrcAlgorithm::Unchecked {
+            return Ok(true);
+        }
+        let sent = packet
+            .get(self.offset_in_packet..self.offset_in_packet + self.length)
+            .ok_or(anyhow::anyhow!("Checksum is outside of the packet"))?;
+        Ok(container_to_raw(sent, self.endianness) == self.calculate(packet)?)
+    }
+
+    /// places the checksum of the bytes it covers into the packet
+    ///
+    /// # Errors
+    ///
+    /// errors if the checksum or the bytes it covers are outside of the packet
+    pub fn write(&self, packet: &mut [u8]) -> anyhow::Result<()> {
+        let crc = self.calculate(packet)?.to_le_bytes();
+        let bytes = packet
+            .get_mut(self.offset_in_packet..self.offset_in_packet + self.length)
+            .ok_or(anyhow::anyhow!("Checksum is outside of the packet"))?;
+        bytes.copy_from_slice(&crc[..self.length]);
+        if self.endianness == Endianness::Big {
+            bytes.reverse();
+        }
+        Ok(())
+    }
+}
+
+/// reads up to 8 bytes as an unsigned integer in the given byte order
+fn container_to_raw(container: &[u8], endianness: Endianness) -> u64 {
+    let mut raw: u64 = 0;
+    for (i, byte) in container.iter().enumerate() {
+        let shift = match endianness {
+            Endianness::Little => i * 8,
+            Endianness::Big => (container.len() - 1 - i) * 8,
+        };
+        raw |= (*byte as u64) << shift;
+    }
+    raw
 }
 
 fn slice_to_fixed_size<const N: usize>(slice: &[u8]) -> [u8; N] {
@@ -188,6 +742,7 @@ fn slice_to_fixed_size<const N: usize>(slice: &[u8]) -> [u8; N] {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::{packet_structure::Calibration, structure_dsl::parse_structure};
     //tests that ints can be made
     #[test]
     fn make_0_uint() {
@@ -227,4 +782,380 @@ mod tests {
         let parsed = unsigned_integer_type.make_from_string("-1").unwrap();
         assert_eq!(parsed, PacketFieldValue::Number(-1.0));
     }
+
+    //tests that the same bytes parse differently depending on byte order
+    #[test]
+    fn parse_big_endian() {
+        let bytes = [0x12, 0x34];
+        let little = PacketFieldType::UnsignedShort
+            .parse_with_endianness(&bytes, Endianness::Little)
+            .unwrap();
+        let big = PacketFieldType::UnsignedShort
+            .parse_with_endianness(&bytes, Endianness::Big)
+            .unwrap();
+        assert_eq!(little, PacketFieldValue::Number(0x3412 as f64));
+        assert_eq!(big, PacketFieldValue::Number(0x1234 as f64));
+    }
+
+    //tests that 24 bit values default to big-endian like they were read before fields had a byte
+    //order, and can still be read little-endian
+    #[test]
+    fn parse_24_bit_endianness() {
+        let bytes = [0x01, 0x02, 0x03];
+        let default = PacketFieldType::UnsignedTwoFour.parse(&bytes).unwrap();
+        let little = PacketFieldType::UnsignedTwoFour
+            .parse_with_endianness(&bytes, Endianness::Little)
+            .unwrap();
+        assert_eq!(default, PacketFieldValue::Number(0x010203 as f64));
+        assert_eq!(little, PacketFieldValue::Number(0x030201 as f64));
+    }
+
+    fn make_bitfield(r#type: PacketFieldType, bit_offset: usize, bit_width: usize) -> PacketField {
+        PacketField {
+            index: 0,
+            name: "test bitfield".to_owned(),
+            r#type,
+            offset_in_packet: 1,
+            endianness: Endianness::Little,
+            bitfield: Some(PacketBitfield {
+                bit_offset,
+                bit_width,
+            }),
+            string_format: None,
+            array: None,
+            calibration: None,
+            unit: None,
+            labels: BTreeMap::new(),
+            timestamp: None,
+            sequence: None,
+            element_names: vec![],
+        }
+    }
+
+    //tests that bitfields pick their own bits out of a shared container
+    #[test]
+    fn parse_bitfields() {
+        let packet = [0xFF, 0b1011_0110];
+        let state = make_bitfield(PacketFieldType::UnsignedByte, 4, 4);
+        let line_a = make_bitfield(PacketFieldType::Bool, 0, 1);
+        let line_b = make_bitfield(PacketFieldType::Bool, 1, 1);
+        let signed = make_bitfield(PacketFieldType::SignedByte, 5, 3);
+        assert_eq!(
+            state.parse_from_packet(&packet).unwrap(),
+            PacketFieldValue::Number(0b1011 as f64)
+        );
+        assert_eq!(
+            line_a.parse_from_packet(&packet).unwrap(),
+            PacketFieldValue::Bool(false)
+        );
+        assert_eq!(
+            line_b.parse_from_packet(&packet).unwrap(),
+            PacketFieldValue::Bool(true)
+        );
+        assert_eq!(
+            signed.parse_from_packet(&packet).unwrap(),
+            PacketFieldValue::Number(-3.0)
+        );
+    }
+
+    //tests that writing a bitfield leaves the rest of its container alone
+    #[test]
+    fn write_bitfields() {
+        let mut packet = [0x00, 0b1000_0001];
+        make_bitfield(PacketFieldType::UnsignedByte, 2, 3)
+            .write_to_packet(&PacketFieldValue::Number(0b101 as f64), &mut packet)
+            .unwrap();
+        assert_eq!(packet, [0x00, 0b1001_0101]);
+        make_bitfield(PacketFieldType::SignedByte, 2, 3)
+            .write_to_packet(&PacketFieldValue::Number(-1.0), &mut packet)
+            .unwrap();
+        assert_eq!(packet, [0x00, 0b1001_1101]);
+    }
+
+    //tests that bitfields in big-endian containers count bits from the last byte
+    #[test]
+    fn big_endian_bitfield() {
+        let mut field = make_bitfield(PacketFieldType::UnsignedShort, 4, 8);
+        field.endianness = Endianness::Big;
+        let mut packet = [0x00, 0x00, 0x00];
+        field
+            .write_to_packet(&PacketFieldValue::Number(0xAB as f64), &mut packet)
+            .unwrap();
+        assert_eq!(packet, [0x00, 0x0A, 0xB0]);
+        assert_eq!(
+            field.parse_from_packet(&packet).unwrap(),
+            PacketFieldValue::Number(0xAB as f64)
+        );
+    }
+
+    //tests that bitfields which dont fit their container are rejected
+    #[test]
+    fn oversized_bitfield() {
+        let field = make_bitfield(PacketFieldType::UnsignedByte, 6, 4);
+        assert!(field.parse_from_packet(&[0x00, 0xFF]).is_err());
+    }
+
+    //tests that fixed length strings stop at their NUL padding
+    #[test]
+    fn parse_fixed_string() {
+        let format = StringFormat::Fixed { length: 6 };
+        let parsed = format.parse(b"KD0\0\0\0", Endianness::Little).unwrap();
+        assert_eq!(parsed, PacketFieldValue::String("KD0".to_owned()));
+        let full = format.parse(b"KD0ABC", Endianness::Little).unwrap();
+        assert_eq!(full, PacketFieldValue::String("KD0ABC".to_owned()));
+    }
+
+    //tests that length prefixed strings can be generated and parsed back
+    #[test]
+    fn length_prefixed_string_round_trip() {
+        let format = StringFormat::LengthPrefixed {
+            prefix: PacketFieldType::UnsignedShort,
+            max_length: 6,
+        };
+        let value = PacketFieldValue::String("v1.2".to_owned());
+        let mut bytes = [0xFF; 8];
+        format.write(&value, &mut bytes, Endianness::Big).unwrap();
+        assert_eq!(bytes, [0x00, 0x04, b'v', b'1', b'.', b'2', 0x00, 0x00]);
+        assert_eq!(format.parse(&bytes, Endianness::Big).unwrap(), value);
+    }
+
+    //tests that strings which dont fit their reserved bytes are rejected
+    #[test]
+    fn oversized_string() {
+        let format = StringFormat::LengthPrefixed {
+            prefix: PacketFieldType::UnsignedByte,
+            max_length: 2,
+        };
+        let mut bytes = [0; 3];
+        assert!(format
+            .write(
+                &PacketFieldValue::String("abc".to_owned()),
+                &mut bytes,
+                Endianness::Little
+            )
+            .is_err());
+        assert!(format.parse(&[3, b'a', b'b'], Endianness::Little).is_err());
+    }
+
+    //tests that big-endian bytes can be generated and parsed back
+    #[test]
+    fn big_endian_round_trip() {
+        let value = PacketFieldValue::Number(-4660.0);
+        let bytes = value
+            .to_bytes(PacketFieldType::SignedInteger, Endianness::Big)
+            .unwrap();
+        assert_eq!(bytes, vec![0xFF, 0xFF, 0xED, 0xCC]);
+        let parsed = PacketFieldType::SignedInteger
+            .parse_with_endianness(&bytes, Endianness::Big)
+            .unwrap();
+        assert_eq!(parsed, value);
+    }
+
+    //tests that linear and polynomial calibrations convert raw values
+    #[test]
+    fn calibrations() {
+        let linear = Calibration::Linear {
+            scale: 750.0,
+            offset: -3750.0,
+        };
+        assert_eq!(linear.apply(9.0), 3000.0);
+        let polynomial = Calibration::Polynomial {
+            coefficients: vec![1.0, 2.0, 3.0],
+        };
+        assert_eq!(polynomial.apply(2.0), 17.0);
+    }
+
+    //tests that calibrations reach every array element but leave count fields raw
+    #[test]
+    fn calibrate_packet() {
+        let structure = parse_structure(
+            "test",
+            r#"count: u8 scale(10) cn0: u16[3 @ count] "dB" scale(0.5, 1)"#,
+        )
+        .unwrap();
+        let mut values = structure.parse_fields(&[2, 0, 4, 0, 8, 0, 0, 0]).unwrap();
+        structure.calibrate(&mut values);
+        assert_eq!(values[0], PacketFieldValue::Number(20.0));
+        assert_eq!(values[1], PacketFieldValue::Number(3.0));
+        assert_eq!(values[2], PacketFieldValue::Number(5.0));
+        assert!(matches!(values[3], PacketFieldValue::Number(n) if n.is_nan()));
+    }
+
+    //tests each checksum algorithm against the standard check input
+    #[test]
+    fn crc_algorithms() {
+        let input = b"123456789";
+        assert_eq!(CrcAlgorithm::Crc8.calculate(input, 1).unwrap(), 0xF4);
+        assert_eq!(
+            CrcAlgorithm::Crc16Ccitt.calculate(input, 2).unwrap(),
+            0x29B1
+        );
+        assert_eq!(CrcAlgorithm::Crc32.calculate(input, 4).unwrap(), 0xCBF43926);
+        assert_eq!(CrcAlgorithm::Xor.calculate(input, 1).unwrap(), 0x31);
+        assert_eq!(CrcAlgorithm::Sum.calculate(input, 1).unwrap(), 0xDD);
+        assert_eq!(CrcAlgorithm::Sum.calculate(input, 2).unwrap(), 0x01DD);
+        assert!(CrcAlgorithm::Crc32.calculate(input, 2).is_err());
+    }
+
+    //tests that a checksum only covers its declared range and is written in its byte order
+    #[test]
+    fn crc_covered_range() {
+        let crc = PacketCRC {
+            length: 2,
+            offset_in_packet: 0,
+            algorithm: CrcAlgorithm::Sum,
+            endianness: Endianness::Big,
+            covered_start: 2,
+            covered_end: Some(4),
+        };
+        let mut packet = [0, 0, 0xF0, 0x20, 0xFF];
+        crc.write(&mut packet).unwrap();
+        assert_eq!(packet, [0x01, 0x10, 0xF0, 0x20, 0xFF]);
+        assert!(crc.check(&packet).unwrap());
+        packet[3] = 0x21;
+        assert!(!crc.check(&packet).unwrap());
+        assert!(crc.check(&packet[..3]).is_err());
+    }
+
+    //tests that 24, 40 and 48 bit values are sign-extended
+    #[test]
+    fn parse_odd_width_signed() {
+        let negative = PacketFieldType::SignedTwoFour
+            .parse(&[0xFF, 0xFF, 0xFE])
+            .unwrap();
+        assert_eq!(negative, PacketFieldValue::Number(-2.0));
+        let negative = PacketFieldType::SignedFourZero
+            .parse(&[0x00, 0x00, 0x00, 0x00, 0x80])
+            .unwrap();
+        assert_eq!(negative, PacketFieldValue::Number(-549755813888.0));
+        let positive = PacketFieldType::SignedFourEight
+            .parse_with_endianness(&[0x7F, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF], Endianness::Big)
+            .unwrap();
+        assert_eq!(positive, PacketFieldValue::Number(140737488355327.0));
+        assert!(PacketFieldType::UnsignedFourEight.parse(&[0; 5]).is_err());
+    }
+
+    //tests that odd width values saturate instead of spilling into the next field
+    #[test]
+    fn odd_width_saturates() {
+        let bytes = PacketFieldValue::Number(-9000000.0)
+            .to_le_bytes(PacketFieldType::SignedTwoFour)
+            .unwrap();
+        assert_eq!(bytes, vec![0x00, 0x00, 0x80]);
+        let bytes = PacketFieldValue::Number(16777216.0)
+            .to_le_bytes(PacketFieldType::UnsignedTwoFour)
+            .unwrap();
+        assert_eq!(bytes, vec![0xFF, 0xFF, 0xFF]);
+    }
+
+    //tests that every type can be generated and parsed back in both byte orders
+    #[test]
+    fn round_trip_every_type() {
+        let values = [
+            (
+                PacketFieldType::UnsignedByte,
+                PacketFieldValue::Number(200.0),
+            ),
+            (
+                PacketFieldType::SignedByte,
+                PacketFieldValue::Number(-100.0),
+            ),
+            (
+                PacketFieldType::UnsignedShort,
+                PacketFieldValue::Number(60000.0),
+            ),
+            (
+                PacketFieldType::SignedShort,
+                PacketFieldValue::Number(-30000.0),
+            ),
+            (
+                PacketFieldType::UnsignedTwoFour,
+                PacketFieldValue::Number(16000000.0),
+            ),
+            (
+                PacketFieldType::SignedTwoFour,
+                PacketFieldValue::Number(-8000000.0),
+            ),
+            (
+                PacketFieldType::UnsignedInteger,
+                PacketFieldValue::Number(4000000000.0),
+            ),
+            (
+                PacketFieldType::SignedInteger,
+                PacketFieldValue::Number(-2000000000.0),
+            ),
+            (
+                PacketFieldType::UnsignedFourZero,
+                PacketFieldValue::Number(1099511627775.0),
+            ),
+            (
+                PacketFieldType::SignedFourZero,
+                PacketFieldValue::Number(-549755813888.0),
+            ),
+            (
+                PacketFieldType::UnsignedFourEight,
+                PacketFieldValue::Number(281474976710655.0),
+            ),
+            (
+                PacketFieldType::SignedFourEight,
+                PacketFieldValue::Number(-140737488355328.0),
+            ),
+            (
+                PacketFieldType::UnsignedLong,
+                PacketFieldValue::Number(1e18),
+            ),
+            (PacketFieldType::SignedLong, PacketFieldValue::Number(-1e18)),
+            (PacketFieldType::Float, PacketFieldValue::Number(-1.5)),
+            (PacketFieldType::Double, PacketFieldValue::Number(0.1)),
+            (
+                PacketFieldType::String,
+                PacketFieldValue::String("abc".to_owned()),
+            ),
+            (PacketFieldType::Bool, PacketFieldValue::Bool(true)),
+        ];
+        for (field_type, value) in values {
+            for endianness in [Endianness::Little, Endianness::Big] {
+                let bytes = value.to_bytes(field_type, endianness).unwrap();
+                if field_type != PacketFieldType::String {
+                    assert_eq!(bytes.len(), field_type.size().unwrap());
+                }
+                let parsed = field_type
+                    .parse_with_endianness(&bytes, endianness)
+                    .unwrap();
+                assert_eq!(parsed, value, "{:?} {:?}", field_type, endianness);
+            }
+        }
+    }
+
+    //tests that enumerated fields are labelled and unknown codes are flagged
+    #[test]
+    fn label_enumerated_fields() {
+        let structure = parse_structure(
+            "test",
+            "state: u8 {0 = Idle, 1 = Pad, 2 = Boost} pyro: u8[2] {0 = Safe, 1 = Armed} altitude: f32",
+        )
+        .unwrap();
+        let labels = structure.label(&[
+            PacketFieldValue::Number(2.0),
+            PacketFieldValue::Number(1.0),
+            PacketFieldValue::Number(7.0),
+            PacketFieldValue::Number(1.0),
+        ]);
+        assert_eq!(labels.len(), 3);
+        assert_eq!(
+            labels[&0],
+            FieldLabel {
+                label: "Boost".to_owned(),
+                known: true
+            }
+        );
+        assert_eq!(labels[&1].label, "Armed");
+        assert_eq!(
+            labels[&2],
+            FieldLabel {
+                label: "Unknown (7)".to_owned(),
+                known: false
+            }
+        );
+    }
 }
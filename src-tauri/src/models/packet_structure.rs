@@ -5,10 +5,12 @@
 //! for fields, delimiters, CRCs, and metadata, as well as utility methods for
 //! constructing and analyzing packet structures.
 
-use std::cmp::max;
+use std::{cmp::max, collections::BTreeMap};
 
 use serde::{Deserialize, Serialize};
 
+use crate::models::packet::{FieldLabel, PacketFieldValue};
+
 #[derive(PartialEq, Deserialize, Serialize, Clone, Debug, Default)]
 
 /// Represents an entire "Data Packet Structure"
@@ -20,7 +22,9 @@ use serde::{Deserialize, Serialize};
 /// - `delimiters` represent any constant values in the packet.
 /// - `packet_crc` represents a delimiter derived from a checksum of previous elements.
 /// - `size` represents the overall size of the packet.
+/// - `length_field` marks the packet as variable length, with its length read from one of its fields.
 pub struct PacketStructure {
+    #[serde(default)]
     pub(crate) id: usize,
     pub(crate) name: String,
     pub(crate) byte_defined: bool, //marks wether or not a packet describes its full binary layout
@@ -28,6 +32,8 @@ pub struct PacketStructure {
     pub(crate) delimiters: Vec<PacketDelimiter>,
     pub(crate) packet_crc: Vec<PacketCRC>,
     pub(crate) size: Option<usize>,
+    #[serde(default)]
+    pub(crate) length_field: Option<PacketLength>,
 }
 
 impl PacketStructure {
@@ -41,6 +47,7 @@ impl PacketStructure {
             delimiters: vec![],
             packet_crc: vec![],
             size: None,
+            length_field: None,
         }
     }
     pub fn make_from_fields_and_delims(
@@ -57,8 +64,88 @@ impl PacketStructure {
             delimiters,
             packet_crc: vec![],
             size: None,
+            length_field: None,
+        }
+    }
+    /// Returns the smallest number of bytes a packet of this structure can take up
+    ///
+    /// variable length packets need to at least reach the end of their length field
+    /// and their first delimiter, everything else has a constant size
+    pub fn min_size(&self) -> usize {
+        let length_field = match self.length_field {
+            Some(length_field) => length_field,
+            None => return self.size(),
+        };
+        let length_field_end = self
+            .fields
+            .iter()
+            .find(|field| field.index == length_field.field_index)
+            .map_or(0, |field| {
+                field.offset_in_packet + field.size().unwrap_or(0)
+            });
+        let first_delimiter_end = self.delimiters.first().map_or(0, |delimiter| {
+            delimiter.offset_in_packet + delimiter.identifier.len()
+        });
+        max(length_field_end, first_delimiter_end)
+    }
+
+    /// Returns the largest number of bytes a packet of this structure can take up
+    pub fn max_size(&self) -> usize {
+        match self.length_field {
+            Some(length_field) => max(self.size(), length_field.max_size),
+            None => self.size(),
+        }
+    }
+
+    /// Finds the length of the packet starting at the first byte of `packet`
+    ///
+    /// constant size packets are always `size()` long, variable length packets read their length
+    /// from their length field. `Ok(None)` is returned if the length field hasn't been received yet
+    ///
+    /// # Errors
+    ///
+    /// errors if the length field is missing, or if it gives a length outside of
+    /// `min_size()..=max_size()`
+    pub fn packet_length(&self, packet: &[u8]) -> anyhow::Result<Option<usize>> {
+        let length_field = match self.length_field {
+            Some(length_field) => length_field,
+            None => return Ok(Some(self.size())),
+        };
+        let field = self
+            .fields
+            .iter()
+            .find(|field| field.index == length_field.field_index && field.array.is_none())
+            .ok_or(anyhow::anyhow!(
+                "Packet {} takes its length from missing field {}",
+                self.name,
+                length_field.field_index
+            ))?;
+        if field.offset_in_packet + field.size()? > packet.len() {
+            return Ok(None);
+        }
+        let length = match field.parse_from_packet(packet)? {
+            PacketFieldValue::Number(length) => {
+                (length as isize).saturating_add(length_field.adjustment)
+            }
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Packet {} takes its length from a non number field",
+                    self.name
+                ))
+            }
+        };
+        if length < self.min_size() as isize || length > self.max_size() as isize {
+            return Err(anyhow::anyhow!(
+                "Packet {} has a length of {}, which is outside of {}..={}",
+                self.name,
+                length,
+                self.min_size(),
+                self.max_size()
+            ));
         }
+        Ok(Some(length as usize))
     }
+
     /// Returns the size of the PacketStructure
     ///
     /// size is found by going through every element in the packetStructure, and finding the largest sum of an elements offset and size.
@@ -70,8 +157,11 @@ impl PacketStructure {
                 let mut max_end: usize = 0;
 
                 for field in &self.fields {
-                    let size = field.r#type.size().unwrap_or(0);
-                    max_end = max(max_end, field.offset_in_packet + size);
+                    let size = field.size().unwrap_or(0);
+                    let last_element = field
+                        .array
+                        .map_or(0, |array| array.count.saturating_sub(1) * array.stride);
+                    max_end = max(max_end, field.offset_in_packet + last_element + size);
                 }
 
                 for delimiter in &self.delimiters {
@@ -81,13 +171,17 @@ impl PacketStructure {
                     );
                 }
 
+                for crc in &self.packet_crc {
+                    max_end = max(max_end, crc.offset_in_packet + crc.length);
+                }
+
                 max_end
             }
         }
     }
 
     /// fills the packet calling it using string inputs,
-    /// THIS IS MEANT FOR TESTING ONLY
+    /// THIS IS MEANT FOR TESTING ONLY, structures that ship are written with `structure_dsl`
     ///
     /// 0 - f,    represents delimiters in hex
     /// _1-inf    represents gaps, the number after is the length in decimal
@@ -95,16 +189,45 @@ impl PacketStructure {
     /// i8 - i64  represents signed ints
     /// F32 & F64 represents floats
     ///
+    /// bool      represents a 1 byte bool
+    ///
+    /// strN      represents a fixed length string of N bytes, padded with NULs
+    /// u8strN    represents a string of up to N bytes after a u8 length prefix, u16 and u32
+    ///           prefixes can be used too ie "u16str32"
+    ///
+    /// any field type can be given a "be" suffix to mark it as big-endian, ie "u16be" or "F32be"
+    ///
+    /// integer and bool types can be given a ":N" suffix to make an N bit wide bitfield, ie "u8:4".
+    /// bitfields of the same container type are packed into one container starting from its least
+    /// significant bit, and a new container is started once the next bitfield no longer fits
+    /// ie "u8:4 bool:1 bool:1 u8:2" is one byte holding 4 fields
+    ///
+    /// "u24", "i24", "u40", "i40", "u48" and "i48" are odd width integers, which are never padded
+    ///
+    /// any type can be given a "be" or "le" suffix to set its byte order, otherwise it gets the
+    /// type's default
+    ///
+    /// non-bitfield types can be given a "[N]" suffix to make an array of N elements, ie "F32[3]".
+    /// "[N@I]" leaves room for N elements but reads how many were sent from the field with index I.
+    /// several types can repeat together as a group, ie "{u8,u8}[12@1]" is 12 pairs of u8s with
+    /// one field per member. arrays are named like "sat.cn0", which gives elements like "sat[3].cn0"
+    ///
+    /// checksums are written as "crc8", "crc16", "crc32", "xor8", "sum8", "sum16" or "sum32"
+    /// (with an optional "be" suffix) and cover every byte before them
+    ///
     /// all delimiters will be named "test delimiter" and all fields "test field"
     ///
     /// spaces are used to format between elements
     /// ie "deadbeef _4 u8 u8 i16 i16 deadbeef" is 2 delimiters and 4 variables and a 4byte gap
+    #[cfg(test)]
     pub fn ez_make(&mut self, input: &str, names: &[&str], reverse_delimiter: bool) {
         self.byte_defined = true;
         let mut curr_offset = 0;
+        // (offset, size, bits used) of the container bitfields are currently being packed into
+        let mut bitfield_container: Option<(usize, usize, usize)> = None;
         for substr in input.split(" ") {
             let first_char = substr.chars().nth(0).unwrap();
-            if first_char.is_ascii_hexdigit()
+            if substr.chars().all(|c| c.is_ascii_hexdigit())
                 && (first_char.is_lowercase() || first_char.is_ascii_digit())
             {
                 let mut new_identifier = hex::decode(substr).unwrap();
@@ -113,6 +236,7 @@ impl PacketStructure {
                     new_identifier.reverse(); //this is the way firmware broadcasts the identifiers
                 }
 
+                bitfield_container = None;
                 let offset = new_identifier.len(); //calculates size of the delimiter in memory
                 curr_offset = (curr_offset + offset - 1) / offset * offset; //aligns the variable
 
@@ -124,13 +248,97 @@ impl PacketStructure {
                 };
                 curr_offset += offset;
                 self.delimiters.push(new_delimiter);
+            } else if first_char == '{' {
+                bitfield_container = None;
+                let (members, array_suffix) = substr[1..].split_once("}[").unwrap();
+                let (count, count_field) = Self::ez_parse_array_suffix(array_suffix);
+
+                // lay the group out on its own to find where each member sits within it
+                let mut group = PacketStructure::default();
+                let member_names = &names[self.fields.len()..];
+                group.ez_make(&members.replace(',', " "), member_names, false);
+                let alignment = group
+                    .fields
+                    .iter()
+                    .map(|field| field.r#type.size().unwrap_or(1))
+                    .max()
+                    .unwrap_or(1);
+                let stride = group.size().div_ceil(alignment) * alignment;
+                curr_offset = curr_offset.div_ceil(alignment) * alignment; //aligns the group
+
+                let group_size = group.fields.len();
+                let first_index = self.value_count();
+                for (member_index, member) in group.fields.into_iter().enumerate() {
+                    let new_field = PacketField {
+                        index: first_index + member_index,
+                        offset_in_packet: curr_offset + member.offset_in_packet,
+                        array: Some(PacketArray {
+                            count,
+                            stride,
+                            count_field,
+                            group_size,
+                        }),
+                        ..member
+                    };
+                    self.fields.push(new_field);
+                }
+                curr_offset += stride * count;
+            } else if let Some((algorithm, length, endianness)) = Self::ez_parse_crc(substr) {
+                bitfield_container = None;
+                curr_offset = curr_offset.div_ceil(length) * length; //aligns the checksum
+                self.packet_crc.push(PacketCRC {
+                    length,
+                    offset_in_packet: curr_offset,
+                    algorithm,
+                    endianness,
+                    covered_start: 0,
+                    covered_end: None,
+                });
+                curr_offset += length;
             } else if first_char == '_' {
+                bitfield_container = None;
                 let trimmed_str = substr.chars().next().map(|c| &substr[c.len_utf8()..]);
                 curr_offset += trimmed_str.unwrap().parse::<usize>().unwrap();
             } else {
                 let offset: usize;
                 let t: PacketFieldType;
-                match substr {
+                let (type_str, array) = match substr.split_once('[') {
+                    Some((type_str, array_suffix)) => {
+                        (type_str, Some(Self::ez_parse_array_suffix(array_suffix)))
+                    }
+                    None => (substr, None),
+                };
+                let (type_str, bit_width) = match type_str.split_once(':') {
+                    Some((type_str, bits)) => (type_str, Some(bits.parse::<usize>().unwrap())),
+                    None => (type_str, None),
+                };
+                let (type_str, endianness) = match type_str.strip_suffix("be") {
+                    Some(type_str) => (type_str, Some(Endianness::Big)),
+                    None => match type_str.strip_suffix("le") {
+                        Some(type_str) => (type_str, Some(Endianness::Little)),
+                        None => (type_str, None),
+                    },
+                };
+                let string_format = type_str.split_once("str").map(|(prefix_str, length)| {
+                    let length = length.parse::<usize>().unwrap();
+                    match prefix_str {
+                        "" => StringFormat::Fixed { length },
+                        "u8" => StringFormat::LengthPrefixed {
+                            prefix: PacketFieldType::UnsignedByte,
+                            max_length: length,
+                        },
+                        "u16" => StringFormat::LengthPrefixed {
+                            prefix: PacketFieldType::UnsignedShort,
+                            max_length: length,
+                        },
+                        "u32" => StringFormat::LengthPrefixed {
+                            prefix: PacketFieldType::UnsignedInteger,
+                            max_length: length,
+                        },
+                        &_ => panic!("invalid packet field: {}", substr),
+                    }
+                });
+                match type_str {
                     "u8" => {
                         offset = 1;
                         t = PacketFieldType::UnsignedByte
@@ -171,22 +379,365 @@ impl PacketStructure {
                         offset = 8;
                         t = PacketFieldType::Double
                     }
-                    &_ => {
-                        panic!("invalid packet field: {}", substr)
+                    "bool" => {
+                        offset = 1;
+                        t = PacketFieldType::Bool
+                    }
+                    "u24" => {
+                        offset = 3;
+                        t = PacketFieldType::UnsignedTwoFour
+                    }
+                    "i24" => {
+                        offset = 3;
+                        t = PacketFieldType::SignedTwoFour
+                    }
+                    "u40" => {
+                        offset = 5;
+                        t = PacketFieldType::UnsignedFourZero
+                    }
+                    "i40" => {
+                        offset = 5;
+                        t = PacketFieldType::SignedFourZero
                     }
+                    "u48" => {
+                        offset = 6;
+                        t = PacketFieldType::UnsignedFourEight
+                    }
+                    "i48" => {
+                        offset = 6;
+                        t = PacketFieldType::SignedFourEight
+                    }
+                    &_ => match string_format {
+                        Some(string_format) => {
+                            offset = string_format.size().unwrap();
+                            t = PacketFieldType::String
+                        }
+                        None => panic!("invalid packet field: {}", substr),
+                    },
+                }
+                // strings only need to be aligned to their length prefix, and odd width integers
+                // only come from packed structs so they aren't aligned at all
+                let alignment = match string_format {
+                    Some(StringFormat::Fixed { .. }) => 1,
+                    Some(StringFormat::LengthPrefixed { prefix, .. }) => prefix.size().unwrap(),
+                    None if !offset.is_power_of_two() => 1,
+                    None => offset,
+                };
+                let (field_offset, bitfield) = match bit_width {
+                    Some(bit_width) => match &mut bitfield_container {
+                        // keep packing into the current container while the bits still fit
+                        Some((container_offset, container_size, used_bits))
+                            if *container_size == offset
+                                && *used_bits + bit_width <= offset * 8 =>
+                        {
+                            let bitfield = PacketBitfield {
+                                bit_offset: *used_bits,
+                                bit_width,
+                            };
+                            *used_bits += bit_width;
+                            (*container_offset, Some(bitfield))
+                        }
+                        _ => {
+                            curr_offset = curr_offset.div_ceil(offset) * offset; //aligns the container
+                            bitfield_container = Some((curr_offset, offset, bit_width));
+                            curr_offset += offset;
+                            let bitfield = PacketBitfield {
+                                bit_offset: 0,
+                                bit_width,
+                            };
+                            (curr_offset - offset, Some(bitfield))
+                        }
+                    },
+                    None => {
+                        bitfield_container = None;
+                        curr_offset = (curr_offset + alignment - 1) / alignment * alignment; //aligns the variable
+                        curr_offset += offset * array.map_or(1, |(count, _)| count);
+                        (
+                            curr_offset - offset * array.map_or(1, |(count, _)| count),
+                            None,
+                        )
+                    }
+                };
+                if array.is_some() && bitfield.is_some() {
+                    panic!("bitfields cannot be arrays: {}", substr)
                 }
-                curr_offset = (curr_offset + offset - 1) / offset * offset; //aligns the variable
                 let new_field = PacketField {
-                    index: self.fields.len(),
+                    index: self.value_count(),
                     name: names[self.fields.len()].to_owned(),
                     r#type: t,
-                    offset_in_packet: curr_offset,
+                    offset_in_packet: field_offset,
+                    endianness: endianness.unwrap_or(t.default_endianness()),
+                    bitfield,
+                    string_format,
+                    array: array.map(|(count, count_field)| PacketArray {
+                        count,
+                        stride: offset,
+                        count_field,
+                        group_size: 0,
+                    }),
+                    calibration: None,
+                    unit: None,
+                    labels: BTreeMap::new(),
+                    timestamp: None,
+                    sequence: None,
+                    element_names: vec![],
                 };
                 self.fields.push(new_field);
-                curr_offset += offset;
             }
         }
     }
+
+    /// parses an ez_make checksum like "crc16be" or "sum8" into its algorithm, length and byte order
+    #[cfg(test)]
+    fn ez_parse_crc(substr: &str) -> Option<(CrcAlgorithm, usize, Endianness)> {
+        let (substr, endianness) = match substr.strip_suffix("be") {
+            Some(substr) => (substr, Endianness::Big),
+            None => (substr, Endianness::Little),
+        };
+        let (algorithm, length) = match substr {
+            "crc8" => (CrcAlgorithm::Crc8, 1),
+            "crc16" => (CrcAlgorithm::Crc16Ccitt, 2),
+            "crc32" => (CrcAlgorithm::Crc32, 4),
+            "xor8" => (CrcAlgorithm::Xor, 1),
+            "sum8" => (CrcAlgorithm::Sum, 1),
+            "sum16" => (CrcAlgorithm::Sum, 2),
+            "sum32" => (CrcAlgorithm::Sum, 4),
+            _ => return None,
+        };
+        Some((algorithm, length, endianness))
+    }
+
+    /// parses the "N]" or "N@I]" left after the opening bracket of an ez_make array
+    #[cfg(test)]
+    fn ez_parse_array_suffix(array_suffix: &str) -> (usize, Option<usize>) {
+        let array_suffix = array_suffix.strip_suffix(']').unwrap();
+        match array_suffix.split_once('@') {
+            Some((count, count_field)) => (
+                count.parse::<usize>().unwrap(),
+                Some(count_field.parse::<usize>().unwrap()),
+            ),
+            None => (array_suffix.parse::<usize>().unwrap(), None),
+        }
+    }
+
+    /// Returns the number of values a packet of this structure parses to,
+    /// array fields give one value per element
+    pub fn value_count(&self) -> usize {
+        self.fields.iter().map(|field| field.value_count()).sum()
+    }
+
+    /// returns the index `field` takes when it is added after every other field
+    ///
+    /// the next member of a group that was started before it takes the index after the last
+    /// member, as the values of a group are numbered element by element. anything else takes
+    /// the index after every value so far
+    pub fn next_field_index(&self, field: &PacketField) -> usize {
+        let array = match field.array {
+            Some(array) if array.group_size > 1 => array,
+            _ => return self.value_count(),
+        };
+        let members_so_far = self
+            .fields
+            .iter()
+            .rev()
+            .take_while(|member| member.array == Some(array))
+            .count();
+        match self.fields.last() {
+            Some(last_member) if members_so_far % array.group_size != 0 => last_member.index + 1,
+            _ => self.value_count(),
+        }
+    }
+
+    /// parses every field out of the bytes of a full packet
+    ///
+    /// `packet` should start at the first byte of the packet. values are returned in index order,
+    /// with array fields expanded into their elements. elements past the number given by an
+    /// array's count field are reported as NaN
+    ///
+    /// for variable length packets `packet` should end where the packet ends, any fields
+    /// that weren't sent are reported as NaN too
+    ///
+    /// # Errors
+    ///
+    /// errors if any field can't be parsed, or if a count field asks for more elements than fit
+    pub fn parse_fields(&self, packet: &[u8]) -> anyhow::Result<Vec<PacketFieldValue>> {
+        let mut elements = Vec::with_capacity(self.value_count());
+        for field in &self.fields {
+            let length = self.array_length(field, packet)?;
+            elements.extend(
+                field
+                    .elements()
+                    .into_iter()
+                    .enumerate()
+                    .map(|(element_index, element)| (element_index < length, element)),
+            );
+        }
+        // the members of a group take turns, so their values aren't in field order
+        elements.sort_by_key(|(_, element)| element.index);
+        let mut field_data = Vec::with_capacity(elements.len());
+        for (is_counted, element) in elements {
+            if is_counted && self.is_field_sent(&element, packet) {
+                field_data.push(element.parse_from_packet(packet)?);
+            } else {
+                field_data.push(PacketFieldValue::Number(f64::NAN));
+            }
+        }
+        Ok(field_data)
+    }
+
+    /// Returns true if every checksum in the packet matches the bytes it covers
+    ///
+    /// # Errors
+    ///
+    /// errors if a checksum or the bytes it covers are outside of the packet
+    pub fn check_crcs(&self, packet: &[u8]) -> anyhow::Result<bool> {
+        for crc in &self.packet_crc {
+            if !crc.check(packet)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Fills in every checksum of a packet that has had all of its other bytes written
+    ///
+    /// # Errors
+    ///
+    /// errors if a checksum or the bytes it covers are outside of the packet
+    pub fn write_crcs(&self, packet: &mut [u8]) -> anyhow::Result<()> {
+        for crc in &self.packet_crc {
+            crc.write(packet)?;
+        }
+        Ok(())
+    }
+
+    /// Names the raw values of a parsed packet's enumerated fields, by field index
+    ///
+    /// values without a name are flagged, values that weren't sent (NaN) are skipped
+    pub fn label(&self, field_data: &[PacketFieldValue]) -> BTreeMap<usize, FieldLabel> {
+        let mut field_labels = BTreeMap::new();
+        for field in self.fields.iter().filter(|field| !field.labels.is_empty()) {
+            for element in field.elements() {
+                let value = match field_data.get(element.index) {
+                    Some(PacketFieldValue::Number(value)) if !value.is_nan() => *value,
+                    Some(PacketFieldValue::Bool(value)) => *value as i64 as f64,
+                    _ => continue,
+                };
+                let label = match field.labels.get(&(value as i64)) {
+                    Some(label) if value.fract() == 0.0 => FieldLabel {
+                        label: label.clone(),
+                        known: true,
+                    },
+                    _ => FieldLabel {
+                        label: format!("Unknown ({})", value),
+                        known: false,
+                    },
+                };
+                field_labels.insert(element.index, label);
+            }
+        }
+        field_labels
+    }
+
+    /// Converts the raw values of a parsed packet into real units, using each field's calibration
+    ///
+    /// values are found by field index, so this works on the values of any parser
+    pub fn calibrate(&self, field_data: &mut [PacketFieldValue]) {
+        for field in &self.fields {
+            let calibration = match &field.calibration {
+                Some(calibration) => calibration,
+                None => continue,
+            };
+            for element in field.elements() {
+                if let Some(value) = field_data.get_mut(element.index) {
+                    value.edit_number(&mut |raw| calibration.apply(*raw));
+                }
+            }
+        }
+    }
+
+    /// Returns false if a variable length packet ended before the field
+    fn is_field_sent(&self, field: &PacketField, packet: &[u8]) -> bool {
+        self.length_field.is_none()
+            || field.offset_in_packet + field.size().unwrap_or(0) <= packet.len()
+    }
+
+    /// Returns the number of elements of an array field that are in the given packet
+    fn array_length(&self, field: &PacketField, packet: &[u8]) -> anyhow::Result<usize> {
+        let array = match field.array {
+            Some(array) => array,
+            None => return Ok(1),
+        };
+        let count_field_index = match array.count_field {
+            Some(count_field_index) => count_field_index,
+            None => return Ok(array.count),
+        };
+        let count_field = self
+            .fields
+            .iter()
+            .find(|count_field| {
+                count_field.index == count_field_index && count_field.array.is_none()
+            })
+            .ok_or(anyhow::anyhow!(
+                "Field {} takes its length from missing field {}",
+                field.name,
+                count_field_index
+            ))?;
+        let length = match count_field.parse_from_packet(packet)? {
+            PacketFieldValue::Number(length) => length as usize,
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Field {} takes its length from a non number field",
+                    field.name
+                ))
+            }
+        };
+        if length > array.count {
+            return Err(anyhow::anyhow!(
+                "Field {} has {} elements but only has room for {}",
+                field.name,
+                length,
+                array.count
+            ));
+        }
+        Ok(length)
+    }
+}
+
+#[derive(PartialEq, Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "kind")]
+/// Represents the conversion from the raw number a sensor sends to a number in real units
+pub enum Calibration {
+    /// `raw * scale + offset`
+    Linear { scale: f64, offset: f64 },
+    /// `coefficients[0] + coefficients[1] * raw + coefficients[2] * raw^2 ...`
+    Polynomial { coefficients: Vec<f64> },
+}
+
+impl Calibration {
+    /// Converts a raw number into real units
+    pub fn apply(&self, raw: f64) -> f64 {
+        match self {
+            Calibration::Linear { scale, offset } => raw * scale + offset,
+            Calibration::Polynomial { coefficients } => coefficients
+                .iter()
+                .rev()
+                .fold(0.0, |total, coefficient| total * raw + coefficient),
+        }
+    }
+}
+
+#[derive(PartialEq, Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+/// Represents a packet length that is read from one of the packet's own fields
+///
+/// the total length of a packet in bytes is the value of the field with index `field_index` plus
+/// `adjustment`. `max_size` caps how long a packet can be, so a corrupted length can't make the
+/// parser hold on to data forever
+pub struct PacketLength {
+    pub(crate) field_index: usize,
+    pub(crate) adjustment: isize,
+    pub(crate) max_size: usize,
 }
 
 #[derive(PartialEq, Serialize, Deserialize, Clone, Debug)]
@@ -199,16 +750,151 @@ pub struct PacketMetaDataFields {
     pub(crate) link_index: usize,
 }
 #[derive(PartialEq, Serialize, Deserialize, Clone, Debug)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", from = "PacketFieldData")]
 /// represents a field within a Packet where the groundstation can expect a piece of data to be stored.
 ///
 /// each packet field contains a number based datatype,
 /// a Packet field can also potentially contain timestamp metadata
+///
+/// `endianness` is the byte order the field is transmitted in, fields default to their type's
+/// `default_endianness`
+///
+/// `bitfield` marks the field as a range of bits inside a container, the container being
+/// the bytes described by `type` and `offset_in_packet`. several bitfields can share a container
+///
+/// `string_format` describes how a String field is laid out, and must be set for String fields
+///
+/// `array` marks the field as repeating, `index` is then the index of the first element's value
+/// and each following element takes the next index
+///
+/// `calibration` converts the raw number that was sent into `unit`, it is applied to every
+/// packet right after it is parsed
+///
+/// `labels` names the raw values of an enumerated field, ie flight states
+///
+/// `timestamp` marks the field as a device's clock, parsed values are turned into seconds
+/// that keep counting up through rollovers
+///
+/// `sequence` marks the field as a packet counter, it is used to find lost, repeated and
+/// reordered packets
+///
+/// `element_names` names the elements of an array in place of names like "acc[0]", so fields that
+/// were merged into an array keep the names logs and displays know them by
 pub struct PacketField {
     pub(crate) index: usize,
     pub(crate) name: String,
     pub(crate) r#type: PacketFieldType,
     pub(crate) offset_in_packet: usize,
+    pub(crate) endianness: Endianness,
+    pub(crate) bitfield: Option<PacketBitfield>,
+    pub(crate) string_format: Option<StringFormat>,
+    pub(crate) array: Option<PacketArray>,
+    pub(crate) calibration: Option<Calibration>,
+    pub(crate) unit: Option<String>,
+    pub(crate) labels: BTreeMap<i64, String>,
+    pub(crate) timestamp: Option<TimestampFormat>,
+    pub(crate) sequence: Option<SequenceFormat>,
+    pub(crate) element_names: Vec<String>,
+}
+/// A PacketField as it is read in, fields saved without a byte order take their type's
+/// `default_endianness`
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PacketFieldData {
+    index: usize,
+    name: String,
+    r#type: PacketFieldType,
+    offset_in_packet: usize,
+    #[serde(default)]
+    endianness: Option<Endianness>,
+    #[serde(default)]
+    bitfield: Option<PacketBitfield>,
+    #[serde(default)]
+    string_format: Option<StringFormat>,
+    #[serde(default)]
+    array: Option<PacketArray>,
+    #[serde(default)]
+    calibration: Option<Calibration>,
+    #[serde(default)]
+    unit: Option<String>,
+    #[serde(default)]
+    labels: BTreeMap<i64, String>,
+    #[serde(default)]
+    timestamp: Option<TimestampFormat>,
+    #[serde(default)]
+    sequence: Option<SequenceFormat>,
+    #[serde(default)]
+    element_names: Vec<String>,
+}
+
+impl From<PacketFieldData> for PacketField {
+    fn from(data: PacketFieldData) -> Self {
+        PacketField {
+            index: data.index,
+            name: data.name,
+            r#type: data.r#type,
+            offset_in_packet: data.offset_in_packet,
+            endianness: data
+                .endianness
+                .unwrap_or_else(|| data.r#type.default_endianness()),
+            bitfield: data.bitfield,
+            string_format: data.string_format,
+            array: data.array,
+            calibration: data.calibration,
+            unit: data.unit,
+            labels: data.labels,
+            timestamp: data.timestamp,
+            sequence: data.sequence,
+            element_names: data.element_names,
+        }
+    }
+}
+
+#[derive(PartialEq, Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+/// Represents a PacketField that repeats several times within a packet
+///
+/// `count` is the number of elements room is left for, and `stride` is the number of bytes from
+/// the start of one element to the start of the next. if `count_field` is set, the number of
+/// elements actually sent is read from the (non-array) field with that index
+///
+/// `group_size` is the number of consecutive fields that repeat together as a group, like the
+/// id and cn0 of each satellite. the values of a group are numbered element by element, so the
+/// first member's elements take every `group_size`th index. arrays that aren't part of a group
+/// leave it at 0, and number their elements one after another
+pub struct PacketArray {
+    pub(crate) count: usize,
+    pub(crate) stride: usize,
+    #[serde(default)]
+    pub(crate) count_field: Option<usize>,
+    #[serde(default)]
+    pub(crate) group_size: usize,
+}
+#[derive(PartialEq, Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+/// Represents a range of bits within the container of a bit-packed PacketField
+///
+/// `bit_offset` is counted from the least significant bit of the container (after the container
+/// has been put in host order), so bit 0 of a u8 container is its 0x01 bit
+pub struct PacketBitfield {
+    pub(crate) bit_offset: usize,
+    pub(crate) bit_width: usize,
+}
+#[derive(PartialEq, Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(tag = "kind")]
+/// Represents how the bytes of a String PacketField are laid out
+///
+/// both formats reserve a constant number of bytes in the packet so the packet keeps a known size
+pub enum StringFormat {
+    /// `length` bytes of text, padded with NULs after the text ends
+    Fixed { length: usize },
+    /// an unsigned integer of type `prefix` holding the length of the text, followed by the text.
+    /// room is always left for `max_length` bytes of text after the prefix
+    LengthPrefixed {
+        prefix: PacketFieldType,
+        #[serde(rename = "maxLength")]
+        max_length: usize,
+    },
 }
 #[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
 ///Represents the different types that can be received in a packetField
@@ -226,6 +912,14 @@ pub enum PacketFieldType {
     UnsignedTwoFour,
     #[serde(rename = "Signed 24")]
     SignedTwoFour,
+    #[serde(rename = "Unsigned 40")]
+    UnsignedFourZero,
+    #[serde(rename = "Signed 40")]
+    SignedFourZero,
+    #[serde(rename = "Unsigned 48")]
+    UnsignedFourEight,
+    #[serde(rename = "Signed 48")]
+    SignedFourEight,
     #[serde(rename = "Unsigned Integer")]
     UnsignedInteger,
     #[serde(rename = "Signed Integer")]
@@ -239,11 +933,46 @@ pub enum PacketFieldType {
     String,
     Bool,
 }
+#[derive(PartialEq, Serialize, Deserialize, Clone, Copy, Debug, Default)]
+/// Represents the byte order of a multi-byte packet field
+///
+/// Most of our boards are little-endian, but some radios and sensors send their data big-endian,
+/// so each field keeps track of its own byte order. 24 bit fields were read big-endian before
+/// fields had a byte order, so they still default to it (see `PacketFieldType::default_endianness`)
+pub enum Endianness {
+    #[default]
+    Little,
+    Big,
+}
+
+#[derive(PartialEq, Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+/// Represents how a timestamp field counts time
+///
+/// `wrap_bits` is the width of the counter, it rolls over to 0 after reaching 2^wrap_bits - 1.
+/// Timestamps that never roll over have no wrap width
+pub struct TimestampFormat {
+    pub(crate) ticks_per_second: f64,
+    pub(crate) wrap_bits: Option<u32>,
+}
+
+#[derive(PartialEq, Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+/// Represents how a sequence number field counts packets
+///
+/// `wrap_bits` is the width of the counter, and `step` is how much it goes up by from one packet
+/// to the next, so a timestamp sent at a fixed rate can be used as a sequence number too
+pub struct SequenceFormat {
+    pub(crate) wrap_bits: u32,
+    pub(crate) step: u64,
+}
+
 #[derive(PartialEq, Serialize, Deserialize, Clone, Copy, Debug)]
 /// Represents metadata type for a packet field.
 pub enum PacketMetadataType {
     None,
     Timestamp,
+    Sequence,
 }
 
 #[derive(PartialEq, Serialize, Deserialize, Clone, Debug)]
@@ -258,7 +987,37 @@ pub struct PacketDelimiter {
 
 #[derive(PartialEq, Serialize, Deserialize, Clone, Debug)]
 /// Represents a CRC (checksum) field within a packet.
+///
+/// The checksum covers the bytes from `covered_start` up to `covered_end`, or up to the start
+/// of the checksum itself when `covered_end` is None. Packets whose checksum doesn't match are
+/// thrown away by the parsers.
 pub struct PacketCRC {
     pub(crate) length: usize,
     pub(crate) offset_in_packet: usize,
+    #[serde(default)]
+    pub(crate) algorithm: CrcAlgorithm,
+    #[serde(default)]
+    pub(crate) endianness: Endianness,
+    #[serde(default)]
+    pub(crate) covered_start: usize,
+    #[serde(default)]
+    pub(crate) covered_end: Option<usize>,
+}
+
+#[derive(PartialEq, Serialize, Deserialize, Clone, Copy, Debug, Default)]
+/// Represents the algorithm used to calculate a `PacketCRC`
+pub enum CrcAlgorithm {
+    /// The bytes are reserved for a checksum, but it is never checked
+    #[default]
+    Unchecked,
+    /// CRC-8 with polynomial 0x07 and an initial value of 0
+    Crc8,
+    /// CRC-16-CCITT with polynomial 0x1021 and an initial value of 0xFFFF
+    Crc16Ccitt,
+    /// The CRC-32 used by ethernet and zip files
+    Crc32,
+    /// Every covered byte XOR'd together
+    Xor,
+    /// Every covered byte added together, truncated to the length of the checksum
+    Sum,
 }
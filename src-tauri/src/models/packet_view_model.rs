@@ -57,7 +57,7 @@ impl PacketStructureViewModel {
 ///
 /// This is primarily used for deserialization logic where the type of
 /// component needs to be identified.
-#[derive(Deserialize, Clone, Copy)]
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
 pub enum PacketComponentType {
     Field,
     Delimiter,
@@ -89,9 +89,11 @@ impl PacketComponent {
     }
 
     /// Returns the length of the component in bytes.
+    ///
+    /// bitfields report the length of their whole container
     fn len(&self) -> usize {
         match self {
-            PacketComponent::Field(field) => field.r#type.size().unwrap_or(0),
+            PacketComponent::Field(field) => field.size().unwrap_or(0),
             // Delimiter identifier is hex encoded, so length is half the string length.
             PacketComponent::Delimiter(delimiter) => delimiter.identifier.len() / 2,
             PacketComponent::Gap(gap) => gap.size,
@@ -134,8 +136,11 @@ pub fn create_packet_view_model(packet_structure: &PacketStructure) -> PacketStr
         Vec::with_capacity(packet_structure.delimiters.len() + packet_structure.fields.len());
 
     // Convert all fields from the data model into `Field` components.
+    // Arrays get a component per element, so each element can be displayed on its own.
     for field in &packet_structure.fields {
-        components.push(PacketComponent::Field(field.clone()));
+        for element in field.elements() {
+            components.push(PacketComponent::Field(element));
+        }
     }
 
     // Convert all delimiters from the data model into `Delimiter` components.
@@ -148,6 +153,16 @@ pub fn create_packet_view_model(packet_structure: &PacketStructure) -> PacketStr
         }));
     }
 
+    // Put the components in packet order. Bitfields share their container's offset,
+    // so they are ordered by where they start within it.
+    components.sort_by_key(|component| match component {
+        PacketComponent::Field(field) => (
+            field.offset_in_packet,
+            field.bitfield.map_or(0, |bitfield| bitfield.bit_offset),
+        ),
+        _ => (component.get_offset_in_packet(), 0),
+    });
+
     // Check if there is a gap at the very beginning of the packet.
     if let Some(first_component) = components.first() {
         if first_component.get_offset_in_packet() != 0 {
@@ -190,7 +205,7 @@ pub fn create_packet_view_model(packet_structure: &PacketStructure) -> PacketStr
 #[cfg(test)]
 mod tests {
     use crate::models::{
-        packet_structure::{PacketDelimiter, PacketStructure},
+        packet_structure::{PacketBitfield, PacketDelimiter, PacketStructure},
         packet_view_model::{
             PacketComponent, PacketDelimiterViewModel, PacketGap, PacketStructureViewModel,
         },
@@ -349,4 +364,84 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn bitfields_share_a_container() {
+        let mut packet_structure = PacketStructure::default();
+        packet_structure.ez_make(
+            "ab u8:4 bool:1 bool:3 u16",
+            &["state", "armed", "flags", "altitude"],
+            false,
+        );
+        let view_model = create_packet_view_model(&packet_structure);
+        let offsets: Vec<(usize, Option<PacketBitfield>)> = view_model
+            .components
+            .iter()
+            .map(|component| match component {
+                PacketComponent::Field(field) => (field.offset_in_packet, field.bitfield),
+                _ => (component.get_offset_in_packet(), None),
+            })
+            .collect();
+        assert_eq!(
+            offsets,
+            vec![
+                (0, None),
+                (
+                    1,
+                    Some(PacketBitfield {
+                        bit_offset: 0,
+                        bit_width: 4
+                    })
+                ),
+                (
+                    1,
+                    Some(PacketBitfield {
+                        bit_offset: 4,
+                        bit_width: 1
+                    })
+                ),
+                (
+                    1,
+                    Some(PacketBitfield {
+                        bit_offset: 5,
+                        bit_width: 3
+                    })
+                ),
+                (2, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn arrays_are_expanded() {
+        let mut packet_structure = PacketStructure::default();
+        packet_structure.ez_make(
+            "u8 {u8,u8}[2@0] i16[2]",
+            &["channels", "sat.svid", "sat.cn0", "accel"],
+            false,
+        );
+        let view_model = create_packet_view_model(&packet_structure);
+        let fields: Vec<(usize, String, usize)> = view_model
+            .components
+            .iter()
+            .filter_map(|component| match component {
+                PacketComponent::Field(field) => {
+                    Some((field.index, field.name.clone(), field.offset_in_packet))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                (0, "channels".to_owned(), 0),
+                (1, "sat[0].svid".to_owned(), 1),
+                (2, "sat[0].cn0".to_owned(), 2),
+                (3, "sat[1].svid".to_owned(), 3),
+                (4, "sat[1].cn0".to_owned(), 4),
+                (5, "accel[0]".to_owned(), 6),
+                (6, "accel[1]".to_owned(), 8),
+            ]
+        );
+    }
 }
@@ -0,0 +1,1869 @@
+//! # Packet Structure Language
+//!
+//! A small text language for describing packet structures, so they can be written by hand and
+//! stored, diffed and reviewed as text. `parse_structure` reads the language and
+//! `serialize_structure` writes any `PacketStructure` back out in it.
+//!
+//! A structure is a list of elements separated by whitespace, and `#` starts a comment:
+//!
+//! ```text
+//! sync = 0xba5eba11                        # a named delimiter, bytes in the order they are sent
+//! 0x08                                     # an unnamed delimiter
+//! _2                                       # a 2 byte gap
+//! Timestamp: u32 clock(1000, 32)           # a field, its name then its type then attributes
+//! state: u8 {0 = Idle, 1 = "On pad"}       # names for the values of an enumerated field
+//! height: i16be "m" scale(0.1, -5)         # big-endian, with a unit and a linear calibration
+//! flags: u8:4                              # a 4 bit bitfield
+//! "GPS lat": i32 at 12                     # a quoted name, placed at byte 12
+//! count: u8
+//! acc: f32[3]                              # an array of 3 floats
+//! sat: { id: u8, cn0: u8 "dB" }[12 @ count] # a group of fields repeated 12 times, with the
+//!                                          # number actually sent read from the count field
+//! crc16be                                  # a checksum of every byte before it
+//! ```
+//!
+//! - types are `u8` `i8` `u16` `i16` `u24` `i24` `u32` `i32` `u40` `i40` `u48` `i48` `u64` `i64`
+//!   `f32` `f64` and `bool`, plus `strN` for an N byte string padded with NULs and `u8strN`,
+//!   `u16strN` or `u32strN` for up to N bytes of text after a length prefix
+//! - any type can be given a `be` or `le` suffix to make it big or little-endian, ie `u16be`.
+//!   types without one are little-endian, apart from `u24` and `i24` which are big-endian
+//! - integer and bool types can be given a `:N` suffix to make an N bit wide bitfield
+//! - arrays are written `[N]`, `[N @ field]` to read the number of elements sent from a field
+//!   (by name or index), and can be given a `step S` inside the brackets to set the number of
+//!   bytes from one element to the next
+//! - field attributes can be given in any order: a quoted unit, `scale(s)` or `scale(s, offset)`,
+//!   `poly(c0, c1, ...)`, value names in braces, `clock(ticks_per_second)` or
+//!   `clock(ticks_per_second, wrap_bits)`, `seq(wrap_bits)` or `seq(wrap_bits, step)` to mark
+//!   a packet counter, `length(adjustment, max_size)` to read the length of the packet from the
+//!   field, `at B` or `at B.b` to place the field at byte B (and bit b), and
+//!   `names(acc_x, acc_y, ...)` to name the elements of an array or group member in order
+//! - checksums are `crc8` `crc16` `crc32` `xorN` `sumN` or `uncheckedN` with N in bits, can be
+//!   big-endian, and can cover a range of bytes other than everything before them with
+//!   `(start)` or `(start, end)`
+//! - delimiters and checksums can be placed with `at` too
+//!
+//! Elements are laid out one after another the way a C compiler would lay out a struct, each one
+//! padded to a multiple of its size. Strings and odd width integers aren't padded, groups are
+//! padded to their largest member, and bitfields are packed into containers of their type starting
+//! from the least significant bit. Starting the text with `packed` turns padding off.
+
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use crate::models::packet_structure::{
+    Calibration, CrcAlgorithm, Endianness, PacketArray, PacketBitfield, PacketCRC, PacketDelimiter,
+    PacketField, PacketFieldType, PacketLength, PacketStructure, SequenceFormat, StringFormat,
+    TimestampFormat,
+};
+
+/// The name of every non-string type, as it is written in structure text
+const TYPE_NAMES: &[(&str, PacketFieldType)] = &[
+    ("u8", PacketFieldType::UnsignedByte),
+    ("i8", PacketFieldType::SignedByte),
+    ("u16", PacketFieldType::UnsignedShort),
+    ("i16", PacketFieldType::SignedShort),
+    ("u24", PacketFieldType::UnsignedTwoFour),
+    ("i24", PacketFieldType::SignedTwoFour),
+    ("u32", PacketFieldType::UnsignedInteger),
+    ("i32", PacketFieldType::SignedInteger),
+    ("u40", PacketFieldType::UnsignedFourZero),
+    ("i40", PacketFieldType::SignedFourZero),
+    ("u48", PacketFieldType::UnsignedFourEight),
+    ("i48", PacketFieldType::SignedFourEight),
+    ("u64", PacketFieldType::UnsignedLong),
+    ("i64", PacketFieldType::SignedLong),
+    ("f32", PacketFieldType::Float),
+    ("f64", PacketFieldType::Double),
+    ("bool", PacketFieldType::Bool),
+];
+
+#[derive(PartialEq, Clone, Debug)]
+/// Represents a mistake in structure text, `line` and `column` count from 1
+pub struct DslError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for DslError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for DslError {}
+
+/// Reads a packet structure out of structure text
+///
+/// # Errors
+///
+/// errors with the line and column of the first mistake in the text
+pub fn parse_structure(name: &str, text: &str) -> Result<PacketStructure, DslError> {
+    let mut structure = PacketStructure::make_default(name.to_owned());
+    structure.byte_defined = true;
+    let parser = Parser {
+        tokens: tokenize(text)?,
+        position: 0,
+        structure,
+        layout: Layout::default(),
+    };
+    parser.parse()
+}
+
+/// Writes a packet structure out as structure text, one element per line
+///
+/// parsing the text gives back the same structure, apart from its id and name which aren't part
+/// of the text. fields and delimiters are renumbered in the order they are written, so indices
+/// that skipped numbers or delimiters listed out of order don't survive the round trip
+pub fn serialize_structure(structure: &PacketStructure) -> String {
+    let aligned = Writer::write(structure, false);
+    let packed = Writer::write(structure, true);
+    if packed.len() < aligned.len() {
+        packed
+    } else {
+        aligned
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+enum TokenKind {
+    Word(String),
+    Number(String),
+    Hex(Vec<u8>),
+    Text(String),
+    Symbol(char),
+    End,
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    kind: TokenKind,
+    line: usize,
+    column: usize,
+}
+
+impl Token {
+    fn error(&self, message: String) -> DslError {
+        DslError {
+            line: self.line,
+            column: self.column,
+            message,
+        }
+    }
+}
+
+/// describes a token the way it should be shown in an error message
+fn describe(kind: &TokenKind) -> String {
+    match kind {
+        TokenKind::Word(word) => format!("'{}'", word),
+        TokenKind::Number(number) => format!("'{}'", number),
+        TokenKind::Hex(bytes) => format!("'0x{}'", hex::encode(bytes)),
+        TokenKind::Text(text) => quote(text),
+        TokenKind::Symbol(symbol) => format!("'{}'", symbol),
+        TokenKind::End => "the end of the structure".to_owned(),
+    }
+}
+
+/// Splits structure text into tokens, keeping track of where each one started
+struct Lexer {
+    chars: Vec<char>,
+    position: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Lexer {
+    fn peek(&self, ahead: usize) -> Option<char> {
+        self.chars.get(self.position + ahead).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek(0)?;
+        self.position += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    /// takes characters while `accept` is true of the next character and everything taken so far
+    fn take_while(&mut self, accept: impl Fn(char, &str) -> bool) -> String {
+        let mut taken = String::new();
+        while let Some(c) = self.peek(0) {
+            if !accept(c, &taken) {
+                break;
+            }
+            taken.push(c);
+            self.bump();
+        }
+        taken
+    }
+
+    fn error(&self, line: usize, column: usize, message: String) -> DslError {
+        DslError {
+            line,
+            column,
+            message,
+        }
+    }
+
+    /// reads the rest of a string after its opening quote
+    fn string(&mut self, line: usize, column: usize) -> Result<String, DslError> {
+        let mut text = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(text),
+                Some('\\') => match self.bump() {
+                    Some('n') => text.push('\n'),
+                    Some(c @ ('"' | '\\')) => text.push(c),
+                    Some(c) => {
+                        return Err(self.error(
+                            self.line,
+                            self.column - 2,
+                            format!("unknown escape '\\{}'", c),
+                        ))
+                    }
+                    None => break,
+                },
+                Some(c) => text.push(c),
+                None => break,
+            }
+        }
+        Err(self.error(line, column, "this string is never closed".to_owned()))
+    }
+
+    fn tokenize(mut self) -> Result<Vec<Token>, DslError> {
+        let mut tokens = vec![];
+        while let Some(c) = self.peek(0) {
+            let (line, column) = (self.line, self.column);
+            let kind = if c.is_whitespace() {
+                self.bump();
+                continue;
+            } else if c == '#' {
+                self.take_while(|c, _| c != '\n');
+                continue;
+            } else if c == '"' {
+                self.bump();
+                TokenKind::Text(self.string(line, column)?)
+            } else if c == '0' && matches!(self.peek(1), Some('x' | 'X')) {
+                let digits = self.take_while(|c, _| c.is_ascii_alphanumeric());
+                let bytes = hex::decode(&digits[2..]).map_err(|_| {
+                    self.error(
+                        line,
+                        column,
+                        format!("'{}' isn't a whole number of bytes written in hex", digits),
+                    )
+                })?;
+                if bytes.is_empty() {
+                    return Err(self.error(
+                        line,
+                        column,
+                        "delimiters need at least one byte".to_owned(),
+                    ));
+                }
+                TokenKind::Hex(bytes)
+            } else if c.is_ascii_digit()
+                || (c == '-' && self.peek(1).is_some_and(|c| c.is_ascii_digit()))
+            {
+                TokenKind::Number(self.take_while(|c, taken| {
+                    taken.is_empty()
+                        || c.is_ascii_alphanumeric()
+                        || c == '.'
+                        || (matches!(c, '+' | '-') && taken.ends_with(['e', 'E']))
+                }))
+            } else if c.is_alphabetic() || c == '_' {
+                TokenKind::Word(self.take_while(|c, _| c.is_alphanumeric() || c == '_' || c == '.'))
+            } else if ":=[]{}(),@".contains(c) {
+                self.bump();
+                TokenKind::Symbol(c)
+            } else {
+                return Err(self.error(line, column, format!("unexpected character '{}'", c)));
+            };
+            tokens.push(Token { kind, line, column });
+        }
+        tokens.push(Token {
+            kind: TokenKind::End,
+            line: self.line,
+            column: self.column,
+        });
+        Ok(tokens)
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, DslError> {
+    Lexer {
+        chars: text.chars().collect(),
+        position: 0,
+        line: 1,
+        column: 1,
+    }
+    .tokenize()
+}
+
+#[derive(Clone, Copy, Debug)]
+/// how much room an element takes up and how it should be aligned
+struct Footprint {
+    size: usize,
+    alignment: usize,
+    bit_width: Option<usize>,
+}
+
+#[derive(Clone, Debug, Default)]
+/// Tracks where the next element of a structure goes, shared by the parser and the writer so
+/// they always agree on where elements that aren't given a position end up
+struct Layout {
+    offset: usize,
+    packed: bool,
+    /// (offset, size, bits used) of the container bitfields are currently being packed into
+    container: Option<(usize, usize, usize)>,
+}
+
+impl Layout {
+    /// places the next element at `at` (byte and bit) if given, or after everything placed
+    /// so far if not. returns the byte and bit the element was placed at
+    fn place(&mut self, footprint: Footprint, at: Option<(usize, usize)>) -> (usize, usize) {
+        let Footprint {
+            size,
+            alignment,
+            bit_width,
+        } = footprint;
+        match (bit_width, at) {
+            (Some(bit_width), None) => match &mut self.container {
+                // keep packing into the current container while the bits still fit
+                Some((container_offset, container_size, used_bits))
+                    if *container_size == size && *used_bits + bit_width <= size * 8 =>
+                {
+                    let bit_offset = *used_bits;
+                    *used_bits += bit_width;
+                    (*container_offset, bit_offset)
+                }
+                _ => {
+                    let start = self.aligned(alignment);
+                    self.offset = start + size;
+                    self.container = Some((start, size, bit_width));
+                    (start, 0)
+                }
+            },
+            (Some(bit_width), Some((byte, bit))) => {
+                self.offset = byte + size;
+                self.container = Some((byte, size, bit + bit_width));
+                (byte, bit)
+            }
+            (None, None) => {
+                let start = self.aligned(alignment);
+                self.offset = start + size;
+                self.container = None;
+                (start, 0)
+            }
+            (None, Some((byte, _))) => {
+                self.offset = byte + size;
+                self.container = None;
+                (byte, 0)
+            }
+        }
+    }
+
+    fn skip(&mut self, gap: usize) {
+        self.offset += gap;
+        self.container = None;
+    }
+
+    fn aligned(&self, alignment: usize) -> usize {
+        match self.packed {
+            true => self.offset,
+            false => self.offset.div_ceil(alignment.max(1)) * alignment.max(1),
+        }
+    }
+}
+
+/// returns the alignment an unpacked field is given, the same as a C compiler would give it
+fn field_alignment(field: &PacketField) -> usize {
+    match field.string_format {
+        Some(StringFormat::Fixed { .. }) => 1,
+        Some(StringFormat::LengthPrefixed { prefix, .. }) => prefix.size().unwrap_or(1),
+        None => match field.r#type.size() {
+            Ok(size) if size.is_power_of_two() => size,
+            _ => 1,
+        },
+    }
+}
+
+/// returns the room a single element of a field takes up
+fn element_footprint(field: &PacketField) -> Footprint {
+    Footprint {
+        size: field.size().unwrap_or(0),
+        alignment: field_alignment(field),
+        bit_width: field.bitfield.map(|bitfield| bitfield.bit_width),
+    }
+}
+
+/// returns the room a whole field takes up, arrays take up `count` strides
+fn field_footprint(field: &PacketField) -> Footprint {
+    let footprint = element_footprint(field);
+    Footprint {
+        size: field
+            .array
+            .map_or(footprint.size, |array| array.stride * array.count),
+        ..footprint
+    }
+}
+
+/// returns the room a delimiter or checksum of `length` bytes takes up
+fn constant_footprint(length: usize) -> Footprint {
+    Footprint {
+        size: length,
+        alignment: if length.is_power_of_two() { length } else { 1 },
+        bit_width: None,
+    }
+}
+
+/// reads a type like "u16be" or "u8str12" into its type, string format and byte order
+fn parse_type(word: &str) -> Option<(PacketFieldType, Option<StringFormat>, Endianness)> {
+    let (word, endianness) = match word.strip_suffix("be") {
+        Some(word) => (word, Some(Endianness::Big)),
+        None => match word.strip_suffix("le") {
+            Some(word) => (word, Some(Endianness::Little)),
+            None => (word, None),
+        },
+    };
+    if let Some((prefix, length)) = word.split_once("str") {
+        let length = length.parse::<usize>().ok()?;
+        let string_format = match prefix {
+            "" => StringFormat::Fixed { length },
+            "u8" => StringFormat::LengthPrefixed {
+                prefix: PacketFieldType::UnsignedByte,
+                max_length: length,
+            },
+            "u16" => StringFormat::LengthPrefixed {
+                prefix: PacketFieldType::UnsignedShort,
+                max_length: length,
+            },
+            "u32" => StringFormat::LengthPrefixed {
+                prefix: PacketFieldType::UnsignedInteger,
+                max_length: length,
+            },
+            _ => return None,
+        };
+        return Some((
+            PacketFieldType::String,
+            Some(string_format),
+            endianness.unwrap_or_default(),
+        ));
+    }
+    TYPE_NAMES
+        .iter()
+        .find(|(name, _)| *name == word)
+        .map(|(_, r#type)| {
+            (
+                *r#type,
+                None,
+                endianness.unwrap_or(r#type.default_endianness()),
+            )
+        })
+}
+
+/// reads a checksum like "crc16be" or "sum8" into its algorithm, length and byte order
+fn parse_checksum(word: &str) -> Option<(CrcAlgorithm, usize, Endianness)> {
+    let (word, endianness) = match word.strip_suffix("be") {
+        Some(word) => (word, Endianness::Big),
+        None => (word, Endianness::Little),
+    };
+    let (algorithm, bits) = match word {
+        "crc8" => (CrcAlgorithm::Crc8, 8),
+        "crc16" => (CrcAlgorithm::Crc16Ccitt, 16),
+        "crc32" => (CrcAlgorithm::Crc32, 32),
+        _ => {
+            let (algorithm, bits) = if let Some(bits) = word.strip_prefix("xor") {
+                (CrcAlgorithm::Xor, bits)
+            } else if let Some(bits) = word.strip_prefix("sum") {
+                (CrcAlgorithm::Sum, bits)
+            } else {
+                (CrcAlgorithm::Unchecked, word.strip_prefix("unchecked")?)
+            };
+            (algorithm, bits.parse::<usize>().ok()?)
+        }
+    };
+    match bits {
+        0 => None,
+        bits if bits % 8 != 0 => None,
+        bits => Some((algorithm, bits / 8, endianness)),
+    }
+}
+
+/// reads a gap like "_4" into its length
+fn parse_gap(word: &str) -> Option<usize> {
+    word.strip_prefix('_')?.parse::<usize>().ok()
+}
+
+/// A position given to an element with `at`
+struct Position {
+    token: Token,
+    byte: usize,
+    bit: Option<usize>,
+}
+
+/// Everything that can follow the type of a field
+#[derive(Default)]
+struct Attributes {
+    at: Option<Position>,
+    length: Option<(Token, isize, usize)>,
+    /// where the element names were given, if they were
+    names: Option<Token>,
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    structure: PacketStructure,
+    layout: Layout,
+}
+
+impl Parser {
+    fn parse(mut self) -> Result<PacketStructure, DslError> {
+        if self.peek(0) == &TokenKind::Word("packed".to_owned())
+            && !matches!(self.peek(1), TokenKind::Symbol(':' | '='))
+        {
+            self.next();
+            self.layout.packed = true;
+        }
+        while self.peek(0) != &TokenKind::End {
+            self.element()?;
+        }
+        Ok(self.structure)
+    }
+
+    fn peek(&self, ahead: usize) -> &TokenKind {
+        let position = (self.position + ahead).min(self.tokens.len() - 1);
+        &self.tokens[position].kind
+    }
+
+    fn current(&self) -> Token {
+        self.tokens[self.position].clone()
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.current();
+        if self.position < self.tokens.len() - 1 {
+            self.position += 1;
+        }
+        token
+    }
+
+    /// moves past `symbol` if it is next, returning whether it was
+    fn skip_symbol(&mut self, symbol: char) -> bool {
+        let found = self.peek(0) == &TokenKind::Symbol(symbol);
+        if found {
+            self.next();
+        }
+        found
+    }
+
+    fn expect_symbol(&mut self, symbol: char) -> Result<(), DslError> {
+        let token = self.next();
+        match token.kind == TokenKind::Symbol(symbol) {
+            true => Ok(()),
+            false => Err(token.error(format!(
+                "expected '{}' but found {}",
+                symbol,
+                describe(&token.kind)
+            ))),
+        }
+    }
+
+    fn number<T: FromStr>(&mut self, what: &str) -> Result<T, DslError> {
+        let token = self.next();
+        match &token.kind {
+            TokenKind::Number(number) => number
+                .parse::<T>()
+                .map_err(|_| token.error(format!("'{}' isn't a valid {}", number, what))),
+            kind => Err(token.error(format!("expected {} but found {}", what, describe(kind)))),
+        }
+    }
+
+    fn name(&mut self, what: &str) -> Result<String, DslError> {
+        let token = self.next();
+        match &token.kind {
+            TokenKind::Word(name) | TokenKind::Text(name) => Ok(name.clone()),
+            kind => Err(token.error(format!("expected {} but found {}", what, describe(kind)))),
+        }
+    }
+
+    /// reads the numbers in "(a, b, ...)", returning the token each one started at
+    fn arguments(&mut self) -> Result<Vec<(Token, f64)>, DslError> {
+        self.expect_symbol('(')?;
+        let mut arguments = vec![];
+        while !self.skip_symbol(')') {
+            if !arguments.is_empty() {
+                self.expect_symbol(',')?;
+            }
+            let token = self.current();
+            arguments.push((token, self.number("number")?));
+        }
+        Ok(arguments)
+    }
+
+    fn element(&mut self) -> Result<(), DslError> {
+        let token = self.next();
+        match (token.kind.clone(), self.peek(0)) {
+            (TokenKind::Word(name) | TokenKind::Text(name), TokenKind::Symbol(':')) => {
+                self.next();
+                self.field(name)
+            }
+            (TokenKind::Word(name) | TokenKind::Text(name), TokenKind::Symbol('=')) => {
+                self.next();
+                let identifier = self.next();
+                match &identifier.kind {
+                    TokenKind::Hex(bytes) => self.delimiter(Some(name), bytes.clone()),
+                    kind => Err(identifier.error(format!(
+                        "expected delimiter bytes like 0xba5eba11 but found {}",
+                        describe(kind)
+                    ))),
+                }
+            }
+            (TokenKind::Hex(bytes), _) => self.delimiter(None, bytes),
+            (TokenKind::Word(word), _) => {
+                if let Some(gap) = parse_gap(&word) {
+                    self.layout.skip(gap);
+                    Ok(())
+                } else if let Some((algorithm, length, endianness)) = parse_checksum(&word) {
+                    self.checksum(algorithm, length, endianness)
+                } else {
+                    Err(token.error(format!(
+                        "'{}' isn't a gap or checksum, fields need a ':' after their name",
+                        word
+                    )))
+                }
+            }
+            (kind, _) => Err(token.error(format!(
+                "expected a field, delimiter, gap or checksum but found {}",
+                describe(&kind)
+            ))),
+        }
+    }
+
+    fn delimiter(&mut self, name: Option<String>, identifier: Vec<u8>) -> Result<(), DslError> {
+        let at = self.position()?;
+        let (offset_in_packet, _) = self.layout.place(
+            constant_footprint(identifier.len()),
+            Self::byte_position(at)?,
+        );
+        let index = self.structure.delimiters.len();
+        self.structure.delimiters.push(PacketDelimiter {
+            index,
+            name: name.unwrap_or(format!("Delimiter {}", index + 1)),
+            identifier,
+            offset_in_packet,
+        });
+        Ok(())
+    }
+
+    fn checksum(
+        &mut self,
+        algorithm: CrcAlgorithm,
+        length: usize,
+        endianness: Endianness,
+    ) -> Result<(), DslError> {
+        let (mut covered_start, mut covered_end) = (0, None);
+        if self.peek(0) == &TokenKind::Symbol('(') {
+            let open = self.current();
+            let range = self.arguments()?;
+            let mut bytes = vec![];
+            for (token, value) in &range {
+                if value.fract() != 0.0 || *value < 0.0 {
+                    return Err(token.error(format!("{} isn't a byte offset", value)));
+                }
+                bytes.push(*value as usize);
+            }
+            match bytes[..] {
+                [start] => covered_start = start,
+                [start, end] if start < end => (covered_start, covered_end) = (start, Some(end)),
+                [_, _] => {
+                    return Err(open.error("checksums have to cover at least 1 byte".to_owned()))
+                }
+                _ => {
+                    return Err(open.error(
+                        "checksums cover (start) or (start, end) bytes of the packet".to_owned(),
+                    ))
+                }
+            }
+        }
+        let at = self.position()?;
+        let (offset_in_packet, _) = self
+            .layout
+            .place(constant_footprint(length), Self::byte_position(at)?);
+        self.structure.packet_crc.push(PacketCRC {
+            length,
+            offset_in_packet,
+            algorithm,
+            endianness,
+            covered_start,
+            covered_end,
+        });
+        Ok(())
+    }
+
+    /// reads an optional "at B" or "at B.b"
+    fn position(&mut self) -> Result<Option<Position>, DslError> {
+        if self.peek(0) != &TokenKind::Word("at".to_owned())
+            || !matches!(self.peek(1), TokenKind::Number(_))
+        {
+            return Ok(None);
+        }
+        self.next();
+        let token = self.next();
+        let text = match &token.kind {
+            TokenKind::Number(text) => text.clone(),
+            _ => unreachable!("checked for a number above"),
+        };
+        let invalid = || token.error(format!("'{}' isn't a byte or byte.bit position", text));
+        let (byte, bit) = match text.split_once('.') {
+            Some((byte, bit)) => (byte, Some(bit.parse::<usize>().map_err(|_| invalid())?)),
+            None => (text.as_str(), None),
+        };
+        Ok(Some(Position {
+            byte: byte.parse::<usize>().map_err(|_| invalid())?,
+            bit,
+            token,
+        }))
+    }
+
+    /// turns a position into a byte, for elements that can't start part way through a byte
+    fn byte_position(at: Option<Position>) -> Result<Option<(usize, usize)>, DslError> {
+        match at {
+            Some(Position {
+                token,
+                bit: Some(_),
+                ..
+            }) => Err(token.error("only bitfields can be placed at a bit".to_owned())),
+            Some(Position { byte, .. }) => Ok(Some((byte, 0))),
+            None => Ok(None),
+        }
+    }
+
+    /// reads a type, with its bitfield width if it has one, into a field that hasn't been placed
+    fn field_type(&mut self, name: String) -> Result<PacketField, DslError> {
+        let token = self.next();
+        let word = match &token.kind {
+            TokenKind::Word(word) => word.clone(),
+            kind => {
+                return Err(token.error(format!("expected a type but found {}", describe(kind))))
+            }
+        };
+        let (r#type, string_format, endianness) =
+            parse_type(&word).ok_or_else(|| token.error(format!("unknown type '{}'", word)))?;
+        let mut bitfield = None;
+        if self.skip_symbol(':') {
+            let width_token = self.current();
+            let bit_width = self.number::<usize>("bit width")?;
+            let container_bits = match r#type {
+                PacketFieldType::Float | PacketFieldType::Double | PacketFieldType::String => 0,
+                _ => r#type.size().unwrap_or(0) * 8,
+            };
+            if bit_width == 0 || bit_width > container_bits {
+                return Err(width_token.error(format!(
+                    "'{}' can't hold a {} bit wide bitfield",
+                    word, bit_width
+                )));
+            }
+            bitfield = Some(PacketBitfield {
+                bit_offset: 0,
+                bit_width,
+            });
+        }
+        Ok(PacketField {
+            index: 0,
+            name,
+            r#type,
+            offset_in_packet: 0,
+            endianness,
+            bitfield,
+            string_format,
+            array: None,
+            calibration: None,
+            unit: None,
+            labels: BTreeMap::new(),
+            timestamp: None,
+            sequence: None,
+            element_names: vec![],
+        })
+    }
+
+    /// reads an optional "[count]", "[count @ count_field]" or "[count step stride]"
+    ///
+    /// the stride of the array is left as 0 if it wasn't given
+    fn array(&mut self) -> Result<Option<PacketArray>, DslError> {
+        if !self.skip_symbol('[') {
+            return Ok(None);
+        }
+        let count_token = self.current();
+        let count = self.number::<usize>("element count")?;
+        if count == 0 {
+            return Err(count_token.error("arrays need at least one element".to_owned()));
+        }
+        let mut array = PacketArray {
+            count,
+            stride: 0,
+            count_field: None,
+            group_size: 0,
+        };
+        if self.skip_symbol('@') {
+            array.count_field = Some(self.count_field()?);
+        }
+        if self.peek(0) == &TokenKind::Word("step".to_owned()) {
+            self.next();
+            array.stride = self.number::<usize>("step")?;
+        }
+        self.expect_symbol(']')?;
+        Ok(Some(array))
+    }
+
+    /// finds the index of the field an array reads its count from, by name or by index
+    fn count_field(&mut self) -> Result<usize, DslError> {
+        let token = self.next();
+        let name = match &token.kind {
+            TokenKind::Number(_) => {
+                self.position -= 1;
+                return self.number::<usize>("field index");
+            }
+            TokenKind::Word(name) | TokenKind::Text(name) => name,
+            kind => {
+                return Err(token.error(format!(
+                    "expected the field holding the count but found {}",
+                    describe(kind)
+                )))
+            }
+        };
+        let mut matches = self
+            .structure
+            .fields
+            .iter()
+            .filter(|field| &field.name == name && field.array.is_none());
+        match (matches.next(), matches.next()) {
+            (Some(field), None) => Ok(field.index),
+            (Some(_), Some(_)) => Err(token.error(format!(
+                "more than one field is named '{}', use its index instead",
+                name
+            ))),
+            (None, _) => Err(token.error(format!(
+                "there is no field named '{}' before this array",
+                name
+            ))),
+        }
+    }
+
+    /// reads the attributes after a field's type, in any order
+    fn attributes(
+        &mut self,
+        field: &mut PacketField,
+        in_group: bool,
+    ) -> Result<Attributes, DslError> {
+        let mut attributes = Attributes::default();
+        loop {
+            let token = self.current();
+            let repeated = || token.error(format!("{} is given twice", describe(&token.kind)));
+            match (self.peek(0).clone(), self.peek(1)) {
+                // a string followed by ':' or '=' is the name of the next element
+                (TokenKind::Text(unit), next) if !matches!(next, TokenKind::Symbol(':' | '=')) => {
+                    if field.unit.is_some() {
+                        return Err(token.error("a unit is given twice".to_owned()));
+                    }
+                    self.next();
+                    field.unit = Some(unit);
+                }
+                (TokenKind::Symbol('{'), _) => {
+                    if !field.labels.is_empty() {
+                        return Err(token.error("value names are given twice".to_owned()));
+                    }
+                    field.labels = self.labels()?;
+                }
+                (TokenKind::Word(word), TokenKind::Symbol('(')) if word == "names" => {
+                    if attributes.names.is_some() {
+                        return Err(token.error("element names are given twice".to_owned()));
+                    }
+                    self.next();
+                    field.element_names = self.element_names()?;
+                    attributes.names = Some(token);
+                }
+                (TokenKind::Word(word), TokenKind::Symbol('(')) => {
+                    match word.as_str() {
+                        "scale" | "poly" if field.calibration.is_some() => {
+                            return Err(token.error("a calibration is given twice".to_owned()))
+                        }
+                        "clock" if field.timestamp.is_some() => return Err(repeated()),
+                        "seq" if field.sequence.is_some() => return Err(repeated()),
+                        "length" if attributes.length.is_some() => return Err(repeated()),
+                        "length" if in_group => {
+                            return Err(token
+                                .error("group members can't hold the packet length".to_owned()))
+                        }
+                        "scale" | "poly" | "clock" | "seq" | "length" => {}
+                        _ => break,
+                    }
+                    self.next();
+                    let arguments = self.arguments()?;
+                    let values: Vec<f64> = arguments.iter().map(|(_, value)| *value).collect();
+                    match (word.as_str(), &values[..]) {
+                        ("scale", [scale]) => {
+                            field.calibration = Some(Calibration::Linear {
+                                scale: *scale,
+                                offset: 0.0,
+                            })
+                        }
+                        ("scale", [scale, offset]) => {
+                            field.calibration = Some(Calibration::Linear {
+                                scale: *scale,
+                                offset: *offset,
+                            })
+                        }
+                        ("poly", [_, ..]) => {
+                            field.calibration = Some(Calibration::Polynomial {
+                                coefficients: values.clone(),
+                            })
+                        }
+                        ("clock", [ticks_per_second, ..]) if *ticks_per_second <= 0.0 => {
+                            return Err(arguments[0]
+                                .0
+                                .error("clocks have to tick forwards".to_owned()))
+                        }
+                        ("clock", [ticks_per_second]) => {
+                            field.timestamp = Some(TimestampFormat {
+                                ticks_per_second: *ticks_per_second,
+                                wrap_bits: None,
+                            })
+                        }
+                        ("clock", [ticks_per_second, wrap_bits])
+                            if wrap_bits.fract() == 0.0 && (1.0..=64.0).contains(wrap_bits) =>
+                        {
+                            field.timestamp = Some(TimestampFormat {
+                                ticks_per_second: *ticks_per_second,
+                                wrap_bits: Some(*wrap_bits as u32),
+                            })
+                        }
+                        ("clock", [_, _]) => {
+                            return Err(arguments[1]
+                                .0
+                                .error("clocks wrap after 1 to 64 bits".to_owned()))
+                        }
+                        ("seq", [wrap_bits, ..])
+                            if wrap_bits.fract() != 0.0 || !(1.0..=64.0).contains(wrap_bits) =>
+                        {
+                            return Err(arguments[0]
+                                .0
+                                .error("sequence numbers wrap after 1 to 64 bits".to_owned()))
+                        }
+                        ("seq", [wrap_bits]) => {
+                            field.sequence = Some(SequenceFormat {
+                                wrap_bits: *wrap_bits as u32,
+                                step: 1,
+                            })
+                        }
+                        ("seq", [wrap_bits, step]) if step.fract() == 0.0 && *step >= 1.0 => {
+                            field.sequence = Some(SequenceFormat {
+                                wrap_bits: *wrap_bits as u32,
+                                step: *step as u64,
+                            })
+                        }
+                        ("seq", [_, _]) => {
+                            return Err(arguments[1]
+                                .0
+                                .error("sequence numbers step up by a whole number".to_owned()))
+                        }
+                        ("length", [adjustment, max_size])
+                            if adjustment.fract() == 0.0
+                                && max_size.fract() == 0.0
+                                && *max_size >= 0.0 =>
+                        {
+                            attributes.length =
+                                Some((token.clone(), *adjustment as isize, *max_size as usize))
+                        }
+                        ("length", [_, _]) => {
+                            return Err(
+                                token.error("packet lengths are given in whole bytes".to_owned())
+                            )
+                        }
+                        _ => {
+                            return Err(token.error(format!(
+                                "wrong number of arguments for {}, expected {}",
+                                word,
+                                match word.as_str() {
+                                    "scale" => "scale(s) or scale(s, offset)",
+                                    "poly" => "poly(c0, c1, ...)",
+                                    "clock" => "clock(ticks_per_second, wrap_bits)",
+                                    "seq" => "seq(wrap_bits) or seq(wrap_bits, step)",
+                                    _ => "length(adjustment, max_size)",
+                                }
+                            )))
+                        }
+                    }
+                }
+                (TokenKind::Word(word), TokenKind::Number(_)) if word == "at" => {
+                    if attributes.at.is_some() {
+                        return Err(repeated());
+                    }
+                    if in_group {
+                        return Err(token.error(
+                            "group members can't be placed with 'at', place the group instead"
+                                .to_owned(),
+                        ));
+                    }
+                    attributes.at = self.position()?;
+                }
+                _ => break,
+            }
+        }
+        Ok(attributes)
+    }
+
+    /// reads value names like "{0 = Idle, 1 = "On pad"}"
+    fn labels(&mut self) -> Result<BTreeMap<i64, String>, DslError> {
+        self.expect_symbol('{')?;
+        let mut labels = BTreeMap::new();
+        while !self.skip_symbol('}') {
+            let token = self.current();
+            let value = self.number::<i64>("whole number")?;
+            self.expect_symbol('=')?;
+            let label = self.name("a name for the value")?;
+            if labels.insert(value, label).is_some() {
+                return Err(token.error(format!("{} is named twice", value)));
+            }
+            self.skip_symbol(',');
+        }
+        Ok(labels)
+    }
+
+    /// reads element names like "(acc_x, acc_y, "acc z")"
+    fn element_names(&mut self) -> Result<Vec<String>, DslError> {
+        self.expect_symbol('(')?;
+        let mut names = vec![];
+        while !self.skip_symbol(')') {
+            if !names.is_empty() {
+                self.expect_symbol(',')?;
+            }
+            names.push(self.name("an element name")?);
+        }
+        Ok(names)
+    }
+
+    /// checks that a field has an element for each of the names given to its elements
+    fn check_element_names(field: &PacketField, names: Option<Token>) -> Result<(), DslError> {
+        let token = match names {
+            Some(token) => token,
+            None => return Ok(()),
+        };
+        match field.array {
+            None => Err(token.error("only arrays can name their elements".to_owned())),
+            Some(array) if field.element_names.len() > array.count => Err(token.error(format!(
+                "{} names are given for {} elements",
+                field.element_names.len(),
+                array.count
+            ))),
+            Some(_) => Ok(()),
+        }
+    }
+
+    fn field(&mut self, name: String) -> Result<(), DslError> {
+        if self.peek(0) == &TokenKind::Symbol('{') {
+            return self.group(name);
+        }
+        let type_token = self.current();
+        let mut field = self.field_type(name)?;
+        let array_token = self.current();
+        field.array = self.array()?;
+        if field.array.is_some() && field.bitfield.is_some() {
+            return Err(array_token.error("bitfields can't be arrays".to_owned()));
+        }
+        let attributes = self.attributes(&mut field, false)?;
+        Self::check_element_names(&field, attributes.names)?;
+        let element_size = field
+            .size()
+            .map_err(|error| type_token.error(error.to_string()))?;
+        if let Some(array) = &mut field.array {
+            if array.stride == 0 {
+                array.stride = element_size;
+            } else if array.stride < element_size {
+                return Err(array_token.error(format!(
+                    "a step of {} is smaller than the {} byte elements",
+                    array.stride, element_size
+                )));
+            }
+        }
+
+        let at = match attributes.at {
+            Some(Position {
+                token,
+                byte,
+                bit: Some(bit),
+            }) => match field.bitfield {
+                Some(bitfield) if bit + bitfield.bit_width <= element_size * 8 => Some((byte, bit)),
+                Some(_) => return Err(token.error("the bitfield doesn't fit there".to_owned())),
+                None => return Err(token.error("only bitfields can be placed at a bit".to_owned())),
+            },
+            at => Self::byte_position(at)?,
+        };
+        let (offset_in_packet, bit_offset) = self.layout.place(field_footprint(&field), at);
+        field.offset_in_packet = offset_in_packet;
+        if let Some(bitfield) = &mut field.bitfield {
+            bitfield.bit_offset = bit_offset;
+        }
+        field.index = self.structure.value_count();
+
+        if let Some((token, adjustment, max_size)) = attributes.length {
+            if field.array.is_some() || self.structure.length_field.is_some() {
+                return Err(token.error(
+                    "the packet length has to come from a single field that isn't an array"
+                        .to_owned(),
+                ));
+            }
+            self.structure.length_field = Some(PacketLength {
+                field_index: field.index,
+                adjustment,
+                max_size,
+            });
+        }
+        self.structure.fields.push(field);
+        Ok(())
+    }
+
+    /// reads a group like "sat: { id: u8, cn0: u8 }[12]" into one array field per member
+    fn group(&mut self, name: String) -> Result<(), DslError> {
+        let open = self.next();
+        let mut layout = Layout {
+            packed: self.layout.packed,
+            ..Layout::default()
+        };
+        let mut members = vec![];
+        while !self.skip_symbol('}') {
+            let member_name = self.name("a group member")?;
+            self.expect_symbol(':')?;
+            let type_token = self.current();
+            let mut member = self.field_type(format!("{}.{}", name, member_name))?;
+            if self.peek(0) == &TokenKind::Symbol('[') {
+                return Err(self
+                    .current()
+                    .error("group members can't be arrays".to_owned()));
+            }
+            let attributes = self.attributes(&mut member, true)?;
+            member
+                .size()
+                .map_err(|error| type_token.error(error.to_string()))?;
+            let (offset_in_packet, bit_offset) = layout.place(field_footprint(&member), None);
+            member.offset_in_packet = offset_in_packet;
+            if let Some(bitfield) = &mut member.bitfield {
+                bitfield.bit_offset = bit_offset;
+            }
+            members.push((member, attributes.names));
+            self.skip_symbol(',');
+        }
+        if members.is_empty() {
+            return Err(open.error("groups need at least one member".to_owned()));
+        }
+
+        let array_token = self.current();
+        let mut array = self.array()?.ok_or_else(|| {
+            array_token.error(format!(
+                "expected a count like [4] after the group but found {}",
+                describe(&array_token.kind)
+            ))
+        })?;
+        let alignment = members
+            .iter()
+            .map(|(member, _)| field_alignment(member))
+            .max()
+            .unwrap_or(1);
+        let size = layout.aligned(alignment);
+        if array.stride == 0 {
+            array.stride = size;
+        } else if array.stride < layout.offset {
+            return Err(array_token.error(format!(
+                "a step of {} is smaller than the {} byte group",
+                array.stride, layout.offset
+            )));
+        }
+        let at = self.position()?;
+        let (start, _) = self.layout.place(
+            Footprint {
+                size: array.stride * array.count,
+                alignment,
+                bit_width: None,
+            },
+            Self::byte_position(at)?,
+        );
+        // the values of each element of the group are numbered together, a group of one is
+        // just an array
+        if members.len() > 1 {
+            array.group_size = members.len();
+        }
+        let first_index = self.structure.value_count();
+        for (member_index, (mut member, names)) in members.into_iter().enumerate() {
+            member.index = first_index + member_index;
+            member.offset_in_packet += start;
+            member.array = Some(array);
+            Self::check_element_names(&member, names)?;
+            self.structure.fields.push(member);
+        }
+        Ok(())
+    }
+}
+
+/// A delimiter or checksum, which are written between fields by offset
+enum Constant<'a> {
+    Delimiter(&'a PacketDelimiter),
+    Checksum(&'a PacketCRC),
+}
+
+/// Writes structure text, laying the structure out as it goes so it knows when an element needs
+/// a gap or an explicit position to land where it is
+struct Writer<'a> {
+    structure: &'a PacketStructure,
+    lines: Vec<String>,
+    layout: Layout,
+    written_fields: Vec<&'a PacketField>,
+    written_delimiters: usize,
+}
+
+impl<'a> Writer<'a> {
+    fn write(structure: &'a PacketStructure, packed: bool) -> String {
+        let mut writer = Writer {
+            structure,
+            lines: vec![],
+            layout: Layout {
+                packed,
+                ..Layout::default()
+            },
+            written_fields: vec![],
+            written_delimiters: 0,
+        };
+        if packed {
+            writer.lines.push("packed".to_owned());
+        }
+
+        let mut fields: Vec<&PacketField> = structure.fields.iter().collect();
+        fields.sort_by_key(|field| field.index);
+        let mut constants: Vec<(usize, Constant)> = structure
+            .delimiters
+            .iter()
+            .map(|delimiter| (delimiter.offset_in_packet, Constant::Delimiter(delimiter)))
+            .chain(
+                structure
+                    .packet_crc
+                    .iter()
+                    .map(|crc| (crc.offset_in_packet, Constant::Checksum(crc))),
+            )
+            .collect();
+        constants.sort_by_key(|(offset, _)| *offset);
+        let mut constants = constants.into_iter().peekable();
+
+        // fields are written in index order, with delimiters and checksums written just before
+        // the first field that comes after them
+        let mut remaining = &fields[..];
+        while !remaining.is_empty() {
+            let member_count = writer.group_length(remaining);
+            let (members, rest) = remaining.split_at(member_count);
+            let start = members[0].offset_in_packet;
+            while let Some((_, constant)) = constants.next_if(|(offset, _)| *offset < start) {
+                writer.constant(constant);
+            }
+            match members {
+                [field] => writer.field(field),
+                members => writer.group(members),
+            }
+            remaining = rest;
+        }
+        for (_, constant) in constants {
+            writer.constant(constant);
+        }
+        writer.lines.join("\n") + "\n"
+    }
+
+    /// moves the layout on to `offset` and `bit_offset` for the next element. a gap is written
+    /// if that is enough to get there, otherwise an " at" position is returned for the element
+    fn place(&mut self, footprint: Footprint, offset: usize, bit_offset: usize) -> String {
+        let target = (offset, bit_offset);
+        let mut natural = self.layout.clone();
+        if natural.place(footprint, None) == target {
+            self.layout = natural;
+            return String::new();
+        }
+        if offset > self.layout.offset {
+            let gap = offset - self.layout.offset;
+            let mut gapped = self.layout.clone();
+            gapped.skip(gap);
+            if gapped.place(footprint, None) == target {
+                self.lines.push(format!("_{}", gap));
+                self.layout = gapped;
+                return String::new();
+            }
+        }
+        self.layout.place(footprint, Some(target));
+        match bit_offset {
+            0 => format!(" at {}", offset),
+            bit_offset => format!(" at {}.{}", offset, bit_offset),
+        }
+    }
+
+    fn constant(&mut self, constant: Constant) {
+        let line = match constant {
+            Constant::Delimiter(delimiter) => {
+                let at = self.place(
+                    constant_footprint(delimiter.identifier.len()),
+                    delimiter.offset_in_packet,
+                    0,
+                );
+                self.written_delimiters += 1;
+                let identifier = format!("0x{}", hex::encode(&delimiter.identifier));
+                match delimiter.name == format!("Delimiter {}", self.written_delimiters) {
+                    true => format!("{}{}", identifier, at),
+                    false => format!("{} = {}{}", name_text(&delimiter.name), identifier, at),
+                }
+            }
+            Constant::Checksum(crc) => {
+                let at = self.place(constant_footprint(crc.length), crc.offset_in_packet, 0);
+                let bits = crc.length * 8;
+                let mut line = match crc.algorithm {
+                    CrcAlgorithm::Crc8 => "crc8".to_owned(),
+                    CrcAlgorithm::Crc16Ccitt => "crc16".to_owned(),
+                    CrcAlgorithm::Crc32 => "crc32".to_owned(),
+                    CrcAlgorithm::Xor => format!("xor{}", bits),
+                    CrcAlgorithm::Sum => format!("sum{}", bits),
+                    CrcAlgorithm::Unchecked => format!("unchecked{}", bits),
+                };
+                if crc.endianness == Endianness::Big {
+                    line += "be";
+                }
+                match (crc.covered_start, crc.covered_end) {
+                    (0, None) => {}
+                    (start, None) => line += &format!("({})", start),
+                    (start, Some(end)) => line += &format!("({}, {})", start, end),
+                }
+                line + &at
+            }
+        };
+        self.lines.push(line);
+    }
+
+    fn field(&mut self, field: &'a PacketField) {
+        let bit_offset = field.bitfield.map_or(0, |bitfield| bitfield.bit_offset);
+        let at = self.place(field_footprint(field), field.offset_in_packet, bit_offset);
+        let mut line = format!("{}: {}", name_text(&field.name), type_text(field));
+        if let Some(array) = field.array {
+            line += &self.array_text(array, field.size().unwrap_or(0));
+        }
+        line += &self.attributes_text(field);
+        self.lines.push(line + &at);
+        self.written_fields.push(field);
+    }
+
+    /// returns how many fields from the start of `fields` can be written as a group, groups
+    /// being consecutive array fields named like "group.member" that repeat together and number
+    /// their values element by element
+    fn group_length(&self, fields: &[&PacketField]) -> usize {
+        let first = fields[0];
+        let (array, prefix) = match (first.array, first.name.split_once('.')) {
+            (Some(array), Some((prefix, _))) if array.group_size > 1 => (array, prefix),
+            _ => return 1,
+        };
+        // members have to be where the parser would put them within the group
+        let mut layout = Layout {
+            packed: self.layout.packed,
+            ..Layout::default()
+        };
+        let member_count = fields
+            .iter()
+            .take(array.group_size)
+            .enumerate()
+            .take_while(|(member_index, field)| {
+                let bit_offset = field.bitfield.map_or(0, |bitfield| bitfield.bit_offset);
+                field.array == Some(array)
+                    && field.index == first.index + member_index
+                    && field.name.split_once('.').map(|(group, _)| group) == Some(prefix)
+                    && field.offset_in_packet >= first.offset_in_packet
+                    && layout.place(element_footprint(field), None)
+                        == (field.offset_in_packet - first.offset_in_packet, bit_offset)
+            })
+            .count();
+        match member_count {
+            _ if member_count != array.group_size => 1,
+            _ if array.stride < Self::group_size(&fields[..member_count], &layout).0 => 1,
+            member_count => member_count,
+        }
+    }
+
+    /// returns the (unpadded size, padded size, alignment) of a group laid out into `layout`
+    fn group_size(members: &[&PacketField], layout: &Layout) -> (usize, usize, usize) {
+        let alignment = members
+            .iter()
+            .map(|member| field_alignment(member))
+            .max()
+            .unwrap_or(1);
+        (layout.offset, layout.aligned(alignment), alignment)
+    }
+
+    fn group(&mut self, members: &[&'a PacketField]) {
+        let first = members[0];
+        let array = first.array.expect("groups are made of arrays");
+        let mut layout = Layout {
+            packed: self.layout.packed,
+            ..Layout::default()
+        };
+        for member in members {
+            layout.place(element_footprint(member), None);
+        }
+        let (_, padded_size, alignment) = Self::group_size(members, &layout);
+        let at = self.place(
+            Footprint {
+                size: array.stride * array.count,
+                alignment,
+                bit_width: None,
+            },
+            first.offset_in_packet,
+            0,
+        );
+
+        let (group_name, _) = first
+            .name
+            .split_once('.')
+            .expect("groups have a '.' in names");
+        self.lines.push(format!("{}: {{", name_text(group_name)));
+        for member in members {
+            let (_, member_name) = member
+                .name
+                .split_once('.')
+                .expect("checked by group_length");
+            self.lines.push(format!(
+                "    {}: {}{}",
+                name_text(member_name),
+                type_text(member),
+                self.attributes_text(member)
+            ));
+        }
+        let array_text = self.array_text(array, padded_size);
+        self.lines.push(format!("}}{}{}", array_text, at));
+        self.written_fields.extend(members);
+    }
+
+    /// writes an array suffix like "[12 @ count]", with a step if the elements aren't `natural_stride` apart
+    fn array_text(&self, array: PacketArray, natural_stride: usize) -> String {
+        let mut text = format!("[{}", array.count);
+        if let Some(count_field) = array.count_field {
+            // the count field is written by name if the parser can find it by that name
+            let singles = self
+                .written_fields
+                .iter()
+                .filter(|field| field.array.is_none());
+            let name = singles
+                .clone()
+                .find(|field| field.index == count_field)
+                .map(|field| &field.name)
+                .filter(|name| singles.filter(|field| &&field.name == name).count() == 1);
+            match name {
+                Some(name) => text += &format!(" @ {}", name_text(name)),
+                None => text += &format!(" @ {}", count_field),
+            }
+        }
+        if array.stride != natural_stride {
+            text += &format!(" step {}", array.stride);
+        }
+        text + "]"
+    }
+
+    /// writes the unit, calibration, value names, clock and packet length of a field
+    fn attributes_text(&self, field: &PacketField) -> String {
+        let mut text = String::new();
+        if let Some(unit) = &field.unit {
+            text += &format!(" {}", quote(unit));
+        }
+        match &field.calibration {
+            Some(Calibration::Linear { scale, offset }) if *offset == 0.0 => {
+                text += &format!(" scale({})", scale)
+            }
+            Some(Calibration::Linear { scale, offset }) => {
+                text += &format!(" scale({}, {})", scale, offset)
+            }
+            Some(Calibration::Polynomial { coefficients }) => {
+                let coefficients: Vec<String> = coefficients
+                    .iter()
+                    .map(|coefficient| coefficient.to_string())
+                    .collect();
+                text += &format!(" poly({})", coefficients.join(", "))
+            }
+            None => {}
+        }
+        if let (Some(array), false) = (field.array, field.element_names.is_empty()) {
+            let names: Vec<String> = field
+                .element_names
+                .iter()
+                .take(array.count)
+                .map(|name| name_text(name))
+                .collect();
+            text += &format!(" names({})", names.join(", "));
+        }
+        if !field.labels.is_empty() {
+            let labels: Vec<String> = field
+                .labels
+                .iter()
+                .map(|(value, label)| format!("{} = {}", value, name_text(label)))
+                .collect();
+            text += &format!(" {{{}}}", labels.join(", "));
+        }
+        match field.timestamp {
+            Some(TimestampFormat {
+                ticks_per_second,
+                wrap_bits: Some(wrap_bits),
+            }) => text += &format!(" clock({}, {})", ticks_per_second, wrap_bits),
+            Some(TimestampFormat {
+                ticks_per_second, ..
+            }) => text += &format!(" clock({})", ticks_per_second),
+            None => {}
+        }
+        match field.sequence {
+            Some(SequenceFormat { wrap_bits, step: 1 }) => text += &format!(" seq({})", wrap_bits),
+            Some(SequenceFormat { wrap_bits, step }) => {
+                text += &format!(" seq({}, {})", wrap_bits, step)
+            }
+            None => {}
+        }
+        match self.structure.length_field {
+            Some(length_field)
+                if length_field.field_index == field.index && field.array.is_none() =>
+            {
+                text += &format!(
+                    " length({}, {})",
+                    length_field.adjustment, length_field.max_size
+                )
+            }
+            _ => {}
+        }
+        text
+    }
+}
+
+/// writes the type of a field like "u16be" or "u8str12", with its bitfield width if it has one
+fn type_text(field: &PacketField) -> String {
+    let type_name = |r#type: PacketFieldType| {
+        TYPE_NAMES
+            .iter()
+            .find(|(_, named_type)| *named_type == r#type)
+            .map_or("str0", |(name, _)| name)
+    };
+    let mut text = match field.string_format {
+        Some(StringFormat::Fixed { length }) => format!("str{}", length),
+        Some(StringFormat::LengthPrefixed { prefix, max_length }) => {
+            format!("{}str{}", type_name(prefix), max_length)
+        }
+        None => type_name(field.r#type).to_owned(),
+    };
+    if field.endianness != field.r#type.default_endianness() {
+        text += match field.endianness {
+            Endianness::Big => "be",
+            Endianness::Little => "le",
+        };
+    }
+    if let Some(bitfield) = field.bitfield {
+        text += &format!(":{}", bitfield.bit_width);
+    }
+    text
+}
+
+/// writes a name as a bare word if it reads back as one, or quoted if it doesn't
+fn name_text(name: &str) -> String {
+    let mut chars = name.chars();
+    let is_word = chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '.');
+    match is_word {
+        true => name.to_owned(),
+        false => quote(name),
+    }
+}
+
+fn quote(text: &str) -> String {
+    format!(
+        "\"{}\"",
+        text.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::packet_structure_manager_state::default_packet_structure_manager;
+
+    fn field<'a>(structure: &'a PacketStructure, name: &str) -> &'a PacketField {
+        structure
+            .fields
+            .iter()
+            .find(|field| field.name == name)
+            .unwrap()
+    }
+
+    fn error(text: &str) -> (usize, usize, String) {
+        let error = parse_structure("test", text).unwrap_err();
+        (error.line, error.column, error.message)
+    }
+
+    //tests the layout and attributes of a plain structure
+    #[test]
+    fn parse_fields_and_attributes() {
+        let structure = parse_structure(
+            "test",
+            r#"
+            sync = 0xba5eba11
+            0x08          # packet type
+            Timestamp: u16 clock(100, 16)
+            state: u8 {0 = Idle, 1 = "On pad"} seq(8)
+            height: i32be "m" scale(0.1, -5)
+            _2
+            "GPS lat": f64 poly(1, 2, 3)
+            crc16be(4)
+            "#,
+        )
+        .unwrap();
+        assert!(structure.byte_defined);
+        assert_eq!(structure.delimiters[0].name, "sync");
+        assert_eq!(structure.delimiters[1].name, "Delimiter 2");
+        assert_eq!(structure.delimiters[1].offset_in_packet, 4);
+
+        let timestamp = field(&structure, "Timestamp");
+        assert_eq!((timestamp.index, timestamp.offset_in_packet), (0, 6));
+        assert_eq!(
+            timestamp.timestamp,
+            Some(TimestampFormat {
+                ticks_per_second: 100.0,
+                wrap_bits: Some(16),
+            })
+        );
+        let state = field(&structure, "state");
+        assert_eq!(state.offset_in_packet, 8);
+        assert_eq!(
+            state.sequence,
+            Some(SequenceFormat {
+                wrap_bits: 8,
+                step: 1
+            })
+        );
+        assert_eq!(state.labels[&1], "On pad");
+        let height = field(&structure, "height");
+        assert_eq!(height.offset_in_packet, 12);
+        assert_eq!(height.endianness, Endianness::Big);
+        assert_eq!(height.unit.as_deref(), Some("m"));
+        assert_eq!(
+            height.calibration,
+            Some(Calibration::Linear {
+                scale: 0.1,
+                offset: -5.0,
+            })
+        );
+        assert_eq!(field(&structure, "GPS lat").offset_in_packet, 24);
+
+        let crc = &structure.packet_crc[0];
+        assert_eq!((crc.offset_in_packet, crc.length), (32, 2));
+        assert_eq!(
+            (crc.algorithm, crc.covered_start),
+            (CrcAlgorithm::Crc16Ccitt, 4)
+        );
+        assert_eq!(structure.size(), 34);
+    }
+
+    //tests that bitfields share containers and can be placed explicitly
+    #[test]
+    fn bitfields_and_positions() {
+        let structure = parse_structure(
+            "test",
+            "packed a: u8:3 b: bool:1 c: u8:5 d: u16:4 at 6.10 e: u24 f: u8str4 length(2, 64)",
+        )
+        .unwrap();
+        let bits = |name| {
+            let field = field(&structure, name);
+            let bitfield = field.bitfield.unwrap();
+            (
+                field.offset_in_packet,
+                bitfield.bit_offset,
+                bitfield.bit_width,
+            )
+        };
+        assert_eq!(bits("a"), (0, 0, 3));
+        assert_eq!(bits("b"), (0, 3, 1));
+        assert_eq!(bits("c"), (1, 0, 5));
+        assert_eq!(bits("d"), (6, 10, 4));
+        assert_eq!(field(&structure, "e").offset_in_packet, 8);
+        assert_eq!(field(&structure, "f").offset_in_packet, 11);
+        assert_eq!(
+            structure.length_field,
+            Some(PacketLength {
+                field_index: 5,
+                adjustment: 2,
+                max_size: 64,
+            })
+        );
+    }
+
+    //tests that groups become one array field per member
+    #[test]
+    fn groups_and_counted_arrays() {
+        let structure = parse_structure(
+            "test",
+            r#"
+            channels: u8
+            sat: { svid: u8, cn0: u16 "dB" }[12 @ channels]
+            acc: f32[3 step 8] names(acc_x, acc_y, "acc z")
+            "#,
+        )
+        .unwrap();
+        let svid = field(&structure, "sat.svid");
+        let cn0 = field(&structure, "sat.cn0");
+        assert_eq!((svid.index, svid.offset_in_packet), (1, 2));
+        assert_eq!((cn0.index, cn0.offset_in_packet), (2, 4));
+        assert_eq!(
+            cn0.array,
+            Some(PacketArray {
+                count: 12,
+                stride: 4,
+                count_field: Some(0),
+                group_size: 2,
+            })
+        );
+        // each satellite's values are next to each other
+        let names: Vec<(usize, String)> = svid
+            .elements()
+            .into_iter()
+            .chain(cn0.elements())
+            .map(|element| (element.index, element.name))
+            .filter(|(index, _)| *index < 5)
+            .collect();
+        assert_eq!(
+            names,
+            [
+                (1, "sat[0].svid".to_owned()),
+                (3, "sat[1].svid".to_owned()),
+                (2, "sat[0].cn0".to_owned()),
+                (4, "sat[1].cn0".to_owned())
+            ]
+        );
+        assert_eq!(cn0.unit.as_deref(), Some("dB"));
+        let acc = field(&structure, "acc");
+        assert_eq!((acc.index, acc.offset_in_packet), (25, 52));
+        assert_eq!(acc.array.unwrap().stride, 8);
+        let names: Vec<String> = acc.elements().into_iter().map(|acc| acc.name).collect();
+        assert_eq!(names, ["acc_x", "acc_y", "acc z"]);
+        assert_eq!(structure.value_count(), 28);
+    }
+
+    //tests that mistakes are reported where they are
+    #[test]
+    fn errors_have_positions() {
+        assert_eq!(
+            error("a: u8\nb: u17"),
+            (2, 4, "unknown type 'u17'".to_owned())
+        );
+        assert_eq!(
+            error("a: u8 b u8"),
+            (
+                1,
+                7,
+                "'b' isn't a gap or checksum, fields need a ':' after their name".to_owned()
+            )
+        );
+        assert_eq!(
+            error("a: u8 \"m"),
+            (1, 7, "this string is never closed".to_owned())
+        );
+        assert_eq!(
+            error("  sat: u8[4 @ count]"),
+            (
+                1,
+                15,
+                "there is no field named 'count' before this array".to_owned()
+            )
+        );
+        assert_eq!(
+            error("a: u8:9"),
+            (1, 7, "'u8' can't hold a 9 bit wide bitfield".to_owned())
+        );
+        assert_eq!(
+            error("0xabc"),
+            (
+                1,
+                1,
+                "'0xabc' isn't a whole number of bytes written in hex".to_owned()
+            )
+        );
+        assert_eq!(
+            error("a: u8 scale(1, 2, 3)"),
+            (
+                1,
+                7,
+                "wrong number of arguments for scale, expected scale(s) or scale(s, offset)"
+                    .to_owned()
+            )
+        );
+        assert_eq!(
+            error("a: u8\n  b: { c: u8 }"),
+            (
+                2,
+                15,
+                "expected a count like [4] after the group but found the end of the structure"
+                    .to_owned()
+            )
+        );
+        assert_eq!(
+            error("a: u16 seq(65)"),
+            (1, 12, "sequence numbers wrap after 1 to 64 bits".to_owned())
+        );
+        assert_eq!(
+            error("a: u8 at 3.1"),
+            (1, 10, "only bitfields can be placed at a bit".to_owned())
+        );
+        assert_eq!(
+            error("a: u8 names(x)"),
+            (1, 7, "only arrays can name their elements".to_owned())
+        );
+        assert_eq!(
+            error("a: { b: u8 names(x, y, z) }[2]"),
+            (1, 12, "3 names are given for 2 elements".to_owned())
+        );
+        assert_eq!(
+            parse_structure("test", "a: u8 ;").unwrap_err().to_string(),
+            "line 1, column 7: unexpected character ';'"
+        );
+    }
+
+    //tests that every built in structure, and some unusual ones, survive being written as text
+    #[test]
+    fn serialize_round_trip() {
+        let mut structures = default_packet_structure_manager().packet_structures.clone();
+        structures.push(
+            parse_structure(
+                "test",
+                r#"
+                packed
+                "first \"sync\"" = 0xabcd
+                flags: u8:3
+                armed: bool:1
+                _1
+                name: str5 "text"
+                channels: u16be length(-4, 256)
+                sat: { svid: u8 names(a, b), cn0: i24le scale(0.25) }[4 @ 2 step 6]
+                late: u8:2 at 40.3 seq(2)
+                tick: u32 at 44 seq(32, 10)
+                odd: i48be[2] at 30 clock(1000) names("odd one", odd_two)
+                unchecked24(2, 10)
+                "#,
+            )
+            .unwrap(),
+        );
+        for structure in structures {
+            let text = serialize_structure(&structure);
+            let mut parsed = parse_structure(&structure.name, &text)
+                .unwrap_or_else(|error| panic!("{}\n{}", error, text));
+            parsed.id = structure.id;
+            assert_eq!(parsed, structure, "{}", text);
+        }
+    }
+}
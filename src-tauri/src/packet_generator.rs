@@ -4,16 +4,22 @@ use crate::models::{packet::PacketFieldValue, packet_structure::PacketStructure}
 
 /// Generate bytes from a packet structure and a list of values.
 ///
-/// The items in field_data are converted to little endian bytes. Only the needed
-/// bytes will actually be used. For example, 0x12345678 becomes LE-bytes:
-///     [0x78, 0x56, 0x34, 0x12, 0x00, 0x00, 0x00, 0x00]]
-/// if used in a signed or unsigned short field, only [0x78, 0x56] are used.
+/// The items in field_data are converted to bytes in each field's byte order (little endian
+/// unless the field says otherwise). For example, 0x12345678 becomes LE-bytes:
+///     [0x78, 0x56, 0x34, 0x12]
+/// in an unsigned integer field, and [0x12, 0x34, 0x56, 0x78] if the field is big-endian.
+/// Bitfields only fill in their own bits of their container, and checksums are calculated
+/// from the finished packet.
+///
+/// field_data is in the same order a parsed packet's values are in, so each array element
+/// takes its own column.
 ///
 /// # Errors
 ///
 /// Some mistakes will be caught and an Err will be returned:
 ///
 ///  - field.index out-of-bounds
+///  - array count fields that are missing or larger than the array
 ///
 /// However some errors will not be caught such as packets overlapping each other, so please
 /// make sure your structures are valid first :)
@@ -32,43 +38,67 @@ pub fn generate_packet(
         }
     }
 
-    for field in &packet_structure.fields {
-        let given_value = match field_data.get(field.index) {
-            Some(value) => value,
-            None => {
-                return Err(anyhow::anyhow!(
-                    "Field {} refers to missing index: {}",
-                    field.name,
-                    field.index
-                ))
-            }
-        };
-        let parsed_value: PacketFieldValue = match field.r#type.make_from_string(given_value) {
-            Ok(value) => value,
-            Err(_) => {
-                return Err(anyhow::anyhow!(
-                    "Field {} refers to missing index: {}",
-                    field.name,
-                    field.index
-                ))
-            }
+    for packet_field in &packet_structure.fields {
+        // arrays with a count field only fill in the elements that were sent
+        let length = match packet_field.array.and_then(|array| array.count_field) {
+            Some(count_field) => match field_data
+                .get(count_field)
+                .and_then(|count| count.parse::<usize>().ok())
+            {
+                Some(length) if length <= packet_field.value_count() => length,
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "Field {} takes its length from invalid index: {}",
+                        packet_field.name,
+                        count_field
+                    ))
+                }
+            },
+            None => packet_field.value_count(),
         };
-        let bytes = parsed_value.to_le_bytes(field.r#type)?;
-        for i in 0..field.r#type.size()? {
-            // guaranteed to not panic due to buffer size calculation previously
-            result[field.offset_in_packet + i] = bytes[i];
+        for field in packet_field.elements().iter().take(length) {
+            let given_value = match field_data.get(field.index) {
+                Some(value) => value,
+                None => {
+                    return Err(anyhow::anyhow!(
+                        "Field {} refers to missing index: {}",
+                        field.name,
+                        field.index
+                    ))
+                }
+            };
+            let parsed_value: PacketFieldValue = match field.r#type.make_from_string(given_value) {
+                Ok(value) => value,
+                Err(_) => {
+                    return Err(anyhow::anyhow!(
+                        "Field {} refers to missing index: {}",
+                        field.name,
+                        field.index
+                    ))
+                }
+            };
+            // bitfields sharing a container are OR'd in rather than overwriting each other
+            field.write_to_packet(&parsed_value, &mut result)?;
         }
     }
 
+    // checksums go last so they cover the finished bytes
+    packet_structure.write_crcs(&mut result)?;
+
     Ok(result)
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
+
     use csv::StringRecord;
 
-    use crate::models::packet_structure::{
-        PacketDelimiter, PacketField, PacketFieldType, PacketStructure,
+    use crate::models::{
+        packet::PacketFieldValue,
+        packet_structure::{
+            Endianness, PacketDelimiter, PacketField, PacketFieldType, PacketStructure,
+        },
     };
 
     use super::generate_packet;
@@ -107,6 +137,16 @@ mod tests {
                 name: "Test Field".to_string(),
                 offset_in_packet: 5,
                 r#type: PacketFieldType::UnsignedInteger,
+                endianness: Endianness::Little,
+                bitfield: None,
+                string_format: None,
+                array: None,
+                calibration: None,
+                unit: None,
+                labels: BTreeMap::new(),
+                timestamp: None,
+                sequence: None,
+                element_names: vec![],
             }],
             vec![],
         );
@@ -121,6 +161,87 @@ mod tests {
         );
     }
 
+    #[test]
+    fn big_endian_field() {
+        let structure = PacketStructure::make_from_fields_and_delims(
+            0,
+            "Test Packet".to_string(),
+            vec![PacketField {
+                index: 0,
+                name: "Test Field".to_string(),
+                offset_in_packet: 1,
+                r#type: PacketFieldType::UnsignedInteger,
+                endianness: Endianness::Big,
+                bitfield: None,
+                string_format: None,
+                array: None,
+                calibration: None,
+                unit: None,
+                labels: BTreeMap::new(),
+                timestamp: None,
+                sequence: None,
+                element_names: vec![],
+            }],
+            vec![],
+        );
+        let packet = generate_packet(&structure, StringRecord::from(vec!["305419896"])).unwrap();
+        assert_eq!(packet, [0, 0x12, 0x34, 0x56, 0x78]);
+    }
+
+    #[test]
+    fn bitfields_share_a_byte() {
+        let mut structure = PacketStructure::default();
+        structure.ez_make(
+            "u8:4 bool:1 bool:1 i8:2",
+            &["state", "a", "b", "trim"],
+            false,
+        );
+        let packet =
+            generate_packet(&structure, StringRecord::from(vec!["7", "true", "f", "-2"])).unwrap();
+        assert_eq!(packet, [0b1001_0111]);
+    }
+
+    #[test]
+    fn strings() {
+        let mut structure = PacketStructure::default();
+        structure.ez_make("str6 u8str4 u16", &["callsign", "version", "count"], false);
+        let packet =
+            generate_packet(&structure, StringRecord::from(vec!["KD0", "v1.2", "258"])).unwrap();
+        assert_eq!(
+            packet,
+            [b'K', b'D', b'0', 0, 0, 0, 4, b'v', b'1', b'.', b'2', 0, 0x02, 0x01]
+        );
+    }
+
+    #[test]
+    fn arrays() {
+        let mut structure = PacketStructure::default();
+        structure.ez_make(
+            "u8 {u8,u8}[3@0]",
+            &["channels", "sat.svid", "sat.cn0"],
+            false,
+        );
+        let packet = generate_packet(
+            &structure,
+            StringRecord::from(vec!["2", "7", "40", "8", "41", "", ""]),
+        )
+        .unwrap();
+        assert_eq!(packet, [2, 7, 40, 8, 41, 0, 0]);
+        assert_eq!(
+            structure.parse_fields(&packet).unwrap()[4],
+            PacketFieldValue::Number(41.0)
+        );
+    }
+
+    #[test]
+    fn crc() {
+        let mut structure = PacketStructure::default();
+        structure.ez_make("deadbeef u8 i8 crc8", &["count", "trim"], false);
+        let packet = generate_packet(&structure, StringRecord::from(vec!["5", "-1"])).unwrap();
+        assert_eq!(packet, [0xDE, 0xAD, 0xBE, 0xEF, 5, 0xFF, 0xDD]);
+        assert!(structure.check_crcs(&packet).unwrap());
+    }
+
     #[test]
     fn field_index_out_of_bounds() {
         let structure = PacketStructure::make_from_fields_and_delims(
@@ -131,6 +252,16 @@ mod tests {
                 name: "Test Field".to_string(),
                 offset_in_packet: 0,
                 r#type: PacketFieldType::UnsignedByte,
+                endianness: Endianness::Little,
+                bitfield: None,
+                string_format: None,
+                array: None,
+                calibration: None,
+                unit: None,
+                labels: BTreeMap::new(),
+                timestamp: None,
+                sequence: None,
+                element_names: vec![],
             }],
             vec![],
         );
@@ -153,12 +284,32 @@ mod tests {
                     name: "Test Field 1".to_string(),
                     offset_in_packet: 3,
                     r#type: PacketFieldType::Float,
+                    endianness: Endianness::Little,
+                    bitfield: None,
+                    string_format: None,
+                    array: None,
+                    calibration: None,
+                    unit: None,
+                    labels: BTreeMap::new(),
+                    timestamp: None,
+                    sequence: None,
+                    element_names: vec![],
                 },
                 PacketField {
                     index: 2,
                     name: "Test Field 2".to_string(),
                     offset_in_packet: 7,
                     r#type: PacketFieldType::SignedLong,
+                    endianness: Endianness::Little,
+                    bitfield: None,
+                    string_format: None,
+                    array: None,
+                    calibration: None,
+                    unit: None,
+                    labels: BTreeMap::new(),
+                    timestamp: None,
+                    sequence: None,
+                    element_names: vec![],
                 },
             ],
             vec![
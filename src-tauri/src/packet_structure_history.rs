@@ -0,0 +1,156 @@
+//! # Packet Structure History
+//!
+//! This module keeps the log of edits made to packet structures, so the `PacketStructureManager`
+//! can undo and redo them.
+//!
+//! Every edit is recorded as a `Change` holding the whole packet structure before and after it.
+//! Edits shift every component after them, so storing whole structures is much simpler than
+//! working out how to reverse each kind of edit. Changes are grouped into `Transaction`s, which
+//! are undone and redone as one step.
+
+use crate::models::packet_structure::PacketStructure;
+
+/// The number of transactions that can be undone, older ones are forgotten
+const MAX_UNDO_STEPS: usize = 100;
+
+/// A packet structure before and after one edit
+///
+/// `before` is None for structures that were created by the edit, and `after` is None for
+/// structures that were deleted by it
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub(crate) packet_structure_id: usize,
+    pub(crate) before: Option<PacketStructure>,
+    pub(crate) after: Option<PacketStructure>,
+}
+
+/// A group of changes that are undone and redone together
+#[derive(Debug, Clone)]
+pub struct Transaction {
+    pub(crate) name: String,
+    pub(crate) changes: Vec<Change>,
+}
+
+/// The undo and redo stacks of the packet structure manager
+///
+/// edits made while a transaction is open are added to it instead of becoming their own step.
+/// transactions can be opened inside each other, and the changes only become a step once the
+/// outermost one is committed
+#[derive(Debug, Clone, Default)]
+pub struct EditHistory {
+    undo_stack: Vec<Transaction>,
+    redo_stack: Vec<Transaction>,
+    open_transaction: Option<Transaction>,
+    open_depth: usize,
+}
+
+impl EditHistory {
+    /// Records a change, as a step called `name` unless a transaction is open
+    ///
+    /// anything that was undone can no longer be redone
+    pub fn record(&mut self, name: &str, change: Change) {
+        match &mut self.open_transaction {
+            Some(transaction) => transaction.changes.push(change),
+            None => self.push(Transaction {
+                name: name.to_owned(),
+                changes: vec![change],
+            }),
+        }
+    }
+
+    /// Opens a transaction called `name`, or joins the one that is already open
+    ///
+    /// returns the number of changes that were already in the transaction, for `rollback`
+    pub fn begin(&mut self, name: &str) -> usize {
+        self.open_depth += 1;
+        let transaction = self.open_transaction.get_or_insert_with(|| Transaction {
+            name: name.to_owned(),
+            changes: vec![],
+        });
+        transaction.changes.len()
+    }
+
+    /// Closes the innermost open transaction, the changes become one step once the outermost
+    /// transaction is closed
+    pub fn commit(&mut self) {
+        self.open_depth = self.open_depth.saturating_sub(1);
+        if self.open_depth > 0 {
+            return;
+        }
+        if let Some(transaction) = self.open_transaction.take() {
+            if !transaction.changes.is_empty() {
+                self.push(transaction);
+            }
+        }
+    }
+
+    /// Closes the innermost open transaction without keeping it
+    ///
+    /// returns the changes made since `begin` returned `mark`, newest first, so they can be
+    /// reverted
+    pub fn rollback(&mut self, mark: usize) -> Vec<Change> {
+        let mut changes = match &mut self.open_transaction {
+            Some(transaction) => transaction
+                .changes
+                .split_off(mark.min(transaction.changes.len())),
+            None => vec![],
+        };
+        changes.reverse();
+        self.commit();
+        changes
+    }
+
+    /// Moves the newest step onto the redo stack and returns it
+    ///
+    /// nothing can be undone while a transaction is open
+    pub fn undo(&mut self) -> Option<&Transaction> {
+        if self.open_depth > 0 {
+            return None;
+        }
+        let transaction = self.undo_stack.pop()?;
+        self.redo_stack.push(transaction);
+        self.redo_stack.last()
+    }
+
+    /// Moves the newest undone step back onto the undo stack and returns it
+    ///
+    /// nothing can be redone while a transaction is open
+    pub fn redo(&mut self) -> Option<&Transaction> {
+        if self.open_depth > 0 {
+            return None;
+        }
+        let transaction = self.redo_stack.pop()?;
+        self.undo_stack.push(transaction);
+        self.undo_stack.last()
+    }
+
+    /// Returns the name of the step `undo` would undo
+    pub fn undo_name(&self) -> Option<&str> {
+        match self.open_depth {
+            0 => self
+                .undo_stack
+                .last()
+                .map(|transaction| transaction.name.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the step `redo` would redo
+    pub fn redo_name(&self) -> Option<&str> {
+        match self.open_depth {
+            0 => self
+                .redo_stack
+                .last()
+                .map(|transaction| transaction.name.as_str()),
+            _ => None,
+        }
+    }
+
+    fn push(&mut self, transaction: Transaction) {
+        self.redo_stack.clear();
+        self.undo_stack.push(transaction);
+        if self.undo_stack.len() > MAX_UNDO_STEPS {
+            self.undo_stack.remove(0);
+        }
+    }
+}
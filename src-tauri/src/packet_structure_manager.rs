@@ -4,17 +4,28 @@
 //! managing, and modifying the definitions of various data packets.
 
 use std::{
-    cmp::{max, min},
+    cmp::max,
     collections::BTreeMap,
+    ops::Range,
+    sync::atomic::{AtomicUsize, Ordering},
     vec,
 };
 use tauri::AppHandle;
 
 use crate::{
-    models::packet_structure::{
-        PacketDelimiter, PacketField, PacketFieldType, PacketMetadataType, PacketStructure,
+    models::{
+        packet::Packet,
+        packet_structure::{
+            Endianness, PacketDelimiter, PacketField, PacketFieldType, PacketMetadataType,
+            PacketStructure, SequenceFormat, StringFormat, TimestampFormat,
+        },
+        packet_view_model::PacketComponentType,
     },
     packet_structure_events::emit_packet_structure_update_event,
+    packet_structure_history::{Change, EditHistory},
+    packet_structure_validation::{
+        find_ambiguities, validate_against_each_other, validate_structure, Ambiguity, Diagnostic,
+    },
 };
 
 // We can start IDs from anywhere, but we start from 1 so that any code that
@@ -22,6 +33,10 @@ use crate::{
 // early.
 const LOWEST_ID: usize = 1;
 
+// Revisions are shared between every manager, so a parser can't mistake a different manager
+// (like one loaded from a new config) for the one it last parsed with
+static NEXT_REVISION: AtomicUsize = AtomicUsize::new(1);
+
 /// Represents all possible errors that can be encountered when managing packet structures.
 #[derive(Debug, Clone, PartialEq)]
 #[allow(warnings)]
@@ -34,6 +49,7 @@ pub enum Error {
     DelimitersAlreadyRegistered(usize),
     FieldOffsetOverflow,
     DelimiterOffsetOverflow,
+    CrcOffsetOverflow,
     /// Contains the character that was invalid
     InvalidHexCharacter(char),
     EmptyDelimiterIdentifier,
@@ -43,6 +59,16 @@ pub enum Error {
     CannotDeleteLastDelimiter,
     NoComponents,
     GapEndOverflow,
+    /// Contains the index of the field, delimiter, or the offset of the gap that was asked for
+    ComponentDoesNotExist(usize),
+    /// Contains the offset that is in the middle of another component
+    OffsetInsideComponent(usize),
+    /// Contains the index of the field whose array count or packet length is read from it
+    FieldInUse(usize),
+    /// Contains the index of the bitfield, which shares its type with the rest of its container
+    FieldInBitfield(usize),
+    /// Contains the errors found in the packet structure's layout
+    InvalidLayout(Vec<Diagnostic>),
     GenericError,
 }
 
@@ -57,6 +83,7 @@ impl Error {
             }
             Self::FieldOffsetOverflow => "Field offset overflow".to_string(),
             Self::DelimiterOffsetOverflow => "Delimiter offset overflow".to_string(),
+            Self::CrcOffsetOverflow => "CRC offset overflow".to_string(),
             Self::InvalidHexCharacter(char) => format!("{char} is an invalid hex character"),
             Self::EmptyDelimiterIdentifier => format!("Delimiter identifier cannot be empty"),
             Self::DelimiterIdentifierCollision(ids) => {
@@ -66,6 +93,21 @@ impl Error {
             Self::CannotDeleteLastDelimiter => "Cannot delete last delimiter".to_string(),
             Self::NoComponents => "No components".to_string(),
             Self::GapEndOverflow => "Gap end overflow".to_string(),
+            Self::ComponentDoesNotExist(index) => format!("Component {index} does not exist"),
+            Self::OffsetInsideComponent(offset) => {
+                format!("Offset {offset} is in the middle of another component")
+            }
+            Self::FieldInUse(index) => {
+                format!("Field {index} holds an array count or the packet length")
+            }
+            Self::FieldInBitfield(index) => {
+                format!("Field {index} is a bitfield, its type is shared with its container")
+            }
+            Self::InvalidLayout(diagnostics) => diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.message.clone())
+                .collect::<Vec<String>>()
+                .join("\n"),
             Self::GenericError => {
                 "Something went wrong, and the devs were too lazy to give it an error".to_string()
             }
@@ -73,17 +115,118 @@ impl Error {
     }
 }
 
+/// Where a component is put when it is inserted into, or moved within, a packet structure
+///
+/// components are always put in front of whatever was at that spot, which is shifted back
+/// to make room
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InsertPosition {
+    /// at a byte offset, which can't be in the middle of another component
+    Offset(usize),
+    /// where a field, delimiter, or gap starts. fields are found by index, and gaps by offset
+    Before(PacketComponentType, usize),
+    /// where a field, delimiter, or gap ends
+    After(PacketComponentType, usize),
+    /// at the end of the packet
+    End,
+}
+
+/// The sizes of a packet structure, worked out whenever it changes so the parsers don't have to
+/// work them out for every byte they look at
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PacketLayout {
+    pub(crate) size: usize,
+    pub(crate) min_size: usize,
+    pub(crate) max_size: usize,
+    /// the index of the delimiter nearest the start of the packet, which is looked for first
+    pub(crate) first_delimiter: Option<usize>,
+    pub(crate) first_delimiter_offset: usize,
+    /// true if the parsers look for this structure in raw data, ie. it is byte defined and has
+    /// a delimiter to be found by
+    pub(crate) searchable: bool,
+}
+
+impl PacketLayout {
+    pub fn of(packet_structure: &PacketStructure) -> PacketLayout {
+        let first_delimiter = packet_structure
+            .delimiters
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, delimiter)| delimiter.offset_in_packet);
+        PacketLayout {
+            size: packet_structure.size(),
+            min_size: packet_structure.min_size(),
+            max_size: packet_structure.max_size(),
+            first_delimiter: first_delimiter.map(|(index, _)| index),
+            first_delimiter_offset: first_delimiter
+                .map_or(0, |(_, delimiter)| delimiter.offset_in_packet),
+            searchable: packet_structure.byte_defined && first_delimiter.is_some(),
+        }
+    }
+}
+
+/// How many searchable packet structures have each size and first delimiter offset, so the
+/// smallest and largest can be found again when a structure is removed
+#[derive(Debug, Clone, Default)]
+struct LayoutCounts {
+    min_sizes: BTreeMap<usize, usize>,
+    max_sizes: BTreeMap<usize, usize>,
+    first_delimiter_offsets: BTreeMap<usize, usize>,
+}
+
+impl LayoutCounts {
+    fn add(&mut self, layout: &PacketLayout) {
+        if !layout.searchable {
+            return;
+        }
+        *self.min_sizes.entry(layout.min_size).or_default() += 1;
+        *self.max_sizes.entry(layout.max_size).or_default() += 1;
+        *self
+            .first_delimiter_offsets
+            .entry(layout.first_delimiter_offset)
+            .or_default() += 1;
+    }
+
+    fn remove(&mut self, layout: &PacketLayout) {
+        if !layout.searchable {
+            return;
+        }
+        for (counts, value) in [
+            (&mut self.min_sizes, layout.min_size),
+            (&mut self.max_sizes, layout.max_size),
+            (
+                &mut self.first_delimiter_offsets,
+                layout.first_delimiter_offset,
+            ),
+        ] {
+            if let Some(count) = counts.get_mut(&value) {
+                *count -= 1;
+                if *count == 0 {
+                    counts.remove(&value);
+                }
+            }
+        }
+    }
+}
+
 /// A packet structure manager is an object that contains all the packets the app is dealing with, this makes them easier to use them from other threads and handle errors
 #[readonly::make]
 #[derive(Debug, Clone)]
 pub struct PacketStructureManager {
     pub(crate) packet_structures: Vec<PacketStructure>,
+    //the sizes of each packet structure, in the same order as packet_structures
+    pub(crate) layouts: Vec<PacketLayout>,
 
-    // These variables are used for parsing packets
+    // These variables are used for parsing packets, and only cover searchable packet structures
     pub(crate) minimum_packet_structure_size: usize,
     pub(crate) maximum_packet_structure_size: usize,
     pub(crate) maximum_first_delimiter: usize,
+    //changes whenever the packet structures do, so parsers know to look for new delimiters
+    pub(crate) revision: usize,
+    layout_counts: LayoutCounts,
 
+    //for finding where a packet structure is in packet_structures from its Id
+    id_to_index: BTreeMap<usize, usize>,
     //for translating between packet names and Ids
     name_to_id: BTreeMap<String, usize>,
     //for translating between packet Ids and names
@@ -91,18 +234,26 @@ pub struct PacketStructureManager {
 
     //for updating the frontend with changes
     app: Option<AppHandle>,
+
+    //for undoing and redoing edits
+    history: EditHistory,
 }
 
 impl Default for PacketStructureManager {
     fn default() -> Self {
         Self {
             packet_structures: Default::default(),
+            layouts: Default::default(),
             minimum_packet_structure_size: usize::MAX,
             maximum_packet_structure_size: 0,
             maximum_first_delimiter: 0,
+            revision: 0,
+            layout_counts: Default::default(),
+            id_to_index: Default::default(),
             name_to_id: Default::default(),
             id_to_name: Default::default(),
             app: None,
+            history: Default::default(),
         }
     }
 }
@@ -148,9 +299,12 @@ impl PacketStructureManager {
 
     pub fn enforce_packet_fields(&mut self, name: &str, names: Vec<&str>) -> usize {
         let id = self.get_packet_structure_by_name(name);
-        let ps = self.get_packet_structure_mut(id);
-        let ps = ps.expect("Attempted to register packet structure, but cannot retrieve it");
+        let ps = self.get_packet_structure(id);
+        let mut ps = ps
+            .expect("Attempted to register packet structure, but cannot retrieve it")
+            .clone();
         if ps.fields.len() < names.len() {
+            let before = ps.clone();
             ps.byte_defined = false; //we're giving the new fields arbitrary types, so this means we can no longer use the packet for direct parsing
             for i in ps.fields.len()..names.len() {
                 let packet_field_count = ps.fields.len();
@@ -160,8 +314,28 @@ impl PacketStructureManager {
                     name: names[i].to_owned(),
                     offset_in_packet: end_of_packet,
                     r#type: PacketFieldType::UnsignedInteger,
+                    endianness: Endianness::Little,
+                    bitfield: None,
+                    string_format: None,
+                    array: None,
+                    calibration: None,
+                    unit: None,
+                    labels: BTreeMap::new(),
+                    timestamp: None,
+                    sequence: None,
+                    element_names: vec![],
                 });
             }
+            self.set_packet_structure_at(self.id_to_index[&id], ps.clone());
+            self.history.record(
+                "Add fields",
+                Change {
+                    packet_structure_id: id,
+                    before: Some(before),
+                    after: Some(ps),
+                },
+            );
+            self.update_tracked_values();
             if self.app.is_some() {
                 emit_packet_structure_update_event(
                     &self.app.clone().unwrap(),
@@ -175,6 +349,12 @@ impl PacketStructureManager {
     }
     /// Takes the given PacketStructure and makes a copy within the manager for future use
     /// Note: this also needs to be unit tested
+    ///
+    /// a structure with the name of one that is already registered is merged into it. the
+    /// resulting structure is checked along with every other registered structure, structures
+    /// with errors in their layout are turned away and new warnings are printed
+    ///
+    /// registering is recorded in the edit history like any other edit
     pub fn register_packet_structure(
         &mut self,
         packet_structure: &mut PacketStructure,
@@ -187,31 +367,77 @@ impl PacketStructureManager {
                 next_packet_id = registered_packet_structure.id + 1;
             }
         }
-        match self.name_to_id.get(&packet_structure.name) {
-            Some(id) => {
-                packet_structure.id = *id;
-                let index = self
-                    .packet_structures
-                    .iter_mut()
-                    .find(&mut |x: &&mut PacketStructure| x.id == *id);
-                match index {
-                    Some(mut structure) => {
-                        structure
-                            .delimiters
-                            .append(&mut packet_structure.delimiters);
-                        structure.delimiters.dedup();
-                        structure.fields.append(&mut packet_structure.fields);
-                        structure.fields.dedup();
-                        structure.size = max(packet_structure.size, structure.size)
-                    }
-                    None => self.packet_structures.push(packet_structure.clone()),
-                }
-            }
-            None => {
-                packet_structure.id = next_packet_id;
-                self.packet_structures.push(packet_structure.clone());
+        let registered_id = self.name_to_id.get(&packet_structure.name).copied();
+        packet_structure.id = registered_id.unwrap_or(next_packet_id);
+        let index = registered_id.and_then(|id| self.id_to_index.get(&id).copied());
+        let structure = match index {
+            Some(index) => {
+                let mut structure = self.packet_structures[index].clone();
+                structure
+                    .delimiters
+                    .append(&mut packet_structure.delimiters);
+                structure.delimiters.dedup();
+                structure.fields.append(&mut packet_structure.fields);
+                structure.fields.dedup();
+                structure.size = max(packet_structure.size, structure.size);
+                structure
             }
+            None => packet_structure.clone(),
+        };
+
+        // only what this structure changed is reported, the rest was reported when it was added.
+        // the other structures were already compared with each other, so each is only compared
+        // with this one, in the order the parser tries them
+        let position = index.unwrap_or(self.packet_structures.len());
+        let against_others = |structure: &PacketStructure| -> Vec<Diagnostic> {
+            self.packet_structures
+                .iter()
+                .enumerate()
+                .filter(|(other_index, _)| *other_index != position)
+                .flat_map(|(other_index, other)| {
+                    let pair = if other_index < position {
+                        [other, structure]
+                    } else {
+                        [structure, other]
+                    };
+                    validate_against_each_other(&pair)
+                })
+                .collect()
+        };
+        let known = match index {
+            Some(index) => against_others(&self.packet_structures[index]),
+            None => vec![],
+        };
+        let (errors, warnings): (Vec<Diagnostic>, Vec<Diagnostic>) = validate_structure(&structure)
+            .into_iter()
+            .chain(
+                against_others(&structure)
+                    .into_iter()
+                    .filter(|diagnostic| !known.contains(diagnostic)),
+            )
+            .partition(Diagnostic::is_error);
+        if !errors.is_empty() {
+            return Err(Error::InvalidLayout(errors));
+        }
+        for warning in warnings {
+            eprintln!("Warning for {}: {}", packet_structure.name, warning.message);
+        }
+        let (name, before) = match index {
+            Some(index) => ("Merge packet", Some(self.packet_structures[index].clone())),
+            None => ("Add packet", None),
+        };
+        match index {
+            Some(index) => self.set_packet_structure_at(index, structure.clone()),
+            None => self.push_packet_structure(structure.clone()),
         }
+        self.history.record(
+            name,
+            Change {
+                packet_structure_id: packet_structure.id,
+                before,
+                after: Some(structure),
+            },
+        );
 
         self.name_to_id
             .insert(packet_structure.name.clone(), packet_structure.id);
@@ -231,32 +457,133 @@ impl PacketStructureManager {
         Ok(packet_structure.id)
     }
 
+    /// Checks every registered packet structure, and how they get along with each other
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics: Vec<Diagnostic> = self
+            .packet_structures
+            .iter()
+            .flat_map(validate_structure)
+            .collect();
+        diagnostics.extend(validate_against_each_other(&self.packet_structures));
+        diagnostics
+    }
+
+    /// Finds the registered packet structures that the same bytes could be parsed as
+    pub fn find_ambiguities(&self) -> Vec<Ambiguity> {
+        find_ambiguities(&self.packet_structures)
+    }
+
     /// Get a immutable borrow to a packet structure by its ID.
     /// This is necessary because the IDs are **not** list indexes.
     pub fn get_packet_structure(
         &self,
         packet_structure_id: usize,
     ) -> Result<&PacketStructure, Error> {
-        for packet_structure in self.packet_structures.iter() {
-            if packet_structure.id == packet_structure_id {
-                return Ok(packet_structure);
+        match self.id_to_index.get(&packet_structure_id) {
+            Some(index) => Ok(&self.packet_structures[*index]),
+            None => Err(Error::PacketDoesNotExist(packet_structure_id)),
+        }
+    }
+
+    /// Get the sizes of a packet structure by its ID
+    pub fn get_packet_layout(&self, packet_structure_id: usize) -> Result<&PacketLayout, Error> {
+        match self.id_to_index.get(&packet_structure_id) {
+            Some(index) => Ok(&self.layouts[*index]),
+            None => Err(Error::PacketDoesNotExist(packet_structure_id)),
+        }
+    }
+
+    /// Replaces the packet structure with the same ID, ie. when a device learns a newer layout
+    pub fn update_packet_structure(
+        &mut self,
+        packet_structure: PacketStructure,
+    ) -> Result<(), Error> {
+        let id = packet_structure.id;
+        let index = *self
+            .id_to_index
+            .get(&id)
+            .ok_or(Error::PacketDoesNotExist(id))?;
+        if packet_structure.name != self.packet_structures[index].name {
+            if let Some(other_id) = self.name_to_id.get(&packet_structure.name) {
+                return Err(Error::NameAlreadyRegistered(*other_id));
             }
         }
-        return Err(Error::PacketDoesNotExist(packet_structure_id));
+        let before = self.packet_structures[index].clone();
+        self.replace_packet_structure(id, Some(packet_structure.clone()));
+        self.history.record(
+            "Update packet",
+            Change {
+                packet_structure_id: id,
+                before: Some(before),
+                after: Some(packet_structure),
+            },
+        );
+        self.update_tracked_values();
+        self.emit_update(vec![id], vec![]);
+        Ok(())
+    }
+
+    /// Converts a parsed packet's raw values into real units using its structure's calibrations
+    pub fn calibrate_packet(&self, packet: &mut Packet) -> Result<(), Error> {
+        self.get_packet_structure(packet.structure_id)?
+            .calibrate(&mut packet.field_data);
+        Ok(())
+    }
+
+    /// Names the values of a parsed packet's enumerated fields using its structure's labels
+    pub fn label_packet(&self, packet: &mut Packet) -> Result<(), Error> {
+        packet.field_labels = self
+            .get_packet_structure(packet.structure_id)?
+            .label(&packet.field_data);
+        Ok(())
     }
 
     /// Get a mutable borrow to a packet structure by its ID.
     /// This is necessary because the IDs are **not** list indexes.
-    pub fn get_packet_structure_mut(
+    ///
+    /// the structure's layout has to be refreshed with `refresh_layout` after changing it
+    fn get_packet_structure_mut(
         &mut self,
         packet_structure_id: usize,
     ) -> Result<&mut PacketStructure, Error> {
-        for packet_structure in self.packet_structures.iter_mut() {
-            if packet_structure.id == packet_structure_id {
-                return Ok(packet_structure);
-            }
+        match self.id_to_index.get(&packet_structure_id) {
+            Some(index) => Ok(&mut self.packet_structures[*index]),
+            None => Err(Error::PacketDoesNotExist(packet_structure_id)),
+        }
+    }
+
+    /// Works out the layout of a packet structure again after it was changed in place
+    fn refresh_layout(&mut self, index: usize) {
+        self.layout_counts.remove(&self.layouts[index]);
+        self.layouts[index] = PacketLayout::of(&self.packet_structures[index]);
+        self.layout_counts.add(&self.layouts[index]);
+    }
+
+    /// Adds a packet structure to the end of the list, along with its layout
+    fn push_packet_structure(&mut self, packet_structure: PacketStructure) {
+        let layout = PacketLayout::of(&packet_structure);
+        self.layout_counts.add(&layout);
+        self.id_to_index
+            .insert(packet_structure.id, self.packet_structures.len());
+        self.packet_structures.push(packet_structure);
+        self.layouts.push(layout);
+    }
+
+    /// Puts a packet structure in place of the one at `index`, which has the same ID
+    fn set_packet_structure_at(&mut self, index: usize, packet_structure: PacketStructure) {
+        self.packet_structures[index] = packet_structure;
+        self.refresh_layout(index);
+    }
+
+    /// Removes the packet structure at `index`, the ones after it move up a spot
+    fn remove_packet_structure_at(&mut self, index: usize) {
+        let packet_structure = self.packet_structures.remove(index);
+        let layout = self.layouts.remove(index);
+        self.layout_counts.remove(&layout);
+        self.id_to_index.remove(&packet_structure.id);
+        for (index, packet_structure) in self.packet_structures.iter().enumerate().skip(index) {
+            self.id_to_index.insert(packet_structure.id, index);
         }
-        return Err(Error::PacketDoesNotExist(packet_structure_id));
     }
 
     /// Sets the name of a specific packet structure
@@ -270,12 +597,10 @@ impl PacketStructureManager {
             }
         }
 
-        self.name_to_id.insert(name.to_owned(), packet_structure_id);
-        self.id_to_name.insert(packet_structure_id, name.to_owned());
-
-        let packet_structure = self.get_packet_structure_mut(packet_structure_id)?;
-        packet_structure.name = String::from(name);
-        Ok(())
+        self.edit_packet_structure(packet_structure_id, "Rename packet", |packet_structure| {
+            packet_structure.name = String::from(name);
+            Ok(())
+        })
     }
 
     /// Sets the name of a specific field within a packet structure
@@ -285,9 +610,10 @@ impl PacketStructureManager {
         field_index: usize,
         name: &str,
     ) -> Result<(), Error> {
-        let packet_structure = self.get_packet_structure_mut(packet_structure_id)?;
-        packet_structure.fields[field_index].name = String::from(name);
-        Ok(())
+        self.edit_packet_structure(packet_structure_id, "Rename field", |packet_structure| {
+            Self::find_field_mut(packet_structure, field_index)?.name = String::from(name);
+            Ok(())
+        })
     }
 
     /// Shifts all components after a specified point in the packet structure
@@ -303,9 +629,19 @@ impl PacketStructureManager {
         packet_structure: &mut PacketStructure,
         offset_diff: isize,
         minimum_offset: usize,
+    ) -> Result<(), Error> {
+        Self::shift_components_from(packet_structure, offset_diff, minimum_offset + 1)
+    }
+
+    /// Shifts all components starting at or after first_offset, along with the declared size
+    /// and the ends of checksums that cover those bytes
+    fn shift_components_from(
+        packet_structure: &mut PacketStructure,
+        offset_diff: isize,
+        first_offset: usize,
     ) -> Result<(), Error> {
         for field in &mut packet_structure.fields {
-            if field.offset_in_packet > minimum_offset {
+            if field.offset_in_packet >= first_offset {
                 field.offset_in_packet =
                     match field.offset_in_packet.checked_add_signed(offset_diff) {
                         Some(n) => n,
@@ -317,7 +653,7 @@ impl PacketStructureManager {
         }
 
         for delimiter in &mut packet_structure.delimiters {
-            if delimiter.offset_in_packet > minimum_offset {
+            if delimiter.offset_in_packet >= first_offset {
                 delimiter.offset_in_packet =
                     match delimiter.offset_in_packet.checked_add_signed(offset_diff) {
                         Some(n) => n,
@@ -328,6 +664,32 @@ impl PacketStructureManager {
             }
         }
 
+        let shift = |offset: usize| {
+            offset
+                .checked_add_signed(offset_diff)
+                .ok_or(Error::CrcOffsetOverflow)
+        };
+        for crc in &mut packet_structure.packet_crc {
+            if crc.offset_in_packet >= first_offset {
+                crc.offset_in_packet = shift(crc.offset_in_packet)?;
+            }
+            if crc.covered_start >= first_offset {
+                crc.covered_start = shift(crc.covered_start)?;
+            }
+            // a checksum only grows when bytes are added before the end of what it covers
+            if let Some(covered_end) = crc.covered_end.filter(|end| *end > first_offset) {
+                crc.covered_end = Some(shift(covered_end)?);
+            }
+        }
+
+        // adding to the very end of a packet makes it longer
+        if let Some(size) = packet_structure.size.filter(|size| *size >= first_offset) {
+            packet_structure.size = Some(
+                size.checked_add_signed(offset_diff)
+                    .ok_or(Error::GapEndOverflow)?,
+            );
+        }
+
         Ok(())
     }
 
@@ -339,37 +701,89 @@ impl PacketStructureManager {
         field_index: usize,
         r#type: PacketFieldType,
     ) -> Result<(), Error> {
-        let packet_structure = self.get_packet_structure_mut(packet_structure_id)?;
+        self.edit_packet_structure(packet_structure_id, "Set field type", |packet_structure| {
+            let minimum_offset: usize;
+            let offset_diff: isize;
+            {
+                let field_to_modify = Self::find_field_mut(packet_structure, field_index)?;
+                // the other bitfields in the container would change size along with it
+                if field_to_modify.bitfield.is_some() {
+                    return Err(Error::FieldInBitfield(field_index));
+                }
+                let old_size = field_to_modify.size().map_err(|_| Error::GenericError)?;
 
-        let minimum_offset: usize;
-        let offset_diff: isize;
-        {
-            let field_to_modify = &mut packet_structure.fields[field_index];
+                let mut modified_field = field_to_modify.clone();
+                modified_field.r#type = r#type;
+                // a field left in its old type's byte order takes on the new type's
+                if field_to_modify.endianness == field_to_modify.r#type.default_endianness() {
+                    modified_field.endianness = r#type.default_endianness();
+                }
+                // a field becoming a string keeps its old footprint as a fixed length string
+                modified_field.string_format = match r#type {
+                    PacketFieldType::String => Some(
+                        field_to_modify
+                            .string_format
+                            .unwrap_or(StringFormat::Fixed { length: old_size }),
+                    ),
+                    _ => None,
+                };
+                let new_size = modified_field.size().map_err(|_| Error::GenericError)?;
 
-            minimum_offset = field_to_modify.offset_in_packet;
-            offset_diff = (r#type.size().unwrap_or(return Err(Error::GenericError)) as isize)
-                - (field_to_modify
-                    .r#type
-                    .size()
-                    .unwrap_or(return Err(Error::GenericError)) as isize);
+                minimum_offset = field_to_modify.offset_in_packet;
+                offset_diff = new_size as isize - old_size as isize;
 
-            field_to_modify.r#type = r#type;
-        }
+                *field_to_modify = modified_field;
+            }
 
-        Self::shift_components_after(packet_structure, offset_diff, minimum_offset)?;
+            Self::shift_components_after(packet_structure, offset_diff, minimum_offset)?;
 
-        Ok(())
+            Ok(())
+        })
     }
 
     /// Set the metadata type for a field of a packet structure.
     pub fn set_field_metadata_type(
         &mut self,
-        _packet_structure_id: usize,
-        _field_index: usize,
-        _metadata_type: PacketMetadataType,
+        packet_structure_id: usize,
+        field_index: usize,
+        metadata_type: PacketMetadataType,
     ) -> Result<(), Error> {
-        // Deprecated old method; to be replaced
-        Ok(())
+        self.edit_packet_structure(
+            packet_structure_id,
+            "Set field metadata type",
+            |packet_structure| {
+                let field = Self::find_field_mut(packet_structure, field_index)?;
+                // integer fields roll over wherever they run out of bits
+                let wrap_bits = match (field.bitfield, field.r#type) {
+                    (Some(bitfield), _) => Some(bitfield.bit_width as u32),
+                    (None, PacketFieldType::Float | PacketFieldType::Double) => None,
+                    (None, field_type) => field_type.size().ok().map(|size| size as u32 * 8),
+                };
+                // a field is either a clock or a counter, and keeps a format that was already
+                // set up. new clocks count in seconds and new counters go up by 1
+                match metadata_type {
+                    PacketMetadataType::None => {
+                        field.timestamp = None;
+                        field.sequence = None;
+                    }
+                    PacketMetadataType::Timestamp => {
+                        field.timestamp = Some(field.timestamp.unwrap_or(TimestampFormat {
+                            ticks_per_second: 1.0,
+                            wrap_bits,
+                        }));
+                        field.sequence = None;
+                    }
+                    PacketMetadataType::Sequence => {
+                        field.sequence = Some(field.sequence.unwrap_or(SequenceFormat {
+                            wrap_bits: wrap_bits.unwrap_or(64).min(64),
+                            step: 1,
+                        }));
+                        field.timestamp = None;
+                    }
+                }
+                Ok(())
+            },
+        )
     }
 
     /// Set the name of a delimiter.
@@ -379,9 +793,14 @@ impl PacketStructureManager {
         delimiter_index: usize,
         name: &str,
     ) -> Result<(), Error> {
-        let packet_structure = self.get_packet_structure_mut(packet_structure_id)?;
-        packet_structure.delimiters[delimiter_index].name = String::from(name);
-        Ok(())
+        self.edit_packet_structure(
+            packet_structure_id,
+            "Rename delimiter",
+            |packet_structure| {
+                packet_structure.delimiters[delimiter_index].name = String::from(name);
+                Ok(())
+            },
+        )
     }
 
     /// Adapted from https://codereview.stackexchange.com/a/201699
@@ -464,8 +883,7 @@ impl PacketStructureManager {
         // Get array of bytes from the given string
         let hex_array = Self::parse_hex(identifier)?;
 
-        let packet_structures = self.packet_structures.clone();
-        let packet_structure = self.get_packet_structure_mut(packet_structure_id)?;
+        let packet_structure = self.get_packet_structure(packet_structure_id)?;
 
         // Clone the delimiters so that the change to the packet structure is not immediately applied;
         // this makes handling an identifier collision easier later
@@ -479,7 +897,7 @@ impl PacketStructureManager {
 
         // Check for collisions with other packet structure identifiers
         if let Some(colliding_ids) = Self::check_for_identifier_collisions(
-            &packet_structures,
+            &self.packet_structures,
             packet_structure_id,
             &delimiters,
         ) {
@@ -487,11 +905,14 @@ impl PacketStructureManager {
         }
 
         // Apply the change
-        packet_structure.delimiters = delimiters;
-
-        Self::shift_components_after(packet_structure, offset_diff, delimiter_offset)?;
-
-        Ok(())
+        self.edit_packet_structure(
+            packet_structure_id,
+            "Set delimiter identifier",
+            |packet_structure| {
+                packet_structure.delimiters = delimiters;
+                Self::shift_components_after(packet_structure, offset_diff, delimiter_offset)
+            },
+        )
     }
 
     /// Changes the size of a gap inside a packet
@@ -501,121 +922,728 @@ impl PacketStructureManager {
         gap_start: usize,
         new_gap_size: isize,
     ) -> Result<(), Error> {
-        let packet_structure = self.get_packet_structure_mut(packet_structure_id)?;
-
-        let field_offsets = packet_structure.fields.iter().map(|f| f.offset_in_packet);
-        let delimiter_offsets = packet_structure
-            .delimiters
-            .iter()
-            .map(|d| d.offset_in_packet);
-        let gap_end: usize = field_offsets
-            .chain(delimiter_offsets)
-            .filter(|offset| *offset >= gap_start)
-            .min()
-            .ok_or(Error::NoComponents)?
-            .try_into()
-            .map_err(|_| Error::GapEndOverflow)?;
-
-        let gap_size: isize = (gap_end - gap_start)
-            .try_into()
-            .map_err(|_| Error::GapEndOverflow)?;
-
-        Self::shift_components_after(packet_structure, new_gap_size - gap_size, gap_start)?;
-        Ok(())
+        self.edit_packet_structure(packet_structure_id, "Resize gap", |packet_structure| {
+            let field_offsets = packet_structure.fields.iter().map(|f| f.offset_in_packet);
+            let delimiter_offsets = packet_structure
+                .delimiters
+                .iter()
+                .map(|d| d.offset_in_packet);
+            let gap_end: usize = field_offsets
+                .chain(delimiter_offsets)
+                .filter(|offset| *offset >= gap_start)
+                .min()
+                .ok_or(Error::NoComponents)?
+                .try_into()
+                .map_err(|_| Error::GapEndOverflow)?;
+
+            let gap_size: isize = (gap_end - gap_start)
+                .try_into()
+                .map_err(|_| Error::GapEndOverflow)?;
+
+            Self::shift_components_after(packet_structure, new_gap_size - gap_size, gap_start)?;
+            Ok(())
+        })
     }
 
-    /// Creates a new field inside the end of the packet
-    /// Needs to be reworked to add to a user-specified location
+    /// Creates a new field at the end of the packet
     pub fn add_field(&mut self, packet_structure_id: usize) -> Result<(), Error> {
-        let packet_structure = self.get_packet_structure_mut(packet_structure_id)?;
+        self.insert_field(packet_structure_id, InsertPosition::End)?;
+        Ok(())
+    }
 
-        let packet_field_count = packet_structure.fields.len();
-        let end_of_packet = packet_structure.size();
+    /// Creates a new delimiter at the end of the packet
+    pub fn add_delimiter(&mut self, packet_structure_id: usize) -> Result<(), Error> {
+        self.insert_delimiter(packet_structure_id, InsertPosition::End)?;
+        Ok(())
+    }
 
-        packet_structure.fields.push(PacketField {
-            index: packet_field_count,
-            name: format!("Field {}", (packet_field_count + 1)),
-            offset_in_packet: end_of_packet,
-            r#type: PacketFieldType::UnsignedInteger,
-        });
+    /// Creates a one byte gap after the specified component, which can be a gap itself
+    pub fn add_gap_after(
+        &mut self,
+        packet_structure_id: usize,
+        is_field: bool,
+        component_index: usize,
+    ) -> Result<(), Error> {
+        let component_type = if is_field {
+            PacketComponentType::Field
+        } else {
+            PacketComponentType::Delimiter
+        };
+        self.insert_gap(
+            packet_structure_id,
+            InsertPosition::After(component_type, component_index),
+            1,
+        )
+    }
 
-        return Ok(());
+    /// Creates a new field at `position`, shifting everything after it back
+    ///
+    /// returns the index of the new field
+    pub fn insert_field(
+        &mut self,
+        packet_structure_id: usize,
+        position: InsertPosition,
+    ) -> Result<usize, Error> {
+        self.edit_packet_structure(packet_structure_id, "Add field", |packet_structure| {
+            let packet_field_count = packet_structure.fields.len();
+            let field = PacketField {
+                index: usize::MAX, // gets renumbered
+                name: format!("Field {}", (packet_field_count + 1)),
+                offset_in_packet: 0,
+                r#type: PacketFieldType::UnsignedInteger,
+                endianness: Endianness::Little,
+                bitfield: None,
+                string_format: None,
+                array: None,
+                calibration: None,
+                unit: None,
+                labels: BTreeMap::new(),
+                timestamp: None,
+                sequence: None,
+                element_names: vec![],
+            };
+            let offset = Self::resolve_position(packet_structure, position, None)?;
+            Self::place_field(packet_structure, field, offset)
+        })
     }
 
-    /// Creates a new delimiter inside the end of the packet
-    /// Needs to be reworked to add to a user-specified location
-    pub fn add_delimiter(&mut self, packet_structure_id: usize) -> Result<(), Error> {
-        let packet_structure = self.get_packet_structure_mut(packet_structure_id)?;
+    /// Creates a new delimiter at `position`, shifting everything after it back
+    ///
+    /// returns the index of the new delimiter
+    pub fn insert_delimiter(
+        &mut self,
+        packet_structure_id: usize,
+        position: InsertPosition,
+    ) -> Result<usize, Error> {
+        self.edit_packet_structure(packet_structure_id, "Add delimiter", |packet_structure| {
+            let packet_delimiter_count = packet_structure.delimiters.len();
+            // TODO: check for collisions
+            let delimiter = PacketDelimiter {
+                index: packet_delimiter_count,
+                name: format!("Delimiter {}", packet_delimiter_count + 1),
+                identifier: vec![0xFF],
+                offset_in_packet: 0,
+            };
+            let offset = Self::resolve_position(packet_structure, position, None)?;
+            Self::place_delimiter(packet_structure, delimiter, offset)
+        })
+    }
 
-        let packet_delimiter_count = packet_structure.delimiters.len();
-        let end_of_packet = packet_structure.size();
+    /// Creates a gap of `size` bytes at `position`, shifting everything after it back
+    pub fn insert_gap(
+        &mut self,
+        packet_structure_id: usize,
+        position: InsertPosition,
+        size: usize,
+    ) -> Result<(), Error> {
+        self.edit_packet_structure(packet_structure_id, "Add gap", |packet_structure| {
+            let offset = Self::resolve_position(packet_structure, position, None)?;
+            Self::open_gap(packet_structure, offset, size)
+        })
+    }
 
-        // TODO: check for collisions
-        packet_structure.delimiters.push(PacketDelimiter {
-            index: packet_delimiter_count,
-            name: format!("Delimiter {}", packet_delimiter_count + 1),
-            identifier: vec![0xFF],
-            offset_in_packet: end_of_packet,
-        });
+    /// Deletes a field, delimiter, or gap and closes up the bytes it took up
+    ///
+    /// fields are found by index and gaps by the offset they start at. bitfields that share
+    /// their container with other bitfields leave the container behind
+    pub fn delete_packet_structure_component(
+        &mut self,
+        packet_structure_id: usize,
+        component_type: PacketComponentType,
+        component_index: usize,
+    ) -> Result<(), Error> {
+        self.edit_packet_structure(
+            packet_structure_id,
+            "Delete component",
+            |packet_structure| {
+                match component_type {
+                    PacketComponentType::Field if packet_structure.fields.len() == 1 => {
+                        return Err(Error::CannotDeleteLastField)
+                    }
+                    // byte defined packets are found by their delimiters, so they need to keep one
+                    PacketComponentType::Delimiter
+                        if packet_structure.byte_defined
+                            && packet_structure.delimiters.len() == 1 =>
+                    {
+                        return Err(Error::CannotDeleteLastDelimiter)
+                    }
+                    PacketComponentType::Field => {
+                        let depends_on_field =
+                            packet_structure.fields.iter().any(|field| {
+                                field.array.and_then(|array| array.count_field)
+                                    == Some(component_index)
+                            }) || packet_structure.length_field.as_ref().is_some_and(
+                                |length_field| length_field.field_index == component_index,
+                            );
+                        if depends_on_field {
+                            return Err(Error::FieldInUse(component_index));
+                        }
+                    }
+                    _ => {}
+                }
+                Self::take_component(packet_structure, component_type, component_index)?;
+                Self::renumber_components(packet_structure);
+                Ok(())
+            },
+        )
+    }
 
-        return Ok(());
+    /// Moves a field, delimiter, or gap to `position`, closing up the bytes it took up and
+    /// making room for it at its new spot
+    ///
+    /// `position` is in terms of the packet before the move. returns the new index of the field
+    /// or delimiter, or the new offset of the gap
+    pub fn move_packet_structure_component(
+        &mut self,
+        packet_structure_id: usize,
+        component_type: PacketComponentType,
+        component_index: usize,
+        position: InsertPosition,
+    ) -> Result<usize, Error> {
+        self.edit_packet_structure(packet_structure_id, "Move component", |packet_structure| {
+            let span = Self::component_span(packet_structure, component_type, component_index)?;
+            let mut offset = Self::resolve_position(packet_structure, position, Some(&span))?;
+            let (component, closed) =
+                Self::take_component(packet_structure, component_type, component_index)?;
+            if closed && offset >= span.end {
+                offset -= span.len();
+            }
+            match component {
+                TakenComponent::Field(field) => Self::place_field(packet_structure, field, offset),
+                TakenComponent::Delimiter(delimiter) => {
+                    Self::place_delimiter(packet_structure, delimiter, offset)
+                }
+                TakenComponent::Gap => {
+                    Self::open_gap(packet_structure, offset, span.len())?;
+                    Ok(offset)
+                }
+            }
+        })
     }
 
-    /// Creates a gap after the specified component_index
-    pub fn add_gap_after(
+    /// Puts a copy of a field, delimiter, or gap right after it
+    ///
+    /// returns the index of the new field or delimiter, or the offset of the new gap
+    pub fn duplicate_packet_structure_component(
         &mut self,
         packet_structure_id: usize,
-        is_field: bool,
+        component_type: PacketComponentType,
         component_index: usize,
+    ) -> Result<usize, Error> {
+        self.edit_packet_structure(
+            packet_structure_id,
+            "Duplicate component",
+            |packet_structure| {
+                let span = Self::component_span(packet_structure, component_type, component_index)?;
+                match component_type {
+                    PacketComponentType::Field => {
+                        let mut field =
+                            Self::find_field(packet_structure, component_index)?.clone();
+                        field.index = usize::MAX; // gets renumbered
+                        field.name = format!("{} copy", field.name);
+                        Self::place_field(packet_structure, field, span.end)
+                    }
+                    PacketComponentType::Delimiter => {
+                        let mut delimiter = packet_structure.delimiters[component_index].clone();
+                        delimiter.name = format!("{} copy", delimiter.name);
+                        Self::place_delimiter(packet_structure, delimiter, span.end)
+                    }
+                    PacketComponentType::Gap => {
+                        Self::open_gap(packet_structure, span.end, span.len())?;
+                        Ok(span.end)
+                    }
+                }
+            },
+        )
+    }
+
+    /// Removes a packet structure from the manager
+    pub fn delete_packet_structure(&mut self, packet_structure_id: usize) -> Result<(), Error> {
+        let packet_structure = self.get_packet_structure(packet_structure_id)?.clone();
+        self.replace_packet_structure(packet_structure_id, None);
+        self.history.record(
+            "Delete packet",
+            Change {
+                packet_structure_id,
+                before: Some(packet_structure),
+                after: None,
+            },
+        );
+
+        self.update_tracked_values();
+        self.emit_update(vec![], vec![packet_structure_id]);
+        Ok(())
+    }
+
+    /// Undoes the newest edit or transaction, and returns its name
+    ///
+    /// returns None if there is nothing to undo, or a transaction is still open
+    pub fn undo(&mut self) -> Option<String> {
+        let transaction = self.history.undo()?.clone();
+        let states = transaction
+            .changes
+            .into_iter()
+            .rev()
+            .map(|change| (change.packet_structure_id, change.before));
+        self.apply_states(states);
+        Some(transaction.name)
+    }
+
+    /// Redoes the newest undone edit or transaction, and returns its name
+    ///
+    /// returns None if there is nothing to redo, or a transaction is still open
+    pub fn redo(&mut self) -> Option<String> {
+        let transaction = self.history.redo()?.clone();
+        let states = transaction
+            .changes
+            .into_iter()
+            .map(|change| (change.packet_structure_id, change.after));
+        self.apply_states(states);
+        Some(transaction.name)
+    }
+
+    /// Forgets every edit, so the structures as they are now can't be undone past. used once a
+    /// set of structures has been loaded, which isn't an edit
+    pub fn clear_history(&mut self) {
+        self.history = EditHistory::default();
+    }
+
+    /// Returns the name of the edit or transaction `undo` would undo
+    pub fn undo_name(&self) -> Option<&str> {
+        self.history.undo_name()
+    }
+
+    /// Returns the name of the edit or transaction `redo` would redo
+    pub fn redo_name(&self) -> Option<&str> {
+        self.history.redo_name()
+    }
+
+    /// Passes the edits another manager made on to this one, given that manager's packet
+    /// structures from before the edits
+    ///
+    /// structures are matched by name, as the two managers may have given them different IDs.
+    /// structures only this manager has, like the ones devices register, are left alone. used
+    /// to bring edits to the config's structures to the manager packets are parsed with, which
+    /// updates the frontend like any other edit
+    pub fn apply_edits_from(
+        &mut self,
+        before: &[PacketStructure],
+        edited: &PacketStructureManager,
     ) -> Result<(), Error> {
-        // TODO: Doesn't seem to work when the current index is a gap or the end of a packet
-        let packet_structure = self.get_packet_structure_mut(packet_structure_id)?;
+        self.transaction("Apply config edits", |packet_structure_manager| {
+            for old in before {
+                if edited.get_packet_structure(old.id).is_ok() {
+                    continue;
+                }
+                if let Some(id) = packet_structure_manager.name_to_id.get(&old.name).copied() {
+                    packet_structure_manager.delete_packet_structure(id)?;
+                }
+            }
+            for new in &edited.packet_structures {
+                let old = before.iter().find(|old| old.id == new.id);
+                if old == Some(new) {
+                    continue;
+                }
+                let name = old.map_or(&new.name, |old| &old.name);
+                match packet_structure_manager.name_to_id.get(name).copied() {
+                    Some(id) => packet_structure_manager
+                        .update_packet_structure(PacketStructure { id, ..new.clone() })?,
+                    None => {
+                        packet_structure_manager.register_packet_structure(&mut new.clone())?;
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
 
-        let minimum_offset = if is_field {
-            packet_structure.fields[component_index].offset_in_packet
-        } else {
-            packet_structure.delimiters[component_index].offset_in_packet
-        };
+    /// Makes several edits as one step called `name`, which is undone and redone as a whole
+    ///
+    /// if any of the edits fail, the ones that were already made are reverted and the error is
+    /// returned. transactions made inside of a transaction become part of it
+    pub fn transaction<T>(
+        &mut self,
+        name: &str,
+        steps: impl FnOnce(&mut Self) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let mark = self.history.begin(name);
+        match steps(self) {
+            Ok(result) => {
+                self.history.commit();
+                Ok(result)
+            }
+            Err(err) => {
+                let states = self
+                    .history
+                    .rollback(mark)
+                    .into_iter()
+                    .map(|change| (change.packet_structure_id, change.before));
+                self.apply_states(states);
+                Err(err)
+            }
+        }
+    }
 
-        Self::shift_components_after(packet_structure, 1, minimum_offset)?;
+    /// Applies an edit to a copy of a packet structure, so a failed edit leaves it untouched,
+    /// then records it as a step called `name` and updates the frontend with the result
+    fn edit_packet_structure<T>(
+        &mut self,
+        packet_structure_id: usize,
+        name: &str,
+        edit: impl FnOnce(&mut PacketStructure) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let before = self.get_packet_structure(packet_structure_id)?.clone();
+        let mut packet_structure = before.clone();
+        let result = edit(&mut packet_structure)?;
+        self.replace_packet_structure(packet_structure_id, Some(packet_structure.clone()));
+        self.history.record(
+            name,
+            Change {
+                packet_structure_id,
+                before: Some(before),
+                after: Some(packet_structure),
+            },
+        );
+
+        self.update_tracked_values();
+        self.emit_update(vec![packet_structure_id], vec![]);
+        Ok(result)
+    }
 
-        return Ok(());
+    /// Puts a packet structure in place of the one with the same ID, adding it if there isn't
+    /// one, or removes it when given None. the name lookups are kept in sync
+    fn replace_packet_structure(
+        &mut self,
+        packet_structure_id: usize,
+        packet_structure: Option<PacketStructure>,
+    ) {
+        let position = self.id_to_index.get(&packet_structure_id).copied();
+        if let Some(old_name) = self.id_to_name.remove(&packet_structure_id) {
+            self.name_to_id.remove(&old_name);
+        }
+        match (position, packet_structure) {
+            (Some(position), Some(packet_structure)) => {
+                self.set_packet_structure_at(position, packet_structure)
+            }
+            (None, Some(packet_structure)) => self.push_packet_structure(packet_structure),
+            (Some(position), None) => {
+                self.remove_packet_structure_at(position);
+                return;
+            }
+            (None, None) => return,
+        }
+        let name = self.packet_structures[position.unwrap_or(self.packet_structures.len() - 1)]
+            .name
+            .clone();
+        self.name_to_id.insert(name.clone(), packet_structure_id);
+        self.id_to_name.insert(packet_structure_id, name);
     }
 
-    /// Updates all of the universal values in the manager
+    /// Puts back the given states of packet structures, in order, and updates the frontend
+    /// with where they ended up
+    fn apply_states(&mut self, states: impl Iterator<Item = (usize, Option<PacketStructure>)>) {
+        let mut ids = vec![];
+        for (packet_structure_id, packet_structure) in states {
+            self.replace_packet_structure(packet_structure_id, packet_structure);
+            if !ids.contains(&packet_structure_id) {
+                ids.push(packet_structure_id);
+            }
+        }
+        self.update_tracked_values();
+
+        let (updated, deleted) = ids
+            .into_iter()
+            .partition(|id| self.get_packet_structure(*id).is_ok());
+        self.emit_update(updated, deleted);
+    }
+
+    /// Tells the frontend about changed packet structures, if the manager is connected to the app
+    fn emit_update(&self, updated_ids: Vec<usize>, deleted_ids: Vec<usize>) {
+        if let Some(app) = &self.app {
+            emit_packet_structure_update_event(
+                app,
+                updated_ids,
+                Some(deleted_ids).filter(|ids| !ids.is_empty()),
+                self,
+            );
+        }
+    }
+
+    fn find_field(packet_structure: &PacketStructure, index: usize) -> Result<&PacketField, Error> {
+        packet_structure
+            .fields
+            .iter()
+            .find(|field| field.index == index)
+            .ok_or(Error::ComponentDoesNotExist(index))
+    }
+
+    fn find_field_mut(
+        packet_structure: &mut PacketStructure,
+        index: usize,
+    ) -> Result<&mut PacketField, Error> {
+        packet_structure
+            .fields
+            .iter_mut()
+            .find(|field| field.index == index)
+            .ok_or(Error::ComponentDoesNotExist(index))
+    }
+
+    /// Returns the bytes taken up by every field, delimiter, and checksum
     ///
-    /// this isn't the most efficient way of doing this since
-    /// not all of these values need to be updated every time the function is called.
-    /// choose to write it this way since its more general and can be reused a lot in the code
-    /// keep in mind that the packet structure manager does not need to be super fast since its not used while the groundstation is running
+    /// arrays take up every byte from their first element to the end of their last
+    fn occupied_spans(packet_structure: &PacketStructure) -> Vec<Range<usize>> {
+        let fields = packet_structure.fields.iter().map(|field| {
+            let last_element = field
+                .array
+                .map_or(0, |array| array.count.saturating_sub(1) * array.stride);
+            let end = field.offset_in_packet + last_element + field.size().unwrap_or(0);
+            field.offset_in_packet..end
+        });
+        let delimiters = packet_structure.delimiters.iter().map(|delimiter| {
+            delimiter.offset_in_packet..delimiter.offset_in_packet + delimiter.identifier.len()
+        });
+        let crcs = packet_structure
+            .packet_crc
+            .iter()
+            .map(|crc| crc.offset_in_packet..crc.offset_in_packet + crc.length);
+        fields.chain(delimiters).chain(crcs).collect()
+    }
+
+    /// Returns the bytes a component takes up, bitfields take up their whole container
     ///
-    /// call this function whenever a packet structures length is changed, or a delimiters location is changed
-    fn update_tracked_values(&mut self) {
-        let mut min_ps = usize::MAX;
-        let mut max_ps = 0;
-        let mut max_delim = 0;
-        for ps in &self.packet_structures {
-            let packet_size = ps.size();
-            min_ps = min(self.minimum_packet_structure_size, packet_size);
-            max_ps = max(self.maximum_packet_structure_size, packet_size);
-
-            // in tests, we can have packets with no delimiters
-            match ps.delimiters.get(0) {
-                Some(delimiter) => {
-                    max_delim = max(self.maximum_first_delimiter, delimiter.offset_in_packet)
+    /// a gap runs from its offset to the start of the next component, or the end of the packet
+    fn component_span(
+        packet_structure: &PacketStructure,
+        component_type: PacketComponentType,
+        component_index: usize,
+    ) -> Result<Range<usize>, Error> {
+        match component_type {
+            PacketComponentType::Field => {
+                let field = Self::find_field(packet_structure, component_index)?;
+                let size = field.size().map_err(|_| Error::GenericError)?;
+                let last_element = field
+                    .array
+                    .map_or(0, |array| array.count.saturating_sub(1) * array.stride);
+                Ok(field.offset_in_packet..field.offset_in_packet + last_element + size)
+            }
+            PacketComponentType::Delimiter => {
+                let delimiter = packet_structure
+                    .delimiters
+                    .get(component_index)
+                    .ok_or(Error::ComponentDoesNotExist(component_index))?;
+                Ok(delimiter.offset_in_packet
+                    ..delimiter.offset_in_packet + delimiter.identifier.len())
+            }
+            PacketComponentType::Gap => {
+                let spans = Self::occupied_spans(packet_structure);
+                if spans.iter().any(|span| span.contains(&component_index)) {
+                    return Err(Error::ComponentDoesNotExist(component_index));
                 }
-                None => {}
+                let gap_end = spans
+                    .iter()
+                    .map(|span| span.start)
+                    .chain(packet_structure.size)
+                    .filter(|offset| *offset > component_index)
+                    .min()
+                    .ok_or(Error::NoComponents)?;
+                Ok(component_index..gap_end)
             }
         }
-        self.minimum_packet_structure_size = min_ps;
-        self.maximum_packet_structure_size = max_ps;
-        self.maximum_first_delimiter = max_delim;
+    }
+
+    /// Finds the offset a position points to, which has to be between components
+    ///
+    /// `moving` is the span of a component that is being moved, which a position can't be inside
+    /// of either but is otherwise ignored
+    fn resolve_position(
+        packet_structure: &PacketStructure,
+        position: InsertPosition,
+        moving: Option<&Range<usize>>,
+    ) -> Result<usize, Error> {
+        let offset = match position {
+            InsertPosition::Offset(offset) => offset,
+            InsertPosition::Before(component_type, component_index) => {
+                Self::component_span(packet_structure, component_type, component_index)?.start
+            }
+            InsertPosition::After(component_type, component_index) => {
+                Self::component_span(packet_structure, component_type, component_index)?.end
+            }
+            InsertPosition::End => packet_structure.size(),
+        };
+        if Self::occupied_spans(packet_structure)
+            .iter()
+            .chain(moving)
+            .any(|span| span.start < offset && offset < span.end)
+        {
+            return Err(Error::OffsetInsideComponent(offset));
+        }
+        Ok(offset)
+    }
+
+    /// Removes a component from the packet structure, and closes up the bytes it took up unless
+    /// another component (ie. a bitfield in the same container) still uses them
+    ///
+    /// returns the component and whether its bytes were closed up
+    fn take_component(
+        packet_structure: &mut PacketStructure,
+        component_type: PacketComponentType,
+        component_index: usize,
+    ) -> Result<(TakenComponent, bool), Error> {
+        let span = Self::component_span(packet_structure, component_type, component_index)?;
+        let component = match component_type {
+            PacketComponentType::Field => {
+                let position = packet_structure
+                    .fields
+                    .iter()
+                    .position(|field| field.index == component_index)
+                    .ok_or(Error::ComponentDoesNotExist(component_index))?;
+                TakenComponent::Field(packet_structure.fields.remove(position))
+            }
+            PacketComponentType::Delimiter => {
+                TakenComponent::Delimiter(packet_structure.delimiters.remove(component_index))
+            }
+            PacketComponentType::Gap => TakenComponent::Gap,
+        };
+        let still_used = Self::occupied_spans(packet_structure)
+            .iter()
+            .any(|other| other.start < span.end && span.start < other.end);
+        if !still_used && !span.is_empty() {
+            Self::shift_components_from(packet_structure, -(span.len() as isize), span.end)?;
+        }
+        Ok((component, !still_used))
+    }
+
+    /// Makes room for `size` bytes at `offset`, declaring the packet longer if there is nothing
+    /// after the gap to shift back
+    fn open_gap(
+        packet_structure: &mut PacketStructure,
+        offset: usize,
+        size: usize,
+    ) -> Result<(), Error> {
+        let end_of_packet = packet_structure.size();
+        Self::shift_components_from(packet_structure, size as isize, offset)?;
+        if offset >= end_of_packet {
+            packet_structure.size = Some(offset + size);
+        }
+        Ok(())
+    }
+
+    /// Makes room for a field at `offset` and adds it in front of the fields after it
+    ///
+    /// returns the field's index once renumbered
+    fn place_field(
+        packet_structure: &mut PacketStructure,
+        mut field: PacketField,
+        offset: usize,
+    ) -> Result<usize, Error> {
+        let size = field.size().map_err(|_| Error::GenericError)?;
+        let last_element = field
+            .array
+            .map_or(0, |array| array.count.saturating_sub(1) * array.stride);
+        Self::shift_components_from(packet_structure, (last_element + size) as isize, offset)?;
+        field.offset_in_packet = offset;
+        let position = packet_structure
+            .fields
+            .iter()
+            .position(|other| other.offset_in_packet > offset)
+            .unwrap_or(packet_structure.fields.len());
+        packet_structure.fields.insert(position, field);
+        Self::renumber_components(packet_structure);
+        Ok(packet_structure.fields[position].index)
+    }
+
+    /// Makes room for a delimiter at `offset` and adds it in front of the delimiters after it
+    ///
+    /// returns the delimiter's index
+    fn place_delimiter(
+        packet_structure: &mut PacketStructure,
+        mut delimiter: PacketDelimiter,
+        offset: usize,
+    ) -> Result<usize, Error> {
+        Self::shift_components_from(
+            packet_structure,
+            delimiter.identifier.len() as isize,
+            offset,
+        )?;
+        delimiter.offset_in_packet = offset;
+        let position = packet_structure
+            .delimiters
+            .iter()
+            .position(|other| other.offset_in_packet > offset)
+            .unwrap_or(packet_structure.delimiters.len());
+        packet_structure.delimiters.insert(position, delimiter);
+        Self::renumber_components(packet_structure);
+        Ok(position)
+    }
+
+    /// Gives fields and delimiters the indexes of where they are in the packet structure
+    ///
+    /// each element of an array field takes up an index, and references to a field's index
+    /// (array counts and the packet length) follow the field to its new index
+    fn renumber_components(packet_structure: &mut PacketStructure) {
+        let mut new_indexes = BTreeMap::new();
+        for mut field in std::mem::take(&mut packet_structure.fields) {
+            let next_index = packet_structure.next_field_index(&field);
+            // new fields don't have an index yet
+            if field.index != usize::MAX {
+                new_indexes.insert(field.index, next_index);
+            }
+            field.index = next_index;
+            packet_structure.fields.push(field);
+        }
+        for field in &mut packet_structure.fields {
+            if let Some(array) = &mut field.array {
+                array.count_field = array
+                    .count_field
+                    .map(|index| *new_indexes.get(&index).unwrap_or(&index));
+            }
+        }
+        if let Some(length_field) = &mut packet_structure.length_field {
+            length_field.field_index = *new_indexes
+                .get(&length_field.field_index)
+                .unwrap_or(&length_field.field_index);
+        }
+
+        for (index, delimiter) in packet_structure.delimiters.iter_mut().enumerate() {
+            delimiter.index = index;
+        }
+    }
+
+    /// Updates all of the universal values in the manager from the layouts of the searchable
+    /// packet structures
+    ///
+    /// call this function whenever packet structures are added, removed or changed
+    fn update_tracked_values(&mut self) {
+        let counts = &self.layout_counts;
+        self.minimum_packet_structure_size = counts
+            .min_sizes
+            .keys()
+            .next()
+            .copied()
+            .unwrap_or(usize::MAX);
+        self.maximum_packet_structure_size =
+            counts.max_sizes.keys().next_back().copied().unwrap_or(0);
+        self.maximum_first_delimiter = counts
+            .first_delimiter_offsets
+            .keys()
+            .next_back()
+            .copied()
+            .unwrap_or(0);
+        self.revision = NEXT_REVISION.fetch_add(1, Ordering::Relaxed);
     }
 }
 
+/// A component that has been taken out of a packet structure to be put back somewhere else
+enum TakenComponent {
+    Field(PacketField),
+    Delimiter(PacketDelimiter),
+    Gap,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*; // lets the unit tests use everything in this file
+    use crate::models::{packet::PacketFieldValue, structure_dsl::parse_structure};
 
     #[test]
     fn get_unknown_packet() {
@@ -670,17 +1698,124 @@ mod tests {
             .unwrap();
 
         packet_structure_manager
-            .set_field_name(id, 0, "name")
+            .set_field_name(id, 0, "name")
+            .unwrap();
+
+        assert_eq!(
+            packet_structure_manager
+                .get_packet_structure(id)
+                .unwrap()
+                .fields[0]
+                .name,
+            "name"
+        )
+    }
+
+    /// structures merged into one with the same name are checked the same as new ones
+    #[test]
+    fn merged_structures_are_validated() {
+        let mut packet_structure_manager = PacketStructureManager::default();
+        let id = packet_structure_manager
+            .register_packet_structure(&mut parse_structure("test", "0xab a: u8").unwrap())
+            .unwrap();
+        let result = packet_structure_manager
+            .register_packet_structure(&mut parse_structure("test", "0xab b: u8").unwrap());
+        assert!(
+            matches!(result, Err(Error::InvalidLayout(errors)) if errors[0].structure_id == id)
+        );
+        assert_eq!(
+            packet_structure_manager
+                .get_packet_structure(id)
+                .unwrap()
+                .fields
+                .len(),
+            1
+        );
+
+        // the merge is let through once it fits
+        packet_structure_manager
+            .register_packet_structure(&mut parse_structure("test", "0xab _1 b: u8").unwrap())
+            .unwrap();
+        assert_eq!(
+            packet_structure_manager
+                .get_packet_structure(id)
+                .unwrap()
+                .fields
+                .len(),
+            2
+        );
+    }
+
+    /// fields are edited by their index, which arrays put out of step with their position
+    #[test]
+    fn fields_are_edited_by_index() {
+        let mut packet_structure_manager = PacketStructureManager::default();
+        let id = packet_structure_manager
+            .register_packet_structure(
+                &mut parse_structure(
+                    "test",
+                    "0xab acc: u8[3] state: u8 flags: u8:3 armed: bool:1",
+                )
+                .unwrap(),
+            )
+            .unwrap();
+
+        packet_structure_manager
+            .set_field_name(id, 3, "flight state")
+            .unwrap();
+        packet_structure_manager
+            .set_field_type(id, 3, PacketFieldType::UnsignedShort)
+            .unwrap();
+        let fields = &packet_structure_manager
+            .get_packet_structure(id)
+            .unwrap()
+            .fields;
+        assert_eq!(fields[0].name, "acc");
+        assert_eq!(fields[1].name, "flight state");
+        assert_eq!(fields[1].r#type, PacketFieldType::UnsignedShort);
+
+        assert_eq!(
+            packet_structure_manager.set_field_name(id, 1, "acc_y"),
+            Err(Error::ComponentDoesNotExist(1))
+        );
+        assert_eq!(
+            packet_structure_manager.set_field_type(id, 9, PacketFieldType::UnsignedByte),
+            Err(Error::ComponentDoesNotExist(9))
+        );
+        assert_eq!(
+            packet_structure_manager.set_field_type(id, 4, PacketFieldType::UnsignedShort),
+            Err(Error::FieldInBitfield(4))
+        );
+    }
+
+    //tests that a field changed to 24 bits reads big-endian, like 24 bit fields always did
+    #[test]
+    fn retyped_24_bit_fields_keep_their_byte_order() {
+        let mut packet_structure_manager = PacketStructureManager::default();
+        let id = packet_structure_manager
+            .register_packet_structure(&mut parse_structure("test", "0xab value: u8").unwrap())
+            .unwrap();
+        packet_structure_manager
+            .set_field_type(id, 0, PacketFieldType::UnsignedTwoFour)
+            .unwrap();
+        let field = &packet_structure_manager
+            .get_packet_structure(id)
+            .unwrap()
+            .fields[0];
+        assert_eq!(field.endianness, Endianness::Big);
+        assert_eq!(
+            field.parse_from_packet(&[0xab, 0x01, 0x02, 0x03]).unwrap(),
+            PacketFieldValue::Number(0x010203 as f64)
+        );
+
+        packet_structure_manager
+            .set_field_type(id, 0, PacketFieldType::UnsignedShort)
             .unwrap();
-
-        assert_eq!(
-            packet_structure_manager
-                .get_packet_structure(id)
-                .unwrap()
-                .fields[0]
-                .name,
-            "name"
-        )
+        let field = &packet_structure_manager
+            .get_packet_structure(id)
+            .unwrap()
+            .fields[0];
+        assert_eq!(field.endianness, Endianness::Little);
     }
 
     #[test]
@@ -699,12 +1834,32 @@ mod tests {
                     name: String::from("name"),
                     r#type: field_type,
                     offset_in_packet: 0,
+                    endianness: Endianness::Little,
+                    bitfield: None,
+                    string_format: None,
+                    array: None,
+                    calibration: None,
+                    unit: None,
+                    labels: BTreeMap::new(),
+                    timestamp: None,
+                    sequence: None,
+                    element_names: vec![],
                 };
                 let packet_field2 = PacketField {
                     index: 1,
                     name: String::from("name2"),
                     r#type: field_type,
                     offset_in_packet: field_type.size().expect("wrong type"),
+                    endianness: Endianness::Little,
+                    bitfield: None,
+                    string_format: None,
+                    array: None,
+                    calibration: None,
+                    unit: None,
+                    labels: BTreeMap::new(),
+                    timestamp: None,
+                    sequence: None,
+                    element_names: vec![],
                 };
 
                 // create a test packet
@@ -741,6 +1896,40 @@ mod tests {
         }
     }
 
+    #[test]
+    fn set_field_type_to_string() {
+        let mut packet_structure_manager = PacketStructureManager::default();
+        let mut packet_structure = PacketStructure::make_default(String::from("First Name"));
+        packet_structure.ez_make("u32 u8", &["name", "name2"], false);
+        let id = packet_structure_manager
+            .register_packet_structure(&mut packet_structure)
+            .unwrap();
+
+        packet_structure_manager
+            .set_field_type(id, 0, PacketFieldType::String)
+            .unwrap();
+        let fields = &packet_structure_manager
+            .get_packet_structure(id)
+            .unwrap()
+            .fields;
+        // the string takes over the bytes the u32 used to have
+        assert_eq!(
+            fields[0].string_format,
+            Some(StringFormat::Fixed { length: 4 })
+        );
+        assert_eq!(fields[1].offset_in_packet, 4);
+
+        packet_structure_manager
+            .set_field_type(id, 0, PacketFieldType::UnsignedShort)
+            .unwrap();
+        let fields = &packet_structure_manager
+            .get_packet_structure(id)
+            .unwrap()
+            .fields;
+        assert_eq!(fields[0].string_format, None);
+        assert_eq!(fields[1].offset_in_packet, 2);
+    }
+
     #[test]
     fn test_set_field_metadata_type() {
         let packet_field_type = PacketFieldType::Double;
@@ -750,6 +1939,16 @@ mod tests {
             name: String::from("name"),
             r#type: packet_field_type,
             offset_in_packet: 0,
+            endianness: Endianness::Little,
+            bitfield: None,
+            string_format: None,
+            array: None,
+            calibration: None,
+            unit: None,
+            labels: BTreeMap::new(),
+            timestamp: None,
+            sequence: None,
+            element_names: vec![],
         };
 
         let mut packet_structure_manager = PacketStructureManager::default();
@@ -765,6 +1964,46 @@ mod tests {
         packet_structure_manager
             .set_field_metadata_type(id, 0, packet_metadata_type2)
             .unwrap();
+        let fields = &packet_structure_manager
+            .get_packet_structure(id)
+            .unwrap()
+            .fields;
+        assert_eq!(
+            fields[0].timestamp,
+            Some(TimestampFormat {
+                ticks_per_second: 1.0,
+                wrap_bits: None
+            })
+        );
+
+        packet_structure_manager
+            .set_field_metadata_type(id, 0, PacketMetadataType::None)
+            .unwrap();
+        let fields = &packet_structure_manager
+            .get_packet_structure(id)
+            .unwrap()
+            .fields;
+        assert_eq!(fields[0].timestamp, None);
+
+        packet_structure_manager
+            .set_field_metadata_type(id, 0, PacketMetadataType::Sequence)
+            .unwrap();
+        let fields = &packet_structure_manager
+            .get_packet_structure(id)
+            .unwrap()
+            .fields;
+        assert_eq!(
+            fields[0].sequence,
+            Some(SequenceFormat {
+                wrap_bits: 64,
+                step: 1
+            })
+        );
+
+        assert_eq!(
+            packet_structure_manager.set_field_metadata_type(id, 3, PacketMetadataType::Timestamp),
+            Err(Error::ComponentDoesNotExist(3))
+        );
     }
 
     #[test]
@@ -954,6 +2193,16 @@ mod tests {
                         name: String::from("Field 1"),
                         r#type: PacketFieldType::SignedByte,
                         offset_in_packet: 1,
+                        endianness: Endianness::Little,
+                        bitfield: None,
+                        string_format: None,
+                        array: None,
+                        calibration: None,
+                        unit: None,
+                        labels: BTreeMap::new(),
+                        timestamp: None,
+                        sequence: None,
+                        element_names: vec![],
                     },
                     // 2 byte gap at offset 2
                     PacketField {
@@ -961,6 +2210,16 @@ mod tests {
                         name: String::from("Field 2"),
                         r#type: PacketFieldType::SignedByte,
                         offset_in_packet: 4,
+                        endianness: Endianness::Little,
+                        bitfield: None,
+                        string_format: None,
+                        array: None,
+                        calibration: None,
+                        unit: None,
+                        labels: BTreeMap::new(),
+                        timestamp: None,
+                        sequence: None,
+                        element_names: vec![],
                     },
                 ],
                 vec![
@@ -995,14 +2254,34 @@ mod tests {
                     index: 0,
                     name: String::from("Field 1"),
                     r#type: PacketFieldType::SignedByte,
-                    offset_in_packet: 2
+                    offset_in_packet: 2,
+                    endianness: Endianness::Little,
+                    bitfield: None,
+                    string_format: None,
+                    array: None,
+                    calibration: None,
+                    unit: None,
+                    labels: BTreeMap::new(),
+                    timestamp: None,
+                    sequence: None,
+                    element_names: vec![],
                 },
                 // 2 byte gap at offset 4
                 PacketField {
                     index: 1,
                     name: String::from("Field 2"),
                     r#type: PacketFieldType::SignedByte,
-                    offset_in_packet: 5
+                    offset_in_packet: 5,
+                    endianness: Endianness::Little,
+                    bitfield: None,
+                    string_format: None,
+                    array: None,
+                    calibration: None,
+                    unit: None,
+                    labels: BTreeMap::new(),
+                    timestamp: None,
+                    sequence: None,
+                    element_names: vec![],
                 }
             ]
         );
@@ -1042,14 +2321,34 @@ mod tests {
                     index: 0,
                     name: String::from("Field 1"),
                     r#type: PacketFieldType::SignedByte,
-                    offset_in_packet: 2
+                    offset_in_packet: 2,
+                    endianness: Endianness::Little,
+                    bitfield: None,
+                    string_format: None,
+                    array: None,
+                    calibration: None,
+                    unit: None,
+                    labels: BTreeMap::new(),
+                    timestamp: None,
+                    sequence: None,
+                    element_names: vec![],
                 },
                 // 2 byte gap at offset 4
                 PacketField {
                     index: 1,
                     name: String::from("Field 2"),
                     r#type: PacketFieldType::SignedByte,
-                    offset_in_packet: 5
+                    offset_in_packet: 5,
+                    endianness: Endianness::Little,
+                    bitfield: None,
+                    string_format: None,
+                    array: None,
+                    calibration: None,
+                    unit: None,
+                    labels: BTreeMap::new(),
+                    timestamp: None,
+                    sequence: None,
+                    element_names: vec![],
                 }
             ]
         );
@@ -1129,18 +2428,529 @@ mod tests {
         )
     }
 
-    // #[test]
-    // fn test_add_gap_after() {
+    /// registers a structure made with `ez_make`, and returns the manager and its id
+    fn ez_manager(input: &str) -> (PacketStructureManager, usize) {
+        let mut packet_structure_manager = PacketStructureManager::default();
+        let mut packet_structure = PacketStructure::make_default(String::from("First Name"));
+        packet_structure.ez_make(input, &["test field"; 8], false);
+        let id = packet_structure_manager
+            .register_packet_structure(&mut packet_structure)
+            .unwrap();
+        packet_structure_manager.clear_history();
+        (packet_structure_manager, id)
+    }
+
+    /// returns the (index, offset) of every field
+    fn field_layout(
+        packet_structure_manager: &PacketStructureManager,
+        id: usize,
+    ) -> Vec<(usize, usize)> {
+        packet_structure_manager
+            .get_packet_structure(id)
+            .unwrap()
+            .fields
+            .iter()
+            .map(|field| (field.index, field.offset_in_packet))
+            .collect()
+    }
+
+    #[test]
+    fn test_add_gap_after() {
+        let (mut packet_structure_manager, id) = ez_manager("ff u8 u16");
+
+        packet_structure_manager.add_gap_after(id, true, 0).unwrap();
+        assert_eq!(
+            field_layout(&packet_structure_manager, id),
+            vec![(0, 1), (1, 3)]
+        );
+
+        // gaps can go after gaps, and at the end of the packet
+        packet_structure_manager
+            .insert_gap(id, InsertPosition::After(PacketComponentType::Gap, 2), 2)
+            .unwrap();
+        packet_structure_manager.add_gap_after(id, true, 1).unwrap();
+        let packet_structure = packet_structure_manager.get_packet_structure(id).unwrap();
+        assert_eq!(packet_structure.fields[1].offset_in_packet, 5);
+        assert_eq!(packet_structure.size(), 8);
+    }
+
+    #[test]
+    fn test_insert_field() {
+        let (mut packet_structure_manager, id) = ez_manager("ff u8 u16");
+
+        let index = packet_structure_manager
+            .insert_field(id, InsertPosition::Before(PacketComponentType::Field, 1))
+            .unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(
+            field_layout(&packet_structure_manager, id),
+            vec![(0, 1), (1, 2), (2, 6)]
+        );
+
+        // the delimiter and the u8 can't be split up
+        assert_eq!(
+            packet_structure_manager.insert_field(id, InsertPosition::Offset(3)),
+            Err(Error::OffsetInsideComponent(3))
+        );
+        assert_eq!(
+            packet_structure_manager.insert_delimiter(id, InsertPosition::Offset(0)),
+            Ok(0)
+        );
+        assert_eq!(
+            field_layout(&packet_structure_manager, id),
+            vec![(0, 2), (1, 3), (2, 7)]
+        );
+    }
+
+    /// the members of a group stay numbered element by element when the fields are renumbered
+    #[test]
+    fn test_renumber_groups() {
+        let (mut packet_structure_manager, id) = ez_manager("ff u8 u8 {u8,u8}[3] u16");
+        packet_structure_manager
+            .delete_packet_structure_component(id, PacketComponentType::Field, 0)
+            .unwrap();
+        let indexes: Vec<usize> = field_layout(&packet_structure_manager, id)
+            .into_iter()
+            .map(|(index, _)| index)
+            .collect();
+        assert_eq!(indexes, vec![0, 1, 2, 7]);
+        let packet_structure = packet_structure_manager.get_packet_structure(id).unwrap();
+        let element_indexes: Vec<usize> = packet_structure.fields[2]
+            .elements()
+            .iter()
+            .map(|element| element.index)
+            .collect();
+        assert_eq!(element_indexes, vec![2, 4, 6]);
+    }
+
+    #[test]
+    fn test_delete_packet_structure_component() {
+        let (mut packet_structure_manager, id) = ez_manager("ff u8 u8[4@0] u32");
+
+        assert_eq!(
+            packet_structure_manager.delete_packet_structure_component(
+                id,
+                PacketComponentType::Field,
+                0
+            ),
+            Err(Error::FieldInUse(0))
+        );
+
+        packet_structure_manager
+            .delete_packet_structure_component(id, PacketComponentType::Field, 1)
+            .unwrap();
+        assert_eq!(
+            field_layout(&packet_structure_manager, id),
+            vec![(0, 1), (1, 4)]
+        );
+
+        // the u32 was aligned, so a gap was left before it
+        packet_structure_manager
+            .delete_packet_structure_component(id, PacketComponentType::Gap, 2)
+            .unwrap();
+        assert_eq!(
+            field_layout(&packet_structure_manager, id),
+            vec![(0, 1), (1, 2)]
+        );
+
+        assert_eq!(
+            packet_structure_manager.delete_packet_structure_component(
+                id,
+                PacketComponentType::Delimiter,
+                0
+            ),
+            Err(Error::CannotDeleteLastDelimiter)
+        );
+
+        packet_structure_manager
+            .delete_packet_structure_component(id, PacketComponentType::Field, 0)
+            .unwrap();
+        assert_eq!(
+            packet_structure_manager.delete_packet_structure_component(
+                id,
+                PacketComponentType::Field,
+                0
+            ),
+            Err(Error::CannotDeleteLastField)
+        );
+        assert_eq!(
+            packet_structure_manager
+                .get_packet_structure(id)
+                .unwrap()
+                .size(),
+            5
+        );
+    }
+
+    #[test]
+    fn test_move_and_duplicate_components() {
+        let (mut packet_structure_manager, id) = ez_manager("ff u8 u8[4@0] u8");
+
+        // the array's count follows the field it is read from
+        let index = packet_structure_manager
+            .move_packet_structure_component(id, PacketComponentType::Field, 0, InsertPosition::End)
+            .unwrap();
+        assert_eq!(index, 5);
+        assert_eq!(
+            field_layout(&packet_structure_manager, id),
+            vec![(0, 1), (4, 5), (5, 6)]
+        );
+        let packet_structure = packet_structure_manager.get_packet_structure(id).unwrap();
+        assert_eq!(
+            packet_structure.fields[0].array.unwrap().count_field,
+            Some(5)
+        );
+
+        let index = packet_structure_manager
+            .duplicate_packet_structure_component(id, PacketComponentType::Delimiter, 0)
+            .unwrap();
+        assert_eq!(index, 1);
+        let index = packet_structure_manager
+            .duplicate_packet_structure_component(id, PacketComponentType::Field, 4)
+            .unwrap();
+        assert_eq!(index, 5);
+        let packet_structure = packet_structure_manager.get_packet_structure(id).unwrap();
+        assert_eq!(packet_structure.delimiters[1].offset_in_packet, 1);
+        assert_eq!(packet_structure.fields[2].name, "test field copy");
+        assert_eq!(
+            field_layout(&packet_structure_manager, id),
+            vec![(0, 2), (4, 6), (5, 7), (6, 8)]
+        );
+    }
+
+    #[test]
+    fn test_delete_packet_structure() {
+        let (mut packet_structure_manager, id) = ez_manager("ff u8");
+
+        packet_structure_manager
+            .delete_packet_structure(id)
+            .unwrap();
+        assert_eq!(
+            packet_structure_manager.get_packet_structure(id),
+            Err(Error::PacketDoesNotExist(id))
+        );
+        assert_eq!(
+            packet_structure_manager.delete_packet_structure(id),
+            Err(Error::PacketDoesNotExist(id))
+        );
+        assert!(packet_structure_manager.name_to_id.is_empty());
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let (mut packet_structure_manager, id) = ez_manager("ff u8 u8");
+        let original = packet_structure_manager
+            .get_packet_structure(id)
+            .unwrap()
+            .clone();
+
+        packet_structure_manager
+            .set_field_type(id, 0, PacketFieldType::UnsignedShort)
+            .unwrap();
+        packet_structure_manager
+            .set_delimiter_identifier(id, 0, "abcd")
+            .unwrap();
+        assert_eq!(
+            field_layout(&packet_structure_manager, id),
+            vec![(0, 2), (1, 4)]
+        );
+
+        assert_eq!(
+            packet_structure_manager.undo_name(),
+            Some("Set delimiter identifier")
+        );
+        assert_eq!(
+            packet_structure_manager.undo().as_deref(),
+            Some("Set delimiter identifier")
+        );
+        assert_eq!(
+            field_layout(&packet_structure_manager, id),
+            vec![(0, 1), (1, 3)]
+        );
+        packet_structure_manager.undo().unwrap();
+        assert_eq!(
+            packet_structure_manager.get_packet_structure(id),
+            Ok(&original)
+        );
+        assert_eq!(packet_structure_manager.undo(), None);
+
+        assert_eq!(
+            packet_structure_manager.redo().as_deref(),
+            Some("Set field type")
+        );
+        assert_eq!(
+            field_layout(&packet_structure_manager, id),
+            vec![(0, 1), (1, 3)]
+        );
+
+        // a new edit can't be redone past
+        packet_structure_manager.add_field(id).unwrap();
+        assert_eq!(packet_structure_manager.redo(), None);
+    }
+
+    #[test]
+    fn test_transactions() {
+        let (mut packet_structure_manager, id) = ez_manager("ff u8 u8");
+        let original = packet_structure_manager
+            .get_packet_structure(id)
+            .unwrap()
+            .clone();
+
+        packet_structure_manager
+            .transaction("Add header", |packet_structure_manager| {
+                packet_structure_manager.insert_delimiter(id, InsertPosition::Offset(0))?;
+                packet_structure_manager.set_delimiter_identifier(id, 0, "cafe")?;
+                packet_structure_manager.set_packet_name(id, "Second Name")
+            })
+            .unwrap();
+        assert_eq!(
+            field_layout(&packet_structure_manager, id),
+            vec![(0, 3), (1, 4)]
+        );
+
+        assert_eq!(
+            packet_structure_manager.undo().as_deref(),
+            Some("Add header")
+        );
+        assert_eq!(
+            packet_structure_manager.get_packet_structure(id),
+            Ok(&original)
+        );
+        assert_eq!(
+            packet_structure_manager.name_to_id.get("First Name"),
+            Some(&id)
+        );
+        assert_eq!(packet_structure_manager.name_to_id.get("Second Name"), None);
+
+        // the steps made before one fails are taken back
+        assert_eq!(
+            packet_structure_manager.transaction("Broken", |packet_structure_manager| {
+                packet_structure_manager.add_field(id)?;
+                packet_structure_manager.set_delimiter_identifier(id, 0, "zz")
+            }),
+            Err(Error::InvalidHexCharacter('z'))
+        );
+        assert_eq!(
+            packet_structure_manager.get_packet_structure(id),
+            Ok(&original)
+        );
+        assert_eq!(packet_structure_manager.undo_name(), None);
+        assert_eq!(packet_structure_manager.redo_name(), Some("Add header"));
+    }
+
+    /// registering and merging are undone like edits, without undoing the edits around them
+    #[test]
+    fn test_undo_register() {
+        let mut packet_structure_manager = PacketStructureManager::default();
+        let id = packet_structure_manager
+            .register_packet_structure(&mut parse_structure("test", "0xab a: u8").unwrap())
+            .unwrap();
+        packet_structure_manager.set_field_name(id, 0, "b").unwrap();
+        packet_structure_manager
+            .register_packet_structure(&mut parse_structure("test", "0xab _1 c: u8").unwrap())
+            .unwrap();
+        let field_names = |packet_structure_manager: &PacketStructureManager| {
+            packet_structure_manager
+                .get_packet_structure(id)
+                .map(|packet_structure| {
+                    packet_structure
+                        .fields
+                        .iter()
+                        .map(|field| field.name.clone())
+                        .collect::<Vec<_>>()
+                })
+        };
+        assert_eq!(
+            field_names(&packet_structure_manager),
+            Ok(vec!["b".into(), "c".into()])
+        );
+
+        assert_eq!(
+            packet_structure_manager.undo().as_deref(),
+            Some("Merge packet")
+        );
+        assert_eq!(field_names(&packet_structure_manager), Ok(vec!["b".into()]));
+        assert_eq!(
+            packet_structure_manager.undo().as_deref(),
+            Some("Rename field")
+        );
+        assert_eq!(field_names(&packet_structure_manager), Ok(vec!["a".into()]));
+        assert_eq!(
+            packet_structure_manager.undo().as_deref(),
+            Some("Add packet")
+        );
+        assert_eq!(
+            field_names(&packet_structure_manager),
+            Err(Error::PacketDoesNotExist(id))
+        );
+        assert_eq!(packet_structure_manager.name_to_id.get("test"), None);
+
+        packet_structure_manager.redo().unwrap();
+        packet_structure_manager.redo().unwrap();
+        assert_eq!(field_names(&packet_structure_manager), Ok(vec!["b".into()]));
+
+        // a device learning a new layout is an edit too
+        let mut updated = packet_structure_manager
+            .get_packet_structure(id)
+            .unwrap()
+            .clone();
+        updated.fields[0].name = "d".to_owned();
+        packet_structure_manager
+            .update_packet_structure(updated)
+            .unwrap();
+        assert_eq!(
+            packet_structure_manager.undo().as_deref(),
+            Some("Update packet")
+        );
+        assert_eq!(field_names(&packet_structure_manager), Ok(vec!["b".into()]));
+
+        packet_structure_manager.clear_history();
+        assert_eq!(packet_structure_manager.undo(), None);
+    }
+
+    /// edits to the config's structures reach the manager packets are parsed with, even when
+    /// it has structures of its own that gave them different IDs
+    #[test]
+    fn test_apply_edits_from() {
+        let mut config_manager = PacketStructureManager::default();
+        config_manager
+            .register_packet_structure(&mut parse_structure("kept", "0xab a: u8").unwrap())
+            .unwrap();
+        let mut live_manager = config_manager.clone();
+        let device_id = live_manager
+            .register_packet_structure(&mut parse_structure("device", "0xcd b: u8").unwrap())
+            .unwrap();
+
+        let apply = |live_manager: &mut PacketStructureManager,
+                     config_manager: &mut PacketStructureManager,
+                     edit: &dyn Fn(&mut PacketStructureManager)| {
+            let before = config_manager.packet_structures.clone();
+            edit(config_manager);
+            let revision = live_manager.revision;
+            live_manager
+                .apply_edits_from(&before, config_manager)
+                .unwrap();
+            assert_ne!(live_manager.revision, revision);
+        };
+        apply(&mut live_manager, &mut config_manager, &|config_manager| {
+            config_manager
+                .register_packet_structure(&mut parse_structure("imported", "0xef c: u8").unwrap())
+                .unwrap();
+        });
+        let imported_id = live_manager.name_to_id["imported"];
+        assert_ne!(imported_id, config_manager.name_to_id["imported"]);
+        assert_eq!(
+            live_manager
+                .get_packet_structure(imported_id)
+                .unwrap()
+                .delimiters[0]
+                .identifier,
+            vec![0xef]
+        );
+
+        apply(&mut live_manager, &mut config_manager, &|config_manager| {
+            let id = config_manager.name_to_id["kept"];
+            config_manager.set_field_name(id, 0, "b").unwrap();
+        });
+        let kept_id = live_manager.name_to_id["kept"];
+        assert_eq!(
+            live_manager.get_packet_structure(kept_id).unwrap().fields[0].name,
+            "b"
+        );
+
+        apply(&mut live_manager, &mut config_manager, &|config_manager| {
+            assert_eq!(config_manager.undo().as_deref(), Some("Rename field"));
+        });
+        assert_eq!(
+            live_manager.get_packet_structure(kept_id).unwrap().fields[0].name,
+            "a"
+        );
+        apply(&mut live_manager, &mut config_manager, &|config_manager| {
+            assert_eq!(config_manager.undo().as_deref(), Some("Add packet"));
+        });
+        assert_eq!(live_manager.name_to_id.get("imported"), None);
+        assert!(live_manager.get_packet_structure(imported_id).is_err());
+        assert!(live_manager.get_packet_structure(device_id).is_ok());
+
+        apply(&mut live_manager, &mut config_manager, &|config_manager| {
+            config_manager.redo().unwrap();
+        });
+        assert!(live_manager.name_to_id.contains_key("imported"));
+    }
+
+    #[test]
+    fn test_undo_delete_packet_structure() {
+        let (mut packet_structure_manager, id) = ez_manager("ff u8");
+        let original = packet_structure_manager
+            .get_packet_structure(id)
+            .unwrap()
+            .clone();
+
+        packet_structure_manager
+            .delete_packet_structure(id)
+            .unwrap();
+        packet_structure_manager.undo().unwrap();
+        assert_eq!(
+            packet_structure_manager.get_packet_structure(id),
+            Ok(&original)
+        );
+        assert_eq!(
+            packet_structure_manager.name_to_id.get("First Name"),
+            Some(&id)
+        );
 
-    // }
+        packet_structure_manager.redo().unwrap();
+        assert!(packet_structure_manager.get_packet_structure(id).is_err());
+    }
 
-    // #[test]
-    // fn test_delete_packet_structure_component() {
+    #[test]
+    fn test_tracked_values() {
+        let mut packet_structure_manager = PacketStructureManager::default();
+        let mut ids = vec![];
+        for (name, input) in [
+            ("short", "ff u8"),
+            ("long", "ff u32 u32 u32"),
+            ("late delimiter", "u16 ee u8"),
+        ] {
+            let mut packet_structure = PacketStructure::make_default(name.to_owned());
+            packet_structure.ez_make(input, &["test field"; 8], false);
+            ids.push(
+                packet_structure_manager
+                    .register_packet_structure(&mut packet_structure)
+                    .unwrap(),
+            );
+        }
+        // structures that can't be found in raw data don't count
+        packet_structure_manager.get_packet_structure_by_name("not byte defined");
 
-    // }
+        assert_eq!(packet_structure_manager.minimum_packet_structure_size, 2);
+        assert_eq!(packet_structure_manager.maximum_packet_structure_size, 16);
+        assert_eq!(packet_structure_manager.maximum_first_delimiter, 2);
 
-    // #[test]
-    // fn test_delete_packet_structure() {
+        packet_structure_manager
+            .delete_packet_structure(ids[0])
+            .unwrap();
+        packet_structure_manager
+            .delete_packet_structure(ids[2])
+            .unwrap();
+        assert_eq!(packet_structure_manager.minimum_packet_structure_size, 16);
+        assert_eq!(packet_structure_manager.maximum_first_delimiter, 0);
+        assert_eq!(
+            packet_structure_manager
+                .get_packet_layout(ids[1])
+                .unwrap()
+                .size,
+            16
+        );
 
-    // }
+        packet_structure_manager.undo().unwrap();
+        assert_eq!(packet_structure_manager.maximum_first_delimiter, 2);
+        assert_eq!(
+            packet_structure_manager
+                .get_packet_structure(ids[2])
+                .unwrap()
+                .name,
+            "late delimiter"
+        );
+    }
 }
@@ -0,0 +1,596 @@
+//! # Packet Structure Validation
+//!
+//! Checks packet structures for layouts the parsers can't handle, and reports what it finds as
+//! `Diagnostic`s that point at the structure, component and bytes involved.
+//!
+//! Errors are layouts that can't be parsed correctly:
+//! - fields, delimiters and checksums that share bytes. bitfields may share a container with other
+//!   bitfields of the same container type as long as their bits don't overlap
+//! - components that run past a declared `size`
+//! - strings without a format, arrays without elements
+//!
+//! Warnings are layouts that parse, but may not parse the way they were meant to:
+//! - empty delimiters, and byte defined structures without a delimiter to find them by
+//! - structures that the same bytes could be read as, see `find_ambiguities`
+
+use std::{borrow::Borrow, cmp::max, ops::Range};
+
+use serde::Serialize;
+
+use crate::{
+    models::packet_structure::{PacketField, PacketStructure},
+    packet_structure_manager::PacketLayout,
+};
+
+/// How serious a `Diagnostic` is
+#[derive(PartialEq, Eq, Serialize, Clone, Copy, Debug)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// The kind of component a `Diagnostic` points at
+#[derive(PartialEq, Eq, Serialize, Clone, Copy, Debug)]
+pub enum ComponentKind {
+    Field,
+    Delimiter,
+    Crc,
+}
+
+/// A component of a packet structure, `index` is its index in the structure's list of that kind
+#[derive(PartialEq, Eq, Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Component {
+    pub(crate) kind: ComponentKind,
+    pub(crate) index: usize,
+}
+
+/// A problem found in a packet structure
+///
+/// `component` and `byte_range` are left out for problems with the structure as a whole
+#[derive(PartialEq, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostic {
+    pub(crate) severity: Severity,
+    pub(crate) structure_id: usize,
+    pub(crate) component: Option<Component>,
+    pub(crate) byte_range: Option<Range<usize>>,
+    pub(crate) message: String,
+}
+
+impl Diagnostic {
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+/// The bytes one component (or one element of an array field) takes up
+struct Span {
+    component: Component,
+    name: String,
+    bytes: Range<usize>,
+    /// (container size, bit offset, bit width) of bitfields
+    bits: Option<(usize, usize, usize)>,
+}
+
+impl Span {
+    /// true if the two spans use the same bytes, bitfields in the same container only overlap if
+    /// their bits do
+    fn overlaps(&self, other: &Span) -> bool {
+        if self.bytes.start >= other.bytes.end || other.bytes.start >= self.bytes.end {
+            return false;
+        }
+        match (self.bits, other.bits) {
+            (Some((size, offset, width)), Some((other_size, other_offset, other_width)))
+                if self.bytes.start == other.bytes.start && size == other_size =>
+            {
+                offset < other_offset + other_width && other_offset < offset + width
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Returns every problem with the layout of one packet structure
+pub fn validate_structure(structure: &PacketStructure) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    let error =
+        |component: Option<Component>, bytes: Option<Range<usize>>, message: String| Diagnostic {
+            severity: Severity::Error,
+            structure_id: structure.id,
+            component,
+            byte_range: bytes,
+            message,
+        };
+    let warning = |component, bytes, message| Diagnostic {
+        severity: Severity::Warning,
+        ..error(component, bytes, message)
+    };
+
+    let mut spans = vec![];
+    for (index, delimiter) in structure.delimiters.iter().enumerate() {
+        let component = Component {
+            kind: ComponentKind::Delimiter,
+            index,
+        };
+        let start = delimiter.offset_in_packet;
+        if delimiter.identifier.is_empty() {
+            diagnostics.push(warning(
+                Some(component),
+                Some(start..start),
+                format!("Delimiter {} is empty", delimiter.name),
+            ));
+            continue;
+        }
+        spans.push(Span {
+            component,
+            name: format!("delimiter {}", delimiter.name),
+            bytes: start..start + delimiter.identifier.len(),
+            bits: None,
+        });
+    }
+    for (index, crc) in structure.packet_crc.iter().enumerate() {
+        spans.push(Span {
+            component: Component {
+                kind: ComponentKind::Crc,
+                index,
+            },
+            name: "checksum".to_owned(),
+            bytes: crc.offset_in_packet..crc.offset_in_packet + crc.length,
+            bits: None,
+        });
+    }
+
+    for (index, field) in structure.fields.iter().enumerate() {
+        let component = Component {
+            kind: ComponentKind::Field,
+            index,
+        };
+        let size = match field.size() {
+            Ok(size) => size,
+            Err(_) => {
+                diagnostics.push(error(
+                    Some(component),
+                    None,
+                    format!("Field {} has no size, strings need a format", field.name),
+                ));
+                continue;
+            }
+        };
+        if field.array.is_some_and(|array| array.count == 0) {
+            diagnostics.push(error(
+                Some(component),
+                None,
+                format!("Array {} has no elements", field.name),
+            ));
+            continue;
+        }
+        spans.extend(
+            field
+                .elements()
+                .iter()
+                .map(|element| field_span(component, element, size)),
+        );
+    }
+    for (span_index, span) in spans.iter().enumerate() {
+        // each overlap is reported once, on the later of the two components
+        if let Some(other) = spans[..span_index]
+            .iter()
+            .find(|other| other.overlaps(span))
+        {
+            let start = span.bytes.start.max(other.bytes.start);
+            let end = span.bytes.end.min(other.bytes.end);
+            diagnostics.push(error(
+                Some(span.component),
+                Some(start..end),
+                format!("{} overlaps {}", capitalize(&span.name), other.name),
+            ));
+        }
+        if let Some(size) = structure.size {
+            if span.bytes.end > size {
+                diagnostics.push(error(
+                    Some(span.component),
+                    Some(span.bytes.clone()),
+                    format!(
+                        "{} ends at byte {}, past the end of the {} byte packet",
+                        capitalize(&span.name),
+                        span.bytes.end,
+                        size
+                    ),
+                ));
+            }
+        }
+    }
+
+    if structure.byte_defined && structure.delimiters.is_empty() {
+        diagnostics.push(warning(
+            None,
+            None,
+            format!(
+                "{} has no delimiters, so it can't be found in the data",
+                structure.name
+            ),
+        ));
+    }
+    diagnostics
+}
+
+fn field_span(component: Component, element: &PacketField, size: usize) -> Span {
+    let start = element.offset_in_packet;
+    Span {
+        component,
+        name: format!("field {}", element.name),
+        bytes: start..start + size,
+        bits: element
+            .bitfield
+            .map(|bitfield| (size, bitfield.bit_offset, bitfield.bit_width)),
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut characters = text.chars();
+    match characters.next() {
+        Some(first) => first.to_uppercase().chain(characters).collect(),
+        None => String::new(),
+    }
+}
+
+/// Two structures whose packets can look the same to the serial parser
+///
+/// `example` is a byte string both structures match, with the packets placed somewhere they
+/// overlap. the parser reads it as `matched_id`, the structure whose first delimiter comes first
+/// in the data, or that comes first in the list when they start at the same byte. so packets of
+/// `shadowed_id` that look like it are read as the wrong structure. `matched_start` and
+/// `shadowed_start` are where each packet starts in `example`.
+///
+/// `checksums_match` is false if the example can't pass the checksums of both structures, in
+/// which case the checksums still tell the packets apart
+#[derive(PartialEq, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Ambiguity {
+    pub(crate) matched_id: usize,
+    pub(crate) shadowed_id: usize,
+    pub(crate) example: Vec<u8>,
+    pub(crate) matched_start: usize,
+    pub(crate) shadowed_start: usize,
+    pub(crate) checksums_match: bool,
+}
+
+/// The bytes a structure's delimiters pin down, as (value, mask) pairs for every byte of its
+/// shortest packet. bytes of fields and gaps have a mask of 0
+fn delimiter_mask(structure: &PacketStructure) -> Vec<(u8, u8)> {
+    let length = structure.min_size();
+    let mut mask = vec![(0, 0); length];
+    // the parser skips the delimiters a variable length packet ends before
+    for delimiter in &structure.delimiters {
+        for (index, byte) in delimiter.identifier.iter().enumerate() {
+            if let Some(masked) = mask.get_mut(delimiter.offset_in_packet + index) {
+                *masked = (*byte, 0xFF);
+            }
+        }
+    }
+    mask
+}
+
+/// The offset of the delimiter the parsers search for, the one nearest the start of the packet
+fn first_delimiter_offset(structure: &PacketStructure) -> usize {
+    PacketLayout::of(structure).first_delimiter_offset
+}
+
+/// Finds every pair of byte defined structures that some bytes could be read as either of
+///
+/// the structures are placed at every offset from each other where their packets overlap, and
+/// every delimiter is compared, byte by byte, with the bytes the other structure's delimiters
+/// pin down at the same place in the data. placements count when the delimiters agree and share
+/// at least one byte. variable length structures are compared at their shortest. each structure
+/// is reported as shadowing another at most once, with the placement nearest to both packets
+/// starting together, preferring the ones the checksums can't rule out
+pub fn find_ambiguities<S: Borrow<PacketStructure>>(structures: &[S]) -> Vec<Ambiguity> {
+    let candidates: Vec<&PacketStructure> = structures
+        .iter()
+        .map(Borrow::borrow)
+        .filter(|structure| structure.byte_defined && !structure.delimiters.is_empty())
+        .collect();
+    let mut ambiguities: Vec<Ambiguity> = vec![];
+    for (index, first) in candidates.iter().enumerate() {
+        for second in &candidates[index + 1..] {
+            let first_length = first.min_size() as isize;
+            let second_length = second.min_size() as isize;
+            // how far the second packet starts after the first, nearest to 0 first
+            let mut shifts: Vec<isize> = (1 - second_length..first_length).collect();
+            shifts.sort_by_key(|shift| (shift.abs(), *shift));
+            for ambiguity in shifts
+                .into_iter()
+                .filter_map(|shift| find_ambiguity(first, second, shift))
+            {
+                let found = ambiguities.iter_mut().find(|found| {
+                    found.matched_id == ambiguity.matched_id
+                        && found.shadowed_id == ambiguity.shadowed_id
+                });
+                match found {
+                    Some(found) if !found.checksums_match && ambiguity.checksums_match => {
+                        *found = ambiguity
+                    }
+                    Some(_) => {}
+                    None => ambiguities.push(ambiguity),
+                }
+            }
+        }
+    }
+    ambiguities
+}
+
+/// Checks the bytes of `first` and `second` placed with `second` starting `shift` bytes after
+/// `first`, `first` being earlier in the list of structures
+fn find_ambiguity(
+    first: &PacketStructure,
+    second: &PacketStructure,
+    shift: isize,
+) -> Option<Ambiguity> {
+    let first_start = max(-shift, 0) as usize;
+    let second_start = max(shift, 0) as usize;
+    // the parser tries the structure whose first delimiter it finds first
+    let (matched, matched_start, shadowed, shadowed_start) = if second_start
+        + first_delimiter_offset(second)
+        < first_start + first_delimiter_offset(first)
+    {
+        (second, second_start, first, first_start)
+    } else {
+        (first, first_start, second, second_start)
+    };
+
+    let matched_mask = delimiter_mask(matched);
+    let shadowed_mask = delimiter_mask(shadowed);
+    let length = max(
+        matched_start + matched_mask.len(),
+        shadowed_start + shadowed_mask.len(),
+    );
+    let mut example = vec![0; length];
+    let mut example_mask = vec![0; length];
+    let mut shares_delimiter = false;
+    for (start, mask) in [
+        (matched_start, &matched_mask),
+        (shadowed_start, &shadowed_mask),
+    ] {
+        for (offset, (value, byte_mask)) in mask.iter().enumerate() {
+            let position = start + offset;
+            let shared = example_mask[position] & byte_mask;
+            if (example[position] ^ value) & shared != 0 {
+                return None;
+            }
+            shares_delimiter |= shared != 0;
+            example[position] |= value & byte_mask;
+            example_mask[position] |= byte_mask;
+        }
+    }
+    // without a delimiter byte in common, one of the delimiters has to turn up in the other
+    // packet's fields, which any data can do by chance
+    if !shares_delimiter {
+        return None;
+    }
+
+    // fill in the checksums, which may land on each other or on the other structure's delimiters
+    let mut checksums_match = true;
+    for (structure, start, mask) in [
+        (shadowed, shadowed_start, &shadowed_mask),
+        (matched, matched_start, &matched_mask),
+    ] {
+        let packet = &mut example[start..start + mask.len()];
+        checksums_match &= structure.write_crcs(packet).is_ok();
+    }
+    for (structure, start, mask) in [
+        (matched, matched_start, &matched_mask),
+        (shadowed, shadowed_start, &shadowed_mask),
+    ] {
+        let packet = &example[start..start + mask.len()];
+        let delimiters_kept = mask
+            .iter()
+            .zip(packet)
+            .all(|((value, byte_mask), byte)| (value ^ byte) & byte_mask == 0);
+        checksums_match &= delimiters_kept && structure.check_crcs(packet).unwrap_or(false);
+    }
+
+    Some(Ambiguity {
+        matched_id: matched.id,
+        shadowed_id: shadowed.id,
+        example,
+        matched_start,
+        shadowed_start,
+        checksums_match,
+    })
+}
+
+/// Returns the problems that come from structures being parsed from the same data
+///
+/// each ambiguity between structures is reported on the structure that loses out to the other.
+/// only byte defined structures are compared, the others aren't looked for in the data
+pub fn validate_against_each_other<S: Borrow<PacketStructure>>(
+    structures: &[S],
+) -> Vec<Diagnostic> {
+    let structure = |id| {
+        structures
+            .iter()
+            .map(Borrow::borrow)
+            .find(|structure: &&PacketStructure| structure.id == id)
+    };
+    let name = |id| structure(id).map_or("", |structure| structure.name.as_str());
+    find_ambiguities(structures)
+        .into_iter()
+        .filter(|ambiguity| ambiguity.checksums_match)
+        .map(|ambiguity| {
+            let shadowed = structure(ambiguity.shadowed_id)
+                .expect("ambiguities are between the given structures");
+            // the delimiter the parser finds the shadowed packets by
+            let first_index = PacketLayout::of(shadowed).first_delimiter.unwrap_or(0);
+            let first = &shadowed.delimiters[first_index];
+            Diagnostic {
+                severity: Severity::Warning,
+                structure_id: ambiguity.shadowed_id,
+                component: Some(Component {
+                    kind: ComponentKind::Delimiter,
+                    index: first_index,
+                }),
+                byte_range: Some(
+                    first.offset_in_packet..first.offset_in_packet + first.identifier.len(),
+                ),
+                message: format!(
+                    "{} packets can look like {} packets, ie 0x{}",
+                    shadowed.name,
+                    name(ambiguity.matched_id),
+                    hex::encode(&ambiguity.example)
+                ),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn structure(id: usize, input: &str) -> PacketStructure {
+        let mut structure = PacketStructure::make_default(format!("structure {}", id));
+        structure.ez_make(input, &["test field"; 8], false);
+        structure.id = id;
+        structure.byte_defined = true;
+        structure
+    }
+
+    #[test]
+    fn valid_structures_have_no_diagnostics() {
+        let structure = structure(1, "deadbeef u8:4 u8:4 u16 F32[3] crc16");
+        assert_eq!(validate_structure(&structure), vec![]);
+    }
+
+    #[test]
+    fn overlaps_are_errors() {
+        let mut structure = structure(3, "deadbeef u8:4 u8:4 u16 u32");
+        // the second bitfield now shares bits with the first
+        structure.fields[1].bitfield.as_mut().unwrap().bit_offset = 2;
+        // the u32 now starts inside the delimiter
+        structure.fields[3].offset_in_packet = 2;
+        structure.size = Some(8);
+        let diagnostics = validate_structure(&structure);
+        let summary: Vec<(Option<Component>, Option<Range<usize>>)> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.component, diagnostic.byte_range.clone()))
+            .collect();
+        let field = |index| {
+            Some(Component {
+                kind: ComponentKind::Field,
+                index,
+            })
+        };
+        assert_eq!(
+            summary,
+            vec![(field(1), Some(4..5)), (field(3), Some(2..4))]
+        );
+        assert!(diagnostics.iter().all(Diagnostic::is_error));
+        assert_eq!(diagnostics[0].structure_id, 3);
+        assert_eq!(
+            diagnostics[1].message,
+            "Field test field overlaps delimiter test delimiter 0"
+        );
+    }
+
+    #[test]
+    fn components_past_the_size_are_errors() {
+        let mut structure = structure(1, "deadbeef u16 u16");
+        structure.size = Some(7);
+        let diagnostics = validate_structure(&structure);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].byte_range, Some(6..8));
+        assert_eq!(
+            diagnostics[0].message,
+            "Field test field ends at byte 8, past the end of the 7 byte packet"
+        );
+
+        structure.delimiters.clear();
+        structure.size = None;
+        let diagnostics = validate_structure(&structure);
+        assert_eq!(diagnostics[0].component, None);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn ambiguous_structures_are_found() {
+        let structures = vec![
+            structure(1, "dead u8"),
+            structure(2, "deadbeef u8"),
+            // told apart by a type byte
+            structure(3, "ba5eba11 01 u8"),
+            structure(4, "ba5eba11 02 u16"),
+            // the type byte was left out, so any type matches
+            structure(5, "ba5eba11 u8 u8"),
+            structure(6, "u16 cafe u8"),
+            structure(7, "ca u8 u8 fe"),
+        ];
+        let pairs: Vec<(usize, usize, String, usize, usize)> = find_ambiguities(&structures)
+            .into_iter()
+            .map(|ambiguity| {
+                (
+                    ambiguity.matched_id,
+                    ambiguity.shadowed_id,
+                    hex::encode(ambiguity.example),
+                    ambiguity.matched_start,
+                    ambiguity.shadowed_start,
+                )
+            })
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                (1, 2, "deadbeef00".to_owned(), 0, 0),
+                (3, 5, "ba5eba110100".to_owned(), 0, 0),
+                (4, 5, "ba5eba1102000000".to_owned(), 0, 0),
+                // the ca of structure 7 comes before the cafe of structure 6
+                (7, 6, "ca00cafe00".to_owned(), 0, 0),
+                (6, 7, "0000cafe00fe".to_owned(), 0, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn structures_are_compared_at_every_offset() {
+        let structures = vec![
+            structure(1, "_2 bb u8"),
+            // the parser finds the aa of structure 2 before the bb of structure 1
+            structure(2, "aa u8 bb"),
+        ];
+        let ambiguities = find_ambiguities(&structures);
+        assert_eq!(ambiguities.len(), 1);
+        assert_eq!(ambiguities[0].matched_id, 2);
+        assert_eq!(ambiguities[0].shadowed_id, 1);
+        assert_eq!(hex::encode(&ambiguities[0].example), "aa00bb00");
+        assert_eq!(
+            (ambiguities[0].matched_start, ambiguities[0].shadowed_start),
+            (0, 0)
+        );
+    }
+
+    #[test]
+    fn checksums_can_tell_structures_apart() {
+        let structures = vec![
+            structure(1, "dead u8 crc8"),
+            structure(2, "dead u8 u8"),
+            structure(3, "dead u8 xor8"),
+        ];
+        let ambiguities = find_ambiguities(&structures);
+        let checksums_match: Vec<bool> = ambiguities
+            .iter()
+            .map(|ambiguity| ambiguity.checksums_match)
+            .collect();
+        // the u8 of structure 2 can hold any checksum. crc8 and xor8 of "dead 00" disagree
+        assert_eq!(checksums_match, vec![true, false, true]);
+        assert_eq!(hex::encode(&ambiguities[0].example), "dead00f2");
+        assert!(structures[0].check_crcs(&ambiguities[0].example).unwrap());
+
+        let diagnostics = validate_against_each_other(&structures);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].structure_id, 2);
+        assert_eq!(
+            diagnostics[0].message,
+            "structure 2 packets can look like structure 1 packets, ie 0xdead00f2"
+        );
+    }
+}
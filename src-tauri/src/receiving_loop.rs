@@ -13,6 +13,8 @@ use crate::{
     state::mutex_utils::use_state_in_mutex,
 };
 
+const PARSER_STATISTICS_UPDATE: &str = "parser-statistics";
+
 pub struct MainLoop {
     refresh_timer_data: RefreshTimerData,
 }
@@ -27,6 +29,14 @@ impl MainLoop {
                 use_state_in_mutex(&ps_manager_arc, &mut |psm| psm.set_app(app_handle.clone()));
             },
         );
+        let statistics_app_handle = app_handle.clone();
+        let statistics_task_guard = timer.schedule_repeating(Duration::seconds(1), move || {
+            let statistics = use_state_in_mutex(
+                &statistics_app_handle.state::<CommunicationManagerState>(),
+                &mut |communication_manager| communication_manager.get_parser_statistics(),
+            );
+            _ = statistics_app_handle.emit_all(PARSER_STATISTICS_UPDATE, statistics);
+        });
         let update_task_guard = timer.schedule_repeating(Duration::milliseconds(0), move || {
             match iterate_receiving_loop(
                 app_handle.state::<CommunicationManagerState>(),
@@ -50,6 +60,7 @@ impl MainLoop {
             refresh_timer_data: RefreshTimerData {
                 timer: timer.into(),
                 update_task_guard: update_task_guard.into(),
+                statistics_task_guard: statistics_task_guard.into(),
             },
         }
     }
@@ -57,12 +68,19 @@ impl MainLoop {
     pub fn destroy(&self) {
         drop(self.refresh_timer_data.timer.lock().unwrap());
         drop(self.refresh_timer_data.update_task_guard.lock().unwrap());
+        drop(
+            self.refresh_timer_data
+                .statistics_task_guard
+                .lock()
+                .unwrap(),
+        );
     }
 }
 
 struct RefreshTimerData {
     timer: Mutex<Timer>,
     update_task_guard: Mutex<Guard>,
+    statistics_task_guard: Mutex<Guard>,
 }
 
 #[derive(serde::Serialize, Debug, Clone)]
@@ -1,46 +1,59 @@
 use crate::{
-    models::packet_structure::PacketStructure, packet_structure_manager::PacketStructureManager,
+    models::structure_dsl::parse_structure, packet_structure_manager::PacketStructureManager,
 };
 
 pub fn default_packet_structure_manager() -> PacketStructureManager {
     let mut packet_structure_manager = PacketStructureManager::default();
 
-    let mut daq_structure = PacketStructure::default();
-    daq_structure.ez_make(
-        "4869205468657265 F64 F64 F64 F64 F64 476f6f6462796521",
-        &["Timestamp", "LoadCell", "Pressure", "Temp1", "Temp2"],
-        false,
-    );
-    daq_structure.name = "daq".to_owned();
+    let mut daq_structure = parse_structure(
+        "daq",
+        r#"
+            0x4869205468657265
+            Timestamp: f64
+            LoadCell: f64 "N" scale(920, 84.3)
+            # the pressure sensor reads 5V at 0 psi and 9V at 3000 psi
+            Pressure: f64 "psi" scale(750, -3750)
+            Temp1: f64
+            Temp2: f64
+            0x476f6f6462796521
+        "#,
+    )
+    .expect("daq packet is valid");
     packet_structure_manager
         .register_packet_structure(&mut daq_structure)
         .expect("Failed to register daq packet");
 
-    let mut daq_structure = PacketStructure::default();
-    daq_structure.ez_make(
-        "aa692054686572aa F64 F64 F64 F64 F64 F64 aa6f6f64627965aa",
-        &[
-            "Time",
-            "PSI",
-            "Newtons",
-            "Impulse",
-            "Burn_time",
-            "Max_pressure",
-        ],
-        false,
-    );
-    daq_structure.name = "daq_adv".to_owned();
+    let mut daq_structure = parse_structure(
+        "daq_adv",
+        r#"
+            0xaa692054686572aa
+            Time: f64
+            PSI: f64
+            Newtons: f64
+            Impulse: f64
+            Burn_time: f64
+            Max_pressure: f64
+            0xaa6f6f64627965aa
+        "#,
+    )
+    .expect("daq_adv packet is valid");
     packet_structure_manager
         .register_packet_structure(&mut daq_structure)
         .expect("Failed to register daq packet");
 
-    let mut draw_structure = PacketStructure::default();
-    draw_structure.ez_make(
-        "ba5eba11 d5a1d5a1 F64 F64 F64 d5a1d5a1 ca11ab1e",
-        &["Timestamp", "rkt_speed", "rkt_speed_also"],
-        false,
-    );
-    draw_structure.name = "draw".to_owned();
+    let mut draw_structure = parse_structure(
+        "draw",
+        r#"
+            0xba5eba11
+            0xd5a1d5a1
+            Timestamp: f64
+            rkt_speed: f64
+            rkt_speed_also: f64
+            0xd5a1d5a1
+            0xca11ab1e
+        "#,
+    )
+    .expect("draw packet is valid");
     packet_structure_manager
         .register_packet_structure(&mut draw_structure)
         .expect("Failed to register draw packet");
@@ -48,28 +61,35 @@ pub fn default_packet_structure_manager() -> PacketStructureManager {
     //################################
     //Leep Hardcoded packets start here
     //################################
-    let mut leep_gps_data_structure = PacketStructure::default();
-    leep_gps_data_structure.ez_make(
-        "b5a6 u32 06 u8 u8 F32 F32 F32 F32",
-        &[
-            "Timestamp",
-            "fixType",
-            "satsInView",
-            "GPS_TimeStamp",
-            "lat",
-            "long",
-            "altitude",
-        ],
-        true,
-    );
-    leep_gps_data_structure.name = "leep_gps".to_owned();
+    let mut leep_gps_data_structure = parse_structure(
+        "leep_gps",
+        r#"
+            0xa6b5
+            Timestamp: u32
+            0x06
+            fixType: u8
+            satsInView: u8
+            GPS_TimeStamp: f32
+            lat: f32
+            long: f32
+            altitude: f32
+        "#,
+    )
+    .expect("leep_gps packet is valid");
     packet_structure_manager
         .register_packet_structure(&mut leep_gps_data_structure)
         .expect("Failed to register leep gps data packet");
 
-    let mut leep_volt_data_structure = PacketStructure::default();
-    leep_volt_data_structure.ez_make("b5a6 u32 07 F32", &["Timestamp", "voltage"], true);
-    leep_volt_data_structure.name = "leep_volt".to_owned();
+    let mut leep_volt_data_structure = parse_structure(
+        "leep_volt",
+        r#"
+            0xa6b5
+            Timestamp: u32
+            0x07
+            voltage: f32
+        "#,
+    )
+    .expect("leep_volt packet is valid");
     packet_structure_manager
         .register_packet_structure(&mut leep_volt_data_structure)
         .expect("Failed to register leep gps data packet");
@@ -77,129 +97,155 @@ pub fn default_packet_structure_manager() -> PacketStructureManager {
     //UFC Hardcoded packets start here
     //################################
 
-    let mut ufc_alt_structure = PacketStructure::default();
-    ufc_alt_structure.ez_make(
-        "ba5eba11 u32 08 u8 u16 F32 F32 ca11ab1e",
-        &["Timestamp", "state", "pkt_len", "temperature", "pressure"],
-        true,
-    );
-    ufc_alt_structure.name = "ufc_alt".to_owned();
+    let mut ufc_alt_structure = parse_structure(
+        "ufc_alt",
+        r#"
+            0x11ba5eba
+            Timestamp: u32
+            0x08
+            state: u8
+            pkt_len: u16
+            temperature: f32
+            pressure: f32
+            0x1eab11ca
+        "#,
+    )
+    .expect("ufc_alt packet is valid");
     packet_structure_manager
         .register_packet_structure(&mut ufc_alt_structure)
         .expect("Failed to register test packet");
 
-    let mut ufc_bno_structure = PacketStructure::default();
-    ufc_bno_structure.ez_make(
-        "ba5eba11 u32 04 u8 u16 F32 F32 F32 F32 F32 F32 F32 F32 F32 ca11ab1e",
-        &[
-            "Timestamp",
-            "state",
-            "pkt_len",
-            "acc_x",
-            "acc_y",
-            "acc_z",
-            "gyro_x",
-            "gyro_y",
-            "gyro_z",
-            "eul_heading",
-            "eul_roll",
-            "eul_pitch",
-        ],
-        true,
-    );
-    ufc_bno_structure.name = "ufc_bno".to_owned();
+    let mut ufc_bno_structure = parse_structure(
+        "ufc_bno",
+        r#"
+            0x11ba5eba
+            Timestamp: u32
+            0x04
+            state: u8
+            pkt_len: u16
+            acc: f32[3] names(acc_x, acc_y, acc_z)
+            gyro: f32[3] names(gyro_x, gyro_y, gyro_z)
+            eul_heading: f32
+            eul_roll: f32
+            eul_pitch: f32
+            0x1eab11ca
+        "#,
+    )
+    .expect("ufc_bno packet is valid");
     packet_structure_manager
         .register_packet_structure(&mut ufc_bno_structure)
         .expect("Failed to register test packet");
 
-    let mut ufc_gps_structure = PacketStructure::default();
-    ufc_gps_structure.ez_make("ba5eba11 u32 10 u8 u16 u32 u8 u8 u8 _1 i32 u32 F32 F32 u32 u32 u8 _2 u32 u32 F32 i32 i32 i32 u32 ca11ab1e",
-    &["Timestamp","state","pkt_len","time_of_week","time_hour",
-    "time_min","time_sec","time_nanosec","timeAccuracy","pos_lat",
-    "pos_lon","height_msl","height_elip","fixType","numSatellites",
-    "verticalAccuracy","horizontalAccuracy","pDOP","vel_north",
-    "vel_east","vel_down","vel_accuracy"],true);
-    ufc_gps_structure.name = "ufc_gps".to_owned();
+    let mut ufc_gps_structure = parse_structure(
+        "ufc_gps",
+        r#"
+            0x11ba5eba
+            Timestamp: u32
+            0x10
+            state: u8
+            pkt_len: u16
+            time_of_week: u32
+            time_hour: u8
+            time_min: u8
+            time_sec: u8
+            _1
+            time_nanosec: i32
+            timeAccuracy: u32
+            pos_lat: f32
+            pos_lon: f32
+            height_msl: u32
+            height_elip: u32
+            fixType: u8
+            _2
+            numSatellites: u32
+            verticalAccuracy: u32
+            horizontalAccuracy: f32
+            pDOP: i32
+            vel_north: i32
+            vel_east: i32
+            vel_down: u32
+            # vel_accuracy was named after vel_down, but never part of the packet layout
+            0x1eab11ca
+        "#,
+    )
+    .expect("ufc_gps packet is valid");
     packet_structure_manager
         .register_packet_structure(&mut ufc_gps_structure)
         .expect("Failed to register test packet");
 
-    let mut ufc_sense_structure = PacketStructure::default();
-    ufc_sense_structure.ez_make(
-        "ba5eba11 u32 02 u8 u16 F32 F32 F32 F32 F32 F32 F32 F32 F32 F32 F32 F32 ca11ab1e",
-        &[
-            "Timestamp",
-            "state",
-            "pkt_len",
-            "low_accel_x",
-            "low_accel_y",
-            "low_accel_z",
-            "high_accel_x",
-            "high_accel_y",
-            "high_accel_z",
-            "mag_x",
-            "mag_y",
-            "mag_z",
-            "gyro_x",
-            "gyro_y",
-            "gyro_z",
-        ],
-        true,
-    );
-    ufc_sense_structure.name = "ufc_sense".to_owned();
+    let mut ufc_sense_structure = parse_structure(
+        "ufc_sense",
+        r#"
+            0x11ba5eba
+            Timestamp: u32
+            0x02
+            state: u8
+            pkt_len: u16
+            low_accel: f32[3] names(low_accel_x, low_accel_y, low_accel_z)
+            high_accel: f32[3] names(high_accel_x, high_accel_y, high_accel_z)
+            mag: f32[3] names(mag_x, mag_y, mag_z)
+            gyro: f32[3] names(gyro_x, gyro_y, gyro_z)
+            0x1eab11ca
+        "#,
+    )
+    .expect("ufc_sense packet is valid");
     packet_structure_manager
         .register_packet_structure(&mut ufc_sense_structure)
         .expect("Failed to register test packet");
 
-    let mut ufc_pitot_center = PacketStructure::default();
-    ufc_pitot_center.ez_make(
-        "ba5eba11 u32 20 u8 u16 F32 F32 ca11ab1e",
-        &[
-            "Timestamp",
-            "state",
-            "pkt_len",
-            "center_port",
-            "static_port",
-        ],
-        true,
-    );
-    ufc_pitot_center.name = "ufc_pitot_center".to_owned();
+    let mut ufc_pitot_center = parse_structure(
+        "ufc_pitot_center",
+        r#"
+            0x11ba5eba
+            Timestamp: u32
+            0x20
+            state: u8
+            pkt_len: u16
+            center_port: f32
+            static_port: f32
+            0x1eab11ca
+        "#,
+    )
+    .expect("ufc_pitot_center packet is valid");
     packet_structure_manager
         .register_packet_structure(&mut ufc_pitot_center)
         .expect("Failed to register test packet");
 
-    let mut ufc_pitot_radial = PacketStructure::default();
-    ufc_pitot_radial.ez_make(
-        "ba5eba11 u32 40 u8 u16 F32 F32 F32 F32 ca11ab1e",
-        &[
-            "Timestamp",
-            "state",
-            "pkt_len",
-            "up_port",
-            "down_port",
-            "left_port",
-            "right_port",
-        ],
-        true,
-    );
-    ufc_pitot_radial.name = "ufc_pitot_radial".to_owned();
+    let mut ufc_pitot_radial = parse_structure(
+        "ufc_pitot_radial",
+        r#"
+            0x11ba5eba
+            Timestamp: u32
+            0x40
+            state: u8
+            pkt_len: u16
+            up_port: f32
+            down_port: f32
+            left_port: f32
+            right_port: f32
+            0x1eab11ca
+        "#,
+    )
+    .expect("ufc_pitot_radial packet is valid");
     packet_structure_manager
         .register_packet_structure(&mut ufc_pitot_radial)
         .expect("Failed to register test packet");
 
-    let mut ufc_test_structure = PacketStructure::default();
-    ufc_test_structure.ez_make(
-        "ba5eba11 0040 _2 i64 u16 u16 u8 u8 _4 ca11ab1e",
-        &[
-            "Timestamp",
-            "rkt_speed",
-            "rkt_speed_also",
-            "rkt_budget",
-            "var8",
-        ],
-        true,
-    );
-    ufc_test_structure.name = "ufc_test".to_owned();
+    let mut ufc_test_structure = parse_structure(
+        "ufc_test",
+        r#"
+            0x11ba5eba
+            0x4000
+            Timestamp: i64
+            rkt_speed: u16
+            rkt_speed_also: u16
+            rkt_budget: u8
+            var8: u8
+            _6
+            0x1eab11ca
+        "#,
+    )
+    .expect("ufc_test packet is valid");
     packet_structure_manager
         .register_packet_structure(&mut ufc_test_structure)
         .expect("Failed to register test packet");
@@ -207,5 +253,555 @@ pub fn default_packet_structure_manager() -> PacketStructureManager {
     //UFC Hardcoded packets end here
     //################################
 
+    // the built-in structures aren't edits that can be undone
+    packet_structure_manager.clear_history();
     packet_structure_manager
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*; // lets the unit tests use everything in this file
+    use crate::models::packet_structure::{Endianness, PacketField, PacketFieldType};
+
+    /// a structure's name, the name, offset, type and byte order of each of its fields, and the
+    /// offset and bytes of each of its delimiters
+    type OldLayout = (
+        &'static str,
+        &'static [(&'static str, usize, PacketFieldType, Endianness)],
+        &'static [(usize, &'static [u8])],
+    );
+
+    /// every field and delimiter of each default structure when they were written with `ez_make`,
+    /// before the structure DSL and arrays
+    const OLD_LAYOUTS: [OldLayout; 12] = [
+        (
+            "daq",
+            &[
+                ("Timestamp", 8, PacketFieldType::Double, Endianness::Little),
+                ("LoadCell", 16, PacketFieldType::Double, Endianness::Little),
+                ("Pressure", 24, PacketFieldType::Double, Endianness::Little),
+                ("Temp1", 32, PacketFieldType::Double, Endianness::Little),
+                ("Temp2", 40, PacketFieldType::Double, Endianness::Little),
+            ],
+            &[
+                (0, &[0x48, 0x69, 0x20, 0x54, 0x68, 0x65, 0x72, 0x65]),
+                (48, &[0x47, 0x6f, 0x6f, 0x64, 0x62, 0x79, 0x65, 0x21]),
+            ],
+        ),
+        (
+            "daq_adv",
+            &[
+                ("Time", 8, PacketFieldType::Double, Endianness::Little),
+                ("PSI", 16, PacketFieldType::Double, Endianness::Little),
+                ("Newtons", 24, PacketFieldType::Double, Endianness::Little),
+                ("Impulse", 32, PacketFieldType::Double, Endianness::Little),
+                ("Burn_time", 40, PacketFieldType::Double, Endianness::Little),
+                (
+                    "Max_pressure",
+                    48,
+                    PacketFieldType::Double,
+                    Endianness::Little,
+                ),
+            ],
+            &[
+                (0, &[0xaa, 0x69, 0x20, 0x54, 0x68, 0x65, 0x72, 0xaa]),
+                (56, &[0xaa, 0x6f, 0x6f, 0x64, 0x62, 0x79, 0x65, 0xaa]),
+            ],
+        ),
+        (
+            "draw",
+            &[
+                ("Timestamp", 8, PacketFieldType::Double, Endianness::Little),
+                ("rkt_speed", 16, PacketFieldType::Double, Endianness::Little),
+                (
+                    "rkt_speed_also",
+                    24,
+                    PacketFieldType::Double,
+                    Endianness::Little,
+                ),
+            ],
+            &[
+                (0, &[0xba, 0x5e, 0xba, 0x11]),
+                (4, &[0xd5, 0xa1, 0xd5, 0xa1]),
+                (32, &[0xd5, 0xa1, 0xd5, 0xa1]),
+                (36, &[0xca, 0x11, 0xab, 0x1e]),
+            ],
+        ),
+        (
+            "leep_gps",
+            &[
+                (
+                    "Timestamp",
+                    4,
+                    PacketFieldType::UnsignedInteger,
+                    Endianness::Little,
+                ),
+                (
+                    "fixType",
+                    9,
+                    PacketFieldType::UnsignedByte,
+                    Endianness::Little,
+                ),
+                (
+                    "satsInView",
+                    10,
+                    PacketFieldType::UnsignedByte,
+                    Endianness::Little,
+                ),
+                (
+                    "GPS_TimeStamp",
+                    12,
+                    PacketFieldType::Float,
+                    Endianness::Little,
+                ),
+                ("lat", 16, PacketFieldType::Float, Endianness::Little),
+                ("long", 20, PacketFieldType::Float, Endianness::Little),
+                ("altitude", 24, PacketFieldType::Float, Endianness::Little),
+            ],
+            &[(0, &[0xa6, 0xb5]), (8, &[0x06])],
+        ),
+        (
+            "leep_volt",
+            &[
+                (
+                    "Timestamp",
+                    4,
+                    PacketFieldType::UnsignedInteger,
+                    Endianness::Little,
+                ),
+                ("voltage", 12, PacketFieldType::Float, Endianness::Little),
+            ],
+            &[(0, &[0xa6, 0xb5]), (8, &[0x07])],
+        ),
+        (
+            "ufc_alt",
+            &[
+                (
+                    "Timestamp",
+                    4,
+                    PacketFieldType::UnsignedInteger,
+                    Endianness::Little,
+                ),
+                (
+                    "state",
+                    9,
+                    PacketFieldType::UnsignedByte,
+                    Endianness::Little,
+                ),
+                (
+                    "pkt_len",
+                    10,
+                    PacketFieldType::UnsignedShort,
+                    Endianness::Little,
+                ),
+                (
+                    "temperature",
+                    12,
+                    PacketFieldType::Float,
+                    Endianness::Little,
+                ),
+                ("pressure", 16, PacketFieldType::Float, Endianness::Little),
+            ],
+            &[
+                (0, &[0x11, 0xba, 0x5e, 0xba]),
+                (8, &[0x08]),
+                (20, &[0x1e, 0xab, 0x11, 0xca]),
+            ],
+        ),
+        (
+            "ufc_bno",
+            &[
+                (
+                    "Timestamp",
+                    4,
+                    PacketFieldType::UnsignedInteger,
+                    Endianness::Little,
+                ),
+                (
+                    "state",
+                    9,
+                    PacketFieldType::UnsignedByte,
+                    Endianness::Little,
+                ),
+                (
+                    "pkt_len",
+                    10,
+                    PacketFieldType::UnsignedShort,
+                    Endianness::Little,
+                ),
+                ("acc_x", 12, PacketFieldType::Float, Endianness::Little),
+                ("acc_y", 16, PacketFieldType::Float, Endianness::Little),
+                ("acc_z", 20, PacketFieldType::Float, Endianness::Little),
+                ("gyro_x", 24, PacketFieldType::Float, Endianness::Little),
+                ("gyro_y", 28, PacketFieldType::Float, Endianness::Little),
+                ("gyro_z", 32, PacketFieldType::Float, Endianness::Little),
+                (
+                    "eul_heading",
+                    36,
+                    PacketFieldType::Float,
+                    Endianness::Little,
+                ),
+                ("eul_roll", 40, PacketFieldType::Float, Endianness::Little),
+                ("eul_pitch", 44, PacketFieldType::Float, Endianness::Little),
+            ],
+            &[
+                (0, &[0x11, 0xba, 0x5e, 0xba]),
+                (8, &[0x04]),
+                (48, &[0x1e, 0xab, 0x11, 0xca]),
+            ],
+        ),
+        (
+            "ufc_gps",
+            &[
+                (
+                    "Timestamp",
+                    4,
+                    PacketFieldType::UnsignedInteger,
+                    Endianness::Little,
+                ),
+                (
+                    "state",
+                    9,
+                    PacketFieldType::UnsignedByte,
+                    Endianness::Little,
+                ),
+                (
+                    "pkt_len",
+                    10,
+                    PacketFieldType::UnsignedShort,
+                    Endianness::Little,
+                ),
+                (
+                    "time_of_week",
+                    12,
+                    PacketFieldType::UnsignedInteger,
+                    Endianness::Little,
+                ),
+                (
+                    "time_hour",
+                    16,
+                    PacketFieldType::UnsignedByte,
+                    Endianness::Little,
+                ),
+                (
+                    "time_min",
+                    17,
+                    PacketFieldType::UnsignedByte,
+                    Endianness::Little,
+                ),
+                (
+                    "time_sec",
+                    18,
+                    PacketFieldType::UnsignedByte,
+                    Endianness::Little,
+                ),
+                (
+                    "time_nanosec",
+                    20,
+                    PacketFieldType::SignedInteger,
+                    Endianness::Little,
+                ),
+                (
+                    "timeAccuracy",
+                    24,
+                    PacketFieldType::UnsignedInteger,
+                    Endianness::Little,
+                ),
+                ("pos_lat", 28, PacketFieldType::Float, Endianness::Little),
+                ("pos_lon", 32, PacketFieldType::Float, Endianness::Little),
+                (
+                    "height_msl",
+                    36,
+                    PacketFieldType::UnsignedInteger,
+                    Endianness::Little,
+                ),
+                (
+                    "height_elip",
+                    40,
+                    PacketFieldType::UnsignedInteger,
+                    Endianness::Little,
+                ),
+                (
+                    "fixType",
+                    44,
+                    PacketFieldType::UnsignedByte,
+                    Endianness::Little,
+                ),
+                (
+                    "numSatellites",
+                    48,
+                    PacketFieldType::UnsignedInteger,
+                    Endianness::Little,
+                ),
+                (
+                    "verticalAccuracy",
+                    52,
+                    PacketFieldType::UnsignedInteger,
+                    Endianness::Little,
+                ),
+                (
+                    "horizontalAccuracy",
+                    56,
+                    PacketFieldType::Float,
+                    Endianness::Little,
+                ),
+                (
+                    "pDOP",
+                    60,
+                    PacketFieldType::SignedInteger,
+                    Endianness::Little,
+                ),
+                (
+                    "vel_north",
+                    64,
+                    PacketFieldType::SignedInteger,
+                    Endianness::Little,
+                ),
+                (
+                    "vel_east",
+                    68,
+                    PacketFieldType::SignedInteger,
+                    Endianness::Little,
+                ),
+                (
+                    "vel_down",
+                    72,
+                    PacketFieldType::UnsignedInteger,
+                    Endianness::Little,
+                ),
+            ],
+            &[
+                (0, &[0x11, 0xba, 0x5e, 0xba]),
+                (8, &[0x10]),
+                (76, &[0x1e, 0xab, 0x11, 0xca]),
+            ],
+        ),
+        (
+            "ufc_sense",
+            &[
+                (
+                    "Timestamp",
+                    4,
+                    PacketFieldType::UnsignedInteger,
+                    Endianness::Little,
+                ),
+                (
+                    "state",
+                    9,
+                    PacketFieldType::UnsignedByte,
+                    Endianness::Little,
+                ),
+                (
+                    "pkt_len",
+                    10,
+                    PacketFieldType::UnsignedShort,
+                    Endianness::Little,
+                ),
+                (
+                    "low_accel_x",
+                    12,
+                    PacketFieldType::Float,
+                    Endianness::Little,
+                ),
+                (
+                    "low_accel_y",
+                    16,
+                    PacketFieldType::Float,
+                    Endianness::Little,
+                ),
+                (
+                    "low_accel_z",
+                    20,
+                    PacketFieldType::Float,
+                    Endianness::Little,
+                ),
+                (
+                    "high_accel_x",
+                    24,
+                    PacketFieldType::Float,
+                    Endianness::Little,
+                ),
+                (
+                    "high_accel_y",
+                    28,
+                    PacketFieldType::Float,
+                    Endianness::Little,
+                ),
+                (
+                    "high_accel_z",
+                    32,
+                    PacketFieldType::Float,
+                    Endianness::Little,
+                ),
+                ("mag_x", 36, PacketFieldType::Float, Endianness::Little),
+                ("mag_y", 40, PacketFieldType::Float, Endianness::Little),
+                ("mag_z", 44, PacketFieldType::Float, Endianness::Little),
+                ("gyro_x", 48, PacketFieldType::Float, Endianness::Little),
+                ("gyro_y", 52, PacketFieldType::Float, Endianness::Little),
+                ("gyro_z", 56, PacketFieldType::Float, Endianness::Little),
+            ],
+            &[
+                (0, &[0x11, 0xba, 0x5e, 0xba]),
+                (8, &[0x02]),
+                (60, &[0x1e, 0xab, 0x11, 0xca]),
+            ],
+        ),
+        (
+            "ufc_pitot_center",
+            &[
+                (
+                    "Timestamp",
+                    4,
+                    PacketFieldType::UnsignedInteger,
+                    Endianness::Little,
+                ),
+                (
+                    "state",
+                    9,
+                    PacketFieldType::UnsignedByte,
+                    Endianness::Little,
+                ),
+                (
+                    "pkt_len",
+                    10,
+                    PacketFieldType::UnsignedShort,
+                    Endianness::Little,
+                ),
+                (
+                    "center_port",
+                    12,
+                    PacketFieldType::Float,
+                    Endianness::Little,
+                ),
+                (
+                    "static_port",
+                    16,
+                    PacketFieldType::Float,
+                    Endianness::Little,
+                ),
+            ],
+            &[
+                (0, &[0x11, 0xba, 0x5e, 0xba]),
+                (8, &[0x20]),
+                (20, &[0x1e, 0xab, 0x11, 0xca]),
+            ],
+        ),
+        (
+            "ufc_pitot_radial",
+            &[
+                (
+                    "Timestamp",
+                    4,
+                    PacketFieldType::UnsignedInteger,
+                    Endianness::Little,
+                ),
+                (
+                    "state",
+                    9,
+                    PacketFieldType::UnsignedByte,
+                    Endianness::Little,
+                ),
+                (
+                    "pkt_len",
+                    10,
+                    PacketFieldType::UnsignedShort,
+                    Endianness::Little,
+                ),
+                ("up_port", 12, PacketFieldType::Float, Endianness::Little),
+                ("down_port", 16, PacketFieldType::Float, Endianness::Little),
+                ("left_port", 20, PacketFieldType::Float, Endianness::Little),
+                ("right_port", 24, PacketFieldType::Float, Endianness::Little),
+            ],
+            &[
+                (0, &[0x11, 0xba, 0x5e, 0xba]),
+                (8, &[0x40]),
+                (28, &[0x1e, 0xab, 0x11, 0xca]),
+            ],
+        ),
+        (
+            "ufc_test",
+            &[
+                (
+                    "Timestamp",
+                    8,
+                    PacketFieldType::SignedLong,
+                    Endianness::Little,
+                ),
+                (
+                    "rkt_speed",
+                    16,
+                    PacketFieldType::UnsignedShort,
+                    Endianness::Little,
+                ),
+                (
+                    "rkt_speed_also",
+                    18,
+                    PacketFieldType::UnsignedShort,
+                    Endianness::Little,
+                ),
+                (
+                    "rkt_budget",
+                    20,
+                    PacketFieldType::UnsignedByte,
+                    Endianness::Little,
+                ),
+                (
+                    "var8",
+                    21,
+                    PacketFieldType::UnsignedByte,
+                    Endianness::Little,
+                ),
+            ],
+            &[
+                (0, &[0x11, 0xba, 0x5e, 0xba]),
+                (4, &[0x40, 0x00]),
+                (28, &[0x1e, 0xab, 0x11, 0xca]),
+            ],
+        ),
+    ];
+
+    //tests that moving the default structures to the DSL kept every field's name, place, type and
+    //byte order, and every delimiter's place and bytes
+    #[test]
+    fn default_layouts_are_unchanged() {
+        let packet_structure_manager = default_packet_structure_manager();
+        let packet_structures = &packet_structure_manager.packet_structures;
+        assert_eq!(packet_structures.len(), OLD_LAYOUTS.len());
+        for (packet_structure, (name, old_fields, old_delimiters)) in
+            packet_structures.iter().zip(OLD_LAYOUTS)
+        {
+            assert_eq!(packet_structure.name, name);
+            let mut elements: Vec<PacketField> = packet_structure
+                .fields
+                .iter()
+                .flat_map(|field| field.elements())
+                .collect();
+            // values are stored in index order, which is what logs and displays go by
+            elements.sort_by_key(|element| element.index);
+            let fields: Vec<(String, usize, PacketFieldType, Endianness)> = elements
+                .into_iter()
+                .map(|element| {
+                    (
+                        element.name,
+                        element.offset_in_packet,
+                        element.r#type,
+                        element.endianness,
+                    )
+                })
+                .collect();
+            let old_fields: Vec<(String, usize, PacketFieldType, Endianness)> = old_fields
+                .iter()
+                .map(|(name, offset, r#type, endianness)| {
+                    (name.to_string(), *offset, *r#type, *endianness)
+                })
+                .collect();
+            assert_eq!(fields, old_fields, "fields of {}", name);
+            let delimiters: Vec<(usize, &[u8])> = packet_structure
+                .delimiters
+                .iter()
+                .map(|delimiter| (delimiter.offset_in_packet, delimiter.identifier.as_slice()))
+                .collect();
+            assert_eq!(delimiters, old_delimiters, "delimiters of {}", name);
+        }
+    }
+}
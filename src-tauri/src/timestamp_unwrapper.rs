@@ -0,0 +1,168 @@
+//! # Timestamp Unwrapping
+//!
+//! Devices send their clocks as counters that roll over back to 0. This module turns those
+//! counters into seconds that keep counting up, separately for every device. A device has one
+//! clock for each way its timestamps count, so every structure sent with the same clock format
+//! rolls over together.
+
+use std::collections::HashMap;
+
+use crate::models::{
+    packet::Packet,
+    packet_structure::{PacketStructure, TimestampFormat},
+};
+
+/// The ticks per second, as bits so it can be hashed, and wrap width of a device's clock
+type ClockKey = (u64, Option<u32>);
+
+/// Holds the last value and rollover count of every clock a single device has sent
+#[derive(Default)]
+pub struct TimestampUnwrapper {
+    /// clock format -> (last counter value, ticks added by rollovers)
+    counters: HashMap<ClockKey, (u64, u64)>,
+}
+
+impl TimestampUnwrapper {
+    /// Converts every timestamp field of a parsed packet into unwrapped seconds
+    pub fn unwrap_packet(&mut self, packet_structure: &PacketStructure, packet: &mut Packet) {
+        for field in &packet_structure.fields {
+            let format = match field.timestamp {
+                Some(format) => format,
+                None => continue,
+            };
+            let key = (format.ticks_per_second.to_bits(), format.wrap_bits);
+            for element in field.elements() {
+                if let Some(value) = packet.field_data.get_mut(element.index) {
+                    value.edit_number(&mut |ticks| self.unwrap(key, *ticks, format));
+                }
+            }
+        }
+    }
+
+    /// Turns a counter value into seconds, adding on any rollovers that have happened
+    ///
+    /// values less than half the counter ahead of the last one (counting through a rollover)
+    /// move the clock forward, anything else is a packet that arrived late
+    fn unwrap(&mut self, key: ClockKey, ticks: f64, format: TimestampFormat) -> f64 {
+        let modulus = match format.wrap_bits {
+            Some(wrap_bits) if !ticks.is_nan() && (1..64).contains(&wrap_bits) => 1u64 << wrap_bits,
+            _ => return ticks / format.ticks_per_second,
+        };
+        let raw = (ticks as u64) % modulus;
+        let (last, rollovers) = self.counters.entry(key).or_insert((raw, 0));
+        let forward = (raw + modulus - *last) % modulus;
+        let unwrapped = if forward <= modulus / 2 {
+            if raw < *last {
+                *rollovers += modulus;
+            }
+            *last = raw;
+            *rollovers + raw
+        } else if raw > *last {
+            // a late packet from before the last rollover
+            rollovers.saturating_sub(modulus) + raw
+        } else {
+            *rollovers + raw
+        };
+        unwrapped as f64 / format.ticks_per_second
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    /// The ticks per second, as bits so it can be hashed, and wrap width of a device's clock
+    type ClockKey = (u64, Option<u32>);
+
+    use crate::models::{packet::PacketFieldValue, structure_dsl::parse_structure};
+
+    use super::*;
+
+    fn make_structure() -> PacketStructure {
+        parse_structure("test", "Timestamp: u16 clock(100, 16) state: u8").unwrap()
+    }
+
+    fn unwrap_all(unwrapper: &mut TimestampUnwrapper, ticks: &[f64]) -> Vec<PacketFieldValue> {
+        let structure = make_structure();
+        ticks
+            .iter()
+            .map(|ticks| {
+                let mut packet = Packet::default(
+                    structure.id,
+                    vec![
+                        PacketFieldValue::Number(*ticks),
+                        PacketFieldValue::Number(2.0),
+                    ],
+                );
+                unwrapper.unwrap_packet(&structure, &mut packet);
+                assert_eq!(packet.field_data[1], PacketFieldValue::Number(2.0));
+                packet.field_data[0].clone()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rollovers_keep_counting_up() {
+        let mut unwrapper = TimestampUnwrapper::default();
+        let times = unwrap_all(&mut unwrapper, &[65000.0, 65500.0, 100.0, 600.0, 30000.0]);
+        assert_eq!(
+            times,
+            vec![
+                PacketFieldValue::Number(650.0),
+                PacketFieldValue::Number(655.0),
+                PacketFieldValue::Number(656.36),
+                PacketFieldValue::Number(661.36),
+                PacketFieldValue::Number(955.36),
+            ]
+        );
+    }
+
+    #[test]
+    fn late_packets_are_not_rollovers() {
+        let mut unwrapper = TimestampUnwrapper::default();
+        let times = unwrap_all(&mut unwrapper, &[65500.0, 100.0, 65400.0, 50.0, 200.0]);
+        assert_eq!(
+            times,
+            vec![
+                PacketFieldValue::Number(655.0),
+                PacketFieldValue::Number(656.36),
+                PacketFieldValue::Number(654.0),
+                PacketFieldValue::Number(655.86),
+                PacketFieldValue::Number(657.36),
+            ]
+        );
+    }
+
+    #[test]
+    fn structures_share_the_device_clock() {
+        let mut unwrapper = TimestampUnwrapper::default();
+        unwrap_all(&mut unwrapper, &[65500.0]);
+        // another structure sent by the same device, after the clock rolled over
+        let structure = parse_structure("gps", "0x05 Timestamp: u16 clock(100, 16)").unwrap();
+        let mut packet = Packet::default(structure.id, vec![PacketFieldValue::Number(100.0)]);
+        unwrapper.unwrap_packet(&structure, &mut packet);
+        assert_eq!(packet.field_data[0], PacketFieldValue::Number(656.36));
+
+        let times = unwrap_all(&mut unwrapper, &[200.0, 65400.0]);
+        assert_eq!(
+            times,
+            vec![
+                PacketFieldValue::Number(657.36),
+                PacketFieldValue::Number(654.0),
+            ]
+        );
+
+        // a clock counting differently is a different clock
+        let structure = parse_structure("slow", "Timestamp: u16 clock(10, 16)").unwrap();
+        let mut packet = Packet::default(structure.id, vec![PacketFieldValue::Number(100.0)]);
+        unwrapper.unwrap_packet(&structure, &mut packet);
+        assert_eq!(packet.field_data[0], PacketFieldValue::Number(10.0));
+    }
+
+    #[test]
+    fn devices_unwrap_separately() {
+        let mut first_device = TimestampUnwrapper::default();
+        let mut second_device = TimestampUnwrapper::default();
+        unwrap_all(&mut first_device, &[65500.0, 100.0]);
+        let times = unwrap_all(&mut second_device, &[100.0]);
+        assert_eq!(times, vec![PacketFieldValue::Number(1.0)]);
+    }
+}
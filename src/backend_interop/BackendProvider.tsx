@@ -9,7 +9,8 @@ import {
     PacketStructureViewModelUpdate,
     PacketStructureViewModelUpdateType,
     SendingLoopState,
-    DisplayComDevice
+    DisplayComDevice,
+    DeviceParserStatistics
 } from "./types";
 import { emit, listen, UnlistenFn } from "@tauri-apps/api/event";
 import { comDeviceSelections, IterateComDevicesIterator, setComDeviceSelections } from "../tabs/SendingTab";
@@ -48,6 +49,11 @@ export type BackendContextValue = {
      * True if the backend just received bytes (these bytes are not necessarily packets)
      */
     gotData: Accessor<boolean>
+
+    /**
+     * What the parser of each device has made of the data it received, updated every second
+     */
+    parserStatistics: Accessor<DeviceParserStatistics[]>
 };
 
 /**
@@ -61,6 +67,7 @@ const BackendContext = createContext<BackendContextValue>({
     sendingLoopState: () => null,
     comDeviceList: (): DisplayComDevice[] => [],
     gotData: () => false,
+    parserStatistics: (): DeviceParserStatistics[] => [],
 });
 
 /**
@@ -78,6 +85,7 @@ export const BackendProvider: ParentComponent = (props) => {
     const [sendingLoopState, setSendingLoopState] = createSignal<SendingLoopState | null>(null);
     const [comDeviceList, setComDeviceList] = createSignal<DisplayComDevice[]>([]);
     const [gotData, setGotData] = createSignal<boolean>(false);
+    const [parserStatistics, setParserStatistics] = createSignal<DeviceParserStatistics[]>([]);
 
     let unlistenFunctions: UnlistenFn[];
 
@@ -154,6 +162,9 @@ export const BackendProvider: ParentComponent = (props) => {
             }),
             await listen<SendingLoopState>("sending-loop-update", ({ payload }) => {
                 setSendingLoopState(payload);
+            }),
+            await listen<DeviceParserStatistics[]>("parser-statistics", ({ payload }) => {
+                setParserStatistics(payload);
             })
         ];
 
@@ -202,6 +213,7 @@ export const BackendProvider: ParentComponent = (props) => {
         sendingLoopState: sendingLoopState,
         comDeviceList: comDeviceList,
         gotData: gotData,
+        parserStatistics: parserStatistics,
     };
 
     return (
@@ -1,5 +1,5 @@
 import { invoke } from "@tauri-apps/api/tauri";
-import { PacketComponentType, PacketFieldType, PacketMetadataType, PacketStructureViewModel, SendingModes } from "./types";
+import { Ambiguity, DeviceParserStatistics, FirmwareCode, Framing, PacketComponentType, PacketFieldType, PacketMetadataType, PacketStructureViewModel, SendingModes } from "./types";
 
 /*
  * All direct function calls to the rust backend are sent through this file, 
@@ -8,6 +8,10 @@ import { PacketComponentType, PacketFieldType, PacketMetadataType, PacketStructu
 
 export const initDevicePort = async (portName: string, baud: number, id: number) => await invoke<void>("init_device_port", { portName, baud, id});
 
+export const setDeviceFraming = async (framing: Framing, id: number) => await invoke<void>("set_device_framing", { framing, id });
+
+export const getParserStatistics = async () => await invoke<DeviceParserStatistics[]>("get_parser_statistics");
+
 export const deleteDevice = async (id: number) => await invoke<void>("delete_device", {id: id});
 
 export const addRfd = async () => await invoke<void>("add_rfd");
@@ -21,6 +25,8 @@ export const addAim = async () => await invoke<void>("add_aim");
 
 export const addFeatherWeight = async () => await invoke<void>("add_featherweight");
 
+export const addMavlink = async () => await invoke<void>("add_mavlink");
+
 export const startSendingLoop = async (interval: number, alreadySent: number, mode : SendingModes, writeId: number) => await invoke<void>("start_sending_loop", { interval, alreadySent, mode, writeId});
 
 export const stopSendingLoop = async () => await invoke<void>("stop_sending_loop");
@@ -53,4 +59,16 @@ export const registerEmptyPacketStructure = async () => await invoke<void>("regi
 
 export const deletePacketStructure = async (packetStructureId: number) => await invoke<void>('delete_packet_structure', { packetStructureId: packetStructureId });
 
+export const getPacketStructureText = async (packetStructureId: number) => await invoke<string>('get_packet_structure_text', { packetStructureId });
+
+export const importCHeader = async (header: string) => await invoke<string[]>('import_c_header', { header });
+
+export const undoPacketStructureEdit = async () => await invoke<string | null>('undo_packet_structure_edit');
+
+export const redoPacketStructureEdit = async () => await invoke<string | null>('redo_packet_structure_edit');
+
+export const getFirmwareCode = async () => await invoke<FirmwareCode>('get_firmware_code');
+
+export const getDelimiterAmbiguities = async () => await invoke<Ambiguity[]>('get_delimiter_ambiguities');
+
 export const setMode = async (mode : SendingModes) => await invoke<void> ('mode_setter', {mode : mode});
\ No newline at end of file
@@ -38,7 +38,8 @@ export const pushParsedPackets = (packets: Packet[]): void => {
         }
         const packetData: PacketData = {
             fieldData: packet.fieldData,
-            metaData: packet.metaData
+            metaData: packet.metaData,
+            fieldLabels: packet.fieldLabels
         };
         sortedNewParsedPackets[packet.structureId].push(packetData);
     }
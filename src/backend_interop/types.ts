@@ -47,7 +47,19 @@ export type Packet = PacketData & {
  */
 export type PacketData = {
     fieldData: number[],
-    metaData: number[]
+    metaData: number[],
+    /**
+     * The names of the values of enumerated fields, keyed by field index. Missing if the packet has none
+     */
+    fieldLabels?: Record<number, FieldLabel>
+};
+
+/**
+ * The name of the value of an enumerated field, values without a name are flagged as not known
+ */
+export type FieldLabel = {
+    label: string,
+    known: boolean
 };
 
 /**
@@ -72,8 +84,14 @@ export enum PacketFieldType {
     SignedByte = "Signed Byte",
     UnsignedShort = "Unsigned Short",
     SignedShort = "Signed Short",
+    UnsignedTwoFour = "Unsigned 24",
+    SignedTwoFour = "Signed 24",
     UnsignedInteger = "Unsigned Integer",
     SignedInteger = "Signed Integer",
+    UnsignedFourZero = "Unsigned 40",
+    SignedFourZero = "Signed 40",
+    UnsignedFourEight = "Unsigned 48",
+    SignedFourEight = "Signed 48",
     UnsignedLong = "Unsigned Long",
     SignedLong = "Signed Long",
     Float = "Float",
@@ -103,6 +121,39 @@ export type PacketStructureViewModelUpdate = {
     data: PacketStructureViewModel | number,
 };
 
+/**
+ * Code laying out every packet structure the way WINGS expects it to be sent
+ */
+export type FirmwareCode = {
+    cHeader: string,
+    rustModule: string,
+    /**
+     * Every packet structure that was left out, and why
+     */
+    problems: string[],
+};
+
+/**
+ * Two packet structures that the same bytes could be parsed as
+ */
+export type Ambiguity = {
+    /**
+     * The structure the parser reads the example as
+     */
+    matchedId: number,
+    /**
+     * The structure the example could also have been
+     */
+    shadowedId: number,
+    example: number[],
+    matchedStart: number,
+    shadowedStart: number,
+    /**
+     * False if the checksums of the structures still tell them apart
+     */
+    checksumsMatch: boolean,
+};
+
 /**
  * The type of a view model for a backend packet structure
  */
@@ -156,17 +207,145 @@ export type PacketField = {
      */
     offsetInPacket: number,
     /**
-     * The metadata type of this packet for special parsing rules 
+     * The byte order this packet's data is sent in
+     */
+    endianness: Endianness,
+    /**
+     * The bits this packet's data occupies inside its container, null if it uses the whole container
+     */
+    bitfield: PacketBitfield | null,
+    /**
+     * How the bytes of a String packet are laid out, null for every other type
+     */
+    stringFormat: StringFormat | null,
+    /**
+     * Always null for fields sent to the frontend, arrays are expanded into a field per element
+     */
+    array: PacketArray | null,
+    /**
+     * The names of an array's elements, in place of names like "acc[0]".
+     * Always empty for fields sent to the frontend, the elements are already named
+     */
+    elementNames: string[],
+    /**
+     * The conversion applied to this packet's raw data after parsing, null if it is shown as sent
+     */
+    calibration: Calibration | null,
+    /**
+     * The unit of this packet's data after calibration, null if it has none
+     */
+    unit: string | null,
+    /**
+     * The names of the raw values of an enumerated packet, ie flight states. Empty for every other packet
+     */
+    labels: Record<number, string>,
+    /**
+     * How this packet counts time if it is a timestamp, null for every other packet.
+     * Parsed timestamps are unwrapped seconds
+     */
+    timestamp: TimestampFormat | null,
+    /**
+     * How this packet counts up if it is a sequence number, null for every other packet.
+     * Gaps, repeats, reordering and restarts in sequence numbers are reported in the link_health packet
+     */
+    sequence: SequenceFormat | null
+};
+
+/**
+ * The type of the clock a timestamp {@link PacketField} counts
+ */
+export type TimestampFormat = {
+    ticksPerSecond: number,
+    /**
+     * The width of the counter in bits, null if it never rolls over
+     */
+    wrapBits: number | null,
+};
+
+/**
+ * The type of the counter a sequence number {@link PacketField} counts
+ */
+export type SequenceFormat = {
+    /**
+     * The width of the counter in bits
+     */
+    wrapBits: number,
+    /**
+     * How much the counter goes up by from one packet to the next
+     */
+    step: number,
+};
+
+/**
+ * An enumeration for the possible byte orders of a {@link PacketField}
+ */
+export enum Endianness {
+    Little = "Little",
+    Big = "Big",
+};
+
+/**
+ * The type of a range of bits inside the container of a {@link PacketField}
+ */
+export type PacketBitfield = {
+    /**
+     * The bit this field starts at, counted from the least significant bit of the container
+     */
+    bitOffset: number,
+    /**
+     * The number of bits this field occupies
+     */
+    bitWidth: number,
+};
+
+/**
+ * The type of the repetition of an array {@link PacketField}
+ */
+export type PacketArray = {
+    /**
+     * The number of elements room is left for
+     */
+    count: number,
+    /**
+     * The number of bytes from the start of one element to the start of the next
+     */
+    stride: number,
+    /**
+     * The index of the field holding the number of elements that were sent, if there is one
+     */
+    countField: number | null,
+    /**
+     * The number of fields repeating together as a group, whose values are numbered element by element.
+     * 0 for arrays that aren't part of a group
      */
-    metadataType: PacketMetadataType
+    groupSize: number,
 };
 
+/**
+ * The type of the layout of a String {@link PacketField}
+ *
+ * Fixed strings are padded with NULs, length prefixed strings always reserve room for maxLength bytes
+ */
+export type StringFormat =
+    { kind: "Fixed", length: number } |
+    { kind: "LengthPrefixed", prefix: PacketFieldType, maxLength: number };
+
+/**
+ * The type of the conversion from a {@link PacketField}'s raw data to real units
+ *
+ * Polynomial coefficients start from the constant term
+ */
+export type Calibration =
+    { kind: "Linear", scale: number, offset: number } |
+    { kind: "Polynomial", coefficients: number[] };
+
 /**
  * An enumeration for the possible special parsing rules for a {@link PacketField}
  */
 export enum PacketMetadataType {
     None = "None",
     Timestamp = "Timestamp",
+    Sequence = "Sequence",
 };
 
 /**
@@ -218,6 +397,13 @@ export type SendingLoopState = {
 /**
  * different ways that the sending loop can generate data to send out 
  */
+export enum Framing {
+    Delimiters = "Delimiters",//packets are found by their delimiters
+    Cobs = "Cobs",//each packet is COBS encoded and ends with 0x00
+    Slip = "Slip",//each packet is SLIP encoded and ends with 0xC0
+    Hdlc = "Hdlc"//each packet is between 0x7E flags, with a CRC-16/X.25
+};
+
 export enum SendingModes {
     FromCSV = "FromCSV",//pulls number from given csv(input.csv)
     AllZeroes = "AllZeros",//fills all zeros
@@ -240,4 +426,40 @@ export type DisplayComDevice = {
      * the type of device (serial port?, teledongle?)
      */
     device_type: string,
-};
\ No newline at end of file
+};
+
+/**
+ * What a device's parser has made of the packets of one packet structure
+ */
+export type StructureStatistics = {
+    packetsMatched: number,
+    crcFailures: number,
+    /**
+     * packets whose start was found, but that were thrown away before the rest arrived
+     */
+    partialPacketsDropped: number,
+    /**
+     * packets that were found and passed their checks, but whose fields couldn't be read
+     */
+    parseFailures: number,
+};
+
+/**
+ * What a device's parser has made of the data it received, sent every second
+ */
+export type DeviceParserStatistics = {
+    id: number,
+    deviceType: string,
+    statistics: {
+        bytesReceived: number,
+        /**
+         * bytes that were thrown away without being part of a packet
+         */
+        bytesDiscarded: number,
+        frameCrcFailures: number,
+        /**
+         * keyed by packet structure id
+         */
+        structures: Record<number, StructureStatistics>,
+    },
+};
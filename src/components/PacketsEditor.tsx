@@ -1,14 +1,28 @@
 import { batch, Component, createMemo, createSignal, For, Match, Show, Switch } from "solid-js";
-import { addDelimiter, addField, addGapAfter, deletePacketStructure, deletePacketStructureComponent, registerEmptyPacketStructure, setDelimiterIdentifier, setDelimiterName, setFieldMetadataType, setFieldName, setFieldType, setGapSize, setPacketName } from "../backend_interop/api_calls";
-import { PacketComponentType, PacketDelimiter, PacketField, PacketFieldType, PacketGap, PacketMetadataType } from "../backend_interop/types";
+import { addDelimiter, addField, addGapAfter, deletePacketStructure, deletePacketStructureComponent, getDelimiterAmbiguities, getPacketStructureText, registerEmptyPacketStructure, setDelimiterIdentifier, setDelimiterName, setFieldMetadataType, setFieldName, setFieldType, setGapSize, setPacketName } from "../backend_interop/api_calls";
+import { PacketComponentType, PacketDelimiter, PacketField, PacketFieldType, PacketGap, PacketMetadataType, StringFormat, Calibration } from "../backend_interop/types";
 import { createInvokeApiSetterFunction } from "../core/packet_editor_helpers";
-import { runImportPacketWindow, runExportPacketWindow, importPacketsFromDirectories} from "../core/file_handling";
+import { runImportPacketWindow, runExportPacketWindow, importPacketsFromDirectories, runImportCHeaderWindow, runExportFirmwareCodeWindow } from "../core/file_handling";
 import { useBackend } from "../backend_interop/BackendProvider";
 import { useModal } from "../core/ModalProvider";
 import ErrorModal from "../modals/ErrorModal";
 import FileModal, { FileModalProps } from "../modals/FilePathModal";
 import { Store } from "tauri-plugin-store-api";
 
+/**
+ * Describes the byte layout of a String field for the field editor
+ */
+const describeStringFormat = (format: StringFormat): string => format.kind === "Fixed"
+    ? `Fixed Length: ${format.length} bytes`
+    : `Length Prefixed: up to ${format.maxLength} bytes after a ${format.prefix}`;
+
+/**
+ * Describes the conversion from a field's raw data to real units for the field editor
+ */
+const describeCalibration = (calibration: Calibration): string => calibration.kind === "Linear"
+    ? `raw × ${calibration.scale} + ${calibration.offset}`
+    : calibration.coefficients.map((coefficient, power) => power === 0 ? `${coefficient}` : `${coefficient} × raw^${power}`).join(" + ");
+
 /**
  * A component that allows the user to manage packet structures. Changes on the frontend are synchronized with the Rust
  * packet structure manager backend. 
@@ -94,6 +108,49 @@ const PacketEditor: Component = () => {
                 }}>
                     Import Packet
                 </button>
+                <button class="externalButton m-0 text-black bg-gray-100 hover:bg-gray-300 focus:outline-none focus:ring-4 focus:ring-gray-300 font-medium rounded-lg text-sm px-5 py-2.5 
+                    dark:text-white dark:bg-gray-800 dark:hover:bg-gray-700 dark:focus:ring-gray-700 dark:border-gray-700" 
+                    onClick={async () => await showErrorModalOnError(async () => {
+                        const problems = await runImportCHeaderWindow();
+                        if (problems.length > 0) {
+                            showModal(ErrorModal, {
+                                error: 'Some of the header could not be imported',
+                                description: problems.join('; ')
+                            });
+                        }
+                    }, 'Failed to import C header')}
+                >
+                    Import C Header...
+                </button>
+                <button class="externalButton m-0 text-black bg-gray-100 hover:bg-gray-300 focus:outline-none focus:ring-4 focus:ring-gray-300 font-medium rounded-lg text-sm px-5 py-2.5 
+                    dark:text-white dark:bg-gray-800 dark:hover:bg-gray-700 dark:focus:ring-gray-700 dark:border-gray-700" 
+                    onClick={async () => await showErrorModalOnError(async () => {
+                        const problems = await runExportFirmwareCodeWindow();
+                        if (problems.length > 0) {
+                            showModal(ErrorModal, {
+                                error: 'Some packets could not be exported',
+                                description: problems.join('; ')
+                            });
+                        }
+                    }, 'Failed to export firmware code')}
+                >
+                    Export Firmware Code...
+                </button>
+                <button class="externalButton m-0 text-black bg-gray-100 hover:bg-gray-300 focus:outline-none focus:ring-4 focus:ring-gray-300 font-medium rounded-lg text-sm px-5 py-2.5 
+                    dark:text-white dark:bg-gray-800 dark:hover:bg-gray-700 dark:focus:ring-gray-700 dark:border-gray-700" 
+                    onClick={async () => await showErrorModalOnError(async () => {
+                        const name = (id: number) => PacketStructureViewModels.find(i => i.id === id)?.name ?? id;
+                        const ambiguities = (await getDelimiterAmbiguities()).filter(ambiguity => ambiguity.checksumsMatch);
+                        showModal(ErrorModal, {
+                            error: ambiguities.length > 0 ? 'Some packets can be mistaken for others' : 'No packets can be mistaken for others',
+                            description: ambiguities.map(ambiguity =>
+                                `${name(ambiguity.shadowedId)} can be read as ${name(ambiguity.matchedId)}, ie 0x${ambiguity.example.map(byte => byte.toString(16).padStart(2, '0')).join('')}`
+                            ).join('; ')
+                        });
+                    }, 'Failed to check delimiters')}
+                >
+                    Check Delimiters
+                </button>
                 {/*<button class="externalButton" onClick={async () => await runImportPacketWindow()}>Add Packet</button>*/}
                 <button class="externalButton m-0 text-black bg-gray-100 hover:bg-gray-300 focus:outline-none focus:ring-4 focus:ring-gray-300 font-medium rounded-lg text-sm px-5 py-2.5 
                     dark:text-white dark:bg-gray-800 dark:hover:bg-gray-700 dark:focus:ring-gray-700 dark:border-gray-700" 
@@ -152,6 +209,16 @@ const PacketEditor: Component = () => {
                                 </div>
                             </div>
                         </div>
+                        <button class="externalButton relative bottom-0 pt-2 mt-5 m-0 dark:text-white hover:bg-gray-300 focus:outline-none focus:ring-4 
+                        focus:ring-gray-300 font-medium rounded-lg text-sm px-5 py-2.5 bg-gray-100 text-black
+                        dark:bg-gray-800 dark:hover:bg-gray-700 dark:focus:ring-gray-700 dark:border-gray-700"
+                            onClick={async () => await showErrorModalOnError(async () => {
+                                // structure text can be pasted into a config or shared for review
+                                await navigator.clipboard.writeText(await getPacketStructureText(selectedPacket()!.id));
+                            }, 'Failed to copy packet structure!')}
+                        >
+                            Copy {selectedPacket()!.name} as Text
+                        </button>
                         <button class="redButton relative bottom-0 pt-2 mt-5 m-0 dark:text-white hover:bg-gray-300 focus:outline-none focus:ring-4 
                         focus:ring-gray-300 font-medium rounded-lg text-sm px-5 py-2.5 bg-gray-100 text-black
                         dark:bg-gray-800 dark:hover:bg-gray-700 dark:focus:ring-gray-700 dark:border-gray-700"
@@ -207,6 +274,21 @@ const PacketEditor: Component = () => {
                                             onInput={async e => await invokeApiSetter(setFieldName, (e.target as HTMLInputElement).value)} />
                                     </div>
                                     <span>Offset in Packet: {selectedFieldData()!.offsetInPacket} byte{selectedFieldData()!.offsetInPacket == 1 ? "" : "s"}</span>
+                                    <Show when={selectedFieldData()!.stringFormat}>
+                                        {format => <span>{describeStringFormat(format())}</span>}
+                                    </Show>
+                                    <Show when={selectedFieldData()!.bitfield}>
+                                        <span>Bits in Container: {selectedFieldData()!.bitfield!.bitOffset} to {selectedFieldData()!.bitfield!.bitOffset + selectedFieldData()!.bitfield!.bitWidth - 1}</span>
+                                    </Show>
+                                    <Show when={selectedFieldData()!.calibration}>
+                                        {calibration => <span>Calibration: {describeCalibration(calibration())}</span>}
+                                    </Show>
+                                    <Show when={selectedFieldData()!.unit}>
+                                        <span>Unit: {selectedFieldData()!.unit}</span>
+                                    </Show>
+                                    <Show when={Object.keys(selectedFieldData()!.labels ?? {}).length > 0}>
+                                        <span>Values: {Object.entries(selectedFieldData()!.labels).map(([value, label]) => `${value} = ${label}`).join(", ")}</span>
+                                    </Show>
                                     <div class="flex flex-col">
                                         <label for="fieldType">Type</label>
                                         {/* <form class="max-w-sm mx-auto"> */}
@@ -229,13 +311,19 @@ const PacketEditor: Component = () => {
                                     </div>
                                     <div class="flex flex-col">
                                         <label for="fieldMetadataType">Metadata Type</label>
-                                        <select class="inputBox" value={selectedFieldData()!.metadataType} id="fieldMetadataType"
+                                        <select class="inputBox" value={selectedFieldData()!.timestamp ? PacketMetadataType.Timestamp : selectedFieldData()!.sequence ? PacketMetadataType.Sequence : PacketMetadataType.None} id="fieldMetadataType"
                                             onInput={async e => await invokeApiSetter(setFieldMetadataType, (e.target as HTMLSelectElement).value as PacketMetadataType)}>
                                             <For each={Object.values(PacketMetadataType).filter(k => isNaN(Number(k)))}>
                                                 {(metadataType) => <option value={metadataType}>{metadataType}</option>}
                                             </For>
                                         </select>
                                     </div>
+                                    <Show when={selectedFieldData()!.timestamp}>
+                                        {timestamp => <span>Clock: {timestamp().ticksPerSecond} ticks per second{timestamp().wrapBits === null ? "" : `, rolls over after ${timestamp().wrapBits} bits`}</span>}
+                                    </Show>
+                                    <Show when={selectedFieldData()!.sequence}>
+                                        {sequence => <span>Sequence: goes up by {sequence().step}, rolls over after {sequence().wrapBits} bits</span>}
+                                    </Show>
                                 </div>
                             </Match>
                             {/* Selected packet structure delimiter editor */}
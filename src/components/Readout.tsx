@@ -2,7 +2,7 @@ import { Component, For, JSX, Show, createEffect, createSignal } from "solid-js"
 import { ReadoutStruct } from "../modals/ReadoutSettingsModal";
 import { useBackend } from "../backend_interop/BackendProvider";
 import { unDecimatedPackets, parsedPackets } from "../backend_interop/buffers";
-import { PacketComponentType, PacketField } from "../backend_interop/types";
+import { FieldLabel, PacketComponentType, PacketField } from "../backend_interop/types";
 
 let _canvas: HTMLCanvasElement | null = null;
 let _ctx: CanvasRenderingContext2D | null = null;
@@ -90,6 +90,8 @@ const ReadoutDisplayElement: Component<ReadoutStruct> = (readout): JSX.Element =
 
     // each index corresponds to readout.fields value
     const [values, setValues] = createSignal([] as number[]);
+    // each index corresponds to readout.fields value, undefined for fields that aren't enumerated
+    const [labels, setLabels] = createSignal([] as (FieldLabel | undefined)[]);
 
     const update = () => {
         if (unDecimatedPackets[readout.packetID] === undefined){
@@ -97,11 +99,13 @@ const ReadoutDisplayElement: Component<ReadoutStruct> = (readout): JSX.Element =
                 const latestValue = NaN;
                 return latestValue;
             }));   
+            setLabels([]);
             return;     
         }
         const lastPacket = unDecimatedPackets[readout.packetID][(unDecimatedPackets[readout.packetID].length) -1];
         if (!lastPacket) {
             setValues([]);
+            setLabels([]);
             return;
         }
 
@@ -110,6 +114,7 @@ const ReadoutDisplayElement: Component<ReadoutStruct> = (readout): JSX.Element =
             const latestValue = lastPacket.fieldData[i.packetFieldIndex];
             return latestValue;
         }));
+        setLabels(readout.fields.map(i => lastPacket.fieldLabels?.[i.packetFieldIndex]));
     };
 
     createEffect(() => {
@@ -132,12 +137,18 @@ const ReadoutDisplayElement: Component<ReadoutStruct> = (readout): JSX.Element =
         </div>
 
         <For each={readout.fields}>{(item, index) => {
-            const field = () => getFieldComponents()[item.packetFieldIndex].data as PacketField;
+            const field = () => getFieldComponents().find(i => (i.data as PacketField).index === item.packetFieldIndex)!.data as PacketField;
+            // the unit typed into the readout wins over the one the packet structure declares
+            const unit = () => item.unit || field().unit || "";
 
             const getValue = (): string => {
                 if (values().length <= index() || Number.isNaN(values()[index()])) {
                     return 'N/A'
                 }
+                const label = labels()[index()];
+                if (label) {
+                    return label.label;
+                }
                 const value = values()[index()];
                 const value_string = value.toFixed(7)
                 const delimited_array = value_string.split(".")
@@ -147,9 +158,9 @@ const ReadoutDisplayElement: Component<ReadoutStruct> = (readout): JSX.Element =
 
                 const prePadding = " ".repeat(Math.max(5 - pre_dec.length,0))
                 const postPadding = " ".repeat(Math.max(3 - post_dec.length,0))
-                const postUnitPadding = " ".repeat(Math.max(10 - item.unit.length,0))
-                if (item.unit) {
-                    return prePadding + pre_dec + "." + post_dec + postPadding + " " + `${item.unit}` + postUnitPadding;
+                const postUnitPadding = " ".repeat(Math.max(10 - unit().length,0))
+                if (unit()) {
+                    return prePadding + pre_dec + "." + post_dec + postPadding + " " + `${unit()}` + postUnitPadding;
                 }
                 return (prePadding + pre_dec + "." + post_dec + postPadding + " ".repeat(11));
             };
@@ -171,7 +182,7 @@ const ReadoutDisplayElement: Component<ReadoutStruct> = (readout): JSX.Element =
                     >
                         <div style={{
                             'white-space': "pre-wrap"
-                        }}>
+                        }} classList={{ "text-red-500": labels()[index()]?.known === false }}>
                             {getValue()}
                         </div>
                     </div>
@@ -1,7 +1,8 @@
 import { open, save } from '@tauri-apps/api/dialog';
 import { PacketStructureViewModel, PacketComponentType } from "../backend_interop/types";
 import { writeTextFile, readTextFile } from '@tauri-apps/api/fs';
-import { addPacket } from "../backend_interop/api_calls";
+import { join } from '@tauri-apps/api/path';
+import { addPacket, getFirmwareCode, importCHeader } from "../backend_interop/api_calls";
 import { Store } from"tauri-plugin-store-api";
 /**
  * Exports the given PacketStructureViewModel as a .json file via a dialogue window.
@@ -68,6 +69,35 @@ const updatePersistentFilePaths = async (filePathString: string) => {
     await store.save();
 }
 
+/**
+ * Imports the structs of a C header as packet structures via a dialogue window.
+ * 
+ * @returns everything in the header that couldn't be imported
+ */
+export const runImportCHeaderWindow = async (): Promise<string[]> => {
+    const selectedFilePath = await open({ title: 'Import C Header', filters: [{ name: 'C Header', extensions: ['h'] }] });
+    if (selectedFilePath === null || Array.isArray(selectedFilePath)) {
+        return [];
+    }
+    return await importCHeader(await readTextFile(selectedFilePath));
+};
+
+/**
+ * Writes a C header and a Rust module describing every packet structure into a folder chosen via a dialogue window.
+ * 
+ * @returns every packet structure that was left out, and why
+ */
+export const runExportFirmwareCodeWindow = async (): Promise<string[]> => {
+    const selectedDirectory = await open({ title: 'Export Firmware Code', directory: true });
+    if (selectedDirectory === null || Array.isArray(selectedDirectory)) {
+        return [];
+    }
+    const code = await getFirmwareCode();
+    await writeTextFile({ contents: code.cHeader, path: await join(selectedDirectory, 'wings_packets.h') });
+    await writeTextFile({ contents: code.rustModule, path: await join(selectedDirectory, 'wings_packets.rs') });
+    return code.problems;
+};
+
 /**
  * Imports a set of packets selected by the user via a dialogue window.
  * 
@@ -1,9 +1,9 @@
 import { Component, batch, createSignal, JSX, For, Show } from "solid-js";
 import { useBackend } from "../backend_interop/BackendProvider";
-import { addAim, addAltusMetrum, addFeatherWeight, addFileManager, addRfd, deleteDevice, initDevicePort, startSendingLoop, stopSendingLoop } from "../backend_interop/api_calls";
+import { addAim, addAltusMetrum, addFeatherWeight, addFileManager, addMavlink, addRfd, deleteDevice, initDevicePort, setDeviceFraming, startSendingLoop, stopSendingLoop } from "../backend_interop/api_calls";
 import ErrorModal from "../modals/ErrorModal";
 import { useModal } from "../core/ModalProvider";
-import { SendingModes } from "../backend_interop/types";
+import { Framing, SendingModes } from "../backend_interop/types";
 import { createStore } from "solid-js/store";
 import { Store } from "tauri-plugin-store-api";
 import FileModal from "../modals/FilePathModal";
@@ -25,7 +25,7 @@ export const IterateComDevicesIterator = () => {
 }
 
 const SendingTab: Component = () => {
-    const { availableDeviceNames: availablePortNames, parsedPacketCount, sendingLoopState, comDeviceList, gotData } = useBackend();
+    const { availableDeviceNames: availablePortNames, parsedPacketCount, sendingLoopState, comDeviceList, gotData, parserStatistics } = useBackend();
     const { showModal } = useModal();
 
     const startSimulating = async () => {
@@ -77,6 +77,14 @@ const SendingTab: Component = () => {
         }
     }
 
+    async function applyFraming(framing: Framing, id: number) {
+        try {
+            await setDeviceFraming(framing, id);
+        } catch (error) {
+            showModal(ErrorModal, { error: 'Failed to set the framing of the device', description: `${error}` });
+        }
+    }
+
     return (
         <div class="flex flex-grow gap-4">
             <div class="flex flex-grow flex-col gap-4" style = {{"flex":"3"}}>
@@ -103,6 +111,9 @@ const SendingTab: Component = () => {
                 <button class ="border border-black bg-gray dark:bg-gray-800 rounded-md"  onClick={() => { setComDeviceSelections([...comDeviceSelections, { id: comDevicesIterator++, selection: "" }]); addFeatherWeight() }}>
                     add FeatherWeight
                 </button>
+                <button class ="border border-black bg-gray dark:bg-gray-800 rounded-md"  onClick={() => { setComDeviceSelections([...comDeviceSelections, { id: comDevicesIterator++, selection: "" }]); addMavlink() }}>
+                    add MAVLink
+                </button>
                 <For each={comDeviceList()}>
                     {(device, device_index) =>
                         <label for="DeviceInput" class="px-2 m-0">
@@ -112,6 +123,13 @@ const SendingTab: Component = () => {
                                 onChange={event => {
                                     console.log((event.target as HTMLInputElement).value!);
                                     applyNewSelectedPort((event.target as HTMLInputElement).value!, baud(), device.id)}} />
+                            <Show when={device.device_type === "SerialPort" || device.device_type === "ByteFile"}>
+                                <select class="mx-1" onChange={event => applyFraming((event.currentTarget as HTMLSelectElement).value as Framing, device.id)}>
+                                    <For each={Object.values(Framing)}>
+                                        {(framing) => <option value={framing}>{framing}</option>}
+                                    </For>
+                                </select>
+                            </Show>
                             <button onClick={() => {
                                 deleteDevice(device.id);
                                 setComDeviceSelections(comDeviceSelections.filter((_, index) => device_index() != index));
@@ -183,6 +201,14 @@ const SendingTab: Component = () => {
             <div class="flex flex-2 flex-grow flex-col gap-4" style = {{"flex":"2"}}>
                 <p><b>Sent: </b>{sendingLoopState()?.packetsSent} packets</p>
                 <p><b>Received: </b>{parsedPacketCount()} packets</p>
+                <For each={parserStatistics()}>
+                    {(device) =>
+                        <p>
+                            <b>{device.deviceType} {device.id}: </b>
+                            {device.statistics.bytesReceived} bytes received, {device.statistics.bytesDiscarded} discarded
+                        </p>
+                    }
+                </For>
                 <button
                     class="py-2 px-4 rounded border-0 text-black"
                     classList={{